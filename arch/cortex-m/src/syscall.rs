@@ -282,4 +282,37 @@ impl kernel::syscall::UserspaceKernelBoundary for SysCall {
             },
         ));
     }
+
+    unsafe fn store_registers(
+        &self,
+        stack_pointer: *const usize,
+        state: &CortexMStoredState,
+        registers: &mut [usize],
+    ) -> usize {
+        // r0-r3 and r12 are in the frame the hardware pushed on the stack,
+        // r4-r11 in the stored state.
+        let frame = |offset| read_volatile(stack_pointer.offset(offset));
+        let values = [
+            frame(0),
+            frame(1),
+            frame(2),
+            frame(3),
+            state.regs[0],
+            state.regs[1],
+            state.regs[2],
+            state.regs[3],
+            state.regs[4],
+            state.regs[5],
+            state.regs[6],
+            state.regs[7],
+            frame(4),
+            stack_pointer as usize,
+            frame(5),
+            frame(6),
+            frame(7),
+        ];
+        let count = core::cmp::min(values.len(), registers.len());
+        registers[..count].copy_from_slice(&values[..count]);
+        count
+    }
 }
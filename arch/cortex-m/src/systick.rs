@@ -136,6 +136,18 @@ impl kernel::SysTick for SysTick {
         SYSTICK_BASE.syst_csr.is_set(ControlAndStatus::COUNTFLAG)
     }
 
+    fn get_remaining_us(&self) -> u32 {
+        let hertz = self.hertz() as u64;
+        if hertz == 0 {
+            return 0;
+        }
+
+        // Convert native tics back to microseconds. As in `set_timer`, this
+        // could overflow in 32-bit arithmetic so we use 64-bit values.
+        let tics = SYSTICK_BASE.syst_cvr.read(CurrentValue::CURRENT) as u64;
+        (tics * 1_000_000 / hertz) as u32
+    }
+
     fn reset(&self) {
         SYSTICK_BASE.syst_csr.set(0);
         SYSTICK_BASE.syst_rvr.set(0);
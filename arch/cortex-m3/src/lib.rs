@@ -269,6 +269,8 @@ unsafe fn kernel_hardfault(faulting_stack: *mut u32) {
     let thumb_bit = ((stacked_xpsr >> 24) & 0x1) == 1;
     let exception_number = (stacked_xpsr & 0x1ff) as usize;
 
+    kernel::panic_record::set_fault_registers(stacked_pc, stacked_lr);
+
     panic!(
         "{} HardFault.\r\n\
          \tKernel version {}\r\n\
@@ -244,160 +244,25 @@ impl CortexMConfig {
         }
         None
     }
-}
-
-/// Struct storing configuration for a Cortex-M MPU region.
-#[derive(Copy, Clone)]
-pub struct CortexMRegion {
-    location: Option<(*const u8, usize)>,
-    base_address: FieldValue<u32, RegionBaseAddress::Register>,
-    attributes: FieldValue<u32, RegionAttributes::Register>,
-}
-
-impl CortexMRegion {
-    fn new(
-        logical_start: *const u8,
-        logical_size: usize,
-        region_start: *const u8,
-        region_size: usize,
-        region_num: usize,
-        subregions: Option<(usize, usize)>,
-        permissions: mpu::Permissions,
-    ) -> CortexMRegion {
-        // Determine access and execute permissions
-        let (access, execute) = match permissions {
-            mpu::Permissions::ReadWriteExecute => (
-                RegionAttributes::AP::ReadWrite,
-                RegionAttributes::XN::Enable,
-            ),
-            mpu::Permissions::ReadWriteOnly => (
-                RegionAttributes::AP::ReadWrite,
-                RegionAttributes::XN::Disable,
-            ),
-            mpu::Permissions::ReadExecuteOnly => {
-                (RegionAttributes::AP::ReadOnly, RegionAttributes::XN::Enable)
-            }
-            mpu::Permissions::ReadOnly => (
-                RegionAttributes::AP::ReadOnly,
-                RegionAttributes::XN::Disable,
-            ),
-            mpu::Permissions::ExecuteOnly => {
-                (RegionAttributes::AP::NoAccess, RegionAttributes::XN::Enable)
-            }
-        };
-
-        // Base address register
-        let base_address = RegionBaseAddress::ADDR.val((region_start as u32) >> 5)
-            + RegionBaseAddress::VALID::UseRBAR
-            + RegionBaseAddress::REGION.val(region_num as u32);
-
-        let size_value = math::log_base_two(region_size as u32) - 1;
-
-        // Attributes register
-        let mut attributes = RegionAttributes::ENABLE::SET
-            + RegionAttributes::SIZE.val(size_value)
-            + access
-            + execute;
-
-        // If using subregions, add a subregion mask. The mask is a 8-bit
-        // bitfield where `0` indicates that the corresponding subregion is enabled.
-        // To compute the mask, we start with all subregions disabled and enable
-        // the ones in the inclusive range [min_subregion, max_subregion].
-        if let Some((min_subregion, max_subregion)) = subregions {
-            let mask = (min_subregion..=max_subregion).fold(u8::max_value(), |res, i| {
-                // Enable subregions bit by bit (1 ^ 1 == 0)
-                res ^ (1 << i)
-            });
-            attributes += RegionAttributes::SRD.val(mask as u32);
-        }
-
-        CortexMRegion {
-            location: Some((logical_start, logical_size)),
-            base_address: base_address,
-            attributes: attributes,
-        }
-    }
-
-    fn empty(region_num: usize) -> CortexMRegion {
-        CortexMRegion {
-            location: None,
-            base_address: RegionBaseAddress::VALID::UseRBAR
-                + RegionBaseAddress::REGION.val(region_num as u32),
-            attributes: RegionAttributes::ENABLE::CLEAR,
-        }
-    }
-
-    fn location(&self) -> Option<(*const u8, usize)> {
-        self.location
-    }
-
-    fn base_address(&self) -> FieldValue<u32, RegionBaseAddress::Register> {
-        self.base_address
-    }
-
-    fn attributes(&self) -> FieldValue<u32, RegionAttributes::Register> {
-        self.attributes
-    }
-
-    fn overlaps(&self, other_start: *const u8, other_size: usize) -> bool {
-        let other_start = other_start as usize;
-        let other_end = other_start + other_size;
-
-        let (region_start, region_end) = match self.location {
-            Some((region_start, region_size)) => {
-                let region_start = region_start as usize;
-                let region_end = region_start + region_size;
-                (region_start, region_end)
-            }
-            None => return false,
-        };
-
-        if region_start < other_end && other_start < region_end {
-            true
-        } else {
-            false
-        }
-    }
-}
-
-impl kernel::mpu::MPU for MPU {
-    type MpuConfig = CortexMConfig;
-
-    fn enable_mpu(&self) {
-        let regs = &*self.registers;
-
-        // Enable the MPU, disable it during HardFault/NMI handlers, and allow
-        // privileged code access to all unprotected memory.
-        regs.ctrl
-            .write(Control::ENABLE::SET + Control::HFNMIENA::CLEAR + Control::PRIVDEFENA::SET);
-    }
-
-    fn disable_mpu(&self) {
-        let regs = &*self.registers;
-        regs.ctrl.write(Control::ENABLE::CLEAR);
-    }
-
-    fn number_total_regions(&self) -> usize {
-        let regs = &*self.registers;
-        regs.mpu_type.read(Type::DREGION) as usize
-    }
 
+    /// Compute a region that covers at least `min_region_size` bytes of the
+    /// unallocated memory, and add it to the configuration. This only changes
+    /// the configuration, `configure_mpu()` writes it to the hardware.
     fn allocate_region(
-        &self,
+        &mut self,
         unallocated_memory_start: *const u8,
         unallocated_memory_size: usize,
         min_region_size: usize,
         permissions: mpu::Permissions,
-        config: &mut Self::MpuConfig,
     ) -> Option<mpu::Region> {
         // Check that no previously allocated regions overlap the unallocated memory.
-        for region in config.regions.iter() {
+        for region in self.regions.iter() {
             if region.overlaps(unallocated_memory_start, unallocated_memory_size) {
                 return None;
             }
         }
 
-        let region_num = config.unused_region_number()?;
+        let region_num = self.unused_region_number()?;
 
         // Logical region
         let mut start = unallocated_memory_start as usize;
@@ -503,24 +368,26 @@ impl kernel::mpu::MPU for MPU {
             permissions,
         );
 
-        config.regions[region_num] = region;
-        config.is_dirty.set(true);
+        self.regions[region_num] = region;
+        self.is_dirty.set(true);
 
         Some(mpu::Region::new(start as *const u8, size))
     }
 
+    /// Compute the region that covers the memory of a process, and add it to
+    /// the configuration. Only the app-owned memory at the start of the
+    /// region is accessible to the process.
     fn allocate_app_memory_region(
-        &self,
+        &mut self,
         unallocated_memory_start: *const u8,
         unallocated_memory_size: usize,
         min_memory_size: usize,
         initial_app_memory_size: usize,
         initial_kernel_memory_size: usize,
         permissions: mpu::Permissions,
-        config: &mut Self::MpuConfig,
     ) -> Option<(*const u8, usize)> {
         // Check that no previously allocated regions overlap the unallocated memory.
-        for region in config.regions.iter() {
+        for region in self.regions.iter() {
             if region.overlaps(unallocated_memory_start, unallocated_memory_size) {
                 return None;
             }
@@ -610,20 +477,21 @@ impl kernel::mpu::MPU for MPU {
             permissions,
         );
 
-        config.regions[APP_MEMORY_REGION_NUM] = region;
-        config.is_dirty.set(true);
+        self.regions[APP_MEMORY_REGION_NUM] = region;
+        self.is_dirty.set(true);
 
         Some((region_start as *const u8, region_size))
     }
 
+    /// Recompute the region that covers the memory of a process after its
+    /// app break or kernel break moved.
     fn update_app_memory_region(
-        &self,
+        &mut self,
         app_memory_break: *const u8,
         kernel_memory_break: *const u8,
         permissions: mpu::Permissions,
-        config: &mut Self::MpuConfig,
     ) -> Result<(), ()> {
-        let (region_start, region_size) = match config.regions[APP_MEMORY_REGION_NUM].location() {
+        let (region_start, region_size) = match self.regions[APP_MEMORY_REGION_NUM].location() {
             Some((start, size)) => (start as usize, size),
             None => {
                 // Error: Process tried to update app memory MPU region before it was created.
@@ -670,11 +538,194 @@ impl kernel::mpu::MPU for MPU {
             permissions,
         );
 
-        config.regions[APP_MEMORY_REGION_NUM] = region;
-        config.is_dirty.set(true);
+        self.regions[APP_MEMORY_REGION_NUM] = region;
+        self.is_dirty.set(true);
 
         Ok(())
     }
+}
+
+/// Struct storing configuration for a Cortex-M MPU region.
+#[derive(Copy, Clone)]
+pub struct CortexMRegion {
+    location: Option<(*const u8, usize)>,
+    base_address: FieldValue<u32, RegionBaseAddress::Register>,
+    attributes: FieldValue<u32, RegionAttributes::Register>,
+}
+
+impl CortexMRegion {
+    fn new(
+        logical_start: *const u8,
+        logical_size: usize,
+        region_start: *const u8,
+        region_size: usize,
+        region_num: usize,
+        subregions: Option<(usize, usize)>,
+        permissions: mpu::Permissions,
+    ) -> CortexMRegion {
+        // Determine access and execute permissions
+        let (access, execute) = match permissions {
+            mpu::Permissions::ReadWriteExecute => (
+                RegionAttributes::AP::ReadWrite,
+                RegionAttributes::XN::Enable,
+            ),
+            mpu::Permissions::ReadWriteOnly => (
+                RegionAttributes::AP::ReadWrite,
+                RegionAttributes::XN::Disable,
+            ),
+            mpu::Permissions::ReadExecuteOnly => {
+                (RegionAttributes::AP::ReadOnly, RegionAttributes::XN::Enable)
+            }
+            mpu::Permissions::ReadOnly => (
+                RegionAttributes::AP::ReadOnly,
+                RegionAttributes::XN::Disable,
+            ),
+            mpu::Permissions::ExecuteOnly => {
+                (RegionAttributes::AP::NoAccess, RegionAttributes::XN::Enable)
+            }
+        };
+
+        // Base address register
+        let base_address = RegionBaseAddress::ADDR.val((region_start as u32) >> 5)
+            + RegionBaseAddress::VALID::UseRBAR
+            + RegionBaseAddress::REGION.val(region_num as u32);
+
+        let size_value = math::log_base_two(region_size as u32) - 1;
+
+        // Attributes register
+        let mut attributes = RegionAttributes::ENABLE::SET
+            + RegionAttributes::SIZE.val(size_value)
+            + access
+            + execute;
+
+        // If using subregions, add a subregion mask. The mask is a 8-bit
+        // bitfield where `0` indicates that the corresponding subregion is enabled.
+        // To compute the mask, we start with all subregions disabled and enable
+        // the ones in the inclusive range [min_subregion, max_subregion].
+        if let Some((min_subregion, max_subregion)) = subregions {
+            let mask = (min_subregion..=max_subregion).fold(u8::max_value(), |res, i| {
+                // Enable subregions bit by bit (1 ^ 1 == 0)
+                res ^ (1 << i)
+            });
+            attributes += RegionAttributes::SRD.val(mask as u32);
+        }
+
+        CortexMRegion {
+            location: Some((logical_start, logical_size)),
+            base_address: base_address,
+            attributes: attributes,
+        }
+    }
+
+    fn empty(region_num: usize) -> CortexMRegion {
+        CortexMRegion {
+            location: None,
+            base_address: RegionBaseAddress::VALID::UseRBAR
+                + RegionBaseAddress::REGION.val(region_num as u32),
+            attributes: RegionAttributes::ENABLE::CLEAR,
+        }
+    }
+
+    fn location(&self) -> Option<(*const u8, usize)> {
+        self.location
+    }
+
+    fn base_address(&self) -> FieldValue<u32, RegionBaseAddress::Register> {
+        self.base_address
+    }
+
+    fn attributes(&self) -> FieldValue<u32, RegionAttributes::Register> {
+        self.attributes
+    }
+
+    fn overlaps(&self, other_start: *const u8, other_size: usize) -> bool {
+        let other_start = other_start as usize;
+        let other_end = other_start + other_size;
+
+        let (region_start, region_end) = match self.location {
+            Some((region_start, region_size)) => {
+                let region_start = region_start as usize;
+                let region_end = region_start + region_size;
+                (region_start, region_end)
+            }
+            None => return false,
+        };
+
+        if region_start < other_end && other_start < region_end {
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl kernel::mpu::MPU for MPU {
+    type MpuConfig = CortexMConfig;
+
+    fn enable_mpu(&self) {
+        let regs = &*self.registers;
+
+        // Enable the MPU, disable it during HardFault/NMI handlers, and allow
+        // privileged code access to all unprotected memory.
+        regs.ctrl
+            .write(Control::ENABLE::SET + Control::HFNMIENA::CLEAR + Control::PRIVDEFENA::SET);
+    }
+
+    fn disable_mpu(&self) {
+        let regs = &*self.registers;
+        regs.ctrl.write(Control::ENABLE::CLEAR);
+    }
+
+    fn number_total_regions(&self) -> usize {
+        let regs = &*self.registers;
+        regs.mpu_type.read(Type::DREGION) as usize
+    }
+
+    fn allocate_region(
+        &self,
+        unallocated_memory_start: *const u8,
+        unallocated_memory_size: usize,
+        min_region_size: usize,
+        permissions: mpu::Permissions,
+        config: &mut Self::MpuConfig,
+    ) -> Option<mpu::Region> {
+        config.allocate_region(
+            unallocated_memory_start,
+            unallocated_memory_size,
+            min_region_size,
+            permissions,
+        )
+    }
+
+    fn allocate_app_memory_region(
+        &self,
+        unallocated_memory_start: *const u8,
+        unallocated_memory_size: usize,
+        min_memory_size: usize,
+        initial_app_memory_size: usize,
+        initial_kernel_memory_size: usize,
+        permissions: mpu::Permissions,
+        config: &mut Self::MpuConfig,
+    ) -> Option<(*const u8, usize)> {
+        config.allocate_app_memory_region(
+            unallocated_memory_start,
+            unallocated_memory_size,
+            min_memory_size,
+            initial_app_memory_size,
+            initial_kernel_memory_size,
+            permissions,
+        )
+    }
+
+    fn update_app_memory_region(
+        &self,
+        app_memory_break: *const u8,
+        kernel_memory_break: *const u8,
+        permissions: mpu::Permissions,
+        config: &mut Self::MpuConfig,
+    ) -> Result<(), ()> {
+        config.update_app_memory_region(app_memory_break, kernel_memory_break, permissions)
+    }
 
     fn configure_mpu(&self, config: &Self::MpuConfig, app_id: &AppId) {
         // If the hardware is already configured for this app and the app's MPU
@@ -692,3 +743,315 @@ impl kernel::mpu::MPU for MPU {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    //! Property tests of the region computation for random memory layouts.
+    //! The regions are decoded from the register values that
+    //! `configure_mpu()` writes, as the hardware would decode them.
+
+    use super::*;
+
+    const ITERATIONS: usize = 500;
+
+    /// Xorshift, so that failures can be reproduced.
+    struct Rng(u32);
+
+    impl Rng {
+        fn below(&mut self, bound: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 17;
+            self.0 ^= self.0 << 5;
+            self.0 as usize % bound
+        }
+    }
+
+    /// What unprivileged code may do with a byte of memory.
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    struct Access {
+        read: bool,
+        write: bool,
+        execute: bool,
+    }
+
+    const NO_ACCESS: Access = Access {
+        read: false,
+        write: false,
+        execute: false,
+    };
+
+    /// The permissions the MPU can give unprivileged code. Execute-only
+    /// memory needs read access, so `ExecuteOnly` cannot be expressed.
+    const PERMISSIONS: [mpu::Permissions; 4] = [
+        mpu::Permissions::ReadWriteExecute,
+        mpu::Permissions::ReadWriteOnly,
+        mpu::Permissions::ReadExecuteOnly,
+        mpu::Permissions::ReadOnly,
+    ];
+
+    fn expected_access(permissions: mpu::Permissions) -> Access {
+        let (read, write, execute) = match permissions {
+            mpu::Permissions::ReadWriteExecute => (true, true, true),
+            mpu::Permissions::ReadWriteOnly => (true, true, false),
+            mpu::Permissions::ReadExecuteOnly => (true, false, true),
+            mpu::Permissions::ReadOnly => (true, false, false),
+            mpu::Permissions::ExecuteOnly => (false, false, true),
+        };
+        Access {
+            read: read,
+            write: write,
+            execute: execute,
+        }
+    }
+
+    /// The enabled part of a region, and the access it gives.
+    fn decode(region: &CortexMRegion) -> Option<(usize, usize, Access)> {
+        let attributes = region.attributes().value;
+        if attributes & 1 == 0 {
+            return None;
+        }
+        let base = (region.base_address().value & !0x1f) as usize;
+        let size = 1usize << (((attributes >> 1) & 0x1f) + 1);
+        assert_eq!(base % size, 0, "region base is not aligned to its size");
+
+        // Subregions are only supported for regions of 256 bytes or more.
+        let (mut start, mut end) = (base, base + size);
+        let disabled = (attributes >> 8) & 0xff;
+        if size >= 256 && disabled != 0 {
+            let enabled: u32 = !disabled & 0xff;
+            let first = enabled.trailing_zeros() as usize;
+            let last = 31 - enabled.leading_zeros() as usize;
+            assert_eq!(
+                enabled.count_ones() as usize,
+                last - first + 1,
+                "enabled subregions are not contiguous"
+            );
+            start = base + first * size / 8;
+            end = base + (last + 1) * size / 8;
+        }
+
+        let read_write = match (attributes >> 24) & 0b111 {
+            0b011 => (true, true),
+            0b010 | 0b110 | 0b111 => (true, false),
+            _ => (false, false),
+        };
+        let execute = read_write.0 && (attributes >> 28) & 1 == 0;
+        Some((
+            start,
+            end,
+            Access {
+                read: read_write.0,
+                write: read_write.1,
+                execute: execute,
+            },
+        ))
+    }
+
+    /// What unprivileged code may do at `address`. The enabled region with
+    /// the highest number decides.
+    fn access(config: &CortexMConfig, address: usize) -> Access {
+        for (number, region) in config.regions.iter().enumerate().rev() {
+            assert_eq!(
+                (region.base_address().value & 0xf) as usize,
+                number,
+                "region is written to the wrong region number"
+            );
+            if let Some((start, end, access)) = decode(region) {
+                if start <= address && address < end {
+                    return access;
+                }
+            }
+        }
+        NO_ACCESS
+    }
+
+    /// Check the access at every address in `[start, end)` where it could
+    /// change. Regions and subregions are at least 32 bytes.
+    fn check_range(config: &CortexMConfig, start: usize, end: usize, expected: Access) {
+        if start >= end {
+            return;
+        }
+        let boundaries = (start / 32 + 1) * 32..end;
+        for address in [start, end - 1]
+            .iter()
+            .cloned()
+            .chain(boundaries.step_by(32))
+        {
+            assert_eq!(
+                access(config, address),
+                expected,
+                "at {:#x} in [{:#x}, {:#x})",
+                address,
+                start,
+                end
+            );
+        }
+    }
+
+    /// Check that the process can use its memory up to the app break, and
+    /// cannot touch the memory from the kernel break on.
+    fn check_app_memory(
+        config: &CortexMConfig,
+        (start, size): (usize, usize),
+        app_break: usize,
+        kernel_break: usize,
+    ) {
+        check_range(
+            config,
+            start,
+            app_break,
+            expected_access(mpu::Permissions::ReadWriteOnly),
+        );
+        check_range(config, kernel_break, start + size, NO_ACCESS);
+        check_range(config, start + size, start + size + 32, NO_ACCESS);
+    }
+
+    #[test]
+    fn test_app_memory_region_protects_kernel_memory() {
+        let mut rng = Rng(0x2545_f491);
+        for _ in 0..ITERATIONS {
+            let mut config = CortexMConfig::default();
+            let memory_start = 0x2000_0000 + rng.below(0x1_0000) * 4;
+            let memory_size = 0x4_0000;
+            let min_memory_size = rng.below(0x4000);
+            let initial_app_memory_size = rng.below(0x2000);
+            let initial_kernel_memory_size = rng.below(0x400) * 4;
+
+            let (start, size) = config
+                .allocate_app_memory_region(
+                    memory_start as *const u8,
+                    memory_size,
+                    min_memory_size,
+                    initial_app_memory_size,
+                    initial_kernel_memory_size,
+                    mpu::Permissions::ReadWriteOnly,
+                )
+                .expect("no app memory region");
+            let start = start as usize;
+            assert!(start >= memory_start && start + size <= memory_start + memory_size);
+            assert!(size >= min_memory_size);
+            assert!(size >= initial_app_memory_size + initial_kernel_memory_size);
+
+            let mut app_break = start + initial_app_memory_size;
+            let mut kernel_break = start + size - initial_kernel_memory_size;
+            check_app_memory(&config, (start, size), app_break, kernel_break);
+
+            // Move the breaks as the process calls brk and the kernel
+            // allocates grants. Failing to cover the new app break must leave
+            // the previous configuration in place.
+            for _ in 0..4 {
+                let new_app_break = start + rng.below(size + 1);
+                let new_kernel_break = new_app_break + rng.below(start + size - new_app_break + 1);
+                if config
+                    .update_app_memory_region(
+                        new_app_break as *const u8,
+                        new_kernel_break as *const u8,
+                        mpu::Permissions::ReadWriteOnly,
+                    )
+                    .is_ok()
+                {
+                    app_break = new_app_break;
+                    kernel_break = new_kernel_break;
+                }
+                check_app_memory(&config, (start, size), app_break, kernel_break);
+            }
+        }
+    }
+
+    #[test]
+    fn test_regions_do_not_overlap() {
+        let mut rng = Rng(0x9e37_79b9);
+        for _ in 0..ITERATIONS {
+            let mut config = CortexMConfig::default();
+
+            // Flash of an app, which is aligned to its size.
+            let flash_size = 512 << rng.below(6);
+            let flash_start = 0x0004_0000 + rng.below(16) * flash_size;
+            let flash = config
+                .allocate_region(
+                    flash_start as *const u8,
+                    flash_size,
+                    flash_size,
+                    mpu::Permissions::ReadExecuteOnly,
+                )
+                .expect("no region for aligned flash");
+            assert_eq!(flash.start_address() as usize, flash_start);
+            assert_eq!(flash.size(), flash_size);
+
+            // Memory of the app.
+            let memory_start = 0x2000_0000 + rng.below(0x1_0000) * 4;
+            let (start, size) = config
+                .allocate_app_memory_region(
+                    memory_start as *const u8,
+                    0x4_0000,
+                    rng.below(0x4000),
+                    3 * 1024,
+                    rng.below(0x400) * 4 + 4,
+                    mpu::Permissions::ReadWriteOnly,
+                )
+                .expect("no app memory region");
+            let start = start as usize;
+            // Memory that is already covered by a region is not allocated
+            // again.
+            assert!(config
+                .allocate_region(
+                    start as *const u8,
+                    size,
+                    32,
+                    mpu::Permissions::ReadWriteOnly
+                )
+                .is_none());
+
+            // Further regions, for example for IPC, at unaligned addresses.
+            let mut regions = [(flash_start, flash_size, mpu::Permissions::ReadExecuteOnly); 8];
+            let mut num_regions = 1;
+            for _ in 0..6 {
+                let unallocated_start = 0x3000_0000 + rng.below(0x1_0000) * 4;
+                let unallocated_size = rng.below(0x4000) + 1;
+                let min_size = rng.below(unallocated_size) + 1;
+                let permissions = PERMISSIONS[rng.below(PERMISSIONS.len())];
+                if let Some(region) = config.allocate_region(
+                    unallocated_start as *const u8,
+                    unallocated_size,
+                    min_size,
+                    permissions,
+                ) {
+                    let region_start = region.start_address() as usize;
+                    assert!(region_start >= unallocated_start);
+                    assert!(region.size() >= min_size);
+                    assert!(region_start + region.size() <= unallocated_start + unallocated_size);
+                    regions[num_regions] = (region_start, region.size(), permissions);
+                    num_regions += 1;
+                }
+            }
+
+            for &(region_start, region_size, permissions) in &regions[..num_regions] {
+                check_range(
+                    &config,
+                    region_start,
+                    region_start + region_size,
+                    expected_access(permissions),
+                );
+            }
+            check_range(&config, start + size - 4, start + size, NO_ACCESS);
+
+            let enabled: [Option<(usize, usize, Access)>; 8] = [
+                decode(&config.regions[0]),
+                decode(&config.regions[1]),
+                decode(&config.regions[2]),
+                decode(&config.regions[3]),
+                decode(&config.regions[4]),
+                decode(&config.regions[5]),
+                decode(&config.regions[6]),
+                decode(&config.regions[7]),
+            ];
+            for (i, a) in enabled.iter().enumerate() {
+                for b in enabled[i + 1..].iter() {
+                    if let (Some(a), Some(b)) = (a, b) {
+                        assert!(a.1 <= b.0 || b.1 <= a.0, "{:x?} overlaps {:x?}", a, b);
+                    }
+                }
+            }
+        }
+    }
+}
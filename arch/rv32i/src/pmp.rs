@@ -182,6 +182,162 @@ impl PMPConfig {
         }
         None
     }
+
+    /// Compute a region that covers at least `min_region_size` bytes of the
+    /// unallocated memory, and add it to the configuration. This only changes
+    /// the configuration, `configure_mpu()` writes it to the PMP.
+    fn allocate_region(
+        &mut self,
+        unallocated_memory_start: *const u8,
+        unallocated_memory_size: usize,
+        min_region_size: usize,
+        permissions: mpu::Permissions,
+    ) -> Option<mpu::Region> {
+        for region in self.regions.iter() {
+            if region.overlaps(unallocated_memory_start, unallocated_memory_size) {
+                return None;
+            }
+        }
+
+        let region_num = self.unused_region_number()?;
+
+        // Logical region
+        let mut start = unallocated_memory_start as usize;
+        let mut size = min_region_size;
+
+        // Region start always has to align to 4 bytes
+        if start % 4 != 0 {
+            start += 4 - (start % 4);
+        }
+
+        // Region size always has to align to 4 bytes
+        if size % 4 != 0 {
+            size += 4 - (size % 4);
+        }
+
+        // Regions must be at least 8 bytes
+        if size < 8 {
+            size = 8;
+        }
+
+        // Check that our logical region fits in memory.
+        if start + size > (unallocated_memory_start as usize) + unallocated_memory_size {
+            return None;
+        }
+
+        let region = PMPRegion::new(start as *const u8, size, permissions);
+
+        self.regions[region_num] = region;
+        self.is_dirty.set(true);
+
+        Some(mpu::Region::new(start as *const u8, size))
+    }
+
+    /// Compute the region that covers the memory of a process, and add it to
+    /// the configuration. Only the app-owned memory at the start of the
+    /// region is accessible to the process.
+    fn allocate_app_memory_region(
+        &mut self,
+        unallocated_memory_start: *const u8,
+        unallocated_memory_size: usize,
+        min_memory_size: usize,
+        initial_app_memory_size: usize,
+        initial_kernel_memory_size: usize,
+        permissions: mpu::Permissions,
+    ) -> Option<(*const u8, usize)> {
+        // Check that no previously allocated regions overlap the unallocated memory.
+        for region in self.regions.iter() {
+            if region.overlaps(unallocated_memory_start, unallocated_memory_size) {
+                return None;
+            }
+        }
+
+        // Make sure there is enough memory for app memory and kernel memory.
+        let memory_size = cmp::max(
+            min_memory_size,
+            initial_app_memory_size + initial_kernel_memory_size,
+        );
+
+        let mut region_size = memory_size;
+
+        // Region size always has to align to 4 bytes
+        if region_size % 4 != 0 {
+            region_size += 4 - (region_size % 4);
+        }
+
+        // The region should start as close as possible to the start of the unallocated memory,
+        // aligned to 4 bytes.
+        let mut region_start = unallocated_memory_start as usize;
+        if region_start % 4 != 0 {
+            region_start += 4 - (region_start % 4);
+        }
+
+        // Make sure the region fits in the unallocated memory.
+        if region_start + region_size
+            > (unallocated_memory_start as usize) + unallocated_memory_size
+        {
+            return None;
+        }
+
+        // The PMP region only covers the app-owned memory at the start of the process memory
+        // block, the kernel-owned memory at its end stays inaccessible to the process.
+        let mut app_memory_size = initial_app_memory_size;
+        if app_memory_size % 4 != 0 {
+            app_memory_size += 4 - (app_memory_size % 4);
+        }
+        if app_memory_size > region_size - initial_kernel_memory_size {
+            return None;
+        }
+
+        let region = PMPRegion::new(region_start as *const u8, app_memory_size, permissions);
+
+        self.regions[APP_MEMORY_REGION_NUM] = region;
+        self.is_dirty.set(true);
+
+        Some((region_start as *const u8, region_size))
+    }
+
+    /// Recompute the region that covers the memory of a process after its
+    /// app break or kernel break moved.
+    fn update_app_memory_region(
+        &mut self,
+        app_memory_break: *const u8,
+        kernel_memory_break: *const u8,
+        permissions: mpu::Permissions,
+    ) -> Result<(), ()> {
+        let region_start = match self.regions[APP_MEMORY_REGION_NUM].location() {
+            Some((start, _)) => start as usize,
+            None => {
+                // Error: Process tried to update app memory MPU region before it was created.
+                return Err(());
+            }
+        };
+
+        let app_memory_break = app_memory_break as usize;
+        let kernel_memory_break = kernel_memory_break as usize;
+
+        // Out of memory
+        if app_memory_break > kernel_memory_break {
+            return Err(());
+        }
+
+        // The region ends at the app break, rounded up to 4 bytes. If that reaches into kernel
+        // memory, we fail.
+        let mut app_memory_size = app_memory_break - region_start;
+        if app_memory_size % 4 != 0 {
+            app_memory_size += 4 - (app_memory_size % 4);
+        }
+        if region_start + app_memory_size > kernel_memory_break {
+            return Err(());
+        }
+
+        let region = PMPRegion::new(region_start as *const u8, app_memory_size, permissions);
+
+        self.regions[APP_MEMORY_REGION_NUM] = region;
+        self.is_dirty.set(true);
+
+        Ok(())
+    }
 }
 
 impl kernel::mpu::MPU for PMPConfig {
@@ -381,42 +537,12 @@ impl kernel::mpu::MPU for PMPConfig {
         permissions: mpu::Permissions,
         config: &mut Self::MpuConfig,
     ) -> Option<mpu::Region> {
-        for region in config.regions.iter() {
-            if region.overlaps(unallocated_memory_start, unallocated_memory_size) {
-                return None;
-            }
-        }
-
-        let region_num = config.unused_region_number()?;
-
-        // Logical region
-        let mut start = unallocated_memory_start as usize;
-        let mut size = min_region_size;
-
-        // Region start always has to align to 4 bytes
-        if start % 4 != 0 {
-            start += 4 - (start % 4);
-        }
-
-        // RISC-V PMP is not inclusive of the final address, while Tock is, increase the size by 1
-        size += 1;
-
-        // Region size always has to align to 4 bytes
-        if size % 4 != 0 {
-            size += 4 - (size % 4);
-        }
-
-        // Regions must be at least 8 bytes
-        if size < 8 {
-            size = 8;
-        }
-
-        let region = PMPRegion::new(start as *const u8, size, permissions);
-
-        config.regions[region_num] = region;
-        config.is_dirty.set(true);
-
-        Some(mpu::Region::new(start as *const u8, size))
+        config.allocate_region(
+            unallocated_memory_start,
+            unallocated_memory_size,
+            min_region_size,
+            permissions,
+        )
     }
 
     fn allocate_app_memory_region(
@@ -429,43 +555,14 @@ impl kernel::mpu::MPU for PMPConfig {
         permissions: mpu::Permissions,
         config: &mut Self::MpuConfig,
     ) -> Option<(*const u8, usize)> {
-        // Check that no previously allocated regions overlap the unallocated memory.
-        for region in config.regions.iter() {
-            if region.overlaps(unallocated_memory_start, unallocated_memory_size) {
-                return None;
-            }
-        }
-
-        // Make sure there is enough memory for app memory and kernel memory.
-        let memory_size = cmp::max(
+        config.allocate_app_memory_region(
+            unallocated_memory_start,
+            unallocated_memory_size,
             min_memory_size,
-            initial_app_memory_size + initial_kernel_memory_size,
-        );
-
-        // RISC-V PMP is not inclusive of the final address, while Tock is, increase the memory_size by 1
-        let mut region_size = memory_size as usize + 1;
-
-        // Region size always has to align to 4 bytes
-        if region_size % 4 != 0 {
-            region_size += 4 - (region_size % 4);
-        }
-
-        // The region should start as close as possible to the start of the unallocated memory.
-        let region_start = unallocated_memory_start as usize;
-
-        // Make sure the region fits in the unallocated memory.
-        if region_start + region_size
-            > (unallocated_memory_start as usize) + unallocated_memory_size
-        {
-            return None;
-        }
-
-        let region = PMPRegion::new(region_start as *const u8, region_size, permissions);
-
-        config.regions[APP_MEMORY_REGION_NUM] = region;
-        config.is_dirty.set(true);
-
-        Some((region_start as *const u8, region_size))
+            initial_app_memory_size,
+            initial_kernel_memory_size,
+            permissions,
+        )
     }
 
     fn update_app_memory_region(
@@ -475,28 +572,7 @@ impl kernel::mpu::MPU for PMPConfig {
         permissions: mpu::Permissions,
         config: &mut Self::MpuConfig,
     ) -> Result<(), ()> {
-        let (region_start, region_size) = match config.regions[APP_MEMORY_REGION_NUM].location() {
-            Some((start, size)) => (start as usize, size),
-            None => {
-                // Error: Process tried to update app memory MPU region before it was created.
-                return Err(());
-            }
-        };
-
-        let app_memory_break = app_memory_break as usize;
-        let kernel_memory_break = kernel_memory_break as usize;
-
-        // Out of memory
-        if app_memory_break > kernel_memory_break {
-            return Err(());
-        }
-
-        let region = PMPRegion::new(region_start as *const u8, region_size, permissions);
-
-        config.regions[APP_MEMORY_REGION_NUM] = region;
-        config.is_dirty.set(true);
-
-        Ok(())
+        config.update_app_memory_region(app_memory_break, kernel_memory_break, permissions)
     }
 
     fn configure_mpu(&self, config: &Self::MpuConfig, app_id: &AppId) {
@@ -652,3 +728,283 @@ impl kernel::mpu::MPU for PMPConfig {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    //! Property tests of the region computation for random memory layouts.
+    //! The regions are decoded from the TOR entries that `configure_mpu()`
+    //! writes, as the hardware would decode them.
+
+    use super::*;
+
+    const ITERATIONS: usize = 500;
+
+    /// Xorshift, so that failures can be reproduced.
+    struct Rng(u32);
+
+    impl Rng {
+        fn below(&mut self, bound: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 17;
+            self.0 ^= self.0 << 5;
+            self.0 as usize % bound
+        }
+    }
+
+    /// What user mode code may do with a byte of memory.
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    struct Access {
+        read: bool,
+        write: bool,
+        execute: bool,
+    }
+
+    const NO_ACCESS: Access = Access {
+        read: false,
+        write: false,
+        execute: false,
+    };
+
+    const PERMISSIONS: [mpu::Permissions; 5] = [
+        mpu::Permissions::ReadWriteExecute,
+        mpu::Permissions::ReadWriteOnly,
+        mpu::Permissions::ReadExecuteOnly,
+        mpu::Permissions::ReadOnly,
+        mpu::Permissions::ExecuteOnly,
+    ];
+
+    fn expected_access(permissions: mpu::Permissions) -> Access {
+        let (read, write, execute) = match permissions {
+            mpu::Permissions::ReadWriteExecute => (true, true, true),
+            mpu::Permissions::ReadWriteOnly => (true, true, false),
+            mpu::Permissions::ReadExecuteOnly => (true, false, true),
+            mpu::Permissions::ReadOnly => (true, false, false),
+            mpu::Permissions::ExecuteOnly => (false, false, true),
+        };
+        Access {
+            read: read,
+            write: write,
+            execute: execute,
+        }
+    }
+
+    /// The used regions in the order `configure_mpu()` writes them to the
+    /// PMP, as the address range and access of the odd TOR entry.
+    fn decode(config: &PMPConfig) -> [Option<(usize, usize, Access)>; 8] {
+        let mut regions_sorted = config.regions.clone();
+        regions_sorted.sort_unstable_by_key(|region| match region.location() {
+            Some((start, _)) => start as usize,
+            None => 0xFFFF_FFFF,
+        });
+        let mut entries = [None; 8];
+        for (entry, region) in entries
+            .iter_mut()
+            .zip(regions_sorted.iter())
+            .take(config.total_regions)
+        {
+            *entry = region.location().map(|(start, size)| {
+                let cfg = region.cfg.value;
+                assert_eq!((cfg >> 3) & 0b11, 1, "region is not TOR");
+                (
+                    (start as usize >> 2) << 2,
+                    ((start as usize + size) >> 2) << 2,
+                    Access {
+                        read: cfg & 0b001 != 0,
+                        write: cfg & 0b010 != 0,
+                        execute: cfg & 0b100 != 0,
+                    },
+                )
+            });
+        }
+        entries
+    }
+
+    /// What user mode code may do at `address`. The first matching entry
+    /// decides, and the even entries below each region give no access.
+    fn access(config: &PMPConfig, address: usize) -> Access {
+        for entry in decode(config).iter() {
+            if let Some((start, end, access)) = *entry {
+                if address < start {
+                    return NO_ACCESS;
+                } else if address < end {
+                    return access;
+                }
+            }
+        }
+        NO_ACCESS
+    }
+
+    /// Check the access at every word in `[start, end)`.
+    fn check_range(config: &PMPConfig, start: usize, end: usize, expected: Access) {
+        if start >= end {
+            return;
+        }
+        for address in [start, end - 1]
+            .iter()
+            .cloned()
+            .chain(((start / 4 + 1) * 4..end).step_by(4))
+        {
+            assert_eq!(
+                access(config, address),
+                expected,
+                "at {:#x} in [{:#x}, {:#x})",
+                address,
+                start,
+                end
+            );
+        }
+    }
+
+    /// Check that the process can use its memory up to the app break, and
+    /// cannot touch the memory from the kernel break on.
+    fn check_app_memory(
+        config: &PMPConfig,
+        (start, size): (usize, usize),
+        app_break: usize,
+        kernel_break: usize,
+    ) {
+        check_range(
+            config,
+            start,
+            app_break,
+            expected_access(mpu::Permissions::ReadWriteOnly),
+        );
+        check_range(config, kernel_break, start + size, NO_ACCESS);
+        check_range(config, start + size, start + size + 4, NO_ACCESS);
+    }
+
+    #[test]
+    fn test_app_memory_region_protects_kernel_memory() {
+        let mut rng = Rng(0x2545_f491);
+        for _ in 0..ITERATIONS {
+            let mut config = PMPConfig::default();
+            let memory_start = 0x8000_0000 + rng.below(0x1_0000) * 4;
+            let memory_size = 0x4_0000;
+            let min_memory_size = rng.below(0x4000);
+            let initial_app_memory_size = rng.below(0x2000);
+            let initial_kernel_memory_size = rng.below(0x400) * 4;
+
+            let (start, size) = config
+                .allocate_app_memory_region(
+                    memory_start as *const u8,
+                    memory_size,
+                    min_memory_size,
+                    initial_app_memory_size,
+                    initial_kernel_memory_size,
+                    mpu::Permissions::ReadWriteOnly,
+                )
+                .expect("no app memory region");
+            let start = start as usize;
+            assert!(start >= memory_start && start + size <= memory_start + memory_size);
+            assert!(size >= min_memory_size);
+            assert!(size >= initial_app_memory_size + initial_kernel_memory_size);
+
+            let mut app_break = start + initial_app_memory_size;
+            let mut kernel_break = start + size - initial_kernel_memory_size;
+            check_app_memory(&config, (start, size), app_break, kernel_break);
+
+            // Move the breaks as the process calls brk and the kernel
+            // allocates grants. Failing to cover the new app break must leave
+            // the previous configuration in place.
+            for _ in 0..4 {
+                let new_app_break = start + rng.below(size + 1);
+                let new_kernel_break = new_app_break + rng.below(start + size - new_app_break + 1);
+                if config
+                    .update_app_memory_region(
+                        new_app_break as *const u8,
+                        new_kernel_break as *const u8,
+                        mpu::Permissions::ReadWriteOnly,
+                    )
+                    .is_ok()
+                {
+                    app_break = new_app_break;
+                    kernel_break = new_kernel_break;
+                }
+                check_app_memory(&config, (start, size), app_break, kernel_break);
+            }
+        }
+    }
+
+    #[test]
+    fn test_regions_do_not_overlap() {
+        let mut rng = Rng(0x9e37_79b9);
+        for _ in 0..ITERATIONS {
+            let mut config = PMPConfig::default();
+
+            // Flash of an app, which is word aligned.
+            let flash_start = 0x2040_0000 + rng.below(0x1_0000) * 4;
+            let flash_size = rng.below(0x2000) * 4 + 8;
+            let flash = config
+                .allocate_region(
+                    flash_start as *const u8,
+                    flash_size,
+                    flash_size,
+                    mpu::Permissions::ReadExecuteOnly,
+                )
+                .expect("no region for flash");
+            assert_eq!(flash.start_address() as usize, flash_start);
+            assert_eq!(flash.size(), flash_size);
+
+            // Memory of the app.
+            let memory_start = 0x8000_0000 + rng.below(0x1_0000) * 4;
+            let (start, size) = config
+                .allocate_app_memory_region(
+                    memory_start as *const u8,
+                    0x4_0000,
+                    rng.below(0x4000),
+                    3 * 1024,
+                    rng.below(0x400) * 4 + 4,
+                    mpu::Permissions::ReadWriteOnly,
+                )
+                .expect("no app memory region");
+            let start = start as usize;
+            // Memory that is already covered by a region is not allocated
+            // again.
+            assert!(config
+                .allocate_region(start as *const u8, size, 4, mpu::Permissions::ReadWriteOnly)
+                .is_none());
+
+            // Further regions, for example for IPC, at unaligned addresses.
+            let mut regions = [(flash_start, flash_size, mpu::Permissions::ReadExecuteOnly); 8];
+            let mut num_regions = 1;
+            for _ in 0..6 {
+                let unallocated_start = 0x9000_0000 + rng.below(0x1_0000) * 4 + rng.below(4);
+                let unallocated_size = rng.below(0x4000) + 1;
+                let min_size = rng.below(unallocated_size) + 1;
+                let permissions = PERMISSIONS[rng.below(PERMISSIONS.len())];
+                if let Some(region) = config.allocate_region(
+                    unallocated_start as *const u8,
+                    unallocated_size,
+                    min_size,
+                    permissions,
+                ) {
+                    let region_start = region.start_address() as usize;
+                    assert!(region_start >= unallocated_start);
+                    assert!(region.size() >= min_size);
+                    assert!(region_start + region.size() <= unallocated_start + unallocated_size);
+                    regions[num_regions] = (region_start, region.size(), permissions);
+                    num_regions += 1;
+                }
+            }
+
+            for &(region_start, region_size, permissions) in &regions[..num_regions] {
+                check_range(
+                    &config,
+                    region_start,
+                    region_start + region_size,
+                    expected_access(permissions),
+                );
+            }
+            check_range(&config, start + size - 4, start + size, NO_ACCESS);
+
+            let entries = decode(&config);
+            for (i, a) in entries.iter().enumerate() {
+                for b in entries[i + 1..].iter() {
+                    if let (Some(a), Some(b)) = (a, b) {
+                        assert!(a.1 <= b.0 || b.1 <= a.0, "{:x?} overlaps {:x?}", a, b);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -428,4 +428,23 @@ impl kernel::syscall::UserspaceKernelBoundary for SysCall {
             stack_pointer as usize,
         ));
     }
+
+    unsafe fn store_registers(
+        &self,
+        _stack_pointer: *const usize,
+        state: &RiscvimacStoredState,
+        registers: &mut [usize],
+    ) -> usize {
+        // x0 is always zero and x1-x31 are in the stored state, followed by
+        // the pc.
+        let count = core::cmp::min(33, registers.len());
+        for (index, register) in registers[..count].iter_mut().enumerate() {
+            *register = match index {
+                0 => 0,
+                32 => state.pc,
+                _ => state.regs[index - 1],
+            };
+        }
+        count
+    }
 }
@@ -98,6 +98,7 @@ impl kernel::Platform for Platform {
             capsules::ambient_light::DRIVER_NUM => f(Some(self.light)),
             capsules::buzzer_driver::DRIVER_NUM => f(Some(self.buzzer)),
             kernel::ipc::DRIVER_NUM => f(Some(&self.ipc)),
+            kernel::ipc::MAILBOX_DRIVER_NUM => f(Some(self.ipc.mailbox())),
             _ => f(None),
         }
     }
@@ -109,6 +110,10 @@ pub unsafe fn reset_handler() {
     // Loads relocations and clears BSS
     nrf52832::init();
 
+    // Paint the unused kernel stack, to measure how much of it the kernel
+    // uses.
+    kernel::kernel_stack::paint(STACK_MEMORY.as_mut_ptr(), STACK_MEMORY.len());
+
     // Create capabilities that the board needs to call certain protected kernel
     // functions.
     let process_management_capability =
@@ -116,7 +121,7 @@ pub unsafe fn reset_handler() {
     let main_loop_capability = create_capability!(capabilities::MainLoopCapability);
     let memory_allocation_capability = create_capability!(capabilities::MemoryAllocationCapability);
 
-    let board_kernel = static_init!(kernel::Kernel, kernel::Kernel::new(&PROCESSES));
+    let board_kernel = static_init!(kernel::Kernel, kernel::Kernel::new(&mut PROCESSES));
 
     let dynamic_deferred_call_clients =
         static_init!([DynamicDeferredCallClientState; 2], Default::default());
@@ -490,7 +495,6 @@ pub unsafe fn reset_handler() {
             &_eapps as *const u8 as usize - &_sapps as *const u8 as usize,
         ),
         &mut APP_MEMORY,
-        &mut PROCESSES,
         FAULT_RESPONSE,
         &process_management_capability,
     )
@@ -499,5 +503,15 @@ pub unsafe fn reset_handler() {
         debug!("{:?}", err);
     });
 
-    board_kernel.kernel_loop(&platform, chip, Some(&platform.ipc), &main_loop_capability);
+    // Let the chip sleep more deeply when the next alarm is far away.
+    board_kernel.set_alarm_deadline(&nrf52832::rtc::RTC, &main_loop_capability);
+
+    let scheduler = components::round_robin::RoundRobinComponent::new().finalize(());
+    board_kernel.kernel_loop(
+        &platform,
+        chip,
+        Some(&platform.ipc),
+        scheduler,
+        &main_loop_capability,
+    );
 }
@@ -71,6 +71,7 @@ impl Platform for ArtyE21 {
             capsules::button::DRIVER_NUM => f(Some(self.button)),
 
             // kernel::ipc::DRIVER_NUM => f(Some(&self.ipc)),
+            // kernel::ipc::MAILBOX_DRIVER_NUM => f(Some(self.ipc.mailbox())),
             _ => f(None),
         }
     }
@@ -85,6 +86,10 @@ pub unsafe fn reset_handler() {
     // Basic setup of the platform.
     rv32i::init_memory();
 
+    // Paint the unused kernel stack, to measure how much of it the kernel
+    // uses.
+    kernel::kernel_stack::paint(STACK_MEMORY.as_mut_ptr(), STACK_MEMORY.len());
+
     let chip = static_init!(
         arty_e21_chip::chip::ArtyExx,
         arty_e21_chip::chip::ArtyExx::new()
@@ -95,7 +100,7 @@ pub unsafe fn reset_handler() {
     let process_mgmt_cap = create_capability!(capabilities::ProcessManagementCapability);
     let main_loop_cap = create_capability!(capabilities::MainLoopCapability);
 
-    let board_kernel = static_init!(kernel::Kernel, kernel::Kernel::new(&PROCESSES));
+    let board_kernel = static_init!(kernel::Kernel, kernel::Kernel::new(&mut PROCESSES));
 
     let dynamic_deferred_call_clients =
         static_init!([DynamicDeferredCallClientState; 2], Default::default());
@@ -238,7 +243,6 @@ pub unsafe fn reset_handler() {
             &_eapps as *const u8 as usize - &_sapps as *const u8 as usize,
         ),
         &mut APP_MEMORY,
-        &mut PROCESSES,
         FAULT_RESPONSE,
         &process_mgmt_cap,
     )
@@ -247,5 +251,6 @@ pub unsafe fn reset_handler() {
         debug!("{:?}", err);
     });
 
-    board_kernel.kernel_loop(&artye21, chip, None, &main_loop_cap);
+    let scheduler = components::round_robin::RoundRobinComponent::new().finalize(());
+    board_kernel.kernel_loop(&artye21, chip, None, scheduler, &main_loop_cap);
 }
@@ -71,6 +71,7 @@ impl kernel::Platform for EduCiaaNXP {
             capsules::console::DRIVER_NUM => f(Some(self.console)),
             capsules::gpio::DRIVER_NUM => f(Some(self.gpio)),
             kernel::ipc::DRIVER_NUM => f(Some(&self.ipc)),
+            kernel::ipc::MAILBOX_DRIVER_NUM => f(Some(self.ipc.mailbox())),
             capsules::led::DRIVER_NUM => f(Some(self.led)),
             _ => f(None),
         }
@@ -87,13 +88,18 @@ impl kernel::Platform for EduCiaaNXP {
 #[inline(never)]
 pub unsafe fn reset_handler() {
     lpc43xx::init();
+
+    // Paint the unused kernel stack, to measure how much of it the kernel
+    // uses.
+    kernel::kernel_stack::paint(STACK_MEMORY.as_mut_ptr(), STACK_MEMORY.len());
+
     lpc43xx::creg::set_flash_acceleration(lpc43xx::creg::FLASHCFG::FLASHTIM::_10_BASE_M4_CLK_CLOCK);
     lpc43xx::cgu::board_setup_clocking(lpc43xx::cgu::BASE_CLK::CLK_SEL::CrystalOscillator, lpc43xx::cgu::MAX_CLOCK_FREQ, true);
     lpc43xx::creg::enable_32khz_1khz_osc();
     lpc43xx::creg::enable_creg6_rmii_mode();
     //lpc43xx::eventrouter::event_router_init();
     lpc43xx::ritimer::disable_rit(); //TODO find why this is enabled at all
-    let board_kernel = static_init!(kernel::Kernel, kernel::Kernel::new(&PROCESSES));
+    let board_kernel = static_init!(kernel::Kernel, kernel::Kernel::new(&mut PROCESSES));
     // GPIO
     let gpio = components::gpio::GpioComponent::new(
         board_kernel,
@@ -251,7 +257,6 @@ pub unsafe fn reset_handler() {
             &_eapps as *const u8 as usize - &_sapps as *const u8 as usize,
         ),
         &mut APP_MEMORY,
-        &mut PROCESSES,
         FAULT_RESPONSE,
         &process_management_capability,
     ).unwrap_or_else(|err| {
@@ -260,10 +265,12 @@ pub unsafe fn reset_handler() {
     });
 
 
+    let scheduler = components::round_robin::RoundRobinComponent::new().finalize(());
     board_kernel.kernel_loop(
         &platform,
         chip,
         Some(&platform.ipc),
+        scheduler,
         &main_loop_capability,
     );
 }
@@ -0,0 +1,130 @@
+//! Component for loading apps over a UART at runtime.
+//!
+//! This provides one Component, AppLoaderComponent, which creates the kernel's
+//! `ProcessLoader` and an `AppLoader` capsule that receives apps on a UART and
+//! writes them to unused app flash. The UART should not be shared with the
+//! console, and `app_memory` is memory set aside for processes loaded at
+//! runtime that is not also passed to `load_processes()`. The loader needs one
+//! `LoadedProcessMemory` for each slot in the processes array.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let app_loader = components::app_loader::AppLoaderComponent::new(
+//!     board_kernel,
+//!     chip,
+//!     loader_uart_mux,
+//!     nv_to_page,
+//!     core::slice::from_raw_parts(
+//!         &_sapps as *const u8,
+//!         &_eapps as *const u8 as usize - &_sapps as *const u8 as usize,
+//!     ),
+//!     &mut DYNAMIC_APP_MEMORY,
+//!     FAULT_RESPONSE,
+//! )
+//! .finalize(components::app_loader_component_helper!(sam4l::chip::Sam4l, NUM_PROCS));
+//! app_loader.start();
+//! ```
+
+use core::mem::MaybeUninit;
+
+use capsules::app_loader::AppLoader;
+use capsules::virtual_uart::{MuxUart, UartDevice};
+use kernel::capabilities;
+use kernel::component::Component;
+use kernel::create_capability;
+use kernel::hil;
+use kernel::procs::{FaultResponse, LoadedProcessMemory, ProcessLoader};
+use kernel::Chip;
+use kernel::{static_init, static_init_half};
+
+// Setup static space for the objects.
+#[macro_export]
+macro_rules! app_loader_component_helper {
+    ($C:ty, $N:expr) => {{
+        use core::mem::MaybeUninit;
+        use kernel::procs::{LoadedProcessMemory, ProcessLoader};
+        static mut BUF: MaybeUninit<ProcessLoader<$C>> = MaybeUninit::uninit();
+        let process_memory = kernel::static_init!([LoadedProcessMemory; $N], Default::default());
+        (&mut BUF, &process_memory[..])
+    };};
+}
+
+pub struct AppLoaderComponent<C: 'static + Chip> {
+    board_kernel: &'static kernel::Kernel,
+    chip: &'static C,
+    uart_mux: &'static MuxUart<'static>,
+    storage: &'static dyn hil::nonvolatile_storage::NonvolatileStorage<'static>,
+    app_flash: &'static [u8],
+    app_memory: &'static mut [u8],
+    fault_response: FaultResponse,
+}
+
+impl<C: 'static + Chip> AppLoaderComponent<C> {
+    pub fn new(
+        board_kernel: &'static kernel::Kernel,
+        chip: &'static C,
+        uart_mux: &'static MuxUart<'static>,
+        storage: &'static dyn hil::nonvolatile_storage::NonvolatileStorage<'static>,
+        app_flash: &'static [u8],
+        app_memory: &'static mut [u8],
+        fault_response: FaultResponse,
+    ) -> AppLoaderComponent<C> {
+        AppLoaderComponent {
+            board_kernel: board_kernel,
+            chip: chip,
+            uart_mux: uart_mux,
+            storage: storage,
+            app_flash: app_flash,
+            app_memory: app_memory,
+            fault_response: fault_response,
+        }
+    }
+}
+
+impl<C: 'static + Chip> Component for AppLoaderComponent<C> {
+    type StaticInput = (
+        &'static mut MaybeUninit<ProcessLoader<C>>,
+        &'static [LoadedProcessMemory],
+    );
+    type Output = &'static AppLoader<'static>;
+
+    unsafe fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let process_mgmt_cap = create_capability!(capabilities::ProcessManagementCapability);
+
+        let process_loader = static_init_half!(
+            static_buffer.0,
+            ProcessLoader<C>,
+            ProcessLoader::new(
+                self.board_kernel,
+                self.chip,
+                self.app_flash,
+                self.app_memory,
+                static_buffer.1,
+                self.fault_response,
+                &process_mgmt_cap,
+            )
+        );
+
+        let loader_uart = static_init!(UartDevice, UartDevice::new(self.uart_mux, true));
+        loader_uart.setup();
+
+        let app_loader = static_init!(
+            AppLoader<'static>,
+            AppLoader::new(
+                loader_uart,
+                self.storage,
+                process_loader,
+                &mut capsules::app_loader::TX_BUF,
+                &mut capsules::app_loader::RX_BUF,
+                &mut capsules::app_loader::HEADER_BUF,
+                &mut capsules::app_loader::SPARE_BUF,
+            )
+        );
+        hil::uart::Transmit::set_transmit_client(loader_uart, app_loader);
+        hil::uart::Receive::set_receive_client(loader_uart, app_loader);
+        self.storage.set_client(app_loader);
+
+        app_loader
+    }
+}
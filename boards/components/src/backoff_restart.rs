@@ -0,0 +1,109 @@
+//! Component for a restart policy that backs off after repeated faults.
+//!
+//! This provides one Component, BackoffRestartComponent, which creates a
+//! `BackoffRestart` policy for `FaultResponse::Restart`. The policy uses the
+//! provided alarm, which must not be shared with other clients, and needs one
+//! `BackoffRestartState` for each slot in the processes array.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let restart_alarm = static_init!(
+//!     VirtualMuxAlarm<'static, Ast>,
+//!     VirtualMuxAlarm::new(mux_alarm)
+//! );
+//! // Restart after 100 ms, doubling the delay up to 10 s. Stop apps that
+//! // fault 5 times without running for a minute in between.
+//! let restart_policy = components::backoff_restart::BackoffRestartComponent::new(
+//!     board_kernel,
+//!     restart_alarm,
+//!     100,
+//!     10_000,
+//!     60_000,
+//!     5,
+//!     kernel::procs::RestartFallback::Stop,
+//! )
+//! .finalize(components::backoff_restart_component_helper!(
+//!     VirtualMuxAlarm<'static, Ast>,
+//!     NUM_PROCS
+//! ));
+//! let fault_response = kernel::procs::FaultResponse::Restart(restart_policy);
+//! ```
+
+use core::mem::MaybeUninit;
+use kernel::component::Component;
+use kernel::hil::time;
+use kernel::procs::{BackoffRestart, BackoffRestartState, RestartFallback};
+use kernel::static_init_half;
+
+// Setup static space for the objects.
+#[macro_export]
+macro_rules! backoff_restart_component_helper {
+    ($A:ty, $N:expr) => {{
+        use core::mem::MaybeUninit;
+        use kernel::procs::{BackoffRestart, BackoffRestartState};
+        static mut BUF: MaybeUninit<BackoffRestart<'static, $A>> = MaybeUninit::uninit();
+        let states = kernel::static_init!([BackoffRestartState; $N], Default::default());
+        (&mut BUF, &states[..])
+    };};
+}
+
+pub struct BackoffRestartComponent<A: 'static + time::Alarm<'static>> {
+    board_kernel: &'static kernel::Kernel,
+    alarm: &'static A,
+    initial_delay_ms: u32,
+    max_delay_ms: u32,
+    stable_period_ms: u32,
+    max_restarts: usize,
+    fallback: RestartFallback,
+}
+
+impl<A: 'static + time::Alarm<'static>> BackoffRestartComponent<A> {
+    pub fn new(
+        board_kernel: &'static kernel::Kernel,
+        alarm: &'static A,
+        initial_delay_ms: u32,
+        max_delay_ms: u32,
+        stable_period_ms: u32,
+        max_restarts: usize,
+        fallback: RestartFallback,
+    ) -> BackoffRestartComponent<A> {
+        BackoffRestartComponent {
+            board_kernel: board_kernel,
+            alarm: alarm,
+            initial_delay_ms: initial_delay_ms,
+            max_delay_ms: max_delay_ms,
+            stable_period_ms: stable_period_ms,
+            max_restarts: max_restarts,
+            fallback: fallback,
+        }
+    }
+}
+
+impl<A: 'static + time::Alarm<'static>> Component for BackoffRestartComponent<A> {
+    type StaticInput = (
+        &'static mut MaybeUninit<BackoffRestart<'static, A>>,
+        &'static [BackoffRestartState],
+    );
+    type Output = &'static BackoffRestart<'static, A>;
+
+    unsafe fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let policy = static_init_half!(
+            static_buffer.0,
+            BackoffRestart<'static, A>,
+            BackoffRestart::new(
+                self.board_kernel,
+                self.alarm,
+                static_buffer.1,
+                self.initial_delay_ms,
+                self.max_delay_ms,
+                self.stable_period_ms,
+                self.max_restarts,
+                self.fallback,
+            )
+        );
+        self.alarm.set_client(policy);
+
+        policy
+    }
+}
@@ -0,0 +1,32 @@
+//! Component for a cooperative scheduler.
+//!
+//! This provides one Component, CooperativeComponent, which creates a
+//! scheduler that runs processes in the order of the processes array without
+//! ever preempting them with a timeslice.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let scheduler = components::cooperative::CooperativeComponent::new().finalize(());
+//! ```
+
+use kernel::component::Component;
+use kernel::schedulers::CooperativeSched;
+use kernel::static_init;
+
+pub struct CooperativeComponent {}
+
+impl CooperativeComponent {
+    pub fn new() -> CooperativeComponent {
+        CooperativeComponent {}
+    }
+}
+
+impl Component for CooperativeComponent {
+    type StaticInput = ();
+    type Output = &'static CooperativeSched;
+
+    unsafe fn finalize(self, _static_buffer: Self::StaticInput) -> Self::Output {
+        static_init!(CooperativeSched, CooperativeSched::new())
+    }
+}
@@ -0,0 +1,121 @@
+//! Components for dumping processes that fault.
+//!
+//! This provides two Components. CrashDumpUartComponent sends crash dumps of
+//! faulted processes over a UART, which can be shared with the console.
+//! CrashDumpStorageComponent writes them to a region of flash reserved for
+//! them, using a `NonvolatileStorage` that has no other clients. Both register
+//! the `CrashDump` capsule with the kernel.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! components::crash_dump::CrashDumpUartComponent::new(board_kernel, uart_mux).finalize(());
+//!
+//! // Or keep the most recent dump in 2 kB of flash.
+//! components::crash_dump::CrashDumpStorageComponent::new(
+//!     board_kernel,
+//!     nv_to_page,
+//!     0x3f800,
+//!     2048,
+//! )
+//! .finalize(());
+//! ```
+
+use capsules::crash_dump::CrashDump;
+use capsules::virtual_uart::{MuxUart, UartDevice};
+use kernel::capabilities;
+use kernel::component::Component;
+use kernel::create_capability;
+use kernel::hil;
+use kernel::static_init;
+
+pub struct CrashDumpUartComponent {
+    board_kernel: &'static kernel::Kernel,
+    uart_mux: &'static MuxUart<'static>,
+}
+
+impl CrashDumpUartComponent {
+    pub fn new(
+        board_kernel: &'static kernel::Kernel,
+        uart_mux: &'static MuxUart<'static>,
+    ) -> CrashDumpUartComponent {
+        CrashDumpUartComponent {
+            board_kernel: board_kernel,
+            uart_mux: uart_mux,
+        }
+    }
+}
+
+impl Component for CrashDumpUartComponent {
+    type StaticInput = ();
+    type Output = &'static CrashDump<'static>;
+
+    unsafe fn finalize(self, _static_buffer: Self::StaticInput) -> Self::Output {
+        let process_mgmt_cap = create_capability!(capabilities::ProcessManagementCapability);
+
+        let crash_dump_uart = static_init!(UartDevice, UartDevice::new(self.uart_mux, false));
+        crash_dump_uart.setup();
+
+        let crash_dump = static_init!(
+            CrashDump<'static>,
+            CrashDump::new_uart(
+                crash_dump_uart,
+                &mut capsules::crash_dump::DUMP_BUF,
+                &mut capsules::crash_dump::TX_BUF,
+            )
+        );
+        hil::uart::Transmit::set_transmit_client(crash_dump_uart, crash_dump);
+        self.board_kernel
+            .set_crash_dump_client(crash_dump, &process_mgmt_cap);
+
+        crash_dump
+    }
+}
+
+pub struct CrashDumpStorageComponent {
+    board_kernel: &'static kernel::Kernel,
+    storage: &'static dyn hil::nonvolatile_storage::NonvolatileStorage<'static>,
+    address: usize,
+    length: usize,
+}
+
+impl CrashDumpStorageComponent {
+    pub fn new(
+        board_kernel: &'static kernel::Kernel,
+        storage: &'static dyn hil::nonvolatile_storage::NonvolatileStorage<'static>,
+        address: usize,
+        length: usize,
+    ) -> CrashDumpStorageComponent {
+        CrashDumpStorageComponent {
+            board_kernel: board_kernel,
+            storage: storage,
+            address: address,
+            length: length,
+        }
+    }
+}
+
+impl Component for CrashDumpStorageComponent {
+    type StaticInput = ();
+    type Output = &'static CrashDump<'static>;
+
+    unsafe fn finalize(self, _static_buffer: Self::StaticInput) -> Self::Output {
+        let process_mgmt_cap = create_capability!(capabilities::ProcessManagementCapability);
+
+        let crash_dump = static_init!(
+            CrashDump<'static>,
+            CrashDump::new_storage(
+                self.storage,
+                self.address,
+                self.length,
+                &mut capsules::crash_dump::DUMP_BUF,
+                &mut capsules::crash_dump::SPARE_DUMP_BUF,
+            )
+        );
+        self.storage.set_client(crash_dump);
+        self.board_kernel
+            .set_crash_dump_client(crash_dump, &process_mgmt_cap);
+
+        crash_dump
+    }
+}
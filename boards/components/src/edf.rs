@@ -0,0 +1,62 @@
+//! Component for an earliest deadline first scheduler.
+//!
+//! This provides one Component, EDFComponent, which creates an earliest
+//! deadline first scheduler for real-time processes. The scheduler uses the
+//! provided time source to release jobs and track deadlines, and needs one
+//! `EDFProcessState` for each slot in the processes array.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let scheduler = components::edf::EDFComponent::new(board_kernel, mux_alarm_virtual).finalize(
+//!     components::edf_component_helper!(VirtualMuxAlarm<'static, Ast>, NUM_PROCS),
+//! );
+//! ```
+
+use core::mem::MaybeUninit;
+use kernel::component::Component;
+use kernel::hil::time;
+use kernel::schedulers::{EDFProcessState, EDFSched};
+use kernel::static_init_half;
+
+// Setup static space for the objects.
+#[macro_export]
+macro_rules! edf_component_helper {
+    ($T:ty, $N:expr) => {{
+        use core::mem::MaybeUninit;
+        use kernel::schedulers::{EDFProcessState, EDFSched};
+        static mut BUF: MaybeUninit<EDFSched<'static, $T>> = MaybeUninit::uninit();
+        let states = kernel::static_init!([EDFProcessState; $N], Default::default());
+        (&mut BUF, &states[..])
+    };};
+}
+
+pub struct EDFComponent<T: 'static + time::Time> {
+    board_kernel: &'static kernel::Kernel,
+    time: &'static T,
+}
+
+impl<T: 'static + time::Time> EDFComponent<T> {
+    pub fn new(board_kernel: &'static kernel::Kernel, time: &'static T) -> EDFComponent<T> {
+        EDFComponent {
+            board_kernel: board_kernel,
+            time: time,
+        }
+    }
+}
+
+impl<T: 'static + time::Time> Component for EDFComponent<T> {
+    type StaticInput = (
+        &'static mut MaybeUninit<EDFSched<'static, T>>,
+        &'static [EDFProcessState],
+    );
+    type Output = &'static EDFSched<'static, T>;
+
+    unsafe fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        static_init_half!(
+            static_buffer.0,
+            EDFSched<'static, T>,
+            EDFSched::new(self.board_kernel, self.time, static_buffer.1)
+        )
+    }
+}
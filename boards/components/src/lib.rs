@@ -2,11 +2,16 @@
 
 pub mod alarm;
 pub mod analog_comparator;
+pub mod app_loader;
+pub mod backoff_restart;
 pub mod button;
 pub mod console;
+pub mod cooperative;
+pub mod crash_dump;
 pub mod crc;
 pub mod debug_queue;
 pub mod debug_writer;
+pub mod edf;
 pub mod gpio;
 pub mod hd44780;
 pub mod hmac;
@@ -17,14 +22,20 @@ pub mod l3gd20;
 pub mod led;
 pub mod lldb;
 pub mod lsm303dlhc;
+pub mod mlfq;
 pub mod mx25r6435f;
 pub mod ninedof;
 pub mod nonvolatile_storage;
 pub mod nrf51822;
 pub mod panic_button;
+pub mod panic_record;
+pub mod priority;
 pub mod process_console;
 pub mod rng;
+pub mod round_robin;
 pub mod segger_rtt;
+pub mod sha;
 pub mod si7021;
 pub mod spi;
+pub mod syscall_trace;
 pub mod temperature;
@@ -0,0 +1,58 @@
+//! Component for a multilevel feedback queue scheduler.
+//!
+//! This provides one Component, MLFQComponent, which creates a multilevel
+//! feedback queue scheduler. The scheduler uses the provided time source to
+//! periodically boost all processes back to the highest priority queue, and
+//! needs one `MLFQProcessState` for each slot in the processes array.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let scheduler = components::mlfq::MLFQComponent::new(mux_alarm_virtual).finalize(
+//!     components::mlfq_component_helper!(VirtualMuxAlarm<'static, Ast>, NUM_PROCS),
+//! );
+//! ```
+
+use core::mem::MaybeUninit;
+use kernel::component::Component;
+use kernel::hil::time;
+use kernel::schedulers::{MLFQProcessState, MLFQSched};
+use kernel::static_init_half;
+
+// Setup static space for the objects.
+#[macro_export]
+macro_rules! mlfq_component_helper {
+    ($T:ty, $N:expr) => {{
+        use core::mem::MaybeUninit;
+        use kernel::schedulers::{MLFQProcessState, MLFQSched};
+        static mut BUF: MaybeUninit<MLFQSched<'static, $T>> = MaybeUninit::uninit();
+        let states = kernel::static_init!([MLFQProcessState; $N], Default::default());
+        (&mut BUF, &states[..])
+    };};
+}
+
+pub struct MLFQComponent<T: 'static + time::Time> {
+    time: &'static T,
+}
+
+impl<T: 'static + time::Time> MLFQComponent<T> {
+    pub fn new(time: &'static T) -> MLFQComponent<T> {
+        MLFQComponent { time: time }
+    }
+}
+
+impl<T: 'static + time::Time> Component for MLFQComponent<T> {
+    type StaticInput = (
+        &'static mut MaybeUninit<MLFQSched<'static, T>>,
+        &'static [MLFQProcessState],
+    );
+    type Output = &'static MLFQSched<'static, T>;
+
+    unsafe fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        static_init_half!(
+            static_buffer.0,
+            MLFQSched<'static, T>,
+            MLFQSched::new(self.time, static_buffer.1)
+        )
+    }
+}
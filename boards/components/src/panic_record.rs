@@ -0,0 +1,43 @@
+//! Component for the panic record syscall driver.
+//!
+//! This provides one Component, PanicRecordComponent, which gives processes
+//! access to the record of the kernel panic before the last reset.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let panic_record = components::panic_record::PanicRecordComponent::new(board_kernel)
+//!     .finalize(());
+//! ```
+
+use capsules::panic_record::PanicRecordDriver;
+use kernel::capabilities;
+use kernel::component::Component;
+use kernel::create_capability;
+use kernel::static_init;
+
+pub struct PanicRecordComponent {
+    board_kernel: &'static kernel::Kernel,
+}
+
+impl PanicRecordComponent {
+    pub fn new(board_kernel: &'static kernel::Kernel) -> PanicRecordComponent {
+        PanicRecordComponent {
+            board_kernel: board_kernel,
+        }
+    }
+}
+
+impl Component for PanicRecordComponent {
+    type StaticInput = ();
+    type Output = &'static PanicRecordDriver;
+
+    unsafe fn finalize(self, _static_buffer: Self::StaticInput) -> Self::Output {
+        let grant_cap = create_capability!(capabilities::MemoryAllocationCapability);
+
+        static_init!(
+            PanicRecordDriver,
+            PanicRecordDriver::new(self.board_kernel.create_grant(&grant_cap))
+        )
+    }
+}
@@ -0,0 +1,36 @@
+//! Component for a fixed priority scheduler.
+//!
+//! This provides one Component, PriorityComponent, which creates a scheduler
+//! that always runs the highest priority ready process. Processes earlier in
+//! the processes array have a higher priority.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let scheduler = components::priority::PriorityComponent::new(board_kernel).finalize(());
+//! ```
+
+use kernel::component::Component;
+use kernel::schedulers::PrioritySched;
+use kernel::static_init;
+
+pub struct PriorityComponent {
+    board_kernel: &'static kernel::Kernel,
+}
+
+impl PriorityComponent {
+    pub fn new(board_kernel: &'static kernel::Kernel) -> PriorityComponent {
+        PriorityComponent {
+            board_kernel: board_kernel,
+        }
+    }
+}
+
+impl Component for PriorityComponent {
+    type StaticInput = ();
+    type Output = &'static PrioritySched;
+
+    unsafe fn finalize(self, _static_buffer: Self::StaticInput) -> Self::Output {
+        static_init!(PrioritySched, PrioritySched::new(self.board_kernel))
+    }
+}
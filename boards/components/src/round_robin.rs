@@ -0,0 +1,48 @@
+//! Component for a round robin scheduler.
+//!
+//! This provides one Component, RoundRobinComponent, which creates a round
+//! robin scheduler that gives each ready process a fixed timeslice in the
+//! order of the processes array.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let scheduler = components::round_robin::RoundRobinComponent::new().finalize(());
+//! ```
+
+use kernel::component::Component;
+use kernel::schedulers::RoundRobinSched;
+use kernel::static_init;
+
+pub struct RoundRobinComponent {
+    timeslice_us: Option<u32>,
+}
+
+impl RoundRobinComponent {
+    pub fn new() -> RoundRobinComponent {
+        RoundRobinComponent { timeslice_us: None }
+    }
+
+    /// Use a timeslice of `timeslice_us` microseconds instead of the kernel's
+    /// default timeslice.
+    pub fn with_timeslice(timeslice_us: u32) -> RoundRobinComponent {
+        RoundRobinComponent {
+            timeslice_us: Some(timeslice_us),
+        }
+    }
+}
+
+impl Component for RoundRobinComponent {
+    type StaticInput = ();
+    type Output = &'static RoundRobinSched;
+
+    unsafe fn finalize(self, _static_buffer: Self::StaticInput) -> Self::Output {
+        match self.timeslice_us {
+            Some(timeslice_us) => static_init!(
+                RoundRobinSched,
+                RoundRobinSched::new_with_timeslice(timeslice_us)
+            ),
+            None => static_init!(RoundRobinSched, RoundRobinSched::new()),
+        }
+    }
+}
@@ -0,0 +1,73 @@
+//! Component for a software SHA-256 or SHA-512 digest engine.
+//!
+//! This provides one Component, ShaSoftwareComponent, which creates a
+//! `ShaSoftware` digest for boards whose chip has no hash hardware. The result
+//! can be passed to the digest and HMAC mux components.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let sha = components::sha::ShaSoftwareComponent::new(dynamic_deferred_caller).finalize(
+//!     components::sha_software_component_helper!(kernel::crypto::sha2::Sha256),
+//! );
+//!
+//! let mux_hmac = components::hmac::HmacMuxComponent::new(sha).finalize(
+//!     components::hmac_mux_component_helper!(
+//!         capsules::sha::ShaSoftware<'static, kernel::crypto::sha2::Sha256>,
+//!         [u8; 32]
+//!     ),
+//! );
+//! ```
+
+use core::marker::PhantomData;
+use core::mem::MaybeUninit;
+
+use capsules::sha::{ShaSoftware, SoftwareHash};
+use kernel::common::dynamic_deferred_call::DynamicDeferredCall;
+use kernel::component::Component;
+use kernel::static_init_half;
+
+// Setup static space for the objects.
+#[macro_export]
+macro_rules! sha_software_component_helper {
+    ($H:ty) => {{
+        use capsules::sha::ShaSoftware;
+        use core::mem::MaybeUninit;
+        static mut BUF: MaybeUninit<ShaSoftware<'static, $H>> = MaybeUninit::uninit();
+        &mut BUF
+    };};
+}
+
+pub struct ShaSoftwareComponent<H: 'static + SoftwareHash> {
+    deferred_caller: &'static DynamicDeferredCall,
+    phantom: PhantomData<H>,
+}
+
+impl<H: 'static + SoftwareHash> ShaSoftwareComponent<H> {
+    pub fn new(deferred_caller: &'static DynamicDeferredCall) -> ShaSoftwareComponent<H> {
+        ShaSoftwareComponent {
+            deferred_caller: deferred_caller,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<H: 'static + SoftwareHash> Component for ShaSoftwareComponent<H> {
+    type StaticInput = &'static mut MaybeUninit<ShaSoftware<'static, H>>;
+    type Output = &'static ShaSoftware<'static, H>;
+
+    unsafe fn finalize(self, s: Self::StaticInput) -> Self::Output {
+        let sha = static_init_half!(
+            s,
+            ShaSoftware<'static, H>,
+            ShaSoftware::new(self.deferred_caller)
+        );
+        sha.initialize_callback_handle(
+            self.deferred_caller
+                .register(sha)
+                .expect("no deferred call slot available for sha"),
+        );
+
+        sha
+    }
+}
@@ -0,0 +1,60 @@
+//! Component for recording the system calls of processes.
+//!
+//! This provides one Component, SyscallTraceComponent, which creates a
+//! `SyscallTrace` that keeps the most recent system calls of processes with
+//! tracing enabled. The board passes it to the kernel, and tracing is switched
+//! on for processes with the `trace` command of the process console.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! // Keep the last 64 system calls, with timestamps from the AST.
+//! let syscall_trace = components::syscall_trace::SyscallTraceComponent::new(&sam4l::ast::AST)
+//!     .finalize(components::syscall_trace_component_helper!(sam4l::ast::Ast, 64));
+//! board_kernel.set_syscall_tracer(syscall_trace, &process_management_capability);
+//! ```
+
+use core::mem::MaybeUninit;
+use kernel::component::Component;
+use kernel::hil::time;
+use kernel::static_init_half;
+use kernel::syscall_trace::{SyscallTrace, SyscallTraceEntry};
+
+// Setup static space for the objects.
+#[macro_export]
+macro_rules! syscall_trace_component_helper {
+    ($T:ty, $N:expr) => {{
+        use core::mem::MaybeUninit;
+        use kernel::syscall_trace::{SyscallTrace, SyscallTraceEntry};
+        static mut BUF: MaybeUninit<SyscallTrace<'static, $T>> = MaybeUninit::uninit();
+        let entries =
+            kernel::static_init!([SyscallTraceEntry; $N], [SyscallTraceEntry::default(); $N]);
+        (&mut BUF, &mut entries[..])
+    };};
+}
+
+pub struct SyscallTraceComponent<T: 'static + time::Time> {
+    time: &'static T,
+}
+
+impl<T: 'static + time::Time> SyscallTraceComponent<T> {
+    pub fn new(time: &'static T) -> SyscallTraceComponent<T> {
+        SyscallTraceComponent { time: time }
+    }
+}
+
+impl<T: 'static + time::Time> Component for SyscallTraceComponent<T> {
+    type StaticInput = (
+        &'static mut MaybeUninit<SyscallTrace<'static, T>>,
+        &'static mut [SyscallTraceEntry],
+    );
+    type Output = &'static SyscallTrace<'static, T>;
+
+    unsafe fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        static_init_half!(
+            static_buffer.0,
+            SyscallTrace<'static, T>,
+            SyscallTrace::new(self.time, static_buffer.1)
+        )
+    }
+}
@@ -100,6 +100,7 @@ impl Platform for Hail {
             capsules::dac::DRIVER_NUM => f(Some(self.dac)),
 
             kernel::ipc::DRIVER_NUM => f(Some(&self.ipc)),
+            kernel::ipc::MAILBOX_DRIVER_NUM => f(Some(self.ipc.mailbox())),
             _ => f(None),
         }
     }
@@ -177,6 +178,10 @@ unsafe fn set_pin_primary_functions() {
 pub unsafe fn reset_handler() {
     sam4l::init();
 
+    // Paint the unused kernel stack, to measure how much of it the kernel
+    // uses.
+    kernel::kernel_stack::paint(STACK_MEMORY.as_mut_ptr(), STACK_MEMORY.len());
+
     sam4l::pm::PM.setup_system_clock(sam4l::pm::SystemClockSource::PllExternalOscillatorAt48MHz {
         frequency: sam4l::pm::OscillatorFrequency::Frequency16MHz,
         startup_mode: sam4l::pm::OscillatorStartup::SlowStart,
@@ -187,7 +192,7 @@ pub unsafe fn reset_handler() {
 
     set_pin_primary_functions();
 
-    let board_kernel = static_init!(kernel::Kernel, kernel::Kernel::new(&PROCESSES));
+    let board_kernel = static_init!(kernel::Kernel, kernel::Kernel::new(&mut PROCESSES));
 
     // Create capabilities that the board needs to call certain protected kernel
     // functions.
@@ -358,6 +363,15 @@ pub unsafe fn reset_handler() {
     // Setup RNG
     let rng = components::rng::RngComponent::new(board_kernel, &sam4l::trng::TRNG).finalize(());
 
+    // Measure how long the clocks of peripherals that switch them off when
+    // idle are on. The process console's `power` command prints them.
+    let power_manager = static_init!(
+        kernel::power_manager::PowerManager<'static>,
+        kernel::power_manager::PowerManager::new(&sam4l::ast::AST)
+    );
+    power_manager.register(sam4l::trng::TRNG.managed_clock());
+    board_kernel.set_power_manager(power_manager, &process_management_capability);
+
     // set GPIO driver controlling remaining GPIO pins
     let gpio = components::gpio::GpioComponent::new(
         board_kernel,
@@ -457,7 +471,6 @@ pub unsafe fn reset_handler() {
             &_eapps as *const u8 as usize - &_sapps as *const u8 as usize,
         ),
         &mut APP_MEMORY,
-        &mut PROCESSES,
         fault_response,
         &process_management_capability,
     )
@@ -466,5 +479,15 @@ pub unsafe fn reset_handler() {
         debug!("{:?}", err);
     });
 
-    board_kernel.kernel_loop(&hail, chip, Some(&hail.ipc), &main_loop_capability);
+    // Let the chip sleep more deeply when the next alarm is far away.
+    board_kernel.set_alarm_deadline(&sam4l::ast::AST, &main_loop_capability);
+
+    let scheduler = components::round_robin::RoundRobinComponent::new().finalize(());
+    board_kernel.kernel_loop(
+        &hail,
+        chip,
+        Some(&hail.ipc),
+        scheduler,
+        &main_loop_capability,
+    );
 }
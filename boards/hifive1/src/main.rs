@@ -79,6 +79,11 @@ impl Platform for HiFive1 {
 pub unsafe fn reset_handler() {
     // Basic setup of the platform.
     rv32i::init_memory();
+
+    // Paint the unused kernel stack, to measure how much of it the kernel
+    // uses.
+    kernel::kernel_stack::paint(STACK_MEMORY.as_mut_ptr(), STACK_MEMORY.len());
+
     // only machine mode
     rv32i::configure_trap_handler(rv32i::PermissionMode::Machine);
 
@@ -96,7 +101,7 @@ pub unsafe fn reset_handler() {
 
     let main_loop_cap = create_capability!(capabilities::MainLoopCapability);
 
-    let board_kernel = static_init!(kernel::Kernel, kernel::Kernel::new(&PROCESSES));
+    let board_kernel = static_init!(kernel::Kernel, kernel::Kernel::new(&mut PROCESSES));
 
     let dynamic_deferred_call_clients =
         static_init!([DynamicDeferredCallClientState; 2], Default::default());
@@ -217,7 +222,6 @@ pub unsafe fn reset_handler() {
             &_eapps as *const u8 as usize - &_sapps as *const u8 as usize,
         ),
         &mut APP_MEMORY,
-        &mut PROCESSES,
         FAULT_RESPONSE,
         &process_mgmt_cap,
     )
@@ -226,5 +230,6 @@ pub unsafe fn reset_handler() {
         debug!("{:?}", err);
     });
 
-    board_kernel.kernel_loop(&hifive1, chip, None, &main_loop_cap);
+    let scheduler = components::round_robin::RoundRobinComponent::new().finalize(());
+    board_kernel.kernel_loop(&hifive1, chip, None, scheduler, &main_loop_cap);
 }
@@ -164,6 +164,7 @@ impl kernel::Platform for Imix {
             capsules::nonvolatile_storage_driver::DRIVER_NUM => f(Some(self.nonvolatile_storage)),
             capsules::rng::DRIVER_NUM => f(Some(self.rng)),
             kernel::ipc::DRIVER_NUM => f(Some(&self.ipc)),
+            kernel::ipc::MAILBOX_DRIVER_NUM => f(Some(self.ipc.mailbox())),
             _ => f(None),
         }
     }
@@ -250,6 +251,10 @@ unsafe fn set_pin_primary_functions() {
 pub unsafe fn reset_handler() {
     sam4l::init();
 
+    // Paint the unused kernel stack, to measure how much of it the kernel
+    // uses.
+    kernel::kernel_stack::paint(STACK_MEMORY.as_mut_ptr(), STACK_MEMORY.len());
+
     sam4l::pm::PM.setup_system_clock(sam4l::pm::SystemClockSource::PllExternalOscillatorAt48MHz {
         frequency: sam4l::pm::OscillatorFrequency::Frequency16MHz,
         startup_mode: sam4l::pm::OscillatorStartup::FastStart,
@@ -273,7 +278,7 @@ pub unsafe fn reset_handler() {
         trng: true,
     });
 
-    let board_kernel = static_init!(kernel::Kernel, kernel::Kernel::new(&PROCESSES));
+    let board_kernel = static_init!(kernel::Kernel, kernel::Kernel::new(&mut PROCESSES));
 
     let dynamic_deferred_call_clients =
         static_init!([DynamicDeferredCallClientState; 2], Default::default());
@@ -392,6 +397,15 @@ pub unsafe fn reset_handler() {
     .finalize(components::acomp_component_buf!(sam4l::acifc::Acifc));
     let rng = RngComponent::new(board_kernel, &sam4l::trng::TRNG).finalize(());
 
+    // Measure how long the clocks of peripherals that switch them off when
+    // idle are on. The process console's `power` command prints them.
+    let power_manager = static_init!(
+        kernel::power_manager::PowerManager<'static>,
+        kernel::power_manager::PowerManager::new(&sam4l::ast::AST)
+    );
+    power_manager.register(sam4l::trng::TRNG.managed_clock());
+    board_kernel.set_power_manager(power_manager, &process_mgmt_cap);
+
     // For now, assign the 802.15.4 MAC address on the device as
     // simply a 16-bit short address which represents the last 16 bits
     // of the serial number of the sam4l for this device.  In the
@@ -554,7 +568,6 @@ pub unsafe fn reset_handler() {
             &_eapps as *const u8 as usize - &_sapps as *const u8 as usize,
         ),
         &mut APP_MEMORY,
-        &mut PROCESSES,
         FAULT_RESPONSE,
         &process_mgmt_cap,
     )
@@ -563,5 +576,11 @@ pub unsafe fn reset_handler() {
         debug!("{:?}", err);
     });
 
-    board_kernel.kernel_loop(&imix, chip, Some(&imix.ipc), &main_cap);
+    // Let the chip sleep more deeply when the next alarm is far away.
+    board_kernel.set_alarm_deadline(&sam4l::ast::AST, &main_cap);
+    // imix often runs on batteries, so allow the deepest sleep mode.
+    chip.set_deepest_sleep_mode(sam4l::chip::SleepMode::Retention);
+
+    let scheduler = components::round_robin::RoundRobinComponent::new().finalize(());
+    board_kernel.kernel_loop(&imix, chip, Some(&imix.ipc), scheduler, &main_cap);
 }
@@ -65,7 +65,11 @@ pub unsafe fn reset_handler() {
     // Loads relocations and clears BSS
     nrf52840::init();
 
-    let board_kernel = static_init!(kernel::Kernel, kernel::Kernel::new(&PROCESSES));
+    // Paint the unused kernel stack, to measure how much of it the kernel
+    // uses.
+    kernel::kernel_stack::paint(STACK_MEMORY.as_mut_ptr(), STACK_MEMORY.len());
+
+    let board_kernel = static_init!(kernel::Kernel, kernel::Kernel::new(&mut PROCESSES));
 
     // GPIOs
     let gpio = components::gpio::GpioComponent::new(
@@ -155,7 +159,6 @@ pub unsafe fn reset_handler() {
         button,
         true,
         &mut APP_MEMORY,
-        &mut PROCESSES,
         FAULT_RESPONSE,
         nrf52840::uicr::Regulator0Output::V3_0,
         false,
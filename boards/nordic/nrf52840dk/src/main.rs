@@ -132,6 +132,10 @@ pub unsafe fn reset_handler() {
     // Loads relocations and clears BSS
     nrf52840::init();
 
+    // Paint the unused kernel stack, to measure how much of it the kernel
+    // uses.
+    kernel::kernel_stack::paint(STACK_MEMORY.as_mut_ptr(), STACK_MEMORY.len());
+
     let uart_channel = if USB_DEBUGGING {
         // Initialize Segger RTT as early as possible so that any panic beyond this point can use the
         // RTT memory object.
@@ -148,7 +152,7 @@ pub unsafe fn reset_handler() {
         UartChannel::Pins(UartPins::new(UART_RTS, UART_TXD, UART_CTS, UART_RXD))
     };
 
-    let board_kernel = static_init!(kernel::Kernel, kernel::Kernel::new(&PROCESSES));
+    let board_kernel = static_init!(kernel::Kernel, kernel::Kernel::new(&mut PROCESSES));
 
     let gpio = components::gpio::GpioComponent::new(
         board_kernel,
@@ -245,7 +249,6 @@ pub unsafe fn reset_handler() {
         button,
         true,
         &mut APP_MEMORY,
-        &mut PROCESSES,
         FAULT_RESPONSE,
         nrf52840::uicr::Regulator0Output::DEFAULT,
         false,
@@ -127,6 +127,7 @@ impl kernel::Platform for Platform {
                 f(self.nonvolatile_storage.map_or(None, |nv| Some(nv)))
             }
             kernel::ipc::DRIVER_NUM => f(Some(&self.ipc)),
+            kernel::ipc::MAILBOX_DRIVER_NUM => f(Some(self.ipc.mailbox())),
             _ => f(None),
         }
     }
@@ -149,7 +150,6 @@ pub unsafe fn setup_board<I: nrf52::interrupt_service::InterruptService>(
     button: &'static capsules::button::Button<'static, nrf52::gpio::GPIOPin>,
     ieee802154: bool,
     app_memory: &mut [u8],
-    process_pointers: &'static mut [Option<&'static dyn kernel::procs::ProcessType>],
     app_fault_response: kernel::procs::FaultResponse,
     reg_vout: Regulator0Output,
     nfc_as_gpios: bool,
@@ -339,7 +339,6 @@ pub unsafe fn setup_board<I: nrf52::interrupt_service::InterruptService>(
             &_eapps as *const u8 as usize - &_sapps as *const u8 as usize,
         ),
         app_memory,
-        process_pointers,
         app_fault_response,
         &process_management_capability,
     )
@@ -348,5 +347,15 @@ pub unsafe fn setup_board<I: nrf52::interrupt_service::InterruptService>(
         debug!("{:?}", err);
     });
 
-    board_kernel.kernel_loop(&platform, chip, Some(&platform.ipc), &main_loop_capability);
+    // Let the chip sleep more deeply when the next alarm is far away.
+    board_kernel.set_alarm_deadline(&nrf52::rtc::RTC, &main_loop_capability);
+
+    let scheduler = components::round_robin::RoundRobinComponent::new().finalize(());
+    board_kernel.kernel_loop(
+        &platform,
+        chip,
+        Some(&platform.ipc),
+        scheduler,
+        &main_loop_capability,
+    );
 }
@@ -72,6 +72,7 @@ impl Platform for NucleoF429ZI {
             capsules::button::DRIVER_NUM => f(Some(self.button)),
             capsules::alarm::DRIVER_NUM => f(Some(self.alarm)),
             kernel::ipc::DRIVER_NUM => f(Some(&self.ipc)),
+            kernel::ipc::MAILBOX_DRIVER_NUM => f(Some(self.ipc.mailbox())),
             capsules::gpio::DRIVER_NUM => f(Some(self.gpio)),
             _ => f(None),
         }
@@ -184,6 +185,10 @@ unsafe fn setup_peripherals() {
 pub unsafe fn reset_handler() {
     stm32f429zi::init();
 
+    // Paint the unused kernel stack, to measure how much of it the kernel
+    // uses.
+    kernel::kernel_stack::paint(STACK_MEMORY.as_mut_ptr(), STACK_MEMORY.len());
+
     // We use the default HSI 16Mhz clock
 
     set_pin_primary_functions();
@@ -192,7 +197,7 @@ pub unsafe fn reset_handler() {
 
     setup_peripherals();
 
-    let board_kernel = static_init!(kernel::Kernel, kernel::Kernel::new(&PROCESSES));
+    let board_kernel = static_init!(kernel::Kernel, kernel::Kernel::new(&mut PROCESSES));
 
     let dynamic_deferred_call_clients =
         static_init!([DynamicDeferredCallClientState; 2], Default::default());
@@ -211,7 +216,6 @@ pub unsafe fn reset_handler() {
     // UART
 
     // Create a shared UART channel for kernel debug.
-    stm32f429zi::usart::USART3.enable_clock();
     let uart_mux = components::console::UartMuxComponent::new(
         &stm32f429zi::usart::USART3,
         115200,
@@ -228,6 +232,15 @@ pub unsafe fn reset_handler() {
     let process_management_capability =
         create_capability!(capabilities::ProcessManagementCapability);
 
+    // Switch the clocks of idle peripherals off, and measure how long they are
+    // on.
+    let power_manager = static_init!(
+        kernel::power_manager::PowerManager<'static>,
+        kernel::power_manager::PowerManager::new(&stm32f429zi::tim2::TIM2)
+    );
+    power_manager.register(stm32f429zi::usart::USART3.managed_clock());
+    board_kernel.set_power_manager(power_manager, &process_management_capability);
+
     // Setup the console.
     let console = components::console::ConsoleComponent::new(board_kernel, uart_mux).finalize(());
     // Create the debugger object that handles calls to `debug!()`.
@@ -435,7 +448,6 @@ pub unsafe fn reset_handler() {
             &_eapps as *const u8 as usize - &_sapps as *const u8 as usize,
         ),
         &mut APP_MEMORY,
-        &mut PROCESSES,
         FAULT_RESPONSE,
         &process_management_capability,
     )
@@ -444,10 +456,12 @@ pub unsafe fn reset_handler() {
         debug!("{:?}", err);
     });
 
+    let scheduler = components::round_robin::RoundRobinComponent::new().finalize(());
     board_kernel.kernel_loop(
         &nucleo_f429zi,
         chip,
         Some(&nucleo_f429zi.ipc),
+        scheduler,
         &main_loop_capability,
     );
 }
@@ -0,0 +1,348 @@
+//! Load new apps over a UART while the kernel is running.
+//!
+//! This capsule receives a TBF image over a UART, writes it to the unused part
+//! of app flash, and asks the kernel to create a process for it. This allows
+//! updating the apps on a board without reflashing it and resetting, so the
+//! processes that are already running keep their state.
+//!
+//! Flash is accessed through a `NonvolatileStorage` interface, typically a
+//! `NonvolatileToPages` on top of the chip's `hil::flash::Flash`. The capsule
+//! should have a UART to itself, since the binary image would otherwise be
+//! interpreted by other users of the UART.
+//!
+//! Protocol
+//! --------
+//!
+//! 1. The host sends the length of the app in bytes as a 4 byte little endian
+//!    integer. The board replies with `ACK` if an app of that size fits in the
+//!    unused app flash, or `NAK` otherwise.
+//! 2. The host sends the app in chunks of `CHUNK_SIZE` bytes (the last chunk
+//!    may be shorter), waiting for a reply after each chunk. The first chunk
+//!    must contain the entire TBF header, which the board validates before
+//!    accepting the app. The board replies `ACK` once a chunk is handled, or
+//!    `NAK` if the app cannot be loaded, in which case the host must start over
+//!    from step 1.
+//! 3. The reply to the last chunk is `ACK` if the process was created, or `NAK`
+//!    if the kernel could not load it.
+//!
+//! The TBF header is written to flash after the rest of the app. This way a
+//! transfer that is interrupted never leaves a partial app that would be found
+//! by `load_processes()` on the next boot.
+//!
+//! `NonvolatileStorage::write` does not give the buffer back if the write fails
+//! to start, so the loader keeps a spare buffer to replace the one it lost and
+//! keep listening to the host. Once the spare has been used up, a further
+//! failed write leaves the loader unable to receive apps.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust
+//! let app_loader = static_init!(
+//!     capsules::app_loader::AppLoader<'static>,
+//!     capsules::app_loader::AppLoader::new(
+//!         loader_uart,
+//!         nv_to_page,
+//!         process_loader,
+//!         &mut capsules::app_loader::TX_BUF,
+//!         &mut capsules::app_loader::RX_BUF,
+//!         &mut capsules::app_loader::HEADER_BUF,
+//!         &mut capsules::app_loader::SPARE_BUF,
+//!     )
+//! );
+//! hil::uart::Transmit::set_transmit_client(loader_uart, app_loader);
+//! hil::uart::Receive::set_receive_client(loader_uart, app_loader);
+//! hil::nonvolatile_storage::NonvolatileStorage::set_client(nv_to_page, app_loader);
+//! app_loader.start();
+//! ```
+
+use core::cell::Cell;
+use core::cmp;
+use core::convert::TryInto;
+use kernel::common::cells::TakeCell;
+use kernel::debug;
+use kernel::hil;
+use kernel::hil::uart;
+use kernel::procs::DynamicProcessLoader;
+use kernel::ReturnCode;
+
+/// Size of the chunks the app is sent in.
+pub const CHUNK_SIZE: usize = 256;
+
+/// Reply when a request succeeded.
+pub const ACK: u8 = 0x06;
+/// Reply when a request failed.
+pub const NAK: u8 = 0x15;
+
+pub static mut TX_BUF: [u8; 1] = [0; 1];
+pub static mut RX_BUF: [u8; CHUNK_SIZE] = [0; CHUNK_SIZE];
+pub static mut HEADER_BUF: [u8; CHUNK_SIZE] = [0; CHUNK_SIZE];
+pub static mut SPARE_BUF: [u8; CHUNK_SIZE] = [0; CHUNK_SIZE];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum State {
+    /// Waiting for the length of the next app.
+    WaitingLength,
+    /// Waiting for a chunk of the app.
+    ReceivingChunk,
+    /// Writing a chunk of the app to flash.
+    WritingChunk,
+    /// Writing the first chunk, which holds the TBF header, to flash.
+    WritingHeader,
+}
+
+pub struct AppLoader<'a> {
+    uart: &'a dyn uart::UartData<'a>,
+    storage: &'a dyn hil::nonvolatile_storage::NonvolatileStorage<'static>,
+    loader: &'a dyn DynamicProcessLoader,
+    state: Cell<State>,
+    tx_buffer: TakeCell<'static, [u8]>,
+    rx_buffer: TakeCell<'static, [u8]>,
+
+    /// Holds the first chunk of the app until the rest has been written.
+    header_buffer: TakeCell<'static, [u8]>,
+    /// Replaces the buffer of a write that failed to start.
+    spare_buffer: TakeCell<'static, [u8]>,
+    /// How many bytes of the first chunk are valid.
+    header_length: Cell<usize>,
+
+    /// Flash address the app is written to.
+    app_address: Cell<usize>,
+    /// Total length of the app.
+    app_length: Cell<usize>,
+    /// How many bytes of the app have been received.
+    received: Cell<usize>,
+}
+
+impl<'a> AppLoader<'a> {
+    pub fn new(
+        uart: &'a dyn uart::UartData<'a>,
+        storage: &'a dyn hil::nonvolatile_storage::NonvolatileStorage<'static>,
+        loader: &'a dyn DynamicProcessLoader,
+        tx_buffer: &'static mut [u8],
+        rx_buffer: &'static mut [u8],
+        header_buffer: &'static mut [u8],
+        spare_buffer: &'static mut [u8],
+    ) -> AppLoader<'a> {
+        AppLoader {
+            uart: uart,
+            storage: storage,
+            loader: loader,
+            state: Cell::new(State::WaitingLength),
+            tx_buffer: TakeCell::new(tx_buffer),
+            rx_buffer: TakeCell::new(rx_buffer),
+            header_buffer: TakeCell::new(header_buffer),
+            spare_buffer: TakeCell::new(spare_buffer),
+            header_length: Cell::new(0),
+            app_address: Cell::new(0),
+            app_length: Cell::new(0),
+            received: Cell::new(0),
+        }
+    }
+
+    /// Start listening for apps.
+    pub fn start(&self) -> ReturnCode {
+        self.rx_buffer.take().map_or(ReturnCode::EBUSY, |buffer| {
+            self.state.set(State::WaitingLength);
+            self.uart.receive_buffer(buffer, 4);
+            ReturnCode::SUCCESS
+        })
+    }
+
+    fn reply(&self, byte: u8) {
+        self.tx_buffer.take().map(|buffer| {
+            buffer[0] = byte;
+            self.uart.transmit_buffer(buffer, 1);
+        });
+    }
+
+    /// Reply to the host and wait for the next chunk of the app.
+    fn receive_next_chunk(&self, buffer: &'static mut [u8]) {
+        let remaining = self.app_length.get() - self.received.get();
+        self.state.set(State::ReceivingChunk);
+        self.reply(ACK);
+        self.uart
+            .receive_buffer(buffer, cmp::min(remaining, buffer.len()));
+    }
+
+    /// Abandon the current app and wait for the host to start over.
+    fn abort(&self, buffer: &'static mut [u8]) {
+        self.state.set(State::WaitingLength);
+        self.reply(NAK);
+        self.uart.receive_buffer(buffer, 4);
+    }
+
+    /// Handle the length of a new app sent by the host.
+    fn length_received(&self, buffer: &'static mut [u8]) {
+        let app_length = buffer
+            .get(0..4)
+            .and_then(|length| length.try_into().ok())
+            .map_or(0, |length| u32::from_le_bytes(length) as usize);
+        let free_flash = self.loader.free_app_flash();
+        if app_length == 0 || app_length > free_flash.len() {
+            self.abort(buffer);
+        } else {
+            self.app_address.set(free_flash.as_ptr() as usize);
+            self.app_length.set(app_length);
+            self.received.set(0);
+            self.receive_next_chunk(buffer);
+        }
+    }
+
+    /// Handle a chunk of the app sent by the host.
+    fn chunk_received(&self, buffer: &'static mut [u8], length: usize) {
+        let offset = self.received.get();
+        self.received.set(offset + length);
+
+        if offset == 0 {
+            // The first chunk holds the TBF header. Check it before writing
+            // anything, then keep it until the rest of the app is in flash.
+            match self.loader.validate_tbf_header(&buffer[..length]) {
+                Ok(app_length) if app_length == self.app_length.get() => {}
+                Ok(_) => {
+                    debug!("AppLoader: app length does not match TBF header");
+                    self.abort(buffer);
+                    return;
+                }
+                Err(error) => {
+                    debug!("AppLoader: cannot load app: {:?}", error);
+                    self.abort(buffer);
+                    return;
+                }
+            }
+
+            self.header_length.set(length);
+            match self.header_buffer.take() {
+                Some(next_buffer) => {
+                    self.header_buffer.replace(buffer);
+                    if length == self.app_length.get() {
+                        self.rx_buffer.replace(next_buffer);
+                        self.write_header();
+                    } else {
+                        self.receive_next_chunk(next_buffer);
+                    }
+                }
+                None => {
+                    debug!("AppLoader: no buffer for the TBF header");
+                    self.abort(buffer);
+                }
+            }
+        } else {
+            self.state.set(State::WritingChunk);
+            let rcode = self
+                .storage
+                .write(buffer, self.app_address.get() + offset, length);
+            if rcode != ReturnCode::SUCCESS {
+                self.write_failed(rcode);
+            }
+        }
+    }
+
+    fn write_header(&self) {
+        self.header_buffer.take().map(|buffer| {
+            self.state.set(State::WritingHeader);
+            let rcode =
+                self.storage
+                    .write(buffer, self.app_address.get(), self.header_length.get());
+            if rcode != ReturnCode::SUCCESS {
+                self.write_failed(rcode);
+            }
+        });
+    }
+
+    /// Handle a write that failed to start. The storage does not give back the
+    /// buffer it was passed, so the spare buffer takes its place before the
+    /// loader waits for the host to start over.
+    fn write_failed(&self, rcode: ReturnCode) {
+        debug!("AppLoader: flash write failed: {:?}", rcode);
+        if self.header_buffer.is_none() {
+            self.spare_buffer
+                .take()
+                .map(|buffer| self.header_buffer.replace(buffer));
+        }
+        if self.rx_buffer.is_none() {
+            self.spare_buffer
+                .take()
+                .map(|buffer| self.rx_buffer.replace(buffer));
+        }
+
+        self.state.set(State::WaitingLength);
+        self.reply(NAK);
+        match self.rx_buffer.take() {
+            Some(buffer) => {
+                self.uart.receive_buffer(buffer, 4);
+            }
+            None => debug!("AppLoader: out of buffers, cannot receive apps"),
+        }
+    }
+
+    /// The whole app is in flash, so create a process for it.
+    fn app_written(&self) {
+        let reply = match self.loader.load_process(self.app_address.get()) {
+            Ok(_) => ACK,
+            Err(error) => {
+                debug!("AppLoader: cannot load app: {:?}", error);
+                NAK
+            }
+        };
+        self.state.set(State::WaitingLength);
+        self.reply(reply);
+        self.rx_buffer.take().map(|buffer| {
+            self.uart.receive_buffer(buffer, 4);
+        });
+    }
+}
+
+impl uart::TransmitClient for AppLoader<'_> {
+    fn transmitted_buffer(&self, buffer: &'static mut [u8], _tx_len: usize, _rcode: ReturnCode) {
+        self.tx_buffer.replace(buffer);
+    }
+}
+
+impl uart::ReceiveClient for AppLoader<'_> {
+    fn received_buffer(
+        &self,
+        buffer: &'static mut [u8],
+        rx_len: usize,
+        _rcode: ReturnCode,
+        error: uart::Error,
+    ) {
+        if error != uart::Error::None {
+            self.abort(buffer);
+            return;
+        }
+        match self.state.get() {
+            State::WaitingLength => self.length_received(buffer),
+            State::ReceivingChunk => self.chunk_received(buffer, rx_len),
+            State::WritingChunk | State::WritingHeader => {
+                self.rx_buffer.replace(buffer);
+            }
+        }
+    }
+}
+
+impl hil::nonvolatile_storage::NonvolatileStorageClient<'static> for AppLoader<'_> {
+    fn read_done(&self, _buffer: &'static mut [u8], _length: usize) {}
+
+    fn write_done(&self, buffer: &'static mut [u8], _length: usize) {
+        match self.state.get() {
+            State::WritingChunk => {
+                if self.received.get() == self.app_length.get() {
+                    self.rx_buffer.replace(buffer);
+                    self.write_header();
+                } else {
+                    self.receive_next_chunk(buffer);
+                }
+            }
+            State::WritingHeader => {
+                self.header_buffer.replace(buffer);
+                self.app_written();
+            }
+            _ => {
+                // The storage finished a write that was reported as failed, so
+                // the buffer it lost is back and can be the spare again.
+                self.spare_buffer.replace(buffer);
+            }
+        }
+    }
+}
@@ -0,0 +1,271 @@
+//! Save crash dumps of faulted processes to flash or send them over a UART.
+//!
+//! When the kernel reports that a process faulted, this capsule copies a crash
+//! dump of the process (see `kernel::crash_dump`) into its buffer and then
+//! either
+//!
+//! - writes it to a region of flash reserved for crash dumps, through a
+//!   `NonvolatileStorage` such as a `NonvolatileToPages` on top of the chip's
+//!   `hil::flash::Flash`. The region holds the most recent dump, which can be
+//!   read out later, for example with `tockloader read`.
+//! - or streams it over a UART as lines of text, which can share the UART of
+//!   the console:
+//!
+//! ```text
+//! crashdump begin <length>
+//! crashdump <offset> <up to 32 bytes in hex>
+//! ...
+//! crashdump end
+//! ```
+//!
+//! `tools/crash_dump_to_core.py` converts either form into a core file for
+//! GDB. While a dump is being written, faults of other processes are not
+//! dumped. Dumps are not written if the fault response of the board is to
+//! panic.
+//!
+//! `NonvolatileStorage::write` does not give the buffer back if the write fails
+//! to start, so dumps to storage need a spare buffer to replace the one that
+//! was lost.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust
+//! let crash_dump = static_init!(
+//!     capsules::crash_dump::CrashDump<'static>,
+//!     capsules::crash_dump::CrashDump::new_uart(
+//!         crash_dump_uart,
+//!         &mut capsules::crash_dump::DUMP_BUF,
+//!         &mut capsules::crash_dump::TX_BUF,
+//!     )
+//! );
+//! hil::uart::Transmit::set_transmit_client(crash_dump_uart, crash_dump);
+//! board_kernel.set_crash_dump_client(crash_dump, &process_management_capability);
+//! ```
+
+use core::cell::Cell;
+use core::cmp;
+use kernel::common::cells::TakeCell;
+use kernel::crash_dump::CrashDumpClient;
+use kernel::debug;
+use kernel::hil;
+use kernel::hil::uart;
+use kernel::procs::ProcessType;
+use kernel::ReturnCode;
+
+/// How many bytes of the dump each line sent over the UART holds.
+const BYTES_PER_LINE: usize = 32;
+
+pub static mut DUMP_BUF: [u8; 2048] = [0; 2048];
+pub static mut SPARE_DUMP_BUF: [u8; 2048] = [0; 2048];
+pub static mut TX_BUF: [u8; 96] = [0; 96];
+
+/// Where dumps are written.
+enum Output<'a> {
+    /// A region of nonvolatile storage starting at `address`.
+    Storage {
+        storage: &'a dyn hil::nonvolatile_storage::NonvolatileStorage<'static>,
+        address: usize,
+        length: usize,
+    },
+    Uart(&'a dyn uart::Transmit<'a>),
+}
+
+pub struct CrashDump<'a> {
+    output: Output<'a>,
+    dump_buffer: TakeCell<'static, [u8]>,
+    /// Replaces the dump buffer if a write to storage fails to start.
+    spare_buffer: TakeCell<'static, [u8]>,
+    tx_buffer: TakeCell<'static, [u8]>,
+    /// Whether a dump is being written.
+    busy: Cell<bool>,
+    /// Length of the dump being written.
+    length: Cell<usize>,
+    /// How many bytes of the dump were sent over the UART, or `None` before
+    /// the dump was announced.
+    sent: Cell<Option<usize>>,
+}
+
+impl<'a> CrashDump<'a> {
+    /// Write dumps to the `length` bytes of `storage` starting at `address`.
+    /// `spare_buffer` must be as large as `dump_buffer`.
+    pub fn new_storage(
+        storage: &'a dyn hil::nonvolatile_storage::NonvolatileStorage<'static>,
+        address: usize,
+        length: usize,
+        dump_buffer: &'static mut [u8],
+        spare_buffer: &'static mut [u8],
+    ) -> CrashDump<'a> {
+        CrashDump::new(
+            Output::Storage {
+                storage: storage,
+                address: address,
+                length: length,
+            },
+            dump_buffer,
+            Some(spare_buffer),
+            None,
+        )
+    }
+
+    /// Send dumps over `uart`, using `tx_buffer` for each line.
+    pub fn new_uart(
+        uart: &'a dyn uart::Transmit<'a>,
+        dump_buffer: &'static mut [u8],
+        tx_buffer: &'static mut [u8],
+    ) -> CrashDump<'a> {
+        CrashDump::new(Output::Uart(uart), dump_buffer, None, Some(tx_buffer))
+    }
+
+    fn new(
+        output: Output<'a>,
+        dump_buffer: &'static mut [u8],
+        spare_buffer: Option<&'static mut [u8]>,
+        tx_buffer: Option<&'static mut [u8]>,
+    ) -> CrashDump<'a> {
+        CrashDump {
+            output: output,
+            dump_buffer: TakeCell::new(dump_buffer),
+            spare_buffer: spare_buffer.map_or(TakeCell::empty(), TakeCell::new),
+            tx_buffer: tx_buffer.map_or(TakeCell::empty(), TakeCell::new),
+            busy: Cell::new(false),
+            length: Cell::new(0),
+            sent: Cell::new(None),
+        }
+    }
+
+    /// Send the next line of the dump over `uart`: the line announcing it,
+    /// the next bytes of it, or once all of it was sent, the last line.
+    fn send_line(&self, uart: &dyn uart::Transmit<'a>) {
+        self.tx_buffer.take().map(|line| {
+            let mut line_length = 0;
+            let mut push = |bytes: &[u8]| {
+                for byte in bytes {
+                    if line_length < line.len() {
+                        line[line_length] = *byte;
+                        line_length += 1;
+                    }
+                }
+            };
+            let length = self.length.get();
+            match self.sent.get() {
+                None => {
+                    push(b"crashdump begin ");
+                    push(&hex_word(length as u32));
+                    self.sent.set(Some(0));
+                }
+                Some(offset) if offset < length => {
+                    let end = cmp::min(offset + BYTES_PER_LINE, length);
+                    push(b"crashdump ");
+                    push(&hex_word(offset as u32));
+                    push(b" ");
+                    self.dump_buffer.map(|dump| {
+                        for byte in dump[offset..end].iter() {
+                            push(&hex_byte(*byte));
+                        }
+                    });
+                    self.sent.set(Some(end));
+                }
+                Some(_) => {
+                    push(b"crashdump end");
+                    self.length.set(0);
+                }
+            }
+            push(b"\r\n");
+
+            if let (_, Some(line)) = uart.transmit_buffer(line, line_length) {
+                self.tx_buffer.replace(line);
+                self.length.set(0);
+                self.busy.set(false);
+            }
+        });
+    }
+}
+
+/// The hex digits of `value`, most significant first.
+fn hex_word(value: u32) -> [u8; 8] {
+    let mut digits = [0; 8];
+    for (index, digit) in digits.iter_mut().enumerate() {
+        *digit = hex_digit((value >> (28 - 4 * index)) as u8);
+    }
+    digits
+}
+
+fn hex_byte(value: u8) -> [u8; 2] {
+    [hex_digit(value >> 4), hex_digit(value)]
+}
+
+fn hex_digit(value: u8) -> u8 {
+    b"0123456789abcdef"[(value & 0xf) as usize]
+}
+
+impl CrashDumpClient for CrashDump<'_> {
+    fn process_faulted(&self, process: &dyn ProcessType) {
+        if self.busy.get() {
+            debug!(
+                "CrashDump: still writing a dump, not dumping {}",
+                process.get_process_name()
+            );
+            return;
+        }
+        self.dump_buffer.take().map(|dump| {
+            let length = match self.output {
+                Output::Storage { length, .. } => {
+                    let length = cmp::min(length, dump.len());
+                    process.write_crash_dump(&mut dump[..length])
+                }
+                Output::Uart(_) => process.write_crash_dump(dump),
+            };
+            self.length.set(length);
+            self.busy.set(true);
+            match self.output {
+                Output::Storage {
+                    storage, address, ..
+                } => {
+                    let rcode = storage.write(dump, address, length);
+                    if rcode != ReturnCode::SUCCESS {
+                        // The storage does not give the buffer back when a
+                        // write fails to start, so the spare buffer takes its
+                        // place for the next dump.
+                        debug!("CrashDump: flash write failed: {:?}", rcode);
+                        self.spare_buffer
+                            .take()
+                            .map(|spare| self.dump_buffer.replace(spare));
+                        self.length.set(0);
+                        self.busy.set(false);
+                    }
+                }
+                Output::Uart(uart) => {
+                    self.dump_buffer.replace(dump);
+                    self.sent.set(None);
+                    self.send_line(uart);
+                }
+            }
+        });
+    }
+}
+
+impl uart::TransmitClient for CrashDump<'_> {
+    fn transmitted_buffer(&self, buffer: &'static mut [u8], _tx_len: usize, _rcode: ReturnCode) {
+        self.tx_buffer.replace(buffer);
+        match self.output {
+            Output::Uart(uart) if self.length.get() > 0 => self.send_line(uart),
+            _ => self.busy.set(false),
+        }
+    }
+}
+
+impl hil::nonvolatile_storage::NonvolatileStorageClient<'static> for CrashDump<'_> {
+    fn read_done(&self, _buffer: &'static mut [u8], _length: usize) {}
+
+    fn write_done(&self, buffer: &'static mut [u8], _length: usize) {
+        if self.dump_buffer.is_some() {
+            // The storage finished a write that was reported as failed, and
+            // the spare buffer already replaced this one.
+            self.spare_buffer.replace(buffer);
+        } else {
+            self.dump_buffer.replace(buffer);
+            self.busy.set(false);
+        }
+    }
+}
@@ -19,6 +19,8 @@ pub enum NUM {
 
     // Kernel
     Ipc                   = 0x10000,
+    IpcMailbox            = 0x10001,
+    PanicRecord           = 0x10002,
 
     // HW Buses
     Spi                   = 0x20001,
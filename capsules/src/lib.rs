@@ -14,10 +14,12 @@ pub mod ambient_light;
 pub mod analog_comparator;
 pub mod analog_sensor;
 pub mod app_flash_driver;
+pub mod app_loader;
 pub mod ble_advertising_driver;
 pub mod button;
 pub mod buzzer_driver;
 pub mod console;
+pub mod crash_dump;
 pub mod crc;
 pub mod dac;
 pub mod debug_process_restart;
@@ -48,6 +50,7 @@ pub mod nonvolatile_storage_driver;
 pub mod nonvolatile_to_pages;
 pub mod nrf51822_serialization;
 pub mod panic_button;
+pub mod panic_record;
 pub mod pca9544a;
 pub mod process_console;
 pub mod rf233;
@@ -55,6 +58,7 @@ pub mod rf233_const;
 pub mod rng;
 pub mod sdcard;
 pub mod segger_rtt;
+pub mod sha;
 pub mod si7021;
 pub mod spi;
 pub mod temperature;
@@ -0,0 +1,125 @@
+//! Provides userspace with the record of the kernel panic before the last
+//! reset.
+//!
+//! The kernel saves a record of each panic that survives a reset (see
+//! `kernel::panic_record`). This driver lets processes read the record of the
+//! panic that ended the previous boot, for example to report it to a server.
+//!
+//! Command 2 copies the record into the buffer a process allowed, as little
+//! endian `u32`s and bytes:
+//!
+//! ```text
+//! pc, lr, uptime in ms, message length, process name length, message, process name
+//! ```
+//!
+//! The process name length is 0 if no process was running.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust
+//! let panic_record = static_init!(
+//!     capsules::panic_record::PanicRecordDriver,
+//!     capsules::panic_record::PanicRecordDriver::new(board_kernel.create_grant(&grant_cap))
+//! );
+//! ```
+
+use kernel::panic_record;
+use kernel::{AppId, AppSlice, Callback, Driver, Grant, ReturnCode, Shared};
+
+/// Syscall driver number.
+use crate::driver;
+pub const DRIVER_NUM: usize = driver::NUM::PanicRecord as usize;
+
+#[derive(Default)]
+pub struct App {
+    buffer: Option<AppSlice<Shared, u8>>,
+}
+
+pub struct PanicRecordDriver {
+    apps: Grant<App>,
+}
+
+impl PanicRecordDriver {
+    pub fn new(grant: Grant<App>) -> PanicRecordDriver {
+        PanicRecordDriver { apps: grant }
+    }
+}
+
+impl Driver for PanicRecordDriver {
+    fn allow(
+        &self,
+        appid: AppId,
+        allow_num: usize,
+        slice: Option<AppSlice<Shared, u8>>,
+    ) -> ReturnCode {
+        match allow_num {
+            0 => self
+                .apps
+                .enter(appid, |app, _| {
+                    app.buffer = slice;
+                    ReturnCode::SUCCESS
+                })
+                .unwrap_or_else(|err| err.into()),
+            _ => ReturnCode::ENOSUPPORT,
+        }
+    }
+
+    fn subscribe(
+        &self,
+        _subscribe_num: usize,
+        _callback: Option<Callback>,
+        _app_id: AppId,
+    ) -> ReturnCode {
+        ReturnCode::ENOSUPPORT
+    }
+
+    fn command(&self, command_num: usize, _: usize, _: usize, appid: AppId) -> ReturnCode {
+        match command_num {
+            0 => ReturnCode::SUCCESS,
+
+            // Whether there is a record.
+            1 => ReturnCode::SuccessWithValue {
+                value: panic_record::last_panic().is_some() as usize,
+            },
+
+            // Copy the record into the allowed buffer.
+            2 => {
+                let record = match panic_record::last_panic() {
+                    Some(record) => record,
+                    None => return ReturnCode::FAIL,
+                };
+                self.apps
+                    .enter(appid, |app, _| {
+                        let buffer = match app.buffer {
+                            Some(ref mut buffer) => buffer.as_mut(),
+                            None => return ReturnCode::EINVAL,
+                        };
+                        let message = record.message().as_bytes();
+                        let process_name = record.process_name().unwrap_or("").as_bytes();
+                        let length = 20 + message.len() + process_name.len();
+                        if buffer.len() < length {
+                            return ReturnCode::ESIZE;
+                        }
+
+                        let words = [
+                            record.pc(),
+                            record.lr(),
+                            record.uptime_ms(),
+                            message.len() as u32,
+                            process_name.len() as u32,
+                        ];
+                        for (chunk, word) in buffer.chunks_mut(4).zip(words.iter()) {
+                            chunk.copy_from_slice(&word.to_le_bytes());
+                        }
+                        buffer[20..20 + message.len()].copy_from_slice(message);
+                        buffer[20 + message.len()..length].copy_from_slice(process_name);
+                        ReturnCode::SuccessWithValue { value: length }
+                    })
+                    .unwrap_or_else(|err| err.into())
+            }
+
+            _ => ReturnCode::ENOSUPPORT,
+        }
+    }
+}
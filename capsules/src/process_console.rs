@@ -5,13 +5,22 @@
 //! --------
 //!
 //! This module provides a simple text-based console to inspect and control
-//! which processes are running. The console has five commands:
+//! which processes are running. The console has these commands:
 //!  - 'help' prints the available commands and arguments
-//!  - 'status' prints the current system status
+//!  - 'status' prints the current system status, including the most of the
+//!    kernel stack the kernel has used so far
 //!  - 'list' lists the current processes with their IDs and running state
 //!  - 'stop n' stops the process with name n
 //!  - 'start n' starts the stopped process with name n
 //!  - 'fault n' forces the process with name n into a fault state
+//!  - 'terminate n' terminates the process with name n
+//!  - 'trace n' starts or stops recording the system calls of the process
+//!    with name n in the kernel's syscall trace
+//!  - 'tracedump' prints the next entries of the syscall trace
+//!  - 'panic' prints the record of the kernel panic before the last reset, if
+//!    the previous boot ended with one
+//!  - 'power' lists the clocks of the board's power manager, how many users
+//!    hold each of them, and how long each was on
 //!
 //! ### `list` Command Fields:
 //!
@@ -28,6 +37,26 @@
 //! - `State`: The state the process is in.
 //! - `Grants`: The number of grants that have been initialized for the process
 //!   out of the total number of grants defined by the kernel.
+//! - `Grant RAM`: How many bytes of the process's memory its grants use.
+//! - `Largest`: The grant using the most memory, and how much it uses.
+//! - `Last Fault`: Why the process faulted the last time, `stack overflow` or
+//!   `other`, or `-` if it never faulted.
+//!
+//! ### `tracedump` Output:
+//!
+//! `tracedump` prints up to eight entries of the syscall trace each time it is
+//! run, continuing after the last entry it printed:
+//!
+//! ```text
+//! trace frequency <timestamp frequency in Hz>
+//! trace <sequence number> <timestamp> <app> <syscall> <driver> <subdriver> <arg0> <arg1> <result>
+//! ...
+//! trace more
+//! ```
+//!
+//! All fields of an entry are hexadecimal. The output ends with `trace end`
+//! once there are no more entries. `tools/syscall_trace.py` decodes captured
+//! output.
 //!
 //! Setup
 //! -----
@@ -85,9 +114,9 @@
 //! Initialization complete. Entering main loop
 //! Hello World!
 //! list
-//! PID    Name    Quanta  Syscalls  Dropped Callbacks  Restarts    State  Grants
-//! 00     blink        0       113                  0         0  Yielded    1/12
-//! 01     c_hello      0         8                  0         0  Yielded    3/12
+//! PID    Name    Quanta  Misses  Syscalls  Dropped Callbacks  Restarts    State  Grants  Grant RAM  Largest
+//! 00     blink        0       0       113                  0         0  Yielded    1/12        16B  #2: 16B
+//! 01     c_hello      0       0         8                  0         0  Yielded    3/12        96B  #0: 48B
 //! ```
 //!
 //! To get a general view of the system, use the status command:
@@ -97,6 +126,8 @@
 //! Total processes: 2
 //! Active processes: 2
 //! Timeslice expirations: 0
+//! Deadline misses: 0
+//! Quota breaches: 0
 //! ```
 //!
 //! and you can control processes with the `start` and `stop` commands:
@@ -108,12 +139,14 @@
 
 use core::cell::Cell;
 use core::cmp;
+use core::fmt;
 use core::str;
 use kernel::capabilities::ProcessManagementCapability;
 use kernel::common::cells::TakeCell;
 use kernel::debug;
 use kernel::hil::uart;
 use kernel::introspection::KernelInfo;
+use kernel::panic_record;
 use kernel::Kernel;
 use kernel::ReturnCode;
 
@@ -123,6 +156,10 @@ pub static mut WRITE_BUF: [u8; 4] = [0; 4];
 // Since reads are byte-by-byte, to properly echo what's typed,
 // we can use a very small read buffer.
 pub static mut READ_BUF: [u8; 4] = [0; 4];
+// How many syscall trace entries `tracedump` prints at a time, so that they fit
+// in the debug buffer.
+const TRACE_DUMP_ENTRIES: usize = 8;
+
 // Commands can be up to 32 bytes long: since commands themselves are 4-5
 // characters, limiting arguments to 25 bytes or so seems fine for now.
 pub static mut COMMAND_BUF: [u8; 32] = [0; 32];
@@ -143,6 +180,9 @@ pub struct ProcessConsole<'a, C: ProcessManagementCapability> {
     /// Internal flag that the process console should parse the command it just
     /// received after finishing echoing the last newline character.
     execute: Cell<bool>,
+
+    /// Sequence number of the next syscall trace entry `tracedump` prints.
+    trace_cursor: Cell<usize>,
     kernel: &'static Kernel,
     capability: C,
 }
@@ -166,6 +206,7 @@ impl<'a, C: ProcessManagementCapability> ProcessConsole<'a, C> {
             command_index: Cell::new(0),
             running: Cell::new(false),
             execute: Cell::new(false),
+            trace_cursor: Cell::new(0),
             kernel: kernel,
             capability: capability,
         }
@@ -183,6 +224,64 @@ impl<'a, C: ProcessManagementCapability> ProcessConsole<'a, C> {
         ReturnCode::SUCCESS
     }
 
+    // Print the next entries of the syscall trace.
+    fn dump_trace(&self) {
+        let tracer = match self.kernel.get_syscall_tracer(&self.capability) {
+            Some(tracer) => tracer,
+            None => {
+                debug!("This board does not record a syscall trace");
+                return;
+            }
+        };
+        // Entries that were overwritten since the last dump are skipped.
+        let start = cmp::max(self.trace_cursor.get(), tracer.first_sequence());
+        let end = cmp::min(start + TRACE_DUMP_ENTRIES, tracer.next_sequence());
+        debug!("trace frequency {}", tracer.frequency());
+        for sequence in start..end {
+            tracer.entry(sequence).map(|entry| {
+                debug!(
+                    "trace {:x} {:x} {:x} {:x} {:x} {:x} {:x} {:x} {:x}",
+                    sequence,
+                    entry.timestamp,
+                    entry.app,
+                    entry.syscall,
+                    entry.driver_number,
+                    entry.subdriver_number,
+                    entry.arg0,
+                    entry.arg1,
+                    entry.result
+                );
+            });
+        }
+        self.trace_cursor.set(end);
+        if end < tracer.next_sequence() {
+            debug!("trace more");
+        } else {
+            debug!("trace end");
+        }
+    }
+
+    // Print the clocks of the power manager.
+    fn print_power(&self) {
+        let power_manager = match self.kernel.get_power_manager(&self.capability) {
+            Some(power_manager) => power_manager,
+            None => {
+                debug!("This board has no power manager");
+                return;
+            }
+        };
+        debug!(" Clock         Users  State       Active");
+        for clock in power_manager.clocks() {
+            debug!(
+                " {:<12}  {:>5}  {:>5}  {:>9}ms",
+                clock.name(),
+                clock.users(),
+                if clock.is_enabled() { "on" } else { "off" },
+                clock.active_us().unwrap_or(0) / 1000
+            );
+        }
+    }
+
     // Process the command in the command buffer and clear the buffer.
     fn read_command(&self) {
         self.command_buffer.map(|command| {
@@ -205,7 +304,7 @@ impl<'a, C: ProcessManagementCapability> ProcessConsole<'a, C> {
                         let clean_str = s.trim();
                         if clean_str.starts_with("help") {
                             debug!("Welcome to the process console.");
-                            debug!("Valid commands are: help status list stop start fault");
+                            debug!("Valid commands are: help status list stop start fault terminate trace tracedump panic power");
                         } else if clean_str.starts_with("start") {
                             let argument = clean_str.split_whitespace().nth(1);
                             argument.map(|name| {
@@ -248,8 +347,43 @@ impl<'a, C: ProcessManagementCapability> ProcessConsole<'a, C> {
                                     },
                                 );
                             });
+                        } else if clean_str.starts_with("terminate") {
+                            let argument = clean_str.split_whitespace().nth(1);
+                            argument.map(|name| {
+                                self.kernel.process_each_capability(
+                                    &self.capability,
+                                    |proc| {
+                                        let proc_name = proc.get_process_name();
+                                        if proc_name == name {
+                                            proc.terminate(None);
+                                            debug!("Process {} terminated", proc_name);
+                                        }
+                                    },
+                                );
+                            });
+                        } else if clean_str.starts_with("tracedump") {
+                            self.dump_trace();
+                        } else if clean_str.starts_with("trace") {
+                            let argument = clean_str.split_whitespace().nth(1);
+                            argument.map(|name| {
+                                self.kernel.process_each_capability(
+                                    &self.capability,
+                                    |proc| {
+                                        let proc_name = proc.get_process_name();
+                                        if proc_name == name {
+                                            let tracing = !proc.syscall_tracing();
+                                            proc.set_syscall_tracing(tracing);
+                                            if tracing {
+                                                debug!("Tracing system calls of process {}", proc_name);
+                                            } else {
+                                                debug!("Stopped tracing system calls of process {}", proc_name);
+                                            }
+                                        }
+                                    },
+                                );
+                            });
                         } else if clean_str.starts_with("list") {
-                            debug!(" PID    Name                Quanta  Syscalls  Dropped Callbacks  Restarts    State  Grants");
+                            debug!(" PID    Name                Quanta  Misses  Syscalls  Dropped Callbacks  Restarts    State  Grants  Grant RAM  Largest    Last Fault");
                             self.kernel
                                 .process_each_capability(&self.capability, |proc| {
                                     let info: KernelInfo = KernelInfo::new(self.kernel);
@@ -258,17 +392,39 @@ impl<'a, C: ProcessManagementCapability> ProcessConsole<'a, C> {
                                     let appid = proc.appid();
                                     let (grants_used, grants_total) = info.number_app_grant_uses(appid, &self.capability);
 
+                                    // Total grant memory, and which grant uses the most of it.
+                                    let mut grant_bytes = 0;
+                                    let mut largest = (0, 0);
+                                    for grant_num in 0..grants_total {
+                                        let bytes = info.number_app_grant_bytes(appid, grant_num, &self.capability);
+                                        grant_bytes += bytes;
+                                        if bytes > largest.1 {
+                                            largest = (grant_num, bytes);
+                                        }
+                                    }
+
+                                    let fault_reason = proc.get_fault_reason();
+                                    let last_fault: &dyn fmt::Display = match fault_reason {
+                                        Some(ref reason) => reason,
+                                        None => &"-",
+                                    };
+
                                     debug!(
-                                        "  {:?}\t{:<20}{:6}{:10}{:19}{:10}  {:?}{:5}/{}",
+                                        "  {:?}\t{:<20}{:6}{:8}{:10}{:19}{:10}  {:?}{:5}/{}{:10}B  #{}: {}B  {}",
                                         appid,
                                         pname,
                                         proc.debug_timeslice_expiration_count(),
+                                        info.number_app_deadline_misses(appid, &self.capability),
                                         proc.debug_syscall_count(),
                                         proc.debug_dropped_callback_count(),
                                         proc.get_restart_count(),
                                         proc.get_state(),
                                         grants_used,
-                                        grants_total
+                                        grants_total,
+                                        grant_bytes,
+                                        largest.0,
+                                        largest.1,
+                                        last_fault
                                     );
                                 });
                         } else if clean_str.starts_with("status") {
@@ -285,8 +441,38 @@ impl<'a, C: ProcessManagementCapability> ProcessConsole<'a, C> {
                                 "Timeslice expirations: {}",
                                 info.timeslice_expirations(&self.capability)
                             );
+                            debug!(
+                                "Deadline misses: {}",
+                                info.deadline_misses(&self.capability)
+                            );
+                            debug!(
+                                "Quota breaches: {}",
+                                info.quota_breaches(&self.capability)
+                            );
+                            match info.kernel_stack_high_water_mark(&self.capability) {
+                                Some((used, size)) => {
+                                    debug!("Kernel stack: {} of {} bytes used", used, size)
+                                }
+                                None => debug!("Kernel stack: not painted"),
+                            }
+                        } else if clean_str.starts_with("power") {
+                            self.print_power();
+                        } else if clean_str.starts_with("panic") {
+                            match panic_record::last_panic() {
+                                Some(record) => {
+                                    debug!("Last panic: {}", record.message());
+                                    debug!(
+                                        "  pc {:#010x}  lr {:#010x}  uptime {}ms  process {}",
+                                        record.pc(),
+                                        record.lr(),
+                                        record.uptime_ms(),
+                                        record.process_name().unwrap_or("none")
+                                    );
+                                }
+                                None => debug!("No panic before the last reset"),
+                            }
                         } else {
-                            debug!("Valid commands are: help status list stop start fault");
+                            debug!("Valid commands are: help status list stop start fault terminate trace tracedump panic power");
                         }
                     }
                     Err(_e) => debug!("Invalid command: {:?}", command),
@@ -0,0 +1,328 @@
+//! Software SHA-256 and SHA-512 digests.
+//!
+//! This provides the `Digest` interface on chips without hash hardware, so
+//! that the digest and HMAC capsules can run on them. The hash is computed in
+//! deferred calls, which makes it complete asynchronously just like a hardware
+//! digest engine. `ShaSoftware<Sha256>` also implements `HMACSha256`.
+//!
+//! SHA-512 digests are 64 bytes long, which is returned as a `Digest64`.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust
+//! let sha = static_init!(
+//!     capsules::sha::ShaSoftware<'static, Sha256>,
+//!     capsules::sha::ShaSoftware::new(dynamic_deferred_caller)
+//! );
+//! sha.initialize_callback_handle(
+//!     dynamic_deferred_caller
+//!         .register(sha)
+//!         .expect("no deferred call slot available for sha"),
+//! );
+//! ```
+
+use core::cell::Cell;
+use kernel::common::cells::{MapCell, OptionalCell, TakeCell};
+use kernel::common::dynamic_deferred_call::{
+    DeferredCallHandle, DynamicDeferredCall, DynamicDeferredCallClient,
+};
+use kernel::common::leasable_buffer::LeasableBuffer;
+use kernel::crypto::sha2::{Sha256, Sha512};
+use kernel::hil::digest::{self, Digest64, DigestType};
+use kernel::ReturnCode;
+
+/// A hash function `ShaSoftware` can compute.
+pub trait SoftwareHash {
+    /// The digest the hash function produces.
+    type Output: 'static + DigestType;
+
+    /// The block size of the hash function in bytes, which HMAC pads its key
+    /// to.
+    const BLOCK_SIZE: usize;
+
+    fn new() -> Self;
+    fn update(&mut self, data: &[u8]);
+    fn finalize(self, digest: &mut Self::Output);
+}
+
+impl SoftwareHash for Sha256 {
+    type Output = [u8; 32];
+    const BLOCK_SIZE: usize = 64;
+
+    fn new() -> Sha256 {
+        Sha256::new()
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        Sha256::update(self, data);
+    }
+
+    fn finalize(self, digest: &mut [u8; 32]) {
+        *digest = Sha256::finalize(self);
+    }
+}
+
+impl SoftwareHash for Sha512 {
+    type Output = Digest64;
+    const BLOCK_SIZE: usize = 128;
+
+    fn new() -> Sha512 {
+        Sha512::new()
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        Sha512::update(self, data);
+    }
+
+    fn finalize(self, digest: &mut Digest64) {
+        digest.0 = Sha512::finalize(self);
+    }
+}
+
+/// Bytes the HMAC key is XORed with for the inner and outer hash.
+const HMAC_INNER_PAD: u8 = 0x36;
+const HMAC_OUTER_PAD: u8 = 0x5c;
+
+pub struct ShaSoftware<'a, H: SoftwareHash> {
+    client: OptionalCell<&'a dyn digest::Client<'a, H::Output>>,
+    hash: MapCell<H>,
+    /// The key of the HMAC being computed, if any.
+    hmac_key: Cell<Option<[u8; 32]>>,
+
+    data: Cell<Option<LeasableBuffer<'static, u8>>>,
+    digest: TakeCell<'static, H::Output>,
+
+    deferred_caller: &'a DynamicDeferredCall,
+    handle: OptionalCell<DeferredCallHandle>,
+}
+
+impl<'a, H: SoftwareHash> ShaSoftware<'a, H> {
+    pub fn new(deferred_caller: &'a DynamicDeferredCall) -> ShaSoftware<'a, H> {
+        ShaSoftware {
+            client: OptionalCell::empty(),
+            hash: MapCell::new(H::new()),
+            hmac_key: Cell::new(None),
+            data: Cell::new(None),
+            digest: TakeCell::empty(),
+            deferred_caller: deferred_caller,
+            handle: OptionalCell::empty(),
+        }
+    }
+
+    /// Initializes a callback handle for deferred callbacks.
+    pub fn initialize_callback_handle(&self, handle: DeferredCallHandle) {
+        self.handle.replace(handle);
+    }
+
+    fn schedule_deferred_call(&self) {
+        self.handle.map(|handle| self.deferred_caller.set(*handle));
+    }
+
+    fn data_pending(&self) -> bool {
+        let data = self.data.take();
+        let pending = data.is_some();
+        self.data.set(data);
+        pending
+    }
+
+    fn busy(&self) -> bool {
+        self.data_pending() || self.digest.is_some()
+    }
+
+    /// Add the HMAC key, XORed with `pad` and padded to the block size, to
+    /// `hash`.
+    fn update_hmac_key(hash: &mut H, key: &[u8; 32], pad: u8) {
+        let mut block = [pad; 128];
+        for (byte, key_byte) in block.iter_mut().zip(key.iter()) {
+            *byte ^= key_byte;
+        }
+        hash.update(&block[..H::BLOCK_SIZE]);
+    }
+
+    /// Finish the hash into `digest` and start a new one.
+    fn finish(&self, digest: &mut H::Output) {
+        if let Some(hash) = self.hash.replace(H::new()) {
+            hash.finalize(digest);
+        }
+        if let Some(key) = self.hmac_key.take() {
+            let mut outer = H::new();
+            Self::update_hmac_key(&mut outer, &key, HMAC_OUTER_PAD);
+            outer.update(digest.as_ref());
+            outer.finalize(digest);
+        }
+    }
+}
+
+impl<'a, H: SoftwareHash> digest::Digest<'a, H::Output> for ShaSoftware<'a, H> {
+    fn set_client(&'a self, client: &'a dyn digest::Client<'a, H::Output>) {
+        self.client.set(client);
+    }
+
+    fn add_data(
+        &self,
+        data: LeasableBuffer<'static, u8>,
+    ) -> Result<usize, (ReturnCode, &'static mut [u8])> {
+        if self.busy() {
+            return Err((ReturnCode::EBUSY, data.take()));
+        }
+        let length = data.len();
+        self.data.set(Some(data));
+        self.schedule_deferred_call();
+        Ok(length)
+    }
+
+    fn run(
+        &'a self,
+        digest: &'static mut H::Output,
+    ) -> Result<(), (ReturnCode, &'static mut H::Output)> {
+        if self.digest.is_some() {
+            return Err((ReturnCode::EBUSY, digest));
+        }
+        self.digest.replace(digest);
+        self.schedule_deferred_call();
+        Ok(())
+    }
+
+    fn clear_data(&self) {
+        self.hash.replace(H::new());
+        self.hmac_key.set(None);
+    }
+}
+
+impl<'a> digest::HMACSha256 for ShaSoftware<'a, Sha256> {
+    fn set_mode_hmacsha256(&self, key: &[u8; 32]) -> Result<(), ReturnCode> {
+        if self.busy() {
+            return Err(ReturnCode::EBUSY);
+        }
+        let mut hash = Sha256::new();
+        Self::update_hmac_key(&mut hash, key, HMAC_INNER_PAD);
+        self.hash.replace(hash);
+        self.hmac_key.set(Some(*key));
+        Ok(())
+    }
+}
+
+impl<'a, H: SoftwareHash> DynamicDeferredCallClient for ShaSoftware<'a, H> {
+    fn call(&self, _handle: DeferredCallHandle) {
+        if let Some(data) = self.data.take() {
+            self.hash.map(|hash| hash.update(&data[..]));
+            // Data added before `run()` must be hashed before the digest is
+            // computed, so finish the digest in another deferred call.
+            if self.digest.is_some() {
+                self.schedule_deferred_call();
+            }
+            self.client
+                .map(move |client| client.add_data_done(Ok(()), data.take()));
+        } else if let Some(digest) = self.digest.take() {
+            self.finish(digest);
+            self.client
+                .map(move |client| client.hash_done(Ok(()), digest));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+
+    use super::{ShaSoftware, SoftwareHash};
+    use core::cell::Cell;
+    use kernel::common::dynamic_deferred_call::{
+        DynamicDeferredCall, DynamicDeferredCallClient, DynamicDeferredCallClientState,
+    };
+    use kernel::common::leasable_buffer::LeasableBuffer;
+    use kernel::crypto::sha2::{Sha256, Sha512};
+    use kernel::hil::digest::{self, Digest, Digest64, HMACSha256};
+    use kernel::ReturnCode;
+    use std::boxed::Box;
+
+    struct TestClient<T: 'static + digest::DigestType> {
+        data_done: Cell<bool>,
+        digest: Cell<Option<&'static mut T>>,
+    }
+
+    impl<'a, T: digest::DigestType> digest::Client<'a, T> for TestClient<T> {
+        fn add_data_done(&'a self, result: Result<(), ReturnCode>, _data: &'static mut [u8]) {
+            assert_eq!(result, Ok(()));
+            self.data_done.set(true);
+        }
+
+        fn hash_done(&'a self, result: Result<(), ReturnCode>, digest: &'static mut T) {
+            assert_eq!(result, Ok(()));
+            self.digest.set(Some(digest));
+        }
+    }
+
+    /// Hash `data` in two pieces into `output`, after `setup` was called on
+    /// the hash engine.
+    fn hash<H: 'static + SoftwareHash, F: Fn(&ShaSoftware<'static, H>)>(
+        data: &[u8],
+        output: H::Output,
+        setup: F,
+    ) -> H::Output {
+        let states: &'static mut [DynamicDeferredCallClientState; 1] =
+            Box::leak(Box::new(Default::default()));
+        let deferred_caller: &'static DynamicDeferredCall =
+            Box::leak(Box::new(DynamicDeferredCall::new(states)));
+        let sha: &'static ShaSoftware<'static, H> =
+            Box::leak(Box::new(ShaSoftware::new(deferred_caller)));
+        let handle = deferred_caller.register(sha).unwrap();
+        sha.initialize_callback_handle(handle);
+        let client: &'static TestClient<H::Output> = Box::leak(Box::new(TestClient {
+            data_done: Cell::new(false),
+            digest: Cell::new(None),
+        }));
+        sha.set_client(client);
+        setup(sha);
+
+        for piece in data.chunks(data.len() / 2 + 1) {
+            let buffer = Box::leak(piece.to_vec().into_boxed_slice());
+            assert_eq!(sha.add_data(LeasableBuffer::new(buffer)), Ok(piece.len()));
+            // Only one piece of data can be pending.
+            let other = Box::leak(Box::new([0u8; 1]));
+            assert!(sha.add_data(LeasableBuffer::new(other)).is_err());
+            assert!(deferred_caller.has_pending());
+            sha.call(handle);
+            assert!(client.data_done.take());
+        }
+
+        let digest = Box::leak(Box::new(output));
+        assert!(sha.run(digest).is_ok());
+        sha.call(handle);
+        *client.digest.take().unwrap()
+    }
+
+    #[test]
+    fn test_sha256() {
+        let digest = hash::<Sha256, _>(b"abc", [0; 32], |_| {});
+        assert_eq!(digest, Sha256::digest(b"abc"));
+    }
+
+    #[test]
+    fn test_sha512() {
+        let digest = hash::<Sha512, _>(b"abc", Digest64([0; 64]), |_| {});
+        let mut expected = Sha512::new();
+        expected.update(b"abc");
+        assert_eq!(&digest.0[..], &expected.finalize()[..]);
+    }
+
+    #[test]
+    fn test_hmac_sha256() {
+        // RFC 4231 test case 2. HMAC pads keys with zeros to the block size, so
+        // the short key can be padded to 32 bytes.
+        let mut key = [0; 32];
+        key[..4].copy_from_slice(b"Jefe");
+        let digest = hash::<Sha256, _>(b"what do ya want for nothing?", [0; 32], |sha| {
+            assert_eq!(sha.set_mode_hmacsha256(&key), Ok(()));
+        });
+        assert_eq!(
+            digest,
+            [
+                0x5b, 0xdc, 0xc1, 0x46, 0xbf, 0x60, 0x75, 0x4e, 0x6a, 0x04, 0x24, 0x26, 0x08, 0x95,
+                0x75, 0xc7, 0x5a, 0x00, 0x3f, 0x08, 0x9d, 0x27, 0x39, 0x83, 0x9d, 0xec, 0x58, 0xb9,
+                0x64, 0xec, 0x38, 0x43,
+            ]
+        );
+    }
+}
@@ -1,16 +1,35 @@
 use crate::deferred_call_tasks::DeferredCallTask;
 use crate::interrupt_service::InterruptService;
 use crate::nvmc;
+use crate::power;
+use core::cell::Cell;
+use core::cmp;
 use core::fmt::Write;
 use cortexm4::{self, nvic};
 use kernel::common::deferred_call;
 use kernel::debug;
 
+/// The modes the chip can sleep in when the kernel has nothing to do, from the
+/// lightest to the deepest.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SleepMode {
+    /// System ON with constant latency: the CPU wakes up quickly.
+    ConstantLatency,
+    /// System ON in low-power mode: the chip powers down more, and the CPU
+    /// takes longer to wake up.
+    LowPower,
+}
+
+/// How long the chip must be able to sleep before it enters low-power mode,
+/// so that alarms which fire soon are not delayed by the longer wakeup.
+const LOW_POWER_MIN_SLEEP_US: u32 = 1_000;
+
 pub struct NRF52<I: InterruptService> {
     mpu: cortexm4::mpu::MPU,
     userspace_kernel_boundary: cortexm4::syscall::SysCall,
     systick: cortexm4::systick::SysTick,
     interrupt_service: I,
+    deepest_sleep_mode: Cell<SleepMode>,
 }
 
 impl<I: InterruptService> NRF52<I> {
@@ -22,6 +41,23 @@ impl<I: InterruptService> NRF52<I> {
             // 64Mhz CPU clock.
             systick: cortexm4::systick::SysTick::new_with_calibration(64000000),
             interrupt_service,
+            deepest_sleep_mode: Cell::new(SleepMode::LowPower),
+        }
+    }
+
+    /// Set the deepest mode the chip sleeps in. The default is low-power
+    /// mode.
+    pub fn set_deepest_sleep_mode(&self, mode: SleepMode) {
+        self.deepest_sleep_mode.set(mode);
+    }
+
+    fn sleep_in(&self, mode: SleepMode) {
+        unsafe {
+            match mode {
+                SleepMode::ConstantLatency => power::POWER.enable_constant_latency(),
+                SleepMode::LowPower => power::POWER.enable_low_power(),
+            }
+            cortexm4::support::wfi();
         }
     }
 }
@@ -69,9 +105,15 @@ impl<I: InterruptService> kernel::Chip for NRF52<I> {
     }
 
     fn sleep(&self) {
-        unsafe {
-            cortexm4::support::wfi();
-        }
+        self.sleep_in(self.deepest_sleep_mode.get());
+    }
+
+    fn sleep_until(&self, us_until_deadline: Option<u32>) {
+        let mode = match us_until_deadline {
+            Some(us) if us < LOW_POWER_MIN_SLEEP_US => SleepMode::ConstantLatency,
+            _ => SleepMode::LowPower,
+        };
+        self.sleep_in(cmp::min(mode, self.deepest_sleep_mode.get()));
     }
 
     unsafe fn atomic<F, R>(&self, f: F) -> R
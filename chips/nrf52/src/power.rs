@@ -347,6 +347,18 @@ impl<'a> Power<'a> {
     pub fn is_usb_power_ready(&self) -> bool {
         self.registers.usbregstatus.is_set(UsbRegStatus::OUTPUTRDY)
     }
+
+    /// Keep the CPU's wakeup latency from sleep constant and short, at the
+    /// cost of more current while the CPU sleeps.
+    pub fn enable_constant_latency(&self) {
+        self.registers.task_constlat.write(Task::ENABLE::SET);
+    }
+
+    /// Let the chip power down more while the CPU sleeps, which makes the
+    /// wakeup latency longer and variable. This is the default after reset.
+    pub fn enable_low_power(&self) {
+        self.registers.task_lowpwr.write(Task::ENABLE::SET);
+    }
 }
 
 pub static mut POWER: Power<'static> = Power::new();
@@ -172,6 +172,22 @@ pub unsafe fn set_ck32source(source: CK32Source) {
         .modify_no_read(control, PowerModeControl::CK32S.val(source as u32));
 }
 
+/// Which mode the chip enters when the CPU goes to deep sleep.
+pub enum DeepSleepMode {
+    /// WAIT mode: all clocks but the 32kHz ones stop.
+    Wait = 0,
+    /// RETENTION mode: as WAIT, and the core domain is kept at a lower
+    /// voltage, which takes longer to wake up from.
+    Retention = 1,
+}
+
+pub unsafe fn set_deep_sleep_mode(mode: DeepSleepMode) {
+    let control = BPM.pmcon.extract();
+    unlock_register(0x1c); // Control
+    BPM.pmcon
+        .modify_no_read(control, PowerModeControl::RET.val(mode as u32));
+}
+
 unsafe fn unlock_register(register_offset: u32) {
     BPM.unlock
         .write(Unlock::KEY.val(BPM_UNLOCK_KEY) + Unlock::ADDR.val(register_offset));
@@ -4,6 +4,7 @@ use crate::acifc;
 use crate::adc;
 use crate::aes;
 use crate::ast;
+use crate::bpm;
 use crate::crccu;
 use crate::dac;
 use crate::deferred_call_tasks::Task;
@@ -19,15 +20,37 @@ use crate::trng;
 use crate::usart;
 use crate::usbc;
 
+use core::cell::Cell;
+use core::cmp;
 use core::fmt::Write;
 use cortexm4;
 use kernel::common::deferred_call;
 use kernel::Chip;
 
+/// The modes the chip can sleep in when the kernel has nothing to do, from the
+/// lightest to the deepest.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SleepMode {
+    /// Only the CPU stops.
+    Sleep,
+    /// The WAIT mode of the BPM.
+    Wait,
+    /// The RETENTION mode of the BPM.
+    Retention,
+}
+
+/// How long the chip must be able to sleep before it enters WAIT mode, which
+/// stops the main clock, as the clock takes time to start again.
+const WAIT_MIN_SLEEP_US: u32 = 2_000;
+
+/// How long the chip must be able to sleep before it enters RETENTION mode.
+const RETENTION_MIN_SLEEP_US: u32 = 10_000;
+
 pub struct Sam4l {
     mpu: cortexm4::mpu::MPU,
     userspace_kernel_boundary: cortexm4::syscall::SysCall,
     systick: cortexm4::systick::SysTick,
+    deepest_sleep_mode: Cell<SleepMode>,
 }
 
 impl Sam4l {
@@ -68,6 +91,36 @@ impl Sam4l {
             mpu: cortexm4::mpu::MPU::new(),
             userspace_kernel_boundary: cortexm4::syscall::SysCall::new(),
             systick: cortexm4::systick::SysTick::new(),
+            deepest_sleep_mode: Cell::new(SleepMode::Wait),
+        }
+    }
+
+    /// Set the deepest mode the chip sleeps in. The default is WAIT mode.
+    pub fn set_deepest_sleep_mode(&self, mode: SleepMode) {
+        self.deepest_sleep_mode.set(mode);
+    }
+
+    /// Sleep in `mode`, or only stop the CPU if peripherals still need their
+    /// clocks.
+    fn sleep_in(&self, mode: SleepMode) {
+        let mode = if pm::deep_sleep_ready() {
+            mode
+        } else {
+            SleepMode::Sleep
+        };
+        unsafe {
+            match mode {
+                SleepMode::Sleep => cortexm4::scb::unset_sleepdeep(),
+                SleepMode::Wait => {
+                    bpm::set_deep_sleep_mode(bpm::DeepSleepMode::Wait);
+                    cortexm4::scb::set_sleepdeep();
+                }
+                SleepMode::Retention => {
+                    bpm::set_deep_sleep_mode(bpm::DeepSleepMode::Retention);
+                    cortexm4::scb::set_sleepdeep();
+                }
+            }
+            cortexm4::support::wfi();
         }
     }
 }
@@ -184,19 +237,16 @@ impl Chip for Sam4l {
     }
 
     fn sleep(&self) {
-        if pm::deep_sleep_ready() {
-            unsafe {
-                cortexm4::scb::set_sleepdeep();
-            }
-        } else {
-            unsafe {
-                cortexm4::scb::unset_sleepdeep();
-            }
-        }
+        self.sleep_in(self.deepest_sleep_mode.get());
+    }
 
-        unsafe {
-            cortexm4::support::wfi();
-        }
+    fn sleep_until(&self, us_until_deadline: Option<u32>) {
+        let mode = match us_until_deadline {
+            Some(us) if us < WAIT_MIN_SLEEP_US => SleepMode::Sleep,
+            Some(us) if us < RETENTION_MIN_SLEEP_US => SleepMode::Wait,
+            _ => SleepMode::Retention,
+        };
+        self.sleep_in(cmp::min(mode, self.deepest_sleep_mode.get()));
     }
 
     unsafe fn atomic<F, R>(&self, f: F) -> R
@@ -1,11 +1,15 @@
 //! Implementation of the SAM4L TRNG. It provides an implementation of
 //! the Entropy32 trait.
+//!
+//! The TRNG holds its clock only while it generates entropy, as the chip
+//! cannot enter deep sleep while the clock is on.
 
 use crate::pm;
 use kernel::common::cells::OptionalCell;
 use kernel::common::registers::{register_bitfields, ReadOnly, WriteOnly};
 use kernel::common::StaticRef;
 use kernel::hil::entropy::{self, Continue};
+use kernel::power_manager::ManagedClock;
 use kernel::ReturnCode;
 
 #[repr(C)]
@@ -47,6 +51,7 @@ const BASE_ADDRESS: StaticRef<TrngRegisters> =
 
 pub struct Trng<'a> {
     regs: StaticRef<TrngRegisters>,
+    clock: ManagedClock<'a>,
     client: OptionalCell<&'a dyn entropy::Client32>,
 }
 
@@ -57,10 +62,16 @@ impl<'a> Trng<'a> {
     const fn new() -> Trng<'a> {
         Trng {
             regs: BASE_ADDRESS,
+            clock: ManagedClock::new("trng", &pm::Clock::PBA(pm::PBAClock::TRNG), None),
             client: OptionalCell::empty(),
         }
     }
 
+    /// The clock of the TRNG, which is only on while it generates entropy.
+    pub fn managed_clock(&self) -> &ManagedClock<'a> {
+        &self.clock
+    }
+
     pub fn handle_interrupt(&self) {
         let regs = &*self.regs;
 
@@ -72,7 +83,7 @@ impl<'a> Trng<'a> {
                 // disable controller
                 regs.cr
                     .write(Control::KEY.val(KEY) + Control::ENABLE::Disable);
-                pm::disable_clock(pm::Clock::PBA(pm::PBAClock::TRNG));
+                self.clock.release();
             } else {
                 regs.ier.write(Interrupt::DATRDY::SET);
             }
@@ -98,7 +109,10 @@ impl Iterator for TrngIter<'_, '_> {
 impl<'a> entropy::Entropy32<'a> for Trng<'a> {
     fn get(&self) -> ReturnCode {
         let regs = &*self.regs;
-        pm::enable_clock(pm::Clock::PBA(pm::PBAClock::TRNG));
+        // A request while the TRNG is running continues the same run.
+        if self.clock.users() == 0 {
+            self.clock.acquire();
+        }
 
         regs.cr
             .write(Control::KEY.val(KEY) + Control::ENABLE::Enable);
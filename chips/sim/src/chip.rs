@@ -0,0 +1,144 @@
+//! The simulated chip.
+
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::fmt::Write;
+
+use kernel::Chip;
+
+use crate::mpu::SimMpu;
+use crate::syscall::SimUserspaceKernelBoundary;
+use crate::time::{SimSysTick, SimTime};
+use crate::uart::SimUart;
+
+pub struct SimChip {
+    mpu: SimMpu,
+    systick: SimSysTick,
+    time: SimTime,
+    userspace_kernel_boundary: SimUserspaceKernelBoundary,
+    uart: SimUart,
+    /// Interrupts that were raised and not handled yet.
+    interrupts: RefCell<VecDeque<Box<dyn FnOnce()>>>,
+    /// How often the kernel put the chip to sleep.
+    sleeps: Cell<usize>,
+    /// The deadline the kernel passed when it last put the chip to sleep.
+    sleep_deadline: Cell<Option<u32>>,
+}
+
+impl SimChip {
+    pub fn new() -> SimChip {
+        SimChip {
+            mpu: SimMpu::new(),
+            systick: SimSysTick::new(),
+            time: SimTime::new(),
+            userspace_kernel_boundary: SimUserspaceKernelBoundary::new(),
+            uart: SimUart::new(),
+            interrupts: RefCell::new(VecDeque::new()),
+            sleeps: Cell::new(0),
+            sleep_deadline: Cell::new(None),
+        }
+    }
+
+    /// The UART the kernel's debug output goes to.
+    pub fn uart(&self) -> &SimUart {
+        &self.uart
+    }
+
+    /// The clock of the chip, which schedulers can use as their time source
+    /// and capsules as their alarm.
+    pub fn time(&self) -> &SimTime {
+        &self.time
+    }
+
+    /// Let `us` microseconds pass. Processes call this to simulate how long
+    /// they compute, which also counts down the SysTick.
+    pub fn advance_time(&self, us: u32) {
+        self.time.advance(us);
+        self.systick.advance(us);
+    }
+
+    /// Raise an interrupt. The kernel calls `handler` the next time it handles
+    /// interrupts, as the interrupt handler of a peripheral, which usually
+    /// calls the client of a capsule.
+    pub fn raise_interrupt(&self, handler: impl FnOnce() + 'static) {
+        self.interrupts.borrow_mut().push_back(Box::new(handler));
+    }
+
+    /// How often the kernel put the chip to sleep because there was nothing
+    /// to do.
+    pub fn sleep_count(&self) -> usize {
+        self.sleeps.get()
+    }
+
+    /// How many microseconds were left until the next alarm when the kernel
+    /// last put the chip to sleep, if the kernel knew.
+    pub fn sleep_deadline(&self) -> Option<u32> {
+        self.sleep_deadline.get()
+    }
+}
+
+impl Chip for SimChip {
+    type MPU = SimMpu;
+    type UserspaceKernelBoundary = SimUserspaceKernelBoundary;
+    type SysTick = SimSysTick;
+
+    fn service_pending_interrupts(&self) {
+        while self.has_pending_interrupts() {
+            if self.uart.has_pending_interrupt() {
+                self.uart.handle_interrupt();
+            }
+            if self.time.has_pending_interrupt() {
+                self.time.handle_interrupt();
+            }
+            // Handlers may raise further interrupts, so the queue must not
+            // be borrowed while one runs.
+            let handler = self.interrupts.borrow_mut().pop_front();
+            if let Some(handler) = handler {
+                handler();
+            }
+        }
+    }
+
+    fn has_pending_interrupts(&self) -> bool {
+        self.uart.has_pending_interrupt()
+            || self.time.has_pending_interrupt()
+            || !self.interrupts.borrow().is_empty()
+    }
+
+    fn mpu(&self) -> &SimMpu {
+        &self.mpu
+    }
+
+    fn systick(&self) -> &SimSysTick {
+        &self.systick
+    }
+
+    fn userspace_kernel_boundary(&self) -> &SimUserspaceKernelBoundary {
+        &self.userspace_kernel_boundary
+    }
+
+    fn sleep(&self) {
+        self.sleeps.set(self.sleeps.get() + 1);
+        self.sleep_deadline.set(None);
+    }
+
+    fn sleep_until(&self, us_until_deadline: Option<u32>) {
+        self.sleeps.set(self.sleeps.get() + 1);
+        self.sleep_deadline.set(us_until_deadline);
+    }
+
+    unsafe fn atomic<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        f()
+    }
+
+    unsafe fn print_state(&self, writer: &mut dyn Write) {
+        let _ = writer.write_fmt(format_args!(
+            "\r\n---| Simulated chip |---\r\n{} pending interrupts, slept {} times\r\n",
+            self.interrupts.borrow().len(),
+            self.sleeps.get()
+        ));
+    }
+}
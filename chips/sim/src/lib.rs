@@ -0,0 +1,44 @@
+//! A simulated chip for running the kernel on the host in tests.
+//!
+//! `SimChip` implements `kernel::Chip` without any hardware: the MPU only
+//! checks and records the regions the kernel sets up, time only passes when a
+//! test or a process calls `advance_time()`, interrupts are closures that
+//! tests raise, and processes are Rust closures that return the system calls
+//! they make instead of code in flash. `Sim` puts a kernel, the chip, a
+//! platform with the drivers a test adds, a scheduler and the processes
+//! together, so that tests can run the real kernel loop, process loading,
+//! grants and system call handling with `cargo test`.
+//!
+//! ```ignore
+//! let mut sim = Sim::new();
+//! sim.add_app("hello", 1024, |resume| match resume {
+//!     Resume::Start(_) => Action::Syscall(Syscall::COMMAND {
+//!         driver_number: 0x90000,
+//!         subdriver_number: 0,
+//!         arg0: 0,
+//!         arg1: 0,
+//!     }),
+//!     _ => Action::Syscall(Syscall::YIELD),
+//! });
+//! sim.load().unwrap();
+//! sim.run_until_idle(100);
+//! ```
+//!
+//! Only one `Sim` exists at a time, as the kernel keeps some state, such as
+//! the debug writer, in globals. `Sim::new()` waits until the previous one is
+//! dropped, which serializes tests that run on several threads.
+
+#![crate_name = "sim"]
+
+pub mod chip;
+pub mod mpu;
+pub mod sim;
+pub mod syscall;
+pub mod time;
+pub mod uart;
+
+pub use crate::chip::SimChip;
+pub use crate::mpu::{SimMpu, SimMpuConfig};
+pub use crate::sim::{Sim, SimPlatform};
+pub use crate::syscall::{Action, Resume, SimUserspaceKernelBoundary};
+pub use crate::time::{SimSysTick, SimTime};
@@ -0,0 +1,250 @@
+//! A simulated MPU that checks the regions the kernel allocates and keeps
+//! track of the configuration the kernel applies, so that tests can check
+//! which memory a process can access.
+
+use std::cell::{Cell, RefCell};
+use std::cmp;
+use std::fmt;
+
+use kernel::mpu::{Permissions, Region, MPU};
+use kernel::AppId;
+
+/// Number of regions, as on the Cortex-M MPU. One of them is used for the
+/// app-owned part of process memory.
+const NUM_REGIONS: usize = 8;
+
+#[derive(Copy, Clone)]
+struct SimRegion {
+    start: usize,
+    end: usize,
+    permissions: Permissions,
+}
+
+impl SimRegion {
+    fn overlaps(&self, start: usize, end: usize) -> bool {
+        self.start < end && start < self.end
+    }
+
+    fn permits(&self, write: bool) -> bool {
+        match self.permissions {
+            Permissions::ReadWriteExecute | Permissions::ReadWriteOnly => true,
+            Permissions::ReadExecuteOnly | Permissions::ReadOnly => !write,
+            Permissions::ExecuteOnly => false,
+        }
+    }
+}
+
+impl fmt::Display for SimRegion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let permissions = match self.permissions {
+            Permissions::ReadWriteExecute => "rwx",
+            Permissions::ReadWriteOnly => "rw-",
+            Permissions::ReadExecuteOnly => "r-x",
+            Permissions::ReadOnly => "r--",
+            Permissions::ExecuteOnly => "--x",
+        };
+        write!(
+            f,
+            "[{:#010X}:{:#010X}] {}",
+            self.start, self.end, permissions
+        )
+    }
+}
+
+/// The memory block of a process. The app owns the part below `app_break`, and
+/// the kernel the part from `kernel_break` on.
+#[derive(Copy, Clone)]
+struct AppMemory {
+    start: usize,
+    end: usize,
+    app_break: usize,
+    kernel_break: usize,
+    permissions: Permissions,
+}
+
+impl AppMemory {
+    fn region(&self) -> SimRegion {
+        SimRegion {
+            start: self.start,
+            end: self.app_break,
+            permissions: self.permissions,
+        }
+    }
+}
+
+/// The regions of one process.
+#[derive(Default)]
+pub struct SimMpuConfig {
+    regions: [Option<SimRegion>; NUM_REGIONS - 1],
+    app_memory: Option<AppMemory>,
+}
+
+impl SimMpuConfig {
+    fn overlaps(&self, start: usize, end: usize) -> bool {
+        self.regions
+            .iter()
+            .flatten()
+            .chain(self.app_memory.map(|memory| memory.region()).iter())
+            .any(|region| region.overlaps(start, end))
+    }
+}
+
+impl fmt::Display for SimMpuConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "\r\n Simulated MPU")?;
+        if let Some(memory) = self.app_memory {
+            write!(
+                f,
+                "\r\n  App memory {} (kernel memory from {:#010X})",
+                memory.region(),
+                memory.kernel_break
+            )?;
+        }
+        for region in self.regions.iter().flatten() {
+            write!(f, "\r\n  Region {}", region)?;
+        }
+        write!(f, "\r\n")
+    }
+}
+
+/// An MPU that refuses regions that do not fit in the memory the kernel
+/// offers or overlap other regions, like a real MPU without alignment
+/// constraints.
+pub struct SimMpu {
+    enabled: Cell<bool>,
+    /// The regions the MPU was last configured with.
+    configured: RefCell<Vec<SimRegion>>,
+    /// The process the MPU was last configured for.
+    configured_for: Cell<Option<AppId>>,
+}
+
+impl SimMpu {
+    pub fn new() -> SimMpu {
+        SimMpu {
+            enabled: Cell::new(false),
+            configured: RefCell::new(Vec::new()),
+            configured_for: Cell::new(None),
+        }
+    }
+
+    /// Whether the kernel enabled the MPU to run a process.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.get()
+    }
+
+    /// The process the kernel last configured the MPU for.
+    pub fn configured_for(&self) -> Option<AppId> {
+        self.configured_for.get()
+    }
+
+    /// Whether the process the MPU was last configured for can read, or with
+    /// `write` write, the `len` bytes starting at `address`.
+    pub fn permits(&self, address: *const u8, len: usize, write: bool) -> bool {
+        let start = address as usize;
+        let end = start + len;
+        self.configured
+            .borrow()
+            .iter()
+            .any(|region| region.start <= start && end <= region.end && region.permits(write))
+    }
+}
+
+impl MPU for SimMpu {
+    type MpuConfig = SimMpuConfig;
+
+    fn enable_mpu(&self) {
+        self.enabled.set(true);
+    }
+
+    fn disable_mpu(&self) {
+        self.enabled.set(false);
+    }
+
+    fn number_total_regions(&self) -> usize {
+        NUM_REGIONS
+    }
+
+    fn allocate_region(
+        &self,
+        unallocated_memory_start: *const u8,
+        unallocated_memory_size: usize,
+        min_region_size: usize,
+        permissions: Permissions,
+        config: &mut SimMpuConfig,
+    ) -> Option<Region> {
+        if min_region_size > unallocated_memory_size {
+            return None;
+        }
+        let start = unallocated_memory_start as usize;
+        let end = start + min_region_size;
+        if config.overlaps(start, end) {
+            return None;
+        }
+        let slot = config.regions.iter_mut().find(|region| region.is_none())?;
+        *slot = Some(SimRegion {
+            start: start,
+            end: end,
+            permissions: permissions,
+        });
+        Some(Region::new(unallocated_memory_start, min_region_size))
+    }
+
+    fn allocate_app_memory_region(
+        &self,
+        unallocated_memory_start: *const u8,
+        unallocated_memory_size: usize,
+        min_memory_size: usize,
+        initial_app_memory_size: usize,
+        initial_kernel_memory_size: usize,
+        permissions: Permissions,
+        config: &mut SimMpuConfig,
+    ) -> Option<(*const u8, usize)> {
+        let memory_size = cmp::max(
+            min_memory_size,
+            initial_app_memory_size.checked_add(initial_kernel_memory_size)?,
+        );
+        if config.app_memory.is_some() || memory_size > unallocated_memory_size {
+            return None;
+        }
+        let start = unallocated_memory_start as usize;
+        let end = start + memory_size;
+        if config.overlaps(start, end) {
+            return None;
+        }
+        config.app_memory = Some(AppMemory {
+            start: start,
+            end: end,
+            app_break: start + initial_app_memory_size,
+            kernel_break: end - initial_kernel_memory_size,
+            permissions: permissions,
+        });
+        Some((unallocated_memory_start, memory_size))
+    }
+
+    fn update_app_memory_region(
+        &self,
+        app_memory_break: *const u8,
+        kernel_memory_break: *const u8,
+        permissions: Permissions,
+        config: &mut SimMpuConfig,
+    ) -> Result<(), ()> {
+        let memory = config.app_memory.as_mut().ok_or(())?;
+        let app_break = app_memory_break as usize;
+        let kernel_break = kernel_memory_break as usize;
+        if app_break < memory.start || app_break > kernel_break || kernel_break > memory.end {
+            return Err(());
+        }
+        memory.app_break = app_break;
+        memory.kernel_break = kernel_break;
+        memory.permissions = permissions;
+        Ok(())
+    }
+
+    fn configure_mpu(&self, config: &SimMpuConfig, app_id: &AppId) {
+        let mut configured = self.configured.borrow_mut();
+        configured.clear();
+        configured.extend(config.regions.iter().flatten());
+        configured.extend(config.app_memory.map(|memory| memory.region()));
+        self.configured_for.set(Some(*app_id));
+    }
+}
@@ -0,0 +1,368 @@
+//! A simulated board: a kernel running processes on a `SimChip`.
+
+use std::cell::RefCell;
+use std::mem;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+use kernel::capabilities;
+use kernel::common::RingBuffer;
+use kernel::create_capability;
+use kernel::debug;
+use kernel::hil;
+use kernel::ipc::{self, IPC};
+use kernel::procs::{
+    self, DynamicProcessLoader, FaultResponse, LoadedProcessMemory, ProcessLoadError,
+    ProcessLoader, ProcessType,
+};
+use kernel::schedulers::RoundRobinSched;
+use kernel::{Chip, Driver, Grant, Kernel, Platform, Scheduler};
+
+use crate::chip::SimChip;
+use crate::syscall::{Action, Resume};
+
+/// How many processes a `Sim` can run.
+pub const NUM_PROCS: usize = 4;
+
+/// How much memory the processes of a `Sim` share.
+const APP_MEMORY_SIZE: usize = 64 * 1024;
+
+/// How much flash each simulated app takes.
+const APP_FLASH_SIZE: usize = 256;
+
+/// Whether a `Sim` exists.
+static SIM_EXISTS: AtomicBool = AtomicBool::new(false);
+
+/// Leak `value` to get a reference to it that lives as long as the statics of
+/// a real board.
+fn leak<T>(value: T) -> &'static mut T {
+    Box::leak(Box::new(value))
+}
+
+/// The system call drivers of a simulated board.
+pub struct SimPlatform {
+    drivers: RefCell<Vec<(usize, &'static dyn Driver)>>,
+}
+
+impl Platform for SimPlatform {
+    fn with_driver<F, R>(&self, driver_num: usize, f: F) -> R
+    where
+        F: FnOnce(Option<&dyn Driver>) -> R,
+    {
+        let driver = self
+            .drivers
+            .borrow()
+            .iter()
+            .find(|(num, _)| *num == driver_num)
+            .map(|(_, driver)| *driver);
+        f(driver)
+    }
+}
+
+/// An app, before its process is loaded.
+struct SimApp {
+    name: &'static str,
+    minimum_ram_size: usize,
+    /// TLV elements to add to the TBF header, as type and value.
+    tlvs: Vec<(u16, Vec<u8>)>,
+    code: Box<dyn FnMut(Resume) -> Action>,
+}
+
+pub struct Sim {
+    kernel: &'static Kernel,
+    chip: &'static SimChip,
+    platform: &'static SimPlatform,
+    scheduler: &'static dyn Scheduler<SimChip>,
+    ipc: Option<&'static IPC>,
+    apps: Vec<SimApp>,
+    fault_response: FaultResponse,
+}
+
+impl Sim {
+    /// Create a kernel without processes. Waits until no other `Sim` exists.
+    pub fn new() -> Sim {
+        while SIM_EXISTS
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            thread::yield_now();
+        }
+
+        let kernel = leak(Kernel::new(Box::leak(
+            vec![None; NUM_PROCS].into_boxed_slice(),
+        )));
+        let chip = leak(SimChip::new());
+
+        let ring_buffer = leak(RingBuffer::new(Box::leak(vec![0; 4096].into_boxed_slice())));
+        let debug_writer = leak(debug::DebugWriter::new(
+            chip.uart(),
+            Box::leak(vec![0; 64].into_boxed_slice()),
+            ring_buffer,
+        ));
+        hil::uart::Transmit::set_transmit_client(chip.uart(), &*debug_writer);
+        unsafe {
+            debug::set_debug_writer_wrapper(leak(debug::DebugWriterWrapper::new(debug_writer)));
+        }
+
+        Sim {
+            kernel: kernel,
+            chip: chip,
+            platform: leak(SimPlatform {
+                drivers: RefCell::new(Vec::new()),
+            }),
+            scheduler: leak(RoundRobinSched::new()),
+            ipc: None,
+            apps: Vec::new(),
+            fault_response: FaultResponse::Panic,
+        }
+    }
+
+    pub fn kernel(&self) -> &'static Kernel {
+        self.kernel
+    }
+
+    pub fn chip(&self) -> &'static SimChip {
+        self.chip
+    }
+
+    pub fn platform(&self) -> &'static SimPlatform {
+        self.platform
+    }
+
+    /// Create a grant. Grants must be created before the processes are
+    /// loaded.
+    pub fn create_grant<T: Default>(&self) -> Grant<T> {
+        let grant_cap = create_capability!(capabilities::MemoryAllocationCapability);
+        self.kernel.create_grant(&grant_cap)
+    }
+
+    /// Make IPC and its mailbox driver available to processes. Like grants,
+    /// IPC must be created before the processes are loaded.
+    pub fn add_ipc(&mut self) -> &'static IPC {
+        let grant_cap = create_capability!(capabilities::MemoryAllocationCapability);
+        let ipc = leak(IPC::new(self.kernel, &grant_cap));
+        self.add_driver(ipc::DRIVER_NUM, ipc);
+        self.add_driver(ipc::MAILBOX_DRIVER_NUM, ipc.mailbox());
+        self.ipc = Some(ipc);
+        ipc
+    }
+
+    /// Schedule processes with `scheduler` instead of the default round robin
+    /// scheduler.
+    pub fn set_scheduler(&mut self, scheduler: &'static dyn Scheduler<SimChip>) {
+        self.scheduler = scheduler;
+    }
+
+    /// Make `driver` available to processes as driver `driver_num`.
+    pub fn add_driver(&self, driver_num: usize, driver: &'static dyn Driver) {
+        self.platform
+            .drivers
+            .borrow_mut()
+            .push((driver_num, driver));
+    }
+
+    /// Add an app that needs `minimum_ram_size` bytes of memory and runs
+    /// `code`. Apps are loaded by `load()`.
+    ///
+    /// The kernel starts every process with its app break 3 kB into its
+    /// memory, so apps that use grants need more than 3 kB of memory.
+    pub fn add_app(
+        &mut self,
+        name: &'static str,
+        minimum_ram_size: usize,
+        code: impl FnMut(Resume) -> Action + 'static,
+    ) {
+        self.add_app_with_tlvs(name, minimum_ram_size, &[], code);
+    }
+
+    /// Add an app whose TBF header also contains `tlvs`, given as type and
+    /// value.
+    pub fn add_app_with_tlvs(
+        &mut self,
+        name: &'static str,
+        minimum_ram_size: usize,
+        tlvs: &[(u16, &[u8])],
+        code: impl FnMut(Resume) -> Action + 'static,
+    ) {
+        self.apps.push(SimApp {
+            name: name,
+            minimum_ram_size: minimum_ram_size,
+            tlvs: tlvs
+                .iter()
+                .map(|(tipe, value)| (*tipe, value.to_vec()))
+                .collect(),
+            code: Box::new(code),
+        });
+    }
+
+    /// How the kernel responds to processes that fault. The default is to
+    /// panic, which fails the test.
+    pub fn set_fault_response(&mut self, fault_response: FaultResponse) {
+        self.fault_response = fault_response;
+    }
+
+    /// Put the apps in flash, and create processes for them.
+    pub fn load(&mut self) -> Result<(), ProcessLoadError> {
+        let flash = self.write_apps();
+        let process_mgmt_cap = create_capability!(capabilities::ProcessManagementCapability);
+        procs::load_processes(
+            self.kernel,
+            self.chip,
+            flash,
+            app_memory(APP_MEMORY_SIZE),
+            self.fault_response,
+            &process_mgmt_cap,
+        )
+    }
+
+    /// Put the apps in flash, and create processes for them with a
+    /// `ProcessLoader` that has `memory_size` bytes of memory, as a capsule
+    /// that loads apps at runtime does. Returns the loader, to unload and load
+    /// processes later.
+    pub fn load_dynamically(
+        &mut self,
+        memory_size: usize,
+    ) -> Result<&'static ProcessLoader<SimChip>, ProcessLoadError> {
+        let num_apps = self.apps.len();
+        let flash = self.write_apps();
+        let process_mgmt_cap = create_capability!(capabilities::ProcessManagementCapability);
+        let loader = leak(ProcessLoader::new(
+            self.kernel,
+            self.chip,
+            flash,
+            app_memory(memory_size),
+            Box::leak(
+                (0..NUM_PROCS)
+                    .map(|_| LoadedProcessMemory::default())
+                    .collect(),
+            ),
+            self.fault_response,
+            &process_mgmt_cap,
+        ));
+        for index in 0..num_apps {
+            loader.load_process(flash.as_ptr() as usize + index * APP_FLASH_SIZE)?;
+        }
+        Ok(loader)
+    }
+
+    /// Put the apps in flash, with the code of each app right after its
+    /// header, and return the flash.
+    fn write_apps(&mut self) -> &'static [u8] {
+        let mut flash = vec![0; APP_FLASH_SIZE * self.apps.len()];
+        let header_sizes: Vec<usize> = self
+            .apps
+            .iter()
+            .zip(flash.chunks_mut(APP_FLASH_SIZE))
+            .map(|(app, image)| write_tbf_header(image, app.name, app.minimum_ram_size, &app.tlvs))
+            .collect();
+        let flash: &'static [u8] = Box::leak(flash.into_boxed_slice());
+        for (index, app) in self.apps.drain(..).enumerate() {
+            let entry = flash.as_ptr() as usize + index * APP_FLASH_SIZE + header_sizes[index];
+            self.chip
+                .userspace_kernel_boundary()
+                .add_app(entry, app.code);
+        }
+        flash
+    }
+
+    /// The process with the name `name`.
+    pub fn process(&self, name: &str) -> Option<&'static dyn ProcessType> {
+        let process_mgmt_cap = create_capability!(capabilities::ProcessManagementCapability);
+        (0..NUM_PROCS)
+            .filter_map(|index| self.kernel.get_process_capability(index, &process_mgmt_cap))
+            .find(|process| process.get_process_name() == name)
+    }
+
+    /// Run `iterations` iterations of the kernel loop.
+    pub fn run(&self, iterations: usize) {
+        let main_loop_cap = create_capability!(capabilities::MainLoopCapability);
+        for _ in 0..iterations {
+            self.kernel.kernel_loop_operation(
+                self.platform,
+                self.chip,
+                self.ipc,
+                self.scheduler,
+                &main_loop_cap,
+            );
+        }
+    }
+
+    /// Run the kernel loop until the chip goes to sleep because there is
+    /// nothing left to do, for at most `max_iterations` iterations. Returns
+    /// whether the kernel went to sleep.
+    pub fn run_until_idle(&self, max_iterations: usize) -> bool {
+        let sleeps = self.chip.sleep_count();
+        for _ in 0..max_iterations {
+            self.run(1);
+            if self.chip.sleep_count() != sleeps {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Everything the kernel printed with `debug!()` so far.
+    pub fn debug_output(&self) -> String {
+        self.chip.uart().output()
+    }
+}
+
+/// Memory for processes, allocated in words so that it is aligned for grants.
+fn app_memory(size: usize) -> &'static mut [u8] {
+    let words = Box::leak(vec![0usize; size / mem::size_of::<usize>()].into_boxed_slice());
+    unsafe { std::slice::from_raw_parts_mut(words.as_mut_ptr() as *mut u8, size) }
+}
+
+impl Drop for Sim {
+    fn drop(&mut self) {
+        SIM_EXISTS.store(false, Ordering::Release);
+    }
+}
+
+/// Write the TBF header of an app to the start of `image`, and return its
+/// size.
+fn write_tbf_header(
+    image: &mut [u8],
+    name: &str,
+    minimum_ram_size: usize,
+    tlvs: &[(u16, Vec<u8>)],
+) -> usize {
+    let name = name.as_bytes();
+    let tlvs_size: usize = tlvs
+        .iter()
+        .map(|(_, value)| 4 + (value.len() + 3) / 4 * 4)
+        .sum();
+    let header_size = 16 + 16 + 4 + (name.len() + 3) / 4 * 4 + tlvs_size;
+    let header = &mut image[..header_size];
+    // Base header: version, header size, total size, flags (enabled).
+    header[0..2].copy_from_slice(&2u16.to_le_bytes());
+    header[2..4].copy_from_slice(&(header_size as u16).to_le_bytes());
+    header[4..8].copy_from_slice(&(APP_FLASH_SIZE as u32).to_le_bytes());
+    header[8..12].copy_from_slice(&1u32.to_le_bytes());
+    // Main element: init function offset, protected size, minimum RAM size.
+    header[16..18].copy_from_slice(&1u16.to_le_bytes());
+    header[18..20].copy_from_slice(&12u16.to_le_bytes());
+    header[28..32].copy_from_slice(&(minimum_ram_size as u32).to_le_bytes());
+    // Package name element.
+    header[32..34].copy_from_slice(&3u16.to_le_bytes());
+    header[34..36].copy_from_slice(&(name.len() as u16).to_le_bytes());
+    header[36..36 + name.len()].copy_from_slice(name);
+    // Further elements, each padded to a multiple of 4 bytes.
+    let mut offset = 36 + (name.len() + 3) / 4 * 4;
+    for (tipe, value) in tlvs {
+        header[offset..offset + 2].copy_from_slice(&tipe.to_le_bytes());
+        header[offset + 2..offset + 4].copy_from_slice(&(value.len() as u16).to_le_bytes());
+        header[offset + 4..offset + 4 + value.len()].copy_from_slice(value);
+        offset += 4 + (value.len() + 3) / 4 * 4;
+    }
+
+    let checksum = header
+        .chunks(4)
+        .enumerate()
+        .filter(|(i, _)| *i != 3)
+        .fold(0, |checksum, (_, word)| {
+            checksum ^ u32::from_le_bytes([word[0], word[1], word[2], word[3]])
+        });
+    header[12..16].copy_from_slice(&checksum.to_le_bytes());
+    header_size
+}
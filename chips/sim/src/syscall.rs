@@ -0,0 +1,159 @@
+//! Processes as closures, switched to through a simulated
+//! `UserspaceKernelBoundary`.
+//!
+//! Each process is a closure that is called whenever the kernel switches to
+//! the process. It is told why it runs again (`Resume`), and returns what the
+//! process does next (`Action`): make a system call or fault. The closure for a
+//! process is found by the address of the process's entry point, which is
+//! where the kernel first starts the process.
+
+use std::cell::RefCell;
+use std::fmt::Write;
+
+use kernel::procs::{FunctionCall, FunctionCallSource};
+use kernel::syscall::{ContextSwitchReason, Syscall, UserspaceKernelBoundary};
+
+/// Why a simulated process runs again.
+#[derive(Copy, Clone, Debug)]
+pub enum Resume {
+    /// The process starts, or restarts after a fault, at its entry point. The
+    /// arguments of the call are the start of the process's flash, the start
+    /// of its memory, the size of its memory and its app break.
+    Start(FunctionCall),
+    /// The last system call returned this value.
+    Returned(isize),
+    /// The kernel calls a callback the process subscribed.
+    Callback(FunctionCall),
+}
+
+/// What a simulated process does next.
+#[derive(Copy, Clone, Debug)]
+pub enum Action {
+    Syscall(Syscall),
+    /// The process faults, for example by accessing memory it must not.
+    Fault,
+}
+
+/// The code of a simulated process.
+pub type App = dyn FnMut(Resume) -> Action;
+
+/// The state of a simulated process while it is not running.
+#[derive(Default)]
+pub struct SimStoredState {
+    /// Index of the process's closure, once it was started.
+    app: Option<usize>,
+    /// Why the process runs next.
+    resume: Option<Resume>,
+    /// The last action of the process.
+    last_action: Option<Action>,
+}
+
+pub struct SimUserspaceKernelBoundary {
+    /// The closures of the processes, with the addresses of their entry points.
+    apps: RefCell<Vec<(usize, Box<App>)>>,
+}
+
+impl SimUserspaceKernelBoundary {
+    pub fn new() -> SimUserspaceKernelBoundary {
+        SimUserspaceKernelBoundary {
+            apps: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Run `app` for the process whose entry point is at `entry`.
+    pub fn add_app(&self, entry: usize, app: Box<App>) {
+        self.apps.borrow_mut().push((entry, app));
+    }
+}
+
+impl UserspaceKernelBoundary for SimUserspaceKernelBoundary {
+    type StoredState = SimStoredState;
+
+    unsafe fn initialize_process(
+        &self,
+        stack_pointer: *const usize,
+        _stack_size: usize,
+        state: &mut Self::StoredState,
+    ) -> Result<*const usize, ()> {
+        *state = SimStoredState::default();
+        Ok(stack_pointer)
+    }
+
+    unsafe fn set_syscall_return_value(
+        &self,
+        _stack_pointer: *const usize,
+        state: &mut Self::StoredState,
+        return_value: isize,
+    ) {
+        state.resume = Some(Resume::Returned(return_value));
+    }
+
+    unsafe fn set_process_function(
+        &self,
+        stack_pointer: *const usize,
+        _remaining_stack_memory: usize,
+        state: &mut Self::StoredState,
+        callback: FunctionCall,
+    ) -> Result<*mut usize, *mut usize> {
+        match callback.source {
+            FunctionCallSource::Kernel => {
+                state.app = self
+                    .apps
+                    .borrow()
+                    .iter()
+                    .position(|(entry, _)| *entry == callback.pc);
+                state.resume = Some(Resume::Start(callback));
+            }
+            FunctionCallSource::Driver(_) => {
+                state.resume = Some(Resume::Callback(callback));
+            }
+        }
+        Ok(stack_pointer as *mut usize)
+    }
+
+    unsafe fn switch_to_process(
+        &self,
+        stack_pointer: *const usize,
+        state: &mut Self::StoredState,
+    ) -> (*mut usize, ContextSwitchReason) {
+        // A process without code faults as soon as it starts.
+        let action = match state.app {
+            Some(index) => {
+                // Processes only run again after a system call returned or
+                // the kernel called a function of the process.
+                let resume = state.resume.take().unwrap_or(Resume::Returned(0));
+                let mut apps = self.apps.borrow_mut();
+                let app = &mut apps[index].1;
+                app(resume)
+            }
+            None => Action::Fault,
+        };
+        state.last_action = Some(action);
+        let reason = match action {
+            Action::Syscall(syscall) => ContextSwitchReason::SyscallFired { syscall: syscall },
+            Action::Fault => ContextSwitchReason::Fault,
+        };
+        (stack_pointer as *mut usize, reason)
+    }
+
+    unsafe fn print_context(
+        &self,
+        stack_pointer: *const usize,
+        state: &Self::StoredState,
+        writer: &mut dyn Write,
+    ) {
+        let _ = writer.write_fmt(format_args!(
+            "\r\n sp: {:?}  last action: {:?}\r\n",
+            stack_pointer, state.last_action
+        ));
+    }
+
+    unsafe fn store_registers(
+        &self,
+        _stack_pointer: *const usize,
+        _state: &Self::StoredState,
+        _registers: &mut [usize],
+    ) -> usize {
+        0
+    }
+}
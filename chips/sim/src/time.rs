@@ -0,0 +1,152 @@
+//! Simulated time: a clock and a `SysTick` that only move when a test, or a
+//! simulated process, says that time passed.
+
+use std::cell::Cell;
+
+use kernel::common::cells::OptionalCell;
+use kernel::hil::time::{Alarm, AlarmClient, Frequency, Time};
+use kernel::SysTick;
+
+/// 1 MHz `Frequency`, so that tics of the simulated clock are microseconds.
+#[derive(Debug)]
+pub struct Freq1MHz;
+
+impl Frequency for Freq1MHz {
+    fn frequency() -> u32 {
+        1_000_000
+    }
+}
+
+/// A 32-bit microsecond clock, for schedulers and capsules that need a time
+/// source. Its alarm raises an interrupt once simulated time reaches it.
+pub struct SimTime {
+    now: Cell<u32>,
+    alarm: Cell<u32>,
+    armed: Cell<bool>,
+    /// Whether the alarm went off and its interrupt was not handled yet.
+    expired: Cell<bool>,
+    client: OptionalCell<&'static dyn AlarmClient>,
+}
+
+impl SimTime {
+    pub fn new() -> SimTime {
+        SimTime {
+            now: Cell::new(0),
+            alarm: Cell::new(0),
+            armed: Cell::new(false),
+            expired: Cell::new(false),
+            client: OptionalCell::empty(),
+        }
+    }
+
+    pub(crate) fn advance(&self, us: u32) {
+        if self.armed.get() && self.alarm.get().wrapping_sub(self.now.get()) <= us {
+            self.armed.set(false);
+            self.expired.set(true);
+        }
+        self.now.set(self.now.get().wrapping_add(us));
+    }
+
+    pub(crate) fn has_pending_interrupt(&self) -> bool {
+        self.expired.get()
+    }
+
+    pub(crate) fn handle_interrupt(&self) {
+        self.expired.set(false);
+        self.client.map(|client| client.fired());
+    }
+}
+
+impl Time for SimTime {
+    type Frequency = Freq1MHz;
+
+    fn now(&self) -> u32 {
+        self.now.get()
+    }
+
+    fn max_tics(&self) -> u32 {
+        u32::MAX
+    }
+}
+
+impl Alarm<'static> for SimTime {
+    fn set_alarm(&self, tics: u32) {
+        self.alarm.set(tics);
+        self.expired.set(tics == self.now.get());
+        self.armed.set(!self.expired.get());
+    }
+
+    fn get_alarm(&self) -> u32 {
+        self.alarm.get()
+    }
+
+    fn set_client(&'static self, client: &'static dyn AlarmClient) {
+        self.client.set(client);
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.armed.get() || self.expired.get()
+    }
+
+    fn disable(&self) {
+        self.armed.set(false);
+        self.expired.set(false);
+    }
+}
+
+/// A `SysTick` that counts down while enabled, as simulated time passes.
+pub struct SimSysTick {
+    remaining_us: Cell<u32>,
+    enabled: Cell<bool>,
+    overflowed: Cell<bool>,
+}
+
+impl SimSysTick {
+    pub fn new() -> SimSysTick {
+        SimSysTick {
+            remaining_us: Cell::new(0),
+            enabled: Cell::new(false),
+            overflowed: Cell::new(false),
+        }
+    }
+
+    pub(crate) fn advance(&self, us: u32) {
+        if self.enabled.get() && !self.overflowed.get() {
+            if us >= self.remaining_us.get() {
+                self.remaining_us.set(0);
+                self.overflowed.set(true);
+            } else {
+                self.remaining_us.set(self.remaining_us.get() - us);
+            }
+        }
+    }
+}
+
+impl SysTick for SimSysTick {
+    fn set_timer(&self, us: u32) {
+        self.remaining_us.set(us);
+        self.overflowed.set(false);
+    }
+
+    fn greater_than(&self, us: u32) -> bool {
+        self.remaining_us.get() > us
+    }
+
+    fn overflowed(&self) -> bool {
+        self.overflowed.get()
+    }
+
+    fn get_remaining_us(&self) -> u32 {
+        self.remaining_us.get()
+    }
+
+    fn reset(&self) {
+        self.remaining_us.set(0);
+        self.enabled.set(false);
+        self.overflowed.set(false);
+    }
+
+    fn enable(&self, _with_interrupt: bool) {
+        self.enabled.set(true);
+    }
+}
@@ -0,0 +1,75 @@
+//! A UART that collects what is transmitted, for the kernel's debug output.
+
+use std::cell::RefCell;
+
+use kernel::common::cells::OptionalCell;
+use kernel::hil::uart;
+use kernel::ReturnCode;
+
+pub struct SimUart {
+    client: OptionalCell<&'static dyn uart::TransmitClient>,
+    /// Everything transmitted so far.
+    output: RefCell<String>,
+    /// The buffer of the transmission that has not completed yet.
+    pending: RefCell<Option<(&'static mut [u8], usize)>>,
+}
+
+impl SimUart {
+    pub fn new() -> SimUart {
+        SimUart {
+            client: OptionalCell::empty(),
+            output: RefCell::new(String::new()),
+            pending: RefCell::new(None),
+        }
+    }
+
+    /// Everything transmitted so far.
+    pub fn output(&self) -> String {
+        self.output.borrow().clone()
+    }
+
+    pub(crate) fn has_pending_interrupt(&self) -> bool {
+        self.pending.borrow().is_some()
+    }
+
+    /// Complete the pending transmission, as the interrupt of a real UART
+    /// would.
+    pub(crate) fn handle_interrupt(&self) {
+        let pending = self.pending.borrow_mut().take();
+        if let Some((buffer, length)) = pending {
+            self.client.map(move |client| {
+                client.transmitted_buffer(buffer, length, ReturnCode::SUCCESS);
+            });
+        }
+    }
+}
+
+impl uart::Transmit<'static> for SimUart {
+    fn set_transmit_client(&self, client: &'static dyn uart::TransmitClient) {
+        self.client.set(client);
+    }
+
+    fn transmit_buffer(
+        &self,
+        tx_buffer: &'static mut [u8],
+        tx_len: usize,
+    ) -> (ReturnCode, Option<&'static mut [u8]>) {
+        if self.pending.borrow().is_some() {
+            return (ReturnCode::EBUSY, Some(tx_buffer));
+        }
+        let length = std::cmp::min(tx_len, tx_buffer.len());
+        let text = String::from_utf8_lossy(&tx_buffer[..length]).into_owned();
+        print!("{}", text);
+        self.output.borrow_mut().push_str(&text);
+        *self.pending.borrow_mut() = Some((tx_buffer, length));
+        (ReturnCode::SUCCESS, None)
+    }
+
+    fn transmit_word(&self, _word: u32) -> ReturnCode {
+        ReturnCode::FAIL
+    }
+
+    fn transmit_abort(&self) -> ReturnCode {
+        ReturnCode::FAIL
+    }
+}
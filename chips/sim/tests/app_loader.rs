@@ -0,0 +1,266 @@
+//! Tests of how the app loader capsule recovers from failed flash writes.
+
+use std::cell::Cell;
+
+use capsules::app_loader::{AppLoader, ACK, CHUNK_SIZE, NAK};
+use kernel::common::cells::TakeCell;
+use kernel::hil::nonvolatile_storage::{NonvolatileStorage, NonvolatileStorageClient};
+use kernel::hil::uart::{self, ReceiveClient, TransmitClient};
+use kernel::procs::{DynamicProcessLoader, ProcessLoadError};
+use kernel::{AppId, ReturnCode};
+use sim::Sim;
+
+static FLASH: [u8; 4 * CHUNK_SIZE] = [0; 4 * CHUNK_SIZE];
+
+fn buffer(length: usize) -> &'static mut [u8] {
+    Box::leak(vec![0; length].into_boxed_slice())
+}
+
+struct TestUart {
+    tx_buffer: TakeCell<'static, [u8]>,
+    rx_buffer: TakeCell<'static, [u8]>,
+    rx_len: Cell<usize>,
+}
+
+impl<'a> uart::Transmit<'a> for TestUart {
+    fn set_transmit_client(&self, _client: &'a dyn uart::TransmitClient) {}
+
+    fn transmit_buffer(
+        &self,
+        tx_buffer: &'static mut [u8],
+        _tx_len: usize,
+    ) -> (ReturnCode, Option<&'static mut [u8]>) {
+        self.tx_buffer.replace(tx_buffer);
+        (ReturnCode::SUCCESS, None)
+    }
+
+    fn transmit_word(&self, _word: u32) -> ReturnCode {
+        ReturnCode::FAIL
+    }
+
+    fn transmit_abort(&self) -> ReturnCode {
+        ReturnCode::FAIL
+    }
+}
+
+impl<'a> uart::Receive<'a> for TestUart {
+    fn set_receive_client(&self, _client: &'a dyn uart::ReceiveClient) {}
+
+    fn receive_buffer(
+        &self,
+        rx_buffer: &'static mut [u8],
+        rx_len: usize,
+    ) -> (ReturnCode, Option<&'static mut [u8]>) {
+        self.rx_buffer.replace(rx_buffer);
+        self.rx_len.set(rx_len);
+        (ReturnCode::SUCCESS, None)
+    }
+
+    fn receive_word(&self) -> ReturnCode {
+        ReturnCode::FAIL
+    }
+
+    fn receive_abort(&self) -> ReturnCode {
+        ReturnCode::FAIL
+    }
+}
+
+impl<'a> uart::UartData<'a> for TestUart {}
+
+/// Storage that drops the buffer of a failed write, like a driver that
+/// cannot give it back.
+struct TestStorage {
+    fail: Cell<bool>,
+    buffer: TakeCell<'static, [u8]>,
+    length: Cell<usize>,
+}
+
+impl NonvolatileStorage<'static> for TestStorage {
+    fn set_client(&self, _client: &'static dyn NonvolatileStorageClient<'static>) {}
+
+    fn read(&self, _buffer: &'static mut [u8], _address: usize, _length: usize) -> ReturnCode {
+        ReturnCode::FAIL
+    }
+
+    fn write(&self, buffer: &'static mut [u8], _address: usize, length: usize) -> ReturnCode {
+        if self.fail.get() {
+            ReturnCode::EBUSY
+        } else {
+            self.buffer.replace(buffer);
+            self.length.set(length);
+            ReturnCode::SUCCESS
+        }
+    }
+}
+
+struct TestLoader {
+    app_length: usize,
+    loaded: Cell<usize>,
+}
+
+impl DynamicProcessLoader for TestLoader {
+    fn validate_tbf_header(&self, _app: &[u8]) -> Result<usize, ProcessLoadError> {
+        Ok(self.app_length)
+    }
+
+    fn free_app_flash(&self) -> &'static [u8] {
+        &FLASH
+    }
+
+    fn load_process(&self, _app_address: usize) -> Result<Option<AppId>, ProcessLoadError> {
+        self.loaded.set(self.loaded.get() + 1);
+        Ok(None)
+    }
+
+    fn unload_process(&self, _app: AppId) -> ReturnCode {
+        ReturnCode::EINVAL
+    }
+}
+
+/// Send `length` bytes from the host, starting with the length of a two chunk
+/// app, and return the reply of the loader.
+fn send(uart: &TestUart, app_loader: &AppLoader, length: usize) -> u8 {
+    assert_eq!(uart.rx_len.get(), length);
+    let rx_buffer = uart.rx_buffer.take().expect("UART is not receiving");
+    rx_buffer[..4].copy_from_slice(&(2 * CHUNK_SIZE as u32).to_le_bytes());
+    app_loader.received_buffer(rx_buffer, length, ReturnCode::SUCCESS, uart::Error::None);
+    reply(uart, app_loader)
+}
+
+fn reply(uart: &TestUart, app_loader: &AppLoader) -> u8 {
+    let tx_buffer = uart.tx_buffer.take().expect("no reply");
+    let byte = tx_buffer[0];
+    app_loader.transmitted_buffer(tx_buffer, 1, ReturnCode::SUCCESS);
+    byte
+}
+
+/// Complete the write the storage has started.
+fn finish_write(storage: &TestStorage, app_loader: &AppLoader) {
+    let buffer = storage.buffer.take().expect("no write in progress");
+    app_loader.write_done(buffer, storage.length.get());
+}
+
+/// Send a whole app to the loader and write it to flash.
+fn load_app(uart: &TestUart, storage: &TestStorage, app_loader: &AppLoader) {
+    assert_eq!(send(uart, app_loader, 4), ACK);
+    assert_eq!(send(uart, app_loader, CHUNK_SIZE), ACK);
+    assert!(uart.tx_buffer.is_none());
+    app_loader.received_buffer(
+        uart.rx_buffer.take().unwrap(),
+        CHUNK_SIZE,
+        ReturnCode::SUCCESS,
+        uart::Error::None,
+    );
+    finish_write(storage, app_loader);
+    finish_write(storage, app_loader);
+    assert_eq!(reply(uart, app_loader), ACK);
+}
+
+/// Create the capsule's peripherals. The `Sim` provides the debug writer the
+/// capsule prints errors with.
+fn setup() -> (Sim, TestUart, TestStorage, TestLoader) {
+    let loader = TestLoader {
+        app_length: 2 * CHUNK_SIZE,
+        loaded: Cell::new(0),
+    };
+    let uart = TestUart {
+        tx_buffer: TakeCell::empty(),
+        rx_buffer: TakeCell::empty(),
+        rx_len: Cell::new(0),
+    };
+    let storage = TestStorage {
+        fail: Cell::new(false),
+        buffer: TakeCell::empty(),
+        length: Cell::new(0),
+    };
+    (Sim::new(), uart, storage, loader)
+}
+
+#[test]
+fn test_failed_chunk_write_uses_spare_buffer() {
+    let (sim, uart, storage, loader) = setup();
+    let app_loader = AppLoader::new(
+        &uart,
+        &storage,
+        &loader,
+        buffer(1),
+        buffer(CHUNK_SIZE),
+        buffer(CHUNK_SIZE),
+        buffer(CHUNK_SIZE),
+    );
+    app_loader.start();
+
+    assert_eq!(send(&uart, &app_loader, 4), ACK);
+    assert_eq!(send(&uart, &app_loader, CHUNK_SIZE), ACK);
+    storage.fail.set(true);
+    assert_eq!(send(&uart, &app_loader, CHUNK_SIZE), NAK);
+
+    // The loader waits for a new app with the spare buffer.
+    storage.fail.set(false);
+    load_app(&uart, &storage, &app_loader);
+    assert_eq!(loader.loaded.get(), 1);
+    assert_eq!(uart.rx_len.get(), 4);
+    sim.run_until_idle(10);
+    assert!(sim.debug_output().contains("flash write failed: EBUSY"));
+}
+
+#[test]
+fn test_failed_header_write_uses_spare_buffer() {
+    let (sim, uart, storage, loader) = setup();
+    let app_loader = AppLoader::new(
+        &uart,
+        &storage,
+        &loader,
+        buffer(1),
+        buffer(CHUNK_SIZE),
+        buffer(CHUNK_SIZE),
+        buffer(CHUNK_SIZE),
+    );
+    app_loader.start();
+
+    assert_eq!(send(&uart, &app_loader, 4), ACK);
+    assert_eq!(send(&uart, &app_loader, CHUNK_SIZE), ACK);
+    app_loader.received_buffer(
+        uart.rx_buffer.take().unwrap(),
+        CHUNK_SIZE,
+        ReturnCode::SUCCESS,
+        uart::Error::None,
+    );
+    storage.fail.set(true);
+    finish_write(&storage, &app_loader);
+    assert_eq!(reply(&uart, &app_loader), NAK);
+    assert_eq!(uart.rx_len.get(), 4);
+
+    // The spare buffer holds the header of the next app.
+    storage.fail.set(false);
+    load_app(&uart, &storage, &app_loader);
+    assert_eq!(loader.loaded.get(), 1);
+    sim.run_until_idle(10);
+    assert!(sim.debug_output().contains("flash write failed: EBUSY"));
+}
+
+#[test]
+fn test_failed_write_without_spare_buffer_stops_receiving() {
+    let (sim, uart, storage, loader) = setup();
+    let app_loader = AppLoader::new(
+        &uart,
+        &storage,
+        &loader,
+        buffer(1),
+        buffer(CHUNK_SIZE),
+        buffer(CHUNK_SIZE),
+        buffer(CHUNK_SIZE),
+    );
+    app_loader.start();
+    storage.fail.set(true);
+
+    assert_eq!(send(&uart, &app_loader, 4), ACK);
+    assert_eq!(send(&uart, &app_loader, CHUNK_SIZE), ACK);
+    assert_eq!(send(&uart, &app_loader, CHUNK_SIZE), NAK);
+    assert_eq!(send(&uart, &app_loader, 4), ACK);
+    assert_eq!(send(&uart, &app_loader, CHUNK_SIZE), ACK);
+    assert_eq!(send(&uart, &app_loader, CHUNK_SIZE), NAK);
+    assert!(uart.rx_buffer.is_none());
+    sim.run_until_idle(10);
+    assert!(sim.debug_output().contains("out of buffers"));
+}
@@ -0,0 +1,86 @@
+//! Tests of the crash dump capsule with simulated processes.
+
+use std::cell::Cell;
+
+use capsules::crash_dump::CrashDump;
+use kernel::capabilities::ProcessManagementCapability;
+use kernel::common::cells::TakeCell;
+use kernel::create_capability;
+use kernel::hil::nonvolatile_storage::{NonvolatileStorage, NonvolatileStorageClient};
+use kernel::procs::{AlwaysRestart, FaultResponse};
+use kernel::syscall::Syscall;
+use kernel::ReturnCode;
+use sim::{Action, Resume, Sim};
+
+fn buffer(length: usize) -> &'static mut [u8] {
+    Box::leak(vec![0; length].into_boxed_slice())
+}
+
+/// Storage that fails to start the first `failures` writes and drops their
+/// buffers, like a driver that cannot give them back.
+struct TestStorage {
+    failures: Cell<usize>,
+    writes: Cell<usize>,
+    buffer: TakeCell<'static, [u8]>,
+}
+
+impl NonvolatileStorage<'static> for TestStorage {
+    fn set_client(&self, _client: &'static dyn NonvolatileStorageClient<'static>) {}
+
+    fn read(&self, _buffer: &'static mut [u8], _address: usize, _length: usize) -> ReturnCode {
+        ReturnCode::FAIL
+    }
+
+    fn write(&self, buffer: &'static mut [u8], _address: usize, _length: usize) -> ReturnCode {
+        self.writes.set(self.writes.get() + 1);
+        if self.failures.get() > 0 {
+            self.failures.set(self.failures.get() - 1);
+            ReturnCode::FAIL
+        } else {
+            self.buffer.replace(buffer);
+            ReturnCode::SUCCESS
+        }
+    }
+}
+
+#[test]
+fn test_failed_dump_write_does_not_stop_dumps() {
+    let mut sim = Sim::new();
+    sim.set_fault_response(FaultResponse::Restart(Box::leak(Box::new(
+        AlwaysRestart::new(),
+    ))));
+    let storage: &'static TestStorage = Box::leak(Box::new(TestStorage {
+        failures: Cell::new(1),
+        writes: Cell::new(0),
+        buffer: TakeCell::empty(),
+    }));
+    let crash_dump = Box::leak(Box::new(CrashDump::new_storage(
+        storage,
+        0,
+        2048,
+        buffer(2048),
+        buffer(2048),
+    )));
+    let process_mgmt_cap = create_capability!(ProcessManagementCapability);
+    sim.kernel()
+        .set_crash_dump_client(crash_dump, &process_mgmt_cap);
+
+    // The app faults twice, and is dumped both times.
+    let mut starts = 0;
+    sim.add_app("crasher", 4096, move |resume| match resume {
+        Resume::Start(_) if starts < 2 => {
+            starts += 1;
+            Action::Fault
+        }
+        _ => Action::Syscall(Syscall::YIELD),
+    });
+    sim.load().unwrap();
+    assert!(sim.run_until_idle(100));
+
+    assert_eq!(storage.writes.get(), 2);
+    assert!(sim.debug_output().contains("flash write failed: FAIL"));
+
+    // The second dump was written from the spare buffer.
+    assert!(storage.buffer.is_some());
+    assert_eq!(sim.process("crasher").unwrap().get_restart_count(), 2);
+}
@@ -0,0 +1,328 @@
+//! Tests of IPC between simulated processes.
+
+use std::cell::{Cell, RefCell};
+use std::ptr;
+use std::rc::Rc;
+
+use kernel::ipc;
+use kernel::syscall::Syscall;
+use kernel::ReturnCode;
+use sim::{Action, Resume, Sim};
+
+/// A system call of an app, made with the start of the app's memory.
+type Call = Box<dyn Fn(usize) -> Syscall>;
+
+/// What happened to an app.
+#[derive(Default)]
+struct Log {
+    /// The start of the app's memory.
+    memory_start: Cell<usize>,
+    /// The return values of the system calls of the app.
+    results: RefCell<Vec<isize>>,
+    /// The first three arguments of the callbacks the app got.
+    callbacks: RefCell<Vec<(usize, usize, usize)>>,
+}
+
+impl Log {
+    /// `len` bytes of the app's memory, starting `offset` bytes in.
+    fn memory(&self, offset: usize, len: usize) -> Vec<u8> {
+        let start = (self.memory_start.get() + offset) as *const u8;
+        unsafe { std::slice::from_raw_parts(start, len) }.to_vec()
+    }
+}
+
+/// An app that copies `data` to the start of its memory, makes `calls` one
+/// after the other and then yields. It continues with the next call after
+/// each callback.
+fn app(data: &'static [u8], calls: Vec<Call>, log: Rc<Log>) -> impl FnMut(Resume) -> Action {
+    let mut next = 0;
+    move |resume| {
+        match resume {
+            Resume::Start(call) => {
+                log.memory_start.set(call.argument1);
+                unsafe {
+                    ptr::copy_nonoverlapping(data.as_ptr(), call.argument1 as *mut u8, data.len());
+                }
+            }
+            Resume::Returned(value) => log.results.borrow_mut().push(value),
+            Resume::Callback(call) => {
+                log.callbacks
+                    .borrow_mut()
+                    .push((call.argument0, call.argument1, call.argument2))
+            }
+        }
+        next += 1;
+        calls
+            .get(next - 1)
+            .map_or(Action::Syscall(Syscall::YIELD), |call| {
+                Action::Syscall(call(log.memory_start.get()))
+            })
+    }
+}
+
+/// Share the first `size` bytes of the app's memory with `driver_number`.
+fn allow(driver_number: usize, subdriver_number: usize, size: usize) -> Call {
+    allow_at(driver_number, subdriver_number, 0, size)
+}
+
+/// Share `size` bytes of the app's memory, starting `offset` bytes in, with
+/// `driver_number`.
+fn allow_at(driver_number: usize, subdriver_number: usize, offset: usize, size: usize) -> Call {
+    Box::new(move |memory_start| Syscall::ALLOW {
+        driver_number: driver_number,
+        subdriver_number: subdriver_number,
+        allow_address: (memory_start + offset) as *mut u8,
+        allow_size: size,
+    })
+}
+
+fn subscribe(driver_number: usize, subdriver_number: usize) -> Call {
+    Box::new(move |_| Syscall::SUBSCRIBE {
+        driver_number: driver_number,
+        subdriver_number: subdriver_number,
+        callback_ptr: 0x1000 as *mut (),
+        appdata: 0,
+    })
+}
+
+/// Wait for a callback.
+fn wait() -> Call {
+    Box::new(|_| Syscall::YIELD)
+}
+
+/// Call a command with the identifier of the process `target` as the first
+/// argument.
+fn command(
+    driver_number: usize,
+    subdriver_number: usize,
+    target: &Rc<Cell<usize>>,
+    arg1: usize,
+) -> Call {
+    let target = target.clone();
+    Box::new(move |_| Syscall::COMMAND {
+        driver_number: driver_number,
+        subdriver_number: subdriver_number,
+        arg0: target.get(),
+        arg1: arg1,
+    })
+}
+
+/// Call a mailbox command that takes no arguments.
+fn mailbox(command_num: usize) -> Call {
+    Box::new(move |_| Syscall::COMMAND {
+        driver_number: ipc::MAILBOX_DRIVER_NUM,
+        subdriver_number: command_num,
+        arg0: 0,
+        arg1: 0,
+    })
+}
+
+/// Send the first `len` bytes of the send buffer to the mailbox of the
+/// process `target`.
+fn send(target: &Rc<Cell<usize>>, len: usize) -> Call {
+    command(ipc::MAILBOX_DRIVER_NUM, 1, target, len)
+}
+
+/// Notify the IPC service of the process `target`.
+fn notify(target: &Rc<Cell<usize>>) -> Call {
+    let target = target.clone();
+    Box::new(move |_| Syscall::COMMAND {
+        driver_number: ipc::DRIVER_NUM,
+        subdriver_number: target.get(),
+        arg0: 0,
+        arg1: 0,
+    })
+}
+
+/// The identifier IPC system calls use for the process `name`.
+fn ipc_id(sim: &Sim, name: &str) -> usize {
+    sim.process(name).unwrap().appid().id() + 1
+}
+
+/// Discover the service `service`, notify it and send a message to its
+/// mailbox.
+fn use_service(service: &Rc<Cell<usize>>) -> Vec<Call> {
+    vec![
+        allow(ipc::DRIVER_NUM, 0, "service".len()),
+        notify(service),
+        allow(ipc::MAILBOX_DRIVER_NUM, 0, 4),
+        send(service, 4),
+    ]
+}
+
+#[test]
+fn test_ipc_clients_are_identified_by_persistent_id() {
+    let mut sim = Sim::new();
+    sim.add_ipc();
+    let service = Rc::new(Cell::new(0));
+    let by_id: Rc<Log> = Rc::default();
+    let by_name: Rc<Log> = Rc::default();
+
+    // Clients listed by package name are ignored, as the board does not
+    // require signed apps.
+    let clients = [
+        0, 7, b'b', b'y', b'_', b'n', b'a', b'm', b'e', 1, 4, 7, 0, 0, 0,
+    ];
+    sim.add_app_with_tlvs("service", 4096, &[(9, &clients)], |_| {
+        Action::Syscall(Syscall::YIELD)
+    });
+    sim.add_app_with_tlvs(
+        "by_id",
+        4096,
+        &[(10, &7u32.to_le_bytes())],
+        app(b"service", use_service(&service), by_id.clone()),
+    );
+    sim.add_app(
+        "by_name",
+        4096,
+        app(b"service", use_service(&service), by_name.clone()),
+    );
+    sim.load().unwrap();
+    service.set(ipc_id(&sim, "service"));
+    sim.run_until_idle(100);
+
+    let refused = isize::from(ReturnCode::EINVAL);
+    assert_eq!(
+        *by_id.results.borrow(),
+        [
+            service.get() as isize,
+            0,
+            0,
+            ipc::MAILBOX_DEPTH as isize - 1
+        ]
+    );
+    assert_eq!(*by_name.results.borrow(), [refused, refused, 0, refused]);
+}
+
+#[test]
+fn test_mailbox_queues_messages_in_order() {
+    let mut sim = Sim::new();
+    sim.add_ipc();
+    let receiver = Rc::new(Cell::new(0));
+    let sender: Rc<Log> = Rc::default();
+    let received: Rc<Log> = Rc::default();
+
+    sim.add_app(
+        "sender",
+        4096,
+        app(
+            b"abc",
+            vec![
+                allow(ipc::MAILBOX_DRIVER_NUM, 0, 3),
+                send(&receiver, 1),
+                send(&receiver, 2),
+                send(&receiver, 3),
+            ],
+            sender.clone(),
+        ),
+    );
+    sim.add_app(
+        "receiver",
+        4096,
+        app(
+            b"",
+            vec![
+                allow(ipc::MAILBOX_DRIVER_NUM, 1, 8),
+                mailbox(3),
+                mailbox(2),
+                mailbox(2),
+                mailbox(2),
+                mailbox(2),
+            ],
+            received.clone(),
+        ),
+    );
+    sim.load().unwrap();
+    receiver.set(ipc_id(&sim, "receiver"));
+    sim.run_until_idle(100);
+
+    assert_eq!(*sender.results.borrow(), [0, 3, 2, 1]);
+    let empty = isize::from(ReturnCode::FAIL);
+    let sender_id = ipc_id(&sim, "sender") as isize;
+    assert_eq!(*received.results.borrow(), [0, sender_id, 1, 2, 3, empty]);
+    assert_eq!(received.memory(0, 4), b"abc\0");
+}
+
+#[test]
+fn test_full_mailbox_refuses_messages_until_space() {
+    let mut sim = Sim::new();
+    sim.add_ipc();
+    let receiver = Rc::new(Cell::new(0));
+    let sender: Rc<Log> = Rc::default();
+    let received: Rc<Log> = Rc::default();
+
+    // The sender fills the mailbox, and tries again once it has space.
+    sim.add_app(
+        "sender",
+        4096,
+        app(
+            b"full",
+            vec![
+                subscribe(ipc::MAILBOX_DRIVER_NUM, 1),
+                allow(ipc::MAILBOX_DRIVER_NUM, 0, 4),
+                send(&receiver, 4),
+                send(&receiver, 4),
+                send(&receiver, 4),
+                send(&receiver, 4),
+                send(&receiver, 4),
+                wait(),
+                send(&receiver, 4),
+            ],
+            sender.clone(),
+        ),
+    );
+    sim.add_app(
+        "receiver",
+        4096,
+        app(
+            b"",
+            vec![
+                allow(ipc::MAILBOX_DRIVER_NUM, 1, 4),
+                mailbox(2),
+                command(ipc::MAILBOX_DRIVER_NUM, 4, &receiver, 0),
+            ],
+            received.clone(),
+        ),
+    );
+    sim.load().unwrap();
+    receiver.set(ipc_id(&sim, "receiver"));
+    sim.run_until_idle(100);
+
+    let full = isize::from(ReturnCode::ENOMEM);
+    assert_eq!(*sender.results.borrow(), [0, 0, 3, 2, 1, 0, full, 0]);
+    assert_eq!(*sender.callbacks.borrow(), [(receiver.get(), 1, 0)]);
+    assert_eq!(*received.results.borrow(), [0, 4, 1]);
+}
+
+#[test]
+fn test_process_can_send_messages_to_itself() {
+    let mut sim = Sim::new();
+    sim.add_ipc();
+    let own = Rc::new(Cell::new(0));
+    let log: Rc<Log> = Rc::default();
+
+    sim.add_app(
+        "echo",
+        4096,
+        app(
+            b"ping",
+            vec![
+                subscribe(ipc::MAILBOX_DRIVER_NUM, 0),
+                allow(ipc::MAILBOX_DRIVER_NUM, 0, 4),
+                allow_at(ipc::MAILBOX_DRIVER_NUM, 1, 4, 4),
+                send(&own, 4),
+                wait(),
+                mailbox(2),
+            ],
+            log.clone(),
+        ),
+    );
+    sim.load().unwrap();
+    own.set(ipc_id(&sim, "echo"));
+    sim.run_until_idle(100);
+
+    let depth = ipc::MAILBOX_DEPTH as isize;
+    assert_eq!(*log.results.borrow(), [0, 0, 0, depth - 1, 4]);
+    assert_eq!(*log.callbacks.borrow(), [(own.get(), 4, 1)]);
+    assert_eq!(log.memory(4, 4), b"ping");
+}
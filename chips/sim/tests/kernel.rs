@@ -0,0 +1,459 @@
+//! Tests of the kernel running simulated processes.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use kernel::capabilities::{MainLoopCapability, ProcessManagementCapability};
+use kernel::create_capability;
+use kernel::hil::time::{Alarm, AlarmClient, Freq1KHz, Time};
+use kernel::procs::{
+    AlwaysRestart, DynamicProcessLoader, FaultReason, FaultResponse, ProcessLoadError,
+};
+use kernel::syscall::Syscall;
+use kernel::{AppId, AppSlice, Callback, Driver, Grant, ReturnCode, Shared};
+use sim::{Action, Resume, Sim};
+
+const COUNTER: usize = 0x90000;
+
+#[derive(Default)]
+struct App {
+    callback: Option<Callback>,
+    count: usize,
+}
+
+/// A driver that counts the commands of each process, and calls the
+/// callbacks of all processes when `fire()` is called.
+struct Counter {
+    apps: Grant<App>,
+}
+
+impl Counter {
+    fn fire(&self, value: usize) {
+        self.apps.each(|app| {
+            app.callback
+                .map(|mut callback| callback.schedule(value, app.count, 0));
+        });
+    }
+}
+
+impl Driver for Counter {
+    fn subscribe(
+        &self,
+        subscribe_num: usize,
+        callback: Option<Callback>,
+        appid: AppId,
+    ) -> ReturnCode {
+        match subscribe_num {
+            0 => self
+                .apps
+                .enter(appid, |app, _| {
+                    app.callback = callback;
+                    ReturnCode::SUCCESS
+                })
+                .unwrap_or_else(|err| err.into()),
+            _ => ReturnCode::ENOSUPPORT,
+        }
+    }
+
+    fn command(&self, command_num: usize, _: usize, _: usize, appid: AppId) -> ReturnCode {
+        match command_num {
+            0 => ReturnCode::SUCCESS,
+            1 => self
+                .apps
+                .enter(appid, |app, _| {
+                    app.count += 1;
+                    ReturnCode::SuccessWithValue { value: app.count }
+                })
+                .unwrap_or_else(|err| err.into()),
+            _ => ReturnCode::ENOSUPPORT,
+        }
+    }
+
+    fn allow(&self, _: AppId, _: usize, _: Option<AppSlice<Shared, u8>>) -> ReturnCode {
+        ReturnCode::ENOSUPPORT
+    }
+}
+
+fn command(driver_number: usize, subdriver_number: usize) -> Action {
+    Action::Syscall(Syscall::COMMAND {
+        driver_number: driver_number,
+        subdriver_number: subdriver_number,
+        arg0: 0,
+        arg1: 0,
+    })
+}
+
+fn subscribe(callback_ptr: usize) -> Action {
+    Action::Syscall(Syscall::SUBSCRIBE {
+        driver_number: COUNTER,
+        subdriver_number: 0,
+        callback_ptr: callback_ptr as *mut (),
+        appdata: 0,
+    })
+}
+
+fn counter(sim: &Sim) -> &'static Counter {
+    let counter = Box::leak(Box::new(Counter {
+        apps: sim.create_grant(),
+    }));
+    sim.add_driver(COUNTER, counter);
+    counter
+}
+
+#[test]
+fn test_command_return_values() {
+    let mut sim = Sim::new();
+    counter(&sim);
+    let returned = Rc::new(RefCell::new(Vec::new()));
+    let log = returned.clone();
+    let mut step = 0;
+    sim.add_app("counter", 4096, move |resume| {
+        if let Resume::Returned(value) = resume {
+            log.borrow_mut().push(value);
+        }
+        step += 1;
+        match step {
+            1 | 2 => command(COUNTER, 1),
+            3 => command(COUNTER, 7),
+            4 => command(0x12345, 0),
+            _ => Action::Syscall(Syscall::YIELD),
+        }
+    });
+    sim.load().unwrap();
+
+    assert!(sim.run_until_idle(100));
+    assert_eq!(
+        *returned.borrow(),
+        vec![
+            1,
+            2,
+            isize::from(ReturnCode::ENOSUPPORT),
+            isize::from(ReturnCode::ENODEVICE)
+        ]
+    );
+}
+
+#[test]
+fn test_callbacks_are_queued_in_order() {
+    let mut sim = Sim::new();
+    let counter = counter(&sim);
+    let calls = Rc::new(RefCell::new(Vec::new()));
+    let log = calls.clone();
+    sim.add_app("listener", 4096, move |resume| match resume {
+        Resume::Start(_) => subscribe(0x1000),
+        Resume::Callback(call) => {
+            log.borrow_mut().push((call.pc, call.argument0));
+            Action::Syscall(Syscall::YIELD)
+        }
+        Resume::Returned(_) => Action::Syscall(Syscall::YIELD),
+    });
+    sim.load().unwrap();
+    assert!(sim.run_until_idle(100));
+
+    for value in 1..4 {
+        sim.chip().raise_interrupt(move || counter.fire(value));
+    }
+    assert!(sim.run_until_idle(100));
+    assert_eq!(*calls.borrow(), vec![(0x1000, 1), (0x1000, 2), (0x1000, 3)]);
+}
+
+#[test]
+fn test_grants_are_allocated_on_first_use() {
+    let mut sim = Sim::new();
+    counter(&sim);
+    let mut step = 0;
+    sim.add_app("user", 4096, move |_| {
+        step += 1;
+        match step {
+            1 => command(COUNTER, 0),
+            2 => command(COUNTER, 1),
+            _ => Action::Syscall(Syscall::YIELD),
+        }
+    });
+    sim.add_app("idle", 4096, |_| Action::Syscall(Syscall::YIELD));
+    sim.load().unwrap();
+
+    assert!(sim.run_until_idle(100));
+    // Command 0 does not use the grant, command 1 does.
+    assert!(sim.process("user").unwrap().grant_memory_size(0) > 0);
+    assert_eq!(sim.process("idle").unwrap().grant_memory_size(0), 0);
+}
+
+#[test]
+fn test_freed_grant_memory_goes_back_to_the_break() {
+    let mut sim = Sim::new();
+    counter(&sim);
+    sim.add_app("idle", 4096, |_| Action::Syscall(Syscall::YIELD));
+    sim.load().unwrap();
+    let process = sim.process("idle").unwrap();
+
+    let a = process.alloc(0, 16, 16).unwrap().as_ptr();
+    unsafe { process.free(0, a, 16, 16) };
+    let top = process.kernel_memory_break();
+    // Align the second block so that there is padding between the blocks.
+    let mut align = 64;
+    while (a as usize - 16) % align == 0 {
+        align *= 2;
+    }
+
+    for &a_first in &[true, false] {
+        assert_eq!(process.alloc(0, 16, 16).unwrap().as_ptr(), a);
+        let b = process.alloc(0, 16, align).unwrap().as_ptr();
+        assert!((b as usize) + 16 < a as usize);
+        assert_eq!(process.grant_memory_size(0), 32);
+
+        unsafe {
+            if a_first {
+                process.free(0, a, 16, 16);
+                process.free(0, b, 16, align);
+            } else {
+                process.free(0, b, 16, align);
+                assert_eq!(process.kernel_memory_break(), a as *const u8);
+                process.free(0, a, 16, 16);
+            }
+        }
+        assert_eq!(process.kernel_memory_break(), top);
+        assert_eq!(process.grant_memory_size(0), 0);
+    }
+}
+
+#[test]
+fn test_restart_after_fault() {
+    let mut sim = Sim::new();
+    sim.set_fault_response(FaultResponse::Restart(Box::leak(Box::new(
+        AlwaysRestart::new(),
+    ))));
+    let starts = Rc::new(RefCell::new(0));
+    let count = starts.clone();
+    sim.add_app("crasher", 4096, move |resume| match resume {
+        Resume::Start(_) => {
+            *count.borrow_mut() += 1;
+            if *count.borrow() == 1 {
+                Action::Fault
+            } else {
+                Action::Syscall(Syscall::YIELD)
+            }
+        }
+        _ => Action::Syscall(Syscall::YIELD),
+    });
+    sim.load().unwrap();
+
+    assert!(sim.run_until_idle(100));
+    assert_eq!(*starts.borrow(), 2);
+    assert_eq!(sim.process("crasher").unwrap().get_restart_count(), 1);
+}
+
+#[test]
+fn test_stack_overflow_is_a_distinct_fault() {
+    let mut sim = Sim::new();
+    sim.set_fault_response(FaultResponse::Stop);
+    let mut stack_bottom = 0;
+    sim.add_app("overflow", 4096, move |resume| match resume {
+        // Tell the kernel that the stack grows down from 1 kB above the
+        // bottom of the memory.
+        Resume::Start(call) => {
+            stack_bottom = call.argument1;
+            Action::Syscall(Syscall::MEMOP {
+                operand: 10,
+                arg0: stack_bottom + 1024,
+            })
+        }
+        // Then grow the stack past the bottom.
+        _ => {
+            unsafe { std::ptr::write_bytes(stack_bottom as *mut u8, 0, 64) };
+            Action::Syscall(Syscall::YIELD)
+        }
+    });
+    sim.load().unwrap();
+
+    assert!(sim.run_until_idle(100));
+    let process = sim.process("overflow").unwrap();
+    assert_eq!(process.get_fault_reason(), Some(FaultReason::StackOverflow));
+}
+
+#[test]
+fn test_unloaded_process_memory_is_reused() {
+    let mut sim = Sim::new();
+    sim.add_app("exit", 4096, |_| {
+        Action::Syscall(Syscall::MEMOP {
+            operand: 14,
+            arg0: 0,
+        })
+    });
+    // There is only enough memory for one process at a time.
+    let loader = sim.load_dynamically(8 * 1024).unwrap();
+    let process = sim.process("exit").unwrap();
+    let (app_address, mem_start) = (process.flash_start() as usize, process.mem_start());
+
+    for _ in 0..5 {
+        assert!(sim.run_until_idle(100));
+        let process = sim.process("exit").unwrap();
+        assert_eq!(loader.unload_process(process.appid()), ReturnCode::SUCCESS);
+        assert!(sim.process("exit").is_none());
+
+        assert!(loader.load_process(app_address).unwrap().is_some());
+        assert_eq!(sim.process("exit").unwrap().mem_start(), mem_start);
+    }
+
+    // A process that has not exited cannot be unloaded, and its memory is
+    // still in use.
+    let process = sim.process("exit").unwrap();
+    assert_eq!(loader.unload_process(process.appid()), ReturnCode::EBUSY);
+    match loader.load_process(app_address) {
+        Err(ProcessLoadError::NotEnoughMemory) => {}
+        _ => panic!("a second process fits in the memory"),
+    }
+}
+
+/// A 24-bit alarm at 1 kHz that is stopped at `now`.
+struct StoppedAlarm {
+    now: u32,
+    alarm: Option<u32>,
+}
+
+impl Time for StoppedAlarm {
+    type Frequency = Freq1KHz;
+
+    fn now(&self) -> u32 {
+        self.now
+    }
+
+    fn max_tics(&self) -> u32 {
+        0xFF_FFFF
+    }
+}
+
+impl<'a> Alarm<'a> for StoppedAlarm {
+    fn set_alarm(&self, _tics: u32) {}
+
+    fn get_alarm(&self) -> u32 {
+        self.alarm.unwrap_or(0)
+    }
+
+    fn set_client(&'a self, _client: &'a dyn AlarmClient) {}
+
+    fn is_enabled(&self) -> bool {
+        self.alarm.is_some()
+    }
+
+    fn disable(&self) {}
+}
+
+#[test]
+fn test_sleep_deadline_is_the_next_alarm() {
+    let mut sim = Sim::new();
+    sim.add_app("idle", 4096, |_| Action::Syscall(Syscall::YIELD));
+    sim.load().unwrap();
+    let main_loop_cap = create_capability!(MainLoopCapability);
+
+    // The alarm fires 48 tics from now, after the clock wrapped around.
+    let alarm: &'static StoppedAlarm = Box::leak(Box::new(StoppedAlarm {
+        now: 0xFF_FFE0,
+        alarm: Some(0x10),
+    }));
+    sim.kernel().set_alarm_deadline(alarm, &main_loop_cap);
+    assert!(sim.run_until_idle(100));
+    assert_eq!(sim.chip().sleep_deadline(), Some(48_000));
+
+    let no_alarm: &'static StoppedAlarm = Box::leak(Box::new(StoppedAlarm {
+        now: 0,
+        alarm: None,
+    }));
+    sim.kernel().set_alarm_deadline(no_alarm, &main_loop_cap);
+    assert!(sim.run_until_idle(100));
+    assert_eq!(sim.chip().sleep_deadline(), None);
+}
+
+/// An app that calls the commands `commands`, given as driver and command
+/// number, and records what they return in `returned`.
+fn command_app(
+    commands: Vec<(usize, usize)>,
+    returned: Rc<RefCell<Vec<isize>>>,
+) -> impl FnMut(Resume) -> Action {
+    let mut step = 0;
+    move |resume| {
+        if let Resume::Returned(value) = resume {
+            returned.borrow_mut().push(value);
+        }
+        step += 1;
+        commands
+            .get(step - 1)
+            .map_or(Action::Syscall(Syscall::YIELD), |&(driver, command_num)| {
+                command(driver, command_num)
+            })
+    }
+}
+
+/// The value of a permissions element that lets an app call the commands in
+/// `allowed_commands` below 64 of driver `driver_num`.
+fn permission(driver_num: usize, allowed_commands: u64) -> Vec<u8> {
+    let mut permission = vec![0; 16];
+    permission[0..4].copy_from_slice(&(driver_num as u32).to_le_bytes());
+    permission[8..16].copy_from_slice(&allowed_commands.to_le_bytes());
+    permission
+}
+
+#[test]
+fn test_syscalls_are_filtered_by_permissions() {
+    let mut sim = Sim::new();
+    counter(&sim);
+    let other = Box::leak(Box::new(Counter {
+        apps: sim.create_grant(),
+    }));
+    sim.add_driver(COUNTER + 1, other);
+    let limited = Rc::new(RefCell::new(Vec::new()));
+    let unlimited = Rc::new(RefCell::new(Vec::new()));
+
+    // Both drivers exist and implement commands 0 and 1, but the app may only
+    // call command 1 of the counter.
+    let commands = vec![(COUNTER, 1), (COUNTER, 0), (COUNTER + 1, 1)];
+    sim.add_app_with_tlvs(
+        "limited",
+        4096,
+        &[(11, &permission(COUNTER, 0b10))],
+        command_app(commands.clone(), limited.clone()),
+    );
+    sim.add_app("unlimited", 4096, command_app(commands, unlimited.clone()));
+    sim.load().unwrap();
+
+    assert!(sim.run_until_idle(100));
+    assert_eq!(
+        *limited.borrow(),
+        vec![
+            1,
+            isize::from(ReturnCode::ENOSUPPORT),
+            isize::from(ReturnCode::ENODEVICE)
+        ]
+    );
+    assert_eq!(*unlimited.borrow(), vec![1, 0, 1]);
+    assert!(sim
+        .debug_output()
+        .contains("limited has no permission to call command 0 of driver 0x90000"));
+}
+
+#[test]
+fn test_apps_without_permissions_are_denied_when_required() {
+    let mut sim = Sim::new();
+    counter(&sim);
+    let process_mgmt_cap = create_capability!(ProcessManagementCapability);
+    sim.kernel().require_syscall_permissions(&process_mgmt_cap);
+    let limited = Rc::new(RefCell::new(Vec::new()));
+    let unlimited = Rc::new(RefCell::new(Vec::new()));
+
+    let commands = vec![(COUNTER, 1)];
+    sim.add_app_with_tlvs(
+        "limited",
+        4096,
+        &[(11, &permission(COUNTER, 0b10))],
+        command_app(commands.clone(), limited.clone()),
+    );
+    sim.add_app("unlimited", 4096, command_app(commands, unlimited.clone()));
+    sim.load().unwrap();
+
+    assert!(sim.run_until_idle(100));
+    assert_eq!(*limited.borrow(), vec![1]);
+    assert_eq!(
+        *unlimited.borrow(),
+        vec![isize::from(ReturnCode::ENODEVICE)]
+    );
+}
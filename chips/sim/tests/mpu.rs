@@ -0,0 +1,199 @@
+//! Tests of the memory protection the kernel sets up for simulated processes.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use kernel::procs::{AlwaysRestart, FaultResponse};
+use kernel::syscall::Syscall;
+use kernel::{AppId, Chip, Driver, Grant, ReturnCode};
+use sim::{Action, Resume, Sim, SimMpu};
+
+const GRANTED: usize = 0x90002;
+
+const BRK: usize = 0;
+const SBRK: usize = 1;
+const GRANT_BEGIN: usize = 6;
+
+/// A driver that allocates its first grant on command 1, and its second grant
+/// on command 2.
+struct Granted {
+    first: Grant<[u64; 4]>,
+    second: Grant<[u64; 4]>,
+}
+
+impl Driver for Granted {
+    fn command(&self, command_num: usize, _: usize, _: usize, appid: AppId) -> ReturnCode {
+        let grant = match command_num {
+            1 => &self.first,
+            2 => &self.second,
+            _ => return ReturnCode::ENOSUPPORT,
+        };
+        grant
+            .enter(appid, |_, _| ReturnCode::SUCCESS)
+            .unwrap_or_else(|err| err.into())
+    }
+}
+
+fn memop(operand: usize, arg0: usize) -> Action {
+    Action::Syscall(Syscall::MEMOP {
+        operand: operand,
+        arg0: arg0,
+    })
+}
+
+fn command(command_num: usize) -> Action {
+    Action::Syscall(Syscall::COMMAND {
+        driver_number: GRANTED,
+        subdriver_number: command_num,
+        arg0: 0,
+        arg1: 0,
+    })
+}
+
+/// An app that starts with `actions` and then yields. The return values of
+/// its system calls, and the app break it starts with, are recorded in
+/// `results`.
+fn app(
+    actions: impl Fn(usize, &[isize]) -> Action + 'static,
+    results: Rc<RefCell<Vec<isize>>>,
+) -> impl FnMut(Resume) -> Action {
+    let mut step = 0;
+    move |resume| {
+        match resume {
+            Resume::Start(call) => results.borrow_mut().push(call.argument3 as isize),
+            Resume::Returned(value) => results.borrow_mut().push(value),
+            Resume::Callback(_) => {}
+        }
+        step += 1;
+        actions(step, &results.borrow())
+    }
+}
+
+fn mpu(sim: &Sim) -> &'static SimMpu {
+    sim.chip().mpu()
+}
+
+#[test]
+fn test_app_region_follows_break() {
+    let mut sim = Sim::new();
+    let results = Rc::new(RefCell::new(Vec::new()));
+    sim.add_app(
+        "app",
+        4096,
+        app(
+            |step, _| match step {
+                1 => memop(SBRK, 256),
+                2 => memop(SBRK, 8192),
+                _ => Action::Syscall(Syscall::YIELD),
+            },
+            results.clone(),
+        ),
+    );
+    sim.load().unwrap();
+    assert!(sim.run_until_idle(100));
+
+    let process = sim.process("app").unwrap();
+    let initial_break = results.borrow()[0] as usize;
+    let no_memory = isize::from(ReturnCode::ENOMEM);
+    assert_eq!(
+        *results.borrow(),
+        [initial_break as isize, initial_break as isize, no_memory]
+    );
+
+    // The kernel turns the MPU off again when it takes over from the process.
+    let mpu = mpu(&sim);
+    assert!(!mpu.is_enabled());
+    assert!(mpu.configured_for() == Some(process.appid()));
+    let mem_start = process.mem_start();
+    let app_break = (initial_break + 256) as *const u8;
+    assert!(mpu.permits(mem_start, app_break as usize - mem_start as usize, true));
+    assert!(!mpu.permits(app_break, 1, false));
+    assert!(!mpu.permits(process.kernel_memory_break(), 1, false));
+
+    // Flash can only be read.
+    let flash_size = process.flash_end() as usize - process.flash_start() as usize;
+    assert!(mpu.permits(process.flash_start(), flash_size, false));
+    assert!(!mpu.permits(process.flash_start(), 1, true));
+}
+
+#[test]
+fn test_app_region_stays_below_grants() {
+    let mut sim = Sim::new();
+    let granted = Box::leak(Box::new(Granted {
+        first: sim.create_grant(),
+        second: sim.create_grant(),
+    }));
+    sim.add_driver(GRANTED, granted);
+    let results = Rc::new(RefCell::new(Vec::new()));
+    sim.add_app(
+        "app",
+        4096,
+        app(
+            |step, results| match step {
+                1 => command(1),
+                2 => memop(GRANT_BEGIN, 0),
+                // The app cannot move its break into the grants, but can use
+                // all memory below them.
+                3 => memop(BRK, results[2] as usize + 4),
+                4 => memop(BRK, results[2] as usize),
+                // There is no memory left for the second grant.
+                5 => command(2),
+                _ => Action::Syscall(Syscall::YIELD),
+            },
+            results.clone(),
+        ),
+    );
+    sim.load().unwrap();
+    assert!(sim.run_until_idle(100));
+
+    let process = sim.process("app").unwrap();
+    let grant_begin = process.kernel_memory_break();
+    let no_memory = isize::from(ReturnCode::ENOMEM);
+    assert_eq!(
+        results.borrow()[1..],
+        [0, grant_begin as isize, no_memory, 0, no_memory]
+    );
+
+    let mpu = mpu(&sim);
+    let mem_start = process.mem_start();
+    assert!(mpu.permits(mem_start, grant_begin as usize - mem_start as usize, true));
+    assert!(!mpu.permits(grant_begin, 1, false));
+}
+
+#[test]
+fn test_restart_shrinks_app_region() {
+    let mut sim = Sim::new();
+    sim.set_fault_response(FaultResponse::Restart(Box::leak(Box::new(
+        AlwaysRestart::new(),
+    ))));
+    let results = Rc::new(RefCell::new(Vec::new()));
+    sim.add_app(
+        "app",
+        4096,
+        app(
+            |step, _| match step {
+                1 => memop(SBRK, 512),
+                2 => Action::Fault,
+                _ => Action::Syscall(Syscall::YIELD),
+            },
+            results.clone(),
+        ),
+    );
+    sim.load().unwrap();
+    assert!(sim.run_until_idle(100));
+
+    let process = sim.process("app").unwrap();
+    assert_eq!(process.get_restart_count(), 1);
+    let initial_break = results.borrow()[0];
+    assert_eq!(
+        *results.borrow(),
+        [initial_break, initial_break, initial_break]
+    );
+
+    // The memory the app had before the restart is not accessible anymore.
+    let mpu = mpu(&sim);
+    let mem_start = process.mem_start();
+    let app_break = initial_break as usize as *const u8;
+    assert!(mpu.permits(mem_start, app_break as usize - mem_start as usize, true));
+    assert!(!mpu.permits(app_break, 1, false));
+}
@@ -0,0 +1,259 @@
+//! Tests of the limits `ProcessQuotas` put on the kernel resources of
+//! simulated processes.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use kernel::capabilities::ProcessManagementCapability;
+use kernel::create_capability;
+use kernel::introspection::KernelInfo;
+use kernel::procs::{ProcessQuotas, QuotaBreaches};
+use kernel::syscall::Syscall;
+use kernel::{AppId, AppSlice, Callback, Driver, Grant, ReturnCode, Shared};
+use sim::{Action, Resume, Sim};
+
+const HOLDER: usize = 0x90000;
+
+#[derive(Default)]
+struct App {
+    callback: Option<Callback>,
+    buffers: [Option<AppSlice<Shared, u8>>; 2],
+}
+
+/// Grant data larger than the grant quota of the tests.
+#[derive(Default)]
+struct Big {
+    _data: [u64; 32],
+}
+
+/// A driver that keeps the buffers processes allow, calls the callbacks of
+/// all processes when `fire()` is called, and uses a large grant on command 1.
+struct Holder {
+    apps: Grant<App>,
+    big: Grant<Big>,
+}
+
+impl Holder {
+    fn fire(&self) {
+        self.apps.each(|app| {
+            app.callback.map(|mut callback| callback.schedule(0, 0, 0));
+        });
+    }
+}
+
+impl Driver for Holder {
+    fn subscribe(&self, _: usize, callback: Option<Callback>, appid: AppId) -> ReturnCode {
+        self.apps
+            .enter(appid, |app, _| {
+                app.callback = callback;
+                ReturnCode::SUCCESS
+            })
+            .unwrap_or_else(|err| err.into())
+    }
+
+    fn command(&self, command_num: usize, _: usize, _: usize, appid: AppId) -> ReturnCode {
+        match command_num {
+            0 => ReturnCode::SUCCESS,
+            1 => self
+                .big
+                .enter(appid, |_, _| ReturnCode::SUCCESS)
+                .unwrap_or_else(|err| err.into()),
+            _ => ReturnCode::ENOSUPPORT,
+        }
+    }
+
+    fn allow(
+        &self,
+        appid: AppId,
+        allow_num: usize,
+        slice: Option<AppSlice<Shared, u8>>,
+    ) -> ReturnCode {
+        self.apps
+            .enter(appid, |app, _| match app.buffers.get_mut(allow_num) {
+                Some(buffer) => {
+                    *buffer = slice;
+                    ReturnCode::SUCCESS
+                }
+                None => ReturnCode::EINVAL,
+            })
+            .unwrap_or_else(|err| err.into())
+    }
+}
+
+/// Create a `Holder` driver and set `quotas`. The grant of the driver for
+/// callbacks and buffers is grant 0, and its large grant is grant 1.
+fn holder(sim: &Sim, quotas: ProcessQuotas) -> &'static Holder {
+    let holder = Box::leak(Box::new(Holder {
+        apps: sim.create_grant(),
+        big: sim.create_grant(),
+    }));
+    sim.add_driver(HOLDER, holder);
+    let process_mgmt_cap = create_capability!(ProcessManagementCapability);
+    sim.kernel().set_process_quotas(quotas, &process_mgmt_cap);
+    holder
+}
+
+/// Allow (or with `size` 0, unallow) the first `size` bytes of memory
+/// starting at `address` as buffer `allow_num` of the holder.
+fn allow(allow_num: usize, address: usize, size: usize) -> Action {
+    Action::Syscall(Syscall::ALLOW {
+        driver_number: HOLDER,
+        subdriver_number: allow_num,
+        allow_address: if size == 0 { 0 } else { address } as *mut u8,
+        allow_size: size,
+    })
+}
+
+fn command(command_num: usize) -> Action {
+    Action::Syscall(Syscall::COMMAND {
+        driver_number: HOLDER,
+        subdriver_number: command_num,
+        arg0: 0,
+        arg1: 0,
+    })
+}
+
+fn breaches(sim: &Sim, name: &str) -> QuotaBreaches {
+    let process_mgmt_cap = create_capability!(ProcessManagementCapability);
+    let info = KernelInfo::new(sim.kernel());
+    info.app_quota_breaches(sim.process(name).unwrap().appid(), &process_mgmt_cap)
+}
+
+fn total_breaches(sim: &Sim) -> usize {
+    let process_mgmt_cap = create_capability!(ProcessManagementCapability);
+    KernelInfo::new(sim.kernel()).quota_breaches(&process_mgmt_cap)
+}
+
+#[test]
+fn test_grant_bytes_quota() {
+    let mut sim = Sim::new();
+    holder(
+        &sim,
+        ProcessQuotas {
+            grant_bytes_per_driver: Some(192),
+            ..ProcessQuotas::default()
+        },
+    );
+    let returned = Rc::new(RefCell::new(Vec::new()));
+    let log = returned.clone();
+    let mut step = 0;
+    sim.add_app("greedy", 4096, move |resume| {
+        if let Resume::Returned(value) = resume {
+            log.borrow_mut().push(value);
+        }
+        step += 1;
+        match step {
+            // The small grant fits in the quota, the large one does not.
+            1 => allow(0, 0, 0),
+            2 | 3 => command(1),
+            _ => Action::Syscall(Syscall::YIELD),
+        }
+    });
+    sim.load().unwrap();
+    assert!(sim.run_until_idle(100));
+
+    let no_memory = isize::from(ReturnCode::ENOMEM);
+    assert_eq!(*returned.borrow(), vec![0, no_memory, no_memory]);
+    assert_eq!(
+        breaches(&sim, "greedy"),
+        QuotaBreaches {
+            grant_bytes: 2,
+            last_grant: Some(1),
+            task_queue: 0,
+            allows: 0,
+        }
+    );
+    assert_eq!(total_breaches(&sim), 2);
+    assert_eq!(sim.debug_output().matches("would use").count(), 1);
+}
+
+#[test]
+fn test_task_queue_quota() {
+    let mut sim = Sim::new();
+    let holder = holder(
+        &sim,
+        ProcessQuotas {
+            task_queue_depth: Some(2),
+            ..ProcessQuotas::default()
+        },
+    );
+    let callbacks = Rc::new(RefCell::new(0));
+    let count = callbacks.clone();
+    sim.add_app("listener", 4096, move |resume| match resume {
+        Resume::Start(_) => Action::Syscall(Syscall::SUBSCRIBE {
+            driver_number: HOLDER,
+            subdriver_number: 0,
+            callback_ptr: 0x1000 as *mut (),
+            appdata: 0,
+        }),
+        Resume::Callback(_) => {
+            *count.borrow_mut() += 1;
+            Action::Syscall(Syscall::YIELD)
+        }
+        Resume::Returned(_) => Action::Syscall(Syscall::YIELD),
+    });
+    sim.load().unwrap();
+    assert!(sim.run_until_idle(100));
+
+    // Four callbacks are scheduled before the process runs again, and only
+    // two fit in its queue.
+    sim.chip().raise_interrupt(move || {
+        for _ in 0..4 {
+            holder.fire();
+        }
+    });
+    assert!(sim.run_until_idle(100));
+
+    assert_eq!(*callbacks.borrow(), 2);
+    assert_eq!(breaches(&sim, "listener").task_queue, 2);
+    assert_eq!(total_breaches(&sim), 2);
+}
+
+#[test]
+fn test_allow_quota() {
+    let mut sim = Sim::new();
+    holder(
+        &sim,
+        ProcessQuotas {
+            outstanding_allows: Some(1),
+            ..ProcessQuotas::default()
+        },
+    );
+    let returned = Rc::new(RefCell::new(Vec::new()));
+    let log = returned.clone();
+    let mut memory_start = 0;
+    let mut step = 0;
+    sim.add_app("sharer", 4096, move |resume| {
+        match resume {
+            Resume::Start(call) => memory_start = call.argument1,
+            Resume::Returned(value) => log.borrow_mut().push(value),
+            Resume::Callback(_) => {}
+        }
+        step += 1;
+        match step {
+            1 => allow(0, memory_start, 16),
+            // The capsule still holds the first buffer.
+            2 => allow(1, memory_start + 16, 16),
+            // Once the first buffer is unallowed, another one can be allowed.
+            3 => allow(0, 0, 0),
+            4 => allow(1, memory_start + 16, 16),
+            _ => Action::Syscall(Syscall::YIELD),
+        }
+    });
+    sim.load().unwrap();
+    assert!(sim.run_until_idle(100));
+
+    assert_eq!(
+        *returned.borrow(),
+        vec![0, isize::from(ReturnCode::ENOMEM), 0, 0]
+    );
+    let process_mgmt_cap = create_capability!(ProcessManagementCapability);
+    let info = KernelInfo::new(sim.kernel());
+    let sharer = sim.process("sharer").unwrap().appid();
+    assert_eq!(
+        info.number_app_outstanding_allows(sharer, &process_mgmt_cap),
+        1
+    );
+    assert_eq!(breaches(&sim, "sharer").allows, 1);
+    assert_eq!(total_breaches(&sim), 1);
+}
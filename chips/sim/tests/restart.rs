@@ -0,0 +1,144 @@
+//! Tests of the `BackoffRestart` policy with simulated processes, keeping
+//! time with the alarm of the simulated chip.
+
+use std::cell::RefCell;
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use kernel::capabilities::ProcessManagementCapability;
+use kernel::create_capability;
+use kernel::hil::time::{Alarm, Time};
+use kernel::introspection::KernelInfo;
+use kernel::procs::{BackoffRestart, BackoffRestartState, FaultResponse, RestartFallback};
+use sim::{Action, Resume, Sim};
+
+/// Use a `BackoffRestart` policy with the given limits for faulting processes.
+fn backoff(
+    sim: &mut Sim,
+    initial_delay_ms: u32,
+    max_delay_ms: u32,
+    stable_period_ms: u32,
+    max_restarts: usize,
+    fallback: RestartFallback,
+) {
+    let states: &'static [BackoffRestartState; 4] = Box::leak(Box::default());
+    let alarm = sim.chip().time();
+    let policy = Box::leak(Box::new(BackoffRestart::new(
+        sim.kernel(),
+        alarm,
+        states,
+        initial_delay_ms,
+        max_delay_ms,
+        stable_period_ms,
+        max_restarts,
+        fallback,
+    )));
+    alarm.set_client(policy);
+    sim.set_fault_response(FaultResponse::Restart(policy));
+}
+
+/// Add an app that records when (in ms) it starts, computes for the number of
+/// ms `run_ms` returns for that start and then faults.
+fn add_crasher(sim: &mut Sim, run_ms: impl Fn(usize) -> u32 + 'static) -> Rc<RefCell<Vec<u32>>> {
+    let chip = sim.chip();
+    let starts = Rc::new(RefCell::new(Vec::new()));
+    let log = starts.clone();
+    sim.add_app("crasher", 4096, move |resume| match resume {
+        Resume::Start(_) => {
+            let start = log.borrow().len();
+            log.borrow_mut().push(chip.time().now() / 1000);
+            chip.advance_time(run_ms(start) * 1000);
+            Action::Fault
+        }
+        _ => unreachable!(),
+    });
+    starts
+}
+
+/// Whether the process of the test is stopped, rather than running or waiting
+/// for a delayed restart.
+fn stopped(sim: &Sim) -> bool {
+    let process_mgmt_cap = create_capability!(ProcessManagementCapability);
+    KernelInfo::new(sim.kernel()).number_inactive_processes(&process_mgmt_cap) == 1
+}
+
+/// Let `ms` milliseconds pass in steps of 50 ms, running the kernel after each.
+fn wait(sim: &Sim, ms: u32) {
+    for _ in 0..ms / 50 {
+        sim.chip().advance_time(50_000);
+        assert!(sim.run_until_idle(100));
+    }
+}
+
+#[test]
+fn test_restart_delay_doubles_up_to_maximum() {
+    let mut sim = Sim::new();
+    backoff(&mut sim, 100, 400, 10_000, 10, RestartFallback::Stop);
+    let starts = add_crasher(&mut sim, |_| 0);
+    sim.load().unwrap();
+    assert!(sim.run_until_idle(100));
+    wait(&sim, 1200);
+
+    assert_eq!(*starts.borrow(), [0, 100, 300, 700, 1100]);
+}
+
+#[test]
+fn test_faults_are_forgotten_after_stable_period() {
+    let mut sim = Sim::new();
+    backoff(&mut sim, 100, 10_000, 1000, 10, RestartFallback::Stop);
+    // The third start runs for the stable period before it faults.
+    let starts = add_crasher(&mut sim, |start| if start == 2 { 1000 } else { 0 });
+    sim.load().unwrap();
+    assert!(sim.run_until_idle(100));
+    // Together with the time the app runs, this waits until 1700 ms.
+    wait(&sim, 700);
+
+    // Without the reset, the fourth start would be delayed by 400 ms.
+    assert_eq!(*starts.borrow(), [0, 100, 300, 1400, 1600]);
+}
+
+#[test]
+fn test_stop_fallback_leaves_process_faulted() {
+    let mut sim = Sim::new();
+    backoff(&mut sim, 0, 0, 1000, 2, RestartFallback::Stop);
+    let starts = add_crasher(&mut sim, |_| 0);
+    sim.load().unwrap();
+    assert!(sim.run_until_idle(100));
+
+    let crasher = sim.process("crasher").unwrap();
+    assert_eq!(starts.borrow().len(), 3);
+    assert!(stopped(&sim));
+    assert_eq!(crasher.get_restart_count(), 2);
+
+    // The process is not restarted once its faults are forgotten either.
+    wait(&sim, 2000);
+    assert_eq!(starts.borrow().len(), 3);
+    assert!(stopped(&sim));
+}
+
+static REBOOTED: AtomicBool = AtomicBool::new(false);
+
+fn reboot() {
+    REBOOTED.store(true, Ordering::SeqCst);
+}
+
+#[test]
+fn test_reboot_fallback_waits_until_fault_is_handled() {
+    let mut sim = Sim::new();
+    backoff(&mut sim, 0, 0, 1000, 2, RestartFallback::Reboot(reboot));
+    let starts = add_crasher(&mut sim, |_| 0);
+    sim.load().unwrap();
+
+    // The kernel finishes handling the last fault and stops the process.
+    assert!(sim.run_until_idle(100));
+    assert_eq!(starts.borrow().len(), 3);
+    assert!(stopped(&sim));
+    assert!(!REBOOTED.load(Ordering::SeqCst));
+
+    // The board reboots when the alarm fires afterwards. The simulated reboot
+    // returns, so the policy panics.
+    let result = panic::catch_unwind(AssertUnwindSafe(|| wait(&sim, 50)));
+    assert!(result.is_err());
+    assert!(REBOOTED.load(Ordering::SeqCst));
+}
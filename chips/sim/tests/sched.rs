@@ -0,0 +1,301 @@
+//! Tests of the order in which the schedulers run simulated processes.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use kernel::procs::{AlwaysRestart, FaultResponse};
+use kernel::schedulers::{
+    CooperativeSched, EDFProcessState, EDFSched, MLFQProcessState, MLFQSched, PrioritySched,
+};
+use kernel::syscall::Syscall;
+use kernel::{AppId, AppSlice, Callback, Driver, Grant, ReturnCode, Shared};
+use sim::sim::NUM_PROCS;
+use sim::{Action, Resume, Sim, SimChip};
+
+const WAKER: usize = 0x90001;
+
+/// The names of the processes, in the order they ran their steps.
+type Log = Rc<RefCell<Vec<&'static str>>>;
+
+/// A driver that calls the callbacks of all processes when `wake()` is
+/// called.
+struct Waker {
+    apps: Grant<Option<Callback>>,
+}
+
+impl Waker {
+    fn wake(&self) {
+        self.apps.each(|callback| {
+            callback.map(|mut callback| callback.schedule(0, 0, 0));
+        });
+    }
+}
+
+impl Driver for Waker {
+    fn subscribe(&self, _: usize, callback: Option<Callback>, appid: AppId) -> ReturnCode {
+        self.apps
+            .enter(appid, |app, _| {
+                **app = callback;
+                ReturnCode::SUCCESS
+            })
+            .unwrap_or_else(|err| err.into())
+    }
+
+    fn command(&self, _: usize, _: usize, _: usize, _: AppId) -> ReturnCode {
+        ReturnCode::ENOSUPPORT
+    }
+
+    fn allow(&self, _: AppId, _: usize, _: Option<AppSlice<Shared, u8>>) -> ReturnCode {
+        ReturnCode::ENOSUPPORT
+    }
+}
+
+fn waker(sim: &Sim) -> &'static Waker {
+    let waker = Box::leak(Box::new(Waker {
+        apps: sim.create_grant(),
+    }));
+    sim.add_driver(WAKER, waker);
+    waker
+}
+
+/// An app that, whenever it starts or gets a callback, computes `steps` steps
+/// of `us_per_step` microseconds each, and then yields. It logs its name at
+/// every step.
+fn worker(
+    chip: &'static SimChip,
+    log: &Log,
+    name: &'static str,
+    steps: usize,
+    us_per_step: u32,
+) -> impl FnMut(Resume) -> Action {
+    let log = log.clone();
+    let mut done = 0;
+    move |resume| {
+        if let Resume::Start(_) | Resume::Callback(_) = resume {
+            done = 0;
+        }
+        if done == steps {
+            return Action::Syscall(Syscall::YIELD);
+        }
+        done += 1;
+        log.borrow_mut().push(name);
+        chip.advance_time(us_per_step);
+        // A command to a driver that does not exist, which returns right away.
+        Action::Syscall(Syscall::COMMAND {
+            driver_number: 0x12345,
+            subdriver_number: 0,
+            arg0: 0,
+            arg1: 0,
+        })
+    }
+}
+
+/// A real-time app with a period of `period_us` that subscribes to the waker
+/// and registers its period when it starts, and then runs `worker` on each
+/// callback.
+fn real_time(
+    period_us: usize,
+    mut worker: impl FnMut(Resume) -> Action,
+) -> impl FnMut(Resume) -> Action {
+    let mut step = 0;
+    move |resume| {
+        step += 1;
+        match step {
+            1 => Action::Syscall(Syscall::SUBSCRIBE {
+                driver_number: WAKER,
+                subdriver_number: 0,
+                callback_ptr: 0x1000 as *mut (),
+                appdata: 0,
+            }),
+            2 => Action::Syscall(Syscall::MEMOP {
+                operand: 12,
+                arg0: period_us,
+            }),
+            3 => Action::Syscall(Syscall::YIELD),
+            _ => worker(resume),
+        }
+    }
+}
+
+fn priority(priority: u32) -> Vec<u8> {
+    [priority, 0xFFFFFFFF, 0xFFFFFFFF]
+        .iter()
+        .flat_map(|value| value.to_le_bytes().to_vec())
+        .collect()
+}
+
+#[test]
+fn test_round_robin_preempts_at_the_end_of_the_timeslice() {
+    let mut sim = Sim::new();
+    let log = Log::default();
+    // Each step takes 4 ms of the 10 ms timeslice, so the SysTick expires
+    // during the third step.
+    sim.add_app("a", 1024, worker(sim.chip(), &log, "a", usize::MAX, 4000));
+    sim.add_app("b", 1024, worker(sim.chip(), &log, "b", usize::MAX, 4000));
+    sim.load().unwrap();
+
+    sim.run(10);
+    assert_eq!(
+        log.borrow()[..12],
+        ["a", "a", "a", "b", "b", "b", "a", "a", "a", "b", "b", "b"]
+    );
+}
+
+#[test]
+fn test_cooperative_runs_processes_until_they_yield() {
+    let mut sim = Sim::new();
+    sim.set_scheduler(Box::leak(Box::new(CooperativeSched::new())));
+    let log = Log::default();
+    sim.add_app("a", 1024, worker(sim.chip(), &log, "a", 3, 20000));
+    sim.add_app("b", 1024, worker(sim.chip(), &log, "b", 3, 20000));
+    sim.load().unwrap();
+
+    assert!(sim.run_until_idle(100));
+    assert_eq!(*log.borrow(), ["a", "a", "a", "b", "b", "b"]);
+}
+
+#[test]
+fn test_priority_runs_higher_priorities_first() {
+    let mut sim = Sim::new();
+    sim.set_scheduler(Box::leak(Box::new(PrioritySched::new(sim.kernel()))));
+    let log = Log::default();
+    let (low, high) = (priority(5), priority(1));
+    sim.add_app_with_tlvs(
+        "low",
+        1024,
+        &[(6, &low)],
+        worker(sim.chip(), &log, "low", 2, 0),
+    );
+    sim.add_app("none", 1024, worker(sim.chip(), &log, "none", 2, 0));
+    sim.add_app_with_tlvs(
+        "high",
+        1024,
+        &[(6, &high)],
+        worker(sim.chip(), &log, "high", 2, 0),
+    );
+    sim.load().unwrap();
+
+    assert!(sim.run_until_idle(100));
+    assert_eq!(
+        *log.borrow(),
+        ["high", "high", "low", "low", "none", "none"]
+    );
+}
+
+#[test]
+fn test_mlfq_lengthens_the_timeslice_of_demoted_processes() {
+    let mut sim = Sim::new();
+    let states: &'static [MLFQProcessState] = Box::leak(
+        (0..NUM_PROCS)
+            .map(|_| MLFQProcessState::default())
+            .collect(),
+    );
+    sim.set_scheduler(Box::leak(Box::new(MLFQSched::new(
+        sim.chip().time(),
+        states,
+    ))));
+    let log = Log::default();
+    sim.add_app("a", 1024, worker(sim.chip(), &log, "a", usize::MAX, 4000));
+    sim.add_app("b", 1024, worker(sim.chip(), &log, "b", usize::MAX, 4000));
+    sim.load().unwrap();
+
+    // Both processes use up the 10 ms timeslice of the first queue, and move
+    // to the second queue, where the timeslice is 20 ms.
+    sim.run(10);
+    let mut expected = vec!["a"; 3];
+    expected.extend(&["b"; 3]);
+    expected.extend(&["a"; 5]);
+    expected.extend(&["b"; 5]);
+    assert_eq!(log.borrow()[..16], expected[..]);
+}
+
+#[test]
+fn test_mlfq_restarted_processes_start_in_the_highest_queue() {
+    let mut sim = Sim::new();
+    sim.set_fault_response(FaultResponse::Restart(Box::leak(Box::new(
+        AlwaysRestart::new(),
+    ))));
+    let states: &'static [MLFQProcessState] = Box::leak(
+        (0..NUM_PROCS)
+            .map(|_| MLFQProcessState::default())
+            .collect(),
+    );
+    sim.set_scheduler(Box::leak(Box::new(MLFQSched::new(
+        sim.chip().time(),
+        states,
+    ))));
+    let log = Log::default();
+    let mut a = worker(sim.chip(), &log, "a", usize::MAX, 4000);
+    let (mut starts, mut steps) = (0, 0);
+    sim.add_app("a", 1024, move |resume| {
+        if let Resume::Start(_) = resume {
+            starts += 1;
+        }
+        // Fault when the process runs again after it was demoted.
+        if starts == 1 && steps == 3 {
+            return Action::Fault;
+        }
+        steps += 1;
+        a(resume)
+    });
+    sim.add_app("b", 1024, worker(sim.chip(), &log, "b", usize::MAX, 4000));
+    sim.load().unwrap();
+
+    // The restarted process is a new process, so it uses up the 10 ms
+    // timeslice of the first queue before its 20 ms timeslice in the second.
+    sim.run(10);
+    let mut expected = vec!["a"; 3];
+    expected.extend(&["b"; 3]);
+    expected.extend(&["a"; 3 + 5]);
+    expected.extend(&["b"; 5]);
+    assert_eq!(log.borrow()[..19], expected[..]);
+}
+
+#[test]
+fn test_edf_runs_the_earliest_deadline_first() {
+    let mut sim = Sim::new();
+    let states: &'static [EDFProcessState] =
+        Box::leak((0..NUM_PROCS).map(|_| EDFProcessState::default()).collect());
+    sim.set_scheduler(Box::leak(Box::new(EDFSched::new(
+        sim.kernel(),
+        sim.chip().time(),
+        states,
+    ))));
+    let waker = waker(&sim);
+    let log = Log::default();
+    let slow = worker(sim.chip(), &log, "slow", 2, 1000);
+    let fast = worker(sim.chip(), &log, "fast", 2, 1000);
+    sim.add_app("slow", 4096, real_time(100_000, slow));
+    sim.add_app("fast", 4096, real_time(20_000, fast));
+    sim.load().unwrap();
+    assert!(sim.run_until_idle(100));
+
+    sim.chip().raise_interrupt(move || waker.wake());
+    assert!(sim.run_until_idle(100));
+    assert_eq!(*log.borrow(), ["fast", "fast", "slow", "slow"]);
+}
+
+#[test]
+fn test_edf_runs_jobs_with_sub_millisecond_periods() {
+    let mut sim = Sim::new();
+    let states: &'static [EDFProcessState] =
+        Box::leak((0..NUM_PROCS).map(|_| EDFProcessState::default()).collect());
+    sim.set_scheduler(Box::leak(Box::new(EDFSched::new(
+        sim.kernel(),
+        sim.chip().time(),
+        states,
+    ))));
+    let waker = waker(&sim);
+    let log = Log::default();
+    // The job has less time until its deadline than the kernel needs left of
+    // a timeslice to run a process.
+    let tight = worker(sim.chip(), &log, "tight", 1, 100);
+    sim.add_app("tight", 4096, real_time(400, tight));
+    sim.load().unwrap();
+    assert!(sim.run_until_idle(100));
+
+    sim.chip().raise_interrupt(move || waker.wake());
+    assert!(sim.run_until_idle(100));
+    assert_eq!(*log.borrow(), ["tight"]);
+    assert_eq!(sim.process("tight").unwrap().debug_deadline_miss_count(), 0);
+}
@@ -0,0 +1,74 @@
+//! Tests of how the kernel parses the TBF headers of simulated apps.
+
+use kernel::procs::{ProcessLoadError, SchedulingParameters};
+use kernel::syscall::Syscall;
+use sim::{Action, Sim};
+
+/// The value of a scheduling parameters element.
+fn scheduling_parameters(priority: u32, timeslice_us: u32, cpu_budget_us: u32) -> Vec<u8> {
+    [priority, timeslice_us, cpu_budget_us]
+        .iter()
+        .flat_map(|value| value.to_le_bytes().to_vec())
+        .collect()
+}
+
+#[test]
+fn test_scheduling_parameters() {
+    let mut sim = Sim::new();
+    let requests = scheduling_parameters(3, 0xFFFFFFFF, 2000);
+    sim.add_app_with_tlvs("requests", 1024, &[(6, &requests)], |_| {
+        Action::Syscall(Syscall::YIELD)
+    });
+    sim.add_app("default", 1024, |_| Action::Syscall(Syscall::YIELD));
+    sim.load().unwrap();
+
+    // Parameters set to 0xFFFFFFFF are not requested.
+    assert_eq!(
+        sim.process("requests").unwrap().get_scheduling_parameters(),
+        SchedulingParameters {
+            priority: Some(3),
+            timeslice_us: None,
+            cpu_budget_us: Some(2000),
+        }
+    );
+    assert_eq!(
+        sim.process("default").unwrap().get_scheduling_parameters(),
+        SchedulingParameters::default()
+    );
+}
+
+#[test]
+fn test_truncated_scheduling_parameters_are_rejected() {
+    let mut sim = Sim::new();
+    let requests = scheduling_parameters(3, 0xFFFFFFFF, 2000);
+    sim.add_app_with_tlvs("truncated", 1024, &[(6, &requests[..8])], |_| {
+        Action::Syscall(Syscall::YIELD)
+    });
+    assert!(sim.load().is_err());
+    assert!(sim.process("truncated").is_none());
+}
+
+#[test]
+fn test_oversized_retained_ram_is_rejected() {
+    let mut sim = Sim::new();
+    // A retained region of 4 GiB does not fit in the memory of the simulated
+    // board. Sizes whose rounding overflows are tested with the kernel.
+    let retained_ram = u32::MAX.to_le_bytes();
+    sim.add_app_with_tlvs("retains", 1024, &[(8, &retained_ram)], |_| {
+        Action::Syscall(Syscall::YIELD)
+    });
+    match sim.load() {
+        Err(ProcessLoadError::NotEnoughMemory) => {}
+        _ => panic!("a process with an oversized retained region was loaded"),
+    }
+    assert!(sim.process("retains").is_none());
+}
+
+#[test]
+fn test_incomplete_permissions_are_rejected() {
+    let mut sim = Sim::new();
+    sim.add_app_with_tlvs("incomplete", 1024, &[(11, &[0; 20])], |_| {
+        Action::Syscall(Syscall::YIELD)
+    });
+    assert!(sim.load().is_err());
+}
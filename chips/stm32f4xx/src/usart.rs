@@ -3,6 +3,7 @@ use kernel::common::cells::OptionalCell;
 use kernel::common::registers::{register_bitfields, ReadWrite};
 use kernel::common::StaticRef;
 use kernel::hil;
+use kernel::power_manager::ManagedClock;
 use kernel::ClockInterface;
 use kernel::ReturnCode;
 
@@ -166,7 +167,7 @@ enum USARTStateTX {
 
 pub struct Usart<'a> {
     registers: StaticRef<UsartRegisters>,
-    clock: UsartClock,
+    clock: ManagedClock<'a>,
 
     tx_client: OptionalCell<&'a dyn hil::uart::TransmitClient>,
     rx_client: OptionalCell<&'a dyn hil::uart::ReceiveClient>,
@@ -189,14 +190,22 @@ pub struct RxDMA<'a>(pub &'a dma1::Stream<'a>);
 
 pub static mut USART2: Usart = Usart::new(
     USART2_BASE,
-    UsartClock(rcc::PeripheralClock::APB1(rcc::PCLK1::USART2)),
+    ManagedClock::new(
+        "usart2",
+        &UsartClock(rcc::PeripheralClock::APB1(rcc::PCLK1::USART2)),
+        None,
+    ),
     Dma1Peripheral::USART2_TX,
     Dma1Peripheral::USART2_RX,
 );
 
 pub static mut USART3: Usart = Usart::new(
     USART3_BASE,
-    UsartClock(rcc::PeripheralClock::APB1(rcc::PCLK1::USART3)),
+    ManagedClock::new(
+        "usart3",
+        &UsartClock(rcc::PeripheralClock::APB1(rcc::PCLK1::USART3)),
+        None,
+    ),
     Dma1Peripheral::USART3_TX,
     Dma1Peripheral::USART3_RX,
 );
@@ -204,7 +213,7 @@ pub static mut USART3: Usart = Usart::new(
 impl<'a> Usart<'a> {
     const fn new(
         base_addr: StaticRef<UsartRegisters>,
-        clock: UsartClock,
+        clock: ManagedClock<'a>,
         tx_dma_pid: Dma1Peripheral,
         rx_dma_pid: Dma1Peripheral,
     ) -> Usart<'a> {
@@ -232,12 +241,11 @@ impl<'a> Usart<'a> {
         self.clock.is_enabled()
     }
 
-    pub fn enable_clock(&self) {
-        self.clock.enable();
-    }
-
-    pub fn disable_clock(&self) {
-        self.clock.disable();
+    /// The clock of the USART, which is only on while the USART is
+    /// configured, transmits, or waits for data to receive. A console keeps a
+    /// receive outstanding, so the clock of its USART stays on.
+    pub fn managed_clock(&self) -> &ManagedClock<'a> {
+        &self.clock
     }
 
     pub fn set_dma(&self, tx_dma: TxDMA<'a>, rx_dma: RxDMA<'a>) {
@@ -256,6 +264,7 @@ impl<'a> Usart<'a> {
         if self.usart_tx_state.get() == USARTStateTX::Transfer_Completing {
             self.disable_tx();
             self.usart_tx_state.set(USARTStateTX::Idle);
+            self.clock.release();
 
             // get buffer
             let buffer = self.tx_dma.map_or(None, |tx_dma| tx_dma.return_buffer());
@@ -278,6 +287,11 @@ impl<'a> Usart<'a> {
 
     // for use by panic in io.rs
     pub fn send_byte(&self, byte: u8) {
+        // The clock stays on for the rest of the panic.
+        if self.clock.users() == 0 {
+            self.clock.acquire();
+        }
+
         // loop till TXE (Transmit data register empty) becomes 1
         while !self.registers.sr.is_set(SR::TXE) {}
 
@@ -307,6 +321,7 @@ impl<'a> Usart<'a> {
     fn abort_tx(&self, rcode: ReturnCode) {
         self.disable_tx();
         self.usart_tx_state.set(USARTStateTX::Idle);
+        self.clock.release();
 
         // get buffer
         let (mut buffer, len) = self.tx_dma.map_or((None, 0), |tx_dma| {
@@ -329,7 +344,10 @@ impl<'a> Usart<'a> {
 
     fn abort_rx(&self, rcode: ReturnCode, error: hil::uart::Error) {
         self.disable_rx();
-        self.usart_rx_state.set(USARTStateRX::Idle);
+        if self.usart_rx_state.get() != USARTStateRX::Idle {
+            self.usart_rx_state.set(USARTStateRX::Idle);
+            self.clock.release();
+        }
 
         // get buffer
         let (mut buffer, len) = self.rx_dma.map_or((None, 0), |rx_dma| {
@@ -382,6 +400,8 @@ impl<'a> hil::uart::Transmit<'a> for Usart<'a> {
             return (ReturnCode::EBUSY, Some(tx_data));
         }
 
+        self.clock.acquire();
+
         // setup and enable dma stream
         self.tx_dma.map(move |dma| {
             self.tx_len.set(tx_len);
@@ -422,6 +442,9 @@ impl<'a> hil::uart::Configure for Usart<'a> {
             );
         }
 
+        // The registers keep their values while the clock is off.
+        self.clock.acquire();
+
         // Configure the word length - 0: 1 Start bit, 8 Data bits, n Stop bits
         self.registers.cr1.modify(CR1::M::CLEAR);
 
@@ -448,6 +471,7 @@ impl<'a> hil::uart::Configure for Usart<'a> {
         // Enable USART
         self.registers.cr1.modify(CR1::UE::SET);
 
+        self.clock.release();
         ReturnCode::SUCCESS
     }
 }
@@ -470,6 +494,8 @@ impl<'a> hil::uart::Receive<'a> for Usart<'a> {
             return (ReturnCode::ESIZE, Some(rx_buffer));
         }
 
+        self.clock.acquire();
+
         // setup and enable dma stream
         self.rx_dma.map(move |dma| {
             self.rx_len.set(rx_len);
@@ -507,6 +533,7 @@ impl dma1::StreamClient for Usart<'_> {
             if self.usart_rx_state.get() == USARTStateRX::DMA_Receiving {
                 self.disable_rx();
                 self.usart_rx_state.set(USARTStateRX::Idle);
+                self.clock.release();
 
                 // get buffer
                 let buffer = self.rx_dma.map_or(None, |rx_dma| rx_dma.return_buffer());
@@ -119,6 +119,15 @@ impl AppId {
         self.identifier
     }
 
+    /// Get the identifier of the app this `AppId` refers to that stays the
+    /// same across reboots and updates of the app, so capsules can use it to
+    /// key state they keep for the app in nonvolatile storage. Returns `None`
+    /// if the app has no persistent identifier or no longer exists.
+    pub fn persistent_id(&self) -> Option<u32> {
+        self.kernel
+            .process_map_or(None, *self, |process| process.persistent_id())
+    }
+
     /// Returns the full address of the start and end of the flash region that
     /// the app owns and can write to. This includes the app's code and data and
     /// any padding at the end of the app. It does not include the TBF header,
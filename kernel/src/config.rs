@@ -32,6 +32,8 @@ pub(crate) struct Config {
     ///
     /// If enabled, the kernel will print a message in the debug output for each system call and
     /// callback, with details including the application ID, and system call or callback parameters.
+    /// To trace system calls without rebuilding the kernel, use a `syscall_trace::SyscallTrace`
+    /// instead.
     pub(crate) trace_syscalls: bool,
 
     /// Whether the kernel should show debugging output when loading processes.
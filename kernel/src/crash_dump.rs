@@ -0,0 +1,156 @@
+//! Structured crash dumps of faulted processes.
+//!
+//! When a process faults, the kernel calls the `CrashDumpClient` the board set
+//! with `Kernel::set_crash_dump_client()` before it restarts or stops the
+//! process. The client can then get a dump of the process with
+//! `ProcessType::write_crash_dump()` and store it or send it to a host, where
+//! `tools/crash_dump_to_core.py` turns it into a core file GDB can load.
+//!
+//! Format
+//! ------
+//!
+//! All values are little endian `u32`s. A dump starts with `MAGIC` and the
+//! length of the whole dump in bytes, followed by records. Each record has a
+//! type, the length of its data in bytes, and the data, padded with zeros to a
+//! multiple of four bytes. The records are:
+//!
+//! - `RECORD_PROCESS`: the start of the process's flash, the start and end of
+//!   its memory, the app break, the kernel memory break and the number of
+//!   restarts, followed by the process name.
+//! - `RECORD_REGISTERS`: the registers of the process in the order GDB numbers
+//!   them: r0-r15 and xPSR on Cortex-M, x0-x31 and pc on RISC-V.
+//! - `RECORD_GRANT_POINTERS`: the grant pointers of the process, starting with
+//!   grant 0.
+//! - `RECORD_TBF_HEADER`: the address of the TBF header, followed by the
+//!   header.
+//! - `RECORD_MEMORY`: an address, followed by the memory at that address. The
+//!   dump has one for the process stack, from the stack pointer up. It is the
+//!   last record, so that only the far end of the stack is lost if the dump
+//!   does not fit in the buffer it is written to.
+
+use crate::process::ProcessType;
+
+/// The first word of a crash dump, "TKCD".
+pub const MAGIC: u32 = 0x4443_4b54;
+
+pub const RECORD_PROCESS: u32 = 1;
+pub const RECORD_REGISTERS: u32 = 2;
+pub const RECORD_GRANT_POINTERS: u32 = 3;
+pub const RECORD_TBF_HEADER: u32 = 4;
+pub const RECORD_MEMORY: u32 = 5;
+
+/// The most registers any architecture stores in a crash dump.
+pub const MAX_REGISTERS: usize = 33;
+
+/// Receives processes that faulted, to dump them.
+pub trait CrashDumpClient {
+    /// Called when `process` faulted, before the kernel responds to the
+    /// fault. The memory of the process is still as it was when it faulted.
+    fn process_faulted(&self, process: &dyn ProcessType);
+}
+
+/// Writes the records of a crash dump into a buffer, dropping whatever does
+/// not fit.
+pub(crate) struct CrashDumpWriter<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+    /// Where the header of the current record starts.
+    record_start: usize,
+}
+
+impl<'a> CrashDumpWriter<'a> {
+    pub(crate) fn new(buf: &'a mut [u8]) -> CrashDumpWriter<'a> {
+        let mut writer = CrashDumpWriter {
+            buf: buf,
+            len: 0,
+            record_start: 0,
+        };
+        writer.push_word(MAGIC as usize);
+        writer.push_word(0);
+        writer
+    }
+
+    /// Start a record of type `record`. The record ends when the next one
+    /// starts or the dump is finished.
+    pub(crate) fn start_record(&mut self, record: u32) {
+        self.end_record();
+        self.record_start = self.len;
+        self.push_word(record as usize);
+        self.push_word(0);
+    }
+
+    pub(crate) fn push_word(&mut self, word: usize) {
+        if self.len + 4 <= self.buf.len() {
+            self.buf[self.len..self.len + 4].copy_from_slice(&(word as u32).to_le_bytes());
+            self.len += 4;
+        }
+    }
+
+    pub(crate) fn push_bytes(&mut self, bytes: &[u8]) {
+        let count = core::cmp::min(bytes.len(), self.buf.len() - self.len);
+        self.buf[self.len..self.len + count].copy_from_slice(&bytes[..count]);
+        self.len += count;
+    }
+
+    /// Fill in the length of the current record and pad it.
+    fn end_record(&mut self) {
+        if self.record_start == 0 || self.record_start + 8 > self.len {
+            return;
+        }
+        let length = (self.len - self.record_start - 8) as u32;
+        self.buf[self.record_start + 4..self.record_start + 8]
+            .copy_from_slice(&length.to_le_bytes());
+        while self.len % 4 != 0 && self.len < self.buf.len() {
+            self.buf[self.len] = 0;
+            self.len += 1;
+        }
+    }
+
+    /// Finish the dump and return its length.
+    pub(crate) fn finish(mut self) -> usize {
+        self.end_record();
+        if self.len >= 8 {
+            self.buf[4..8].copy_from_slice(&(self.len as u32).to_le_bytes());
+        }
+        self.len
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_truncated_record() {
+        let mut buf = [0xff; 26];
+        let mut writer = CrashDumpWriter::new(&mut buf);
+        writer.start_record(RECORD_MEMORY);
+        writer.push_word(0x2000_0000);
+        writer.push_bytes(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+        assert_eq!(writer.finish(), 26);
+
+        assert_eq!(&buf[0..4], b"TKCD");
+        assert_eq!(&buf[4..8], &26u32.to_le_bytes());
+        assert_eq!(&buf[8..12], &RECORD_MEMORY.to_le_bytes());
+        // Only 6 of the bytes fit after the address.
+        assert_eq!(&buf[12..16], &10u32.to_le_bytes());
+        assert_eq!(&buf[16..20], &0x2000_0000u32.to_le_bytes());
+        assert_eq!(&buf[20..26], &[1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn test_padding() {
+        let mut buf = [0xff; 64];
+        let mut writer = CrashDumpWriter::new(&mut buf);
+        writer.start_record(RECORD_PROCESS);
+        writer.push_bytes(b"blink");
+        writer.start_record(RECORD_REGISTERS);
+        writer.push_word(7);
+        assert_eq!(writer.finish(), 36);
+
+        assert_eq!(&buf[12..16], &5u32.to_le_bytes());
+        assert_eq!(&buf[16..24], b"blink\0\0\0");
+        assert_eq!(&buf[24..28], &RECORD_REGISTERS.to_le_bytes());
+        assert_eq!(&buf[28..32], &4u32.to_le_bytes());
+    }
+}
@@ -0,0 +1,226 @@
+//! Checking the credentials of apps before they run.
+//!
+//! Apps can carry credentials in footers after their binary: a SHA-256 hash of
+//! the app, and signatures of that hash made with ECDSA NIST P-256 or Ed25519
+//! keys. A board that sets a `CredentialsPolicy` only gets processes for apps
+//! whose credentials satisfy that policy. Without a policy, footers are
+//! ignored and all apps run.
+//!
+//! The credentials cover the app binary, which is everything from the start of
+//! the TBF header to the offset in the header's binary end entry. This
+//! includes the entire TBF header, so an app cannot be given more permissions
+//! without invalidating its credentials.
+//!
+//! Apps accepted because of a signature get a persistent identifier derived
+//! from the signing key and the identifier their TBF header sets, or their
+//! package name if the header sets none. Binding the identifier to the key
+//! keeps an app signed with another key, or an unsigned app, from taking it.
+
+use crate::crypto::sha2::Sha256;
+use crate::crypto::{ed25519, p256};
+use crate::tbfheader::{self, TbfFooterV2CredentialsType, TbfHeader};
+
+/// A public key the kernel accepts app signatures from.
+#[derive(Clone, Copy)]
+pub enum AppSigningKey {
+    /// An ECDSA key on the NIST P-256 curve, as the big endian x and y
+    /// coordinates of the public point.
+    EcdsaNistP256([u8; 64]),
+
+    /// An Ed25519 public key in its standard 32 byte encoding.
+    Ed25519([u8; 32]),
+}
+
+impl AppSigningKey {
+    fn as_bytes(&self) -> &[u8] {
+        match self {
+            AppSigningKey::EcdsaNistP256(key) => key,
+            AppSigningKey::Ed25519(key) => key,
+        }
+    }
+}
+
+/// Which apps the kernel is willing to run.
+#[derive(Clone, Copy)]
+pub enum CredentialsPolicy {
+    /// Only run apps with a SHA-256 footer that matches the app. This detects
+    /// apps that were corrupted, but not apps that were modified on purpose.
+    RequireHash,
+
+    /// Only run apps with a signature footer from one of these keys.
+    RequireSignature(&'static [AppSigningKey]),
+}
+
+/// How the credentials of an app satisfied a `CredentialsPolicy`.
+#[derive(Clone, Copy)]
+pub(crate) enum Accepted {
+    /// The app has a SHA-256 footer.
+    Hash,
+
+    /// The app is signed with this key.
+    Signature(&'static AppSigningKey),
+}
+
+/// Check whether the credentials in the footers of `app_flash` satisfy
+/// `policy`. Returns `None` if they do not.
+///
+/// Any SHA-256 footer must match the app. Signatures from keys the board does
+/// not know are ignored, since the app may also be signed for other boards.
+pub(crate) fn check_credentials(
+    policy: CredentialsPolicy,
+    app_flash: &[u8],
+    header: &TbfHeader,
+) -> Option<Accepted> {
+    let binary_end = header.get_binary_end_offset()? as usize;
+    let binary = app_flash.get(..binary_end)?;
+    let mut footers = app_flash.get(binary_end..)?;
+    let digest = Sha256::digest(binary);
+
+    let mut accepted = None;
+    while !footers.is_empty() {
+        let credentials = match tbfheader::parse_tbf_footer(footers) {
+            Ok((credentials, remaining)) => {
+                footers = remaining;
+                credentials
+            }
+            Err(_) => return None,
+        };
+        let credentials = match credentials {
+            Some(credentials) => credentials,
+            None => continue,
+        };
+
+        match credentials.format() {
+            TbfFooterV2CredentialsType::Padding => {}
+            TbfFooterV2CredentialsType::SHA256 => {
+                if credentials.data() != digest {
+                    return None;
+                }
+                if let CredentialsPolicy::RequireHash = policy {
+                    accepted = Some(Accepted::Hash);
+                }
+            }
+            TbfFooterV2CredentialsType::EcdsaNistP256 | TbfFooterV2CredentialsType::Ed25519 => {
+                if let CredentialsPolicy::RequireSignature(keys) = policy {
+                    // The footer parser checked the length of the signature.
+                    let mut signature = [0; 64];
+                    signature.copy_from_slice(credentials.data());
+                    let format = credentials.format();
+                    let signer = keys.iter().find(|key| match (key, format) {
+                        (
+                            AppSigningKey::EcdsaNistP256(key),
+                            TbfFooterV2CredentialsType::EcdsaNistP256,
+                        ) => p256::verify(key, &digest, &signature),
+                        (AppSigningKey::Ed25519(key), TbfFooterV2CredentialsType::Ed25519) => {
+                            ed25519::verify(key, &digest, &signature)
+                        }
+                        _ => false,
+                    });
+                    if let Some(key) = signer {
+                        accepted = Some(Accepted::Signature(key));
+                    }
+                }
+            }
+        }
+    }
+    accepted
+}
+
+/// Derive the persistent identifier of an app signed with `key` from the
+/// identifier `header_id` its TBF header sets, or from its package name if the
+/// header sets none. It stays the same when the app is updated, and differs
+/// between apps signed with the same key.
+pub(crate) fn key_derived_app_id(
+    key: &AppSigningKey,
+    package_name: &str,
+    header_id: Option<u32>,
+) -> u32 {
+    let mut hash = Sha256::new();
+    hash.update(key.as_bytes());
+    // The first byte keeps a package name from hashing like a header
+    // identifier.
+    match header_id {
+        Some(id) => {
+            hash.update(&[1]);
+            hash.update(&id.to_le_bytes());
+        }
+        None => {
+            hash.update(&[0]);
+            hash.update(package_name.as_bytes());
+        }
+    }
+    let digest = hash.finalize();
+    u32::from_le_bytes([digest[0], digest[1], digest[2], digest[3]])
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        check_credentials, key_derived_app_id, Accepted, AppSigningKey, CredentialsPolicy,
+    };
+    use crate::tbfheader;
+
+    /// An app signed with `tools/sign_tbf.py --key`, without the zeros of the
+    /// padding footer that fills it up to 256 bytes.
+    const SIGNED_APP: [u8; 140] = [
+        0x02, 0x00, 0x28, 0x00, 0x00, 0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x45, 0x05, 0x20,
+        0x00, 0x01, 0x00, 0x0c, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04,
+        0x00, 0x00, 0x07, 0x00, 0x04, 0x00, 0x40, 0x00, 0x00, 0x00, 0x00, 0x01, 0x02, 0x03, 0x04,
+        0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f, 0x10, 0x11, 0x12, 0x13,
+        0x14, 0x15, 0x16, 0x17, 0x80, 0x00, 0x44, 0x00, 0x03, 0x00, 0x00, 0x00, 0x20, 0x46, 0xe0,
+        0xfd, 0x12, 0x80, 0xb0, 0x6a, 0x89, 0x51, 0x69, 0xaa, 0xb2, 0xf3, 0x01, 0x13, 0x47, 0x8d,
+        0xfc, 0xca, 0x86, 0x49, 0x45, 0xa4, 0x9f, 0xb8, 0x40, 0xee, 0x5d, 0xdf, 0x23, 0xac, 0x04,
+        0x26, 0xc6, 0x7f, 0xb3, 0xe2, 0x72, 0x9c, 0x24, 0x0a, 0xa7, 0x83, 0x97, 0x41, 0xc4, 0x8c,
+        0x88, 0x6f, 0x7a, 0xb3, 0xd0, 0x49, 0x5b, 0x55, 0xe9, 0xab, 0xda, 0xa3, 0xf0, 0x9b, 0xe1,
+        0x0a, 0x80, 0x00, 0x74, 0x00,
+    ];
+
+    const KEYS: [AppSigningKey; 1] = [AppSigningKey::Ed25519([
+        0x07, 0x35, 0xb9, 0x92, 0x02, 0x2f, 0xef, 0x56, 0xc5, 0xd0, 0x31, 0xbe, 0x1a, 0x65, 0x42,
+        0xd0, 0x3d, 0x56, 0x73, 0x1b, 0x57, 0x61, 0xda, 0xa2, 0xf7, 0xbc, 0x80, 0x97, 0xd4, 0xcb,
+        0x68, 0x72,
+    ])];
+
+    fn check(policy: CredentialsPolicy, app: &[u8]) -> bool {
+        let (header, _) = tbfheader::parse_and_validate_tbf_header(app).unwrap();
+        check_credentials(policy, app, &header).is_some()
+    }
+
+    #[test]
+    fn test_check_credentials() {
+        let mut app = [0; 256];
+        app[..SIGNED_APP.len()].copy_from_slice(&SIGNED_APP);
+        assert!(check(CredentialsPolicy::RequireSignature(&KEYS), &app));
+        // The app has no hash footer.
+        assert!(!check(CredentialsPolicy::RequireHash, &app));
+        assert!(!check(CredentialsPolicy::RequireSignature(&[]), &app));
+
+        // The signing key identifies the app.
+        let (header, _) = tbfheader::parse_and_validate_tbf_header(&app).unwrap();
+        match check_credentials(CredentialsPolicy::RequireSignature(&KEYS), &app, &header) {
+            Some(Accepted::Signature(key)) => {
+                assert_eq!(key.as_bytes(), KEYS[0].as_bytes());
+                assert_ne!(
+                    key_derived_app_id(key, "app", None),
+                    key_derived_app_id(key, "other_app", None)
+                );
+                // An identifier set in the header is bound to the key.
+                let other_key = AppSigningKey::Ed25519([0; 32]);
+                assert_eq!(
+                    key_derived_app_id(key, "app", Some(7)),
+                    key_derived_app_id(key, "renamed_app", Some(7))
+                );
+                assert_ne!(
+                    key_derived_app_id(key, "app", Some(7)),
+                    key_derived_app_id(&other_key, "app", Some(7))
+                );
+                assert_ne!(key_derived_app_id(key, "app", Some(7)), 7);
+            }
+            _ => panic!("app not accepted by its key"),
+        }
+
+        // Changing the binary invalidates the signature.
+        app[50] ^= 1;
+        assert!(!check(CredentialsPolicy::RequireSignature(&KEYS), &app));
+    }
+}
@@ -0,0 +1,201 @@
+//! 256-bit integers and arithmetic modulo odd 256-bit moduli.
+//!
+//! Modular arithmetic uses Montgomery multiplication, which works for any odd
+//! modulus, so the same code serves the fields and scalar groups of both P-256
+//! and Ed25519. Signature verification only handles public values, so none of
+//! these operations try to run in constant time.
+
+use core::convert::TryInto;
+
+/// Unsigned 256-bit integer, least significant limb first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct U256(pub(crate) [u64; 4]);
+
+impl U256 {
+    pub(crate) const ZERO: U256 = U256([0, 0, 0, 0]);
+    pub(crate) const ONE: U256 = U256([1, 0, 0, 0]);
+
+    pub(crate) fn from_be_bytes(bytes: &[u8; 32]) -> U256 {
+        let mut limbs = [0; 4];
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            let start = 32 - 8 * (i + 1);
+            *limb = u64::from_be_bytes(bytes[start..start + 8].try_into().unwrap_or([0; 8]));
+        }
+        U256(limbs)
+    }
+
+    pub(crate) fn from_le_bytes(bytes: &[u8; 32]) -> U256 {
+        let mut limbs = [0; 4];
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            *limb = u64::from_le_bytes(bytes[8 * i..8 * i + 8].try_into().unwrap_or([0; 8]));
+        }
+        U256(limbs)
+    }
+
+    pub(crate) fn to_le_bytes(&self) -> [u8; 32] {
+        let mut bytes = [0; 32];
+        for (i, limb) in self.0.iter().enumerate() {
+            bytes[8 * i..8 * i + 8].copy_from_slice(&limb.to_le_bytes());
+        }
+        bytes
+    }
+
+    pub(crate) fn is_zero(&self) -> bool {
+        *self == U256::ZERO
+    }
+
+    /// Returns bit `index` of the number.
+    pub(crate) fn bit(&self, index: usize) -> bool {
+        (self.0[index / 64] >> (index % 64)) & 1 == 1
+    }
+
+    /// Returns `self + other` and whether the addition overflowed.
+    pub(crate) fn add(&self, other: &U256) -> (U256, bool) {
+        let mut result = [0; 4];
+        let mut carry = 0;
+        for i in 0..4 {
+            let sum = self.0[i] as u128 + other.0[i] as u128 + carry;
+            result[i] = sum as u64;
+            carry = sum >> 64;
+        }
+        (U256(result), carry != 0)
+    }
+
+    /// Returns `self - other` and whether the subtraction underflowed.
+    pub(crate) fn sub(&self, other: &U256) -> (U256, bool) {
+        let mut result = [0; 4];
+        let mut borrow = 0;
+        for i in 0..4 {
+            let difference = (self.0[i] as u128)
+                .wrapping_sub(other.0[i] as u128)
+                .wrapping_sub(borrow);
+            result[i] = difference as u64;
+            borrow = (difference >> 64) & 1;
+        }
+        (U256(result), borrow != 0)
+    }
+
+    /// Returns whether `self < other`.
+    pub(crate) fn lt(&self, other: &U256) -> bool {
+        self.sub(other).1
+    }
+}
+
+/// An odd modulus and the constants needed for Montgomery multiplication
+/// with R = 2^256.
+pub(crate) struct Modulus {
+    /// The modulus.
+    pub(crate) m: U256,
+    /// -m^-1 mod 2^64.
+    pub(crate) m0inv: u64,
+    /// R^2 mod m.
+    pub(crate) r2: U256,
+}
+
+/// Returns `a + b * c + carry` as (low, high) limbs.
+fn mac(a: u64, b: u64, c: u64, carry: u64) -> (u64, u64) {
+    let result = a as u128 + b as u128 * c as u128 + carry as u128;
+    (result as u64, (result >> 64) as u64)
+}
+
+impl Modulus {
+    /// Subtract the modulus from `a` if `a` (plus an extra 2^256 if `carry` is
+    /// set) is not less than it.
+    fn reduce_once(&self, a: U256, carry: bool) -> U256 {
+        if carry || !a.lt(&self.m) {
+            a.sub(&self.m).0
+        } else {
+            a
+        }
+    }
+
+    /// Returns `a + b` for reduced `a` and `b`.
+    pub(crate) fn add(&self, a: &U256, b: &U256) -> U256 {
+        let (sum, carry) = a.add(b);
+        self.reduce_once(sum, carry)
+    }
+
+    /// Returns `a - b` for reduced `a` and `b`.
+    pub(crate) fn sub(&self, a: &U256, b: &U256) -> U256 {
+        let (difference, borrow) = a.sub(b);
+        if borrow {
+            difference.add(&self.m).0
+        } else {
+            difference
+        }
+    }
+
+    /// Returns `-a` for a reduced `a`.
+    pub(crate) fn neg(&self, a: &U256) -> U256 {
+        self.sub(&U256::ZERO, a)
+    }
+
+    /// Montgomery multiplication: returns `a * b / R mod m`. The result is
+    /// reduced as long as `a * b < m * R`.
+    pub(crate) fn mul(&self, a: &U256, b: &U256) -> U256 {
+        let m = &self.m.0;
+        let mut t = [0u64; 6];
+        for i in 0..4 {
+            let mut carry = 0;
+            for j in 0..4 {
+                let (low, high) = mac(t[j], a.0[j], b.0[i], carry);
+                t[j] = low;
+                carry = high;
+            }
+            let (sum, overflow) = t[4].overflowing_add(carry);
+            t[4] = sum;
+            t[5] = overflow as u64;
+
+            let factor = t[0].wrapping_mul(self.m0inv);
+            let (_, mut carry) = mac(t[0], factor, m[0], 0);
+            for j in 1..4 {
+                let (low, high) = mac(t[j], factor, m[j], carry);
+                t[j - 1] = low;
+                carry = high;
+            }
+            let (sum, overflow) = t[4].overflowing_add(carry);
+            t[3] = sum;
+            t[4] = t[5] + overflow as u64;
+        }
+        self.reduce_once(U256([t[0], t[1], t[2], t[3]]), t[4] != 0)
+    }
+
+    /// Returns `a^2 / R mod m`.
+    pub(crate) fn square(&self, a: &U256) -> U256 {
+        self.mul(a, a)
+    }
+
+    /// Convert any 256-bit number to Montgomery form, reducing it.
+    pub(crate) fn to_montgomery(&self, a: &U256) -> U256 {
+        self.mul(a, &self.r2)
+    }
+
+    /// Convert a number in Montgomery form back to its normal form.
+    pub(crate) fn from_montgomery(&self, a: &U256) -> U256 {
+        self.mul(a, &U256::ONE)
+    }
+
+    /// Returns 1 in Montgomery form.
+    pub(crate) fn one(&self) -> U256 {
+        self.to_montgomery(&U256::ONE)
+    }
+
+    /// Returns `a^exponent` for `a` in Montgomery form.
+    pub(crate) fn pow(&self, a: &U256, exponent: &U256) -> U256 {
+        let mut result = self.one();
+        for i in (0..256).rev() {
+            result = self.square(&result);
+            if exponent.bit(i) {
+                result = self.mul(&result, a);
+            }
+        }
+        result
+    }
+
+    /// Returns `a^-1` for a non-zero `a` in Montgomery form. The modulus must
+    /// be prime.
+    pub(crate) fn invert(&self, a: &U256) -> U256 {
+        let exponent = self.m.sub(&U256([2, 0, 0, 0])).0;
+        self.pow(a, &exponent)
+    }
+}
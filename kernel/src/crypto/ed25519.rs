@@ -0,0 +1,237 @@
+//! Ed25519 signature verification (RFC 8032).
+
+use super::bignum::{Modulus, U256};
+use super::sha2::Sha512;
+
+/// The field prime p = 2^255 - 19.
+const FIELD: Modulus = Modulus {
+    m: U256([
+        0xffffffffffffffed,
+        0xffffffffffffffff,
+        0xffffffffffffffff,
+        0x7fffffffffffffff,
+    ]),
+    m0inv: 0x86bca1af286bca1b,
+    r2: U256([0x00000000000005a4, 0, 0, 0]),
+};
+
+/// The order L of the base point.
+const SCALAR: Modulus = Modulus {
+    m: U256([
+        0x5812631a5cf5d3ed,
+        0x14def9dea2f79cd6,
+        0x0000000000000000,
+        0x1000000000000000,
+    ]),
+    m0inv: 0xd2b51da312547e1b,
+    r2: U256([
+        0xa40611e3449c0f01,
+        0xd00e1ba768859347,
+        0xceec73d217f5be65,
+        0x0399411b7c309a3d,
+    ]),
+};
+
+/// R^3 mod L, used to reduce 512-bit hashes modulo L.
+const SCALAR_R3: U256 = U256([
+    0x2a9e49687b83a2db,
+    0x278324e6aef7f3ec,
+    0x8065dc6c04ec5b65,
+    0x0e530b773599cec7,
+]);
+
+/// The constant d = -121665 / 121666 of the curve equation
+/// -x^2 + y^2 = 1 + d x^2 y^2.
+const D: U256 = U256([
+    0x75eb4dca135978a3,
+    0x00700a4d4141d8ab,
+    0x8cc740797779e898,
+    0x52036cee2b6ffe73,
+]);
+
+/// A square root of -1 modulo p.
+const SQRT_M1: U256 = U256([
+    0xc4ee1b274a0ea0b0,
+    0x2f431806ad2fe478,
+    0x2b4d00993dfbd7a7,
+    0x2b8324804fc1df0b,
+]);
+
+/// Encoding of the base point B.
+const BASE_POINT: [u8; 32] = [
+    0x58, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66,
+    0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66,
+];
+
+/// A point in extended coordinates (X / Z, Y / Z) with T = XY / Z, with the
+/// coordinates in Montgomery form.
+#[derive(Clone, Copy)]
+struct Point {
+    x: U256,
+    y: U256,
+    z: U256,
+    t: U256,
+}
+
+impl Point {
+    fn identity() -> Point {
+        Point {
+            x: U256::ZERO,
+            y: FIELD.one(),
+            z: FIELD.one(),
+            t: U256::ZERO,
+        }
+    }
+
+    /// Decode a point from its 32 byte encoding (RFC 8032 section 5.1.3).
+    fn decode(bytes: &[u8; 32]) -> Option<Point> {
+        let f = &FIELD;
+        let x_0 = bytes[31] >> 7 == 1;
+        let mut y_bytes = *bytes;
+        y_bytes[31] &= 0x7f;
+        let y = U256::from_le_bytes(&y_bytes);
+        if !y.lt(&f.m) {
+            return None;
+        }
+
+        // Recover x from x^2 = (y^2 - 1) / (d y^2 + 1).
+        let y = f.to_montgomery(&y);
+        let one = f.one();
+        let y2 = f.square(&y);
+        let u = f.sub(&y2, &one);
+        let v = f.add(&f.mul(&f.to_montgomery(&D), &y2), &one);
+        let v3 = f.mul(&f.square(&v), &v);
+        let v7 = f.mul(&f.square(&v3), &v);
+        // (p - 5) / 8
+        let exponent = U256([
+            0xfffffffffffffffd,
+            0xffffffffffffffff,
+            0xffffffffffffffff,
+            0x0fffffffffffffff,
+        ]);
+        let mut x = f.mul(&f.mul(&u, &v3), &f.pow(&f.mul(&u, &v7), &exponent));
+        let vx2 = f.mul(&v, &f.square(&x));
+        if vx2 != u {
+            if vx2 == f.neg(&u) {
+                x = f.mul(&x, &f.to_montgomery(&SQRT_M1));
+            } else {
+                return None;
+            }
+        }
+
+        let x_normal = f.from_montgomery(&x);
+        if x_normal.is_zero() && x_0 {
+            return None;
+        }
+        if x_normal.bit(0) != x_0 {
+            x = f.neg(&x);
+        }
+        Some(Point {
+            x: x,
+            y: y,
+            z: one,
+            t: f.mul(&x, &y),
+        })
+    }
+
+    /// Encode the point (RFC 8032 section 5.1.2).
+    fn encode(&self) -> [u8; 32] {
+        let f = &FIELD;
+        let z_inverse = f.invert(&self.z);
+        let x = f.from_montgomery(&f.mul(&self.x, &z_inverse));
+        let y = f.from_montgomery(&f.mul(&self.y, &z_inverse));
+        let mut bytes = y.to_le_bytes();
+        if x.bit(0) {
+            bytes[31] |= 0x80;
+        }
+        bytes
+    }
+
+    fn negate(&self) -> Point {
+        Point {
+            x: FIELD.neg(&self.x),
+            y: self.y,
+            z: self.z,
+            t: FIELD.neg(&self.t),
+        }
+    }
+
+    /// Returns self + other (RFC 8032 section 5.1.4). The formulas are
+    /// complete, so they also work for doubling.
+    fn add(&self, other: &Point) -> Point {
+        let f = &FIELD;
+        let a = f.mul(&f.sub(&self.y, &self.x), &f.sub(&other.y, &other.x));
+        let b = f.mul(&f.add(&self.y, &self.x), &f.add(&other.y, &other.x));
+        let d2 = f.to_montgomery(&D);
+        let d2 = f.add(&d2, &d2);
+        let c = f.mul(&f.mul(&self.t, &d2), &other.t);
+        let d = f.mul(&self.z, &other.z);
+        let d = f.add(&d, &d);
+        let e = f.sub(&b, &a);
+        let f_ = f.sub(&d, &c);
+        let g = f.add(&d, &c);
+        let h = f.add(&b, &a);
+        Point {
+            x: f.mul(&e, &f_),
+            y: f.mul(&g, &h),
+            z: f.mul(&f_, &g),
+            t: f.mul(&e, &h),
+        }
+    }
+
+    /// Returns scalar * self.
+    fn multiply(&self, scalar: &U256) -> Point {
+        let mut result = Point::identity();
+        for i in (0..256).rev() {
+            result = result.add(&result);
+            if scalar.bit(i) {
+                result = result.add(self);
+            }
+        }
+        result
+    }
+}
+
+/// Reduce a 512-bit little endian number modulo L.
+fn reduce_scalar(bytes: &[u8; 64]) -> U256 {
+    let mut low = [0; 32];
+    let mut high = [0; 32];
+    low.copy_from_slice(&bytes[0..32]);
+    high.copy_from_slice(&bytes[32..64]);
+    let l = &SCALAR;
+    // low * R and high * R^2, both in Montgomery form, sum to the number
+    // times R.
+    let low = l.to_montgomery(&U256::from_le_bytes(&low));
+    let high = l.mul(&U256::from_le_bytes(&high), &SCALAR_R3);
+    l.from_montgomery(&l.add(&low, &high))
+}
+
+/// Verify an Ed25519 signature of `message` made with `public_key`.
+pub(crate) fn verify(public_key: &[u8; 32], message: &[u8], signature: &[u8; 64]) -> bool {
+    let a = match Point::decode(public_key) {
+        Some(a) => a,
+        None => return false,
+    };
+    let mut r_bytes = [0; 32];
+    let mut s_bytes = [0; 32];
+    r_bytes.copy_from_slice(&signature[0..32]);
+    s_bytes.copy_from_slice(&signature[32..64]);
+    let s = U256::from_le_bytes(&s_bytes);
+    if !s.lt(&SCALAR.m) {
+        return false;
+    }
+
+    let mut hash = Sha512::new();
+    hash.update(&r_bytes);
+    hash.update(public_key);
+    hash.update(message);
+    let k = reduce_scalar(&hash.finalize());
+
+    // Check that [s]B - [k]A encodes to R.
+    let base = match Point::decode(&BASE_POINT) {
+        Some(base) => base,
+        None => return false,
+    };
+    let point = base.multiply(&s).add(&a.negate().multiply(&k));
+    point.encode() == r_bytes
+}
@@ -0,0 +1,126 @@
+//! Cryptographic primitives the kernel uses to check app credentials.
+//!
+//! These are small software implementations meant for verifying signatures
+//! and hashes of app images when they are loaded. The signature checks only
+//! operate on public data and are not hardened against side channels, so they
+//! must not be used with secret keys.
+//!
+//! The SHA-2 hashes have no data dependent branches or table lookups, and are
+//! also used by capsules that provide hashing on chips without hash hardware.
+
+pub(crate) mod bignum;
+pub(crate) mod ed25519;
+pub(crate) mod p256;
+pub mod sha2;
+
+#[cfg(test)]
+mod test {
+    use super::sha2::{Sha256, Sha512};
+    use super::{ed25519, p256};
+
+    /// Fill `out` with the bytes of the hex string `hex`.
+    fn from_hex(hex: &str, out: &mut [u8]) {
+        assert_eq!(hex.len(), out.len() * 2);
+        for (i, byte) in out.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_sha256() {
+        let mut expected = [0; 32];
+        from_hex(
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            &mut expected,
+        );
+        assert_eq!(Sha256::digest(b"abc"), expected);
+
+        // Hash data in uneven pieces that cross block boundaries.
+        from_hex(
+            "41edece42d63e8d9bf515a9ba6932e1c20cbc9f5a5d134645adb5db1b9737ea3",
+            &mut expected,
+        );
+        let data = [b'a'; 1000];
+        let mut hash = Sha256::new();
+        for chunk in data.chunks(37) {
+            hash.update(chunk);
+        }
+        assert_eq!(hash.finalize(), expected);
+    }
+
+    #[test]
+    fn test_sha512() {
+        let mut expected = [0; 64];
+        from_hex(
+            "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+             2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f",
+            &mut expected,
+        );
+        let mut hash = Sha512::new();
+        hash.update(b"abc");
+        assert_eq!(&hash.finalize()[..], &expected[..]);
+
+        from_hex(
+            "67ba5535a46e3f86dbfbed8cbbaf0125c76ed549ff8b0b9e03e0c88cf90fa634\
+             fa7b12b47d77b694de488ace8d9a65967dc96df599727d3292a8d9d447709c97",
+            &mut expected,
+        );
+        let data = [b'a'; 1000];
+        let mut hash = Sha512::new();
+        for chunk in data.chunks(37) {
+            hash.update(chunk);
+        }
+        assert_eq!(&hash.finalize()[..], &expected[..]);
+    }
+
+    #[test]
+    fn test_p256_verify() {
+        let mut public_key = [0; 64];
+        let mut signature = [0; 64];
+        from_hex(
+            "471c3e758c4904285bba7e53118ed0f524adeb0757d25bd2f8e7b0d76dfa714c\
+             dd520f7aca8a8b917acc37f51de8f0c9bbe3ad858382e702dc25a12d09f7a858",
+            &mut public_key,
+        );
+        from_hex(
+            "eabcbbb15ac5ac3e7a3bba1d9e1bd0e9b082b55bc2f263d0f575bce3eff18843\
+             df17d7c97899bd4f6b9d2ecb186a8b6c80ec8df540bf8b8a9e460c4290b24864",
+            &mut signature,
+        );
+        let digest = Sha256::digest(b"Tock app signing test");
+        assert!(p256::verify(&public_key, &digest, &signature));
+
+        let other_digest = Sha256::digest(b"Tock app signing test!");
+        assert!(!p256::verify(&public_key, &other_digest, &signature));
+
+        let mut bad_signature = signature;
+        bad_signature[63] ^= 1;
+        assert!(!p256::verify(&public_key, &digest, &bad_signature));
+
+        let mut bad_key = public_key;
+        bad_key[63] ^= 1;
+        assert!(!p256::verify(&bad_key, &digest, &signature));
+    }
+
+    #[test]
+    fn test_ed25519_verify() {
+        // Test 3 from RFC 8032 section 7.1.
+        let mut public_key = [0; 32];
+        let mut signature = [0; 64];
+        from_hex(
+            "fc51cd8e6218a1a38da47ed00230f0580816ed13ba3303ac5deb911548908025",
+            &mut public_key,
+        );
+        from_hex(
+            "6291d657deec24024827e69c3abe01a30ce548a284743a445e3680d7db5ac3ac\
+             18ff9b538d16f290ae67f760984dc6594a7c15e9716ed28dc027beceea1ec40a",
+            &mut signature,
+        );
+        assert!(ed25519::verify(&public_key, &[0xaf, 0x82], &signature));
+        assert!(!ed25519::verify(&public_key, &[0xaf, 0x83], &signature));
+
+        let mut bad_signature = signature;
+        bad_signature[0] ^= 1;
+        assert!(!ed25519::verify(&public_key, &[0xaf, 0x82], &bad_signature));
+    }
+}
@@ -0,0 +1,242 @@
+//! ECDSA signature verification on the NIST P-256 curve (FIPS 186-4).
+
+use super::bignum::{Modulus, U256};
+
+/// The field prime p = 2^256 - 2^224 + 2^192 + 2^96 - 1.
+const FIELD: Modulus = Modulus {
+    m: U256([
+        0xffffffffffffffff,
+        0x00000000ffffffff,
+        0x0000000000000000,
+        0xffffffff00000001,
+    ]),
+    m0inv: 0x0000000000000001,
+    r2: U256([
+        0x0000000000000003,
+        0xfffffffbffffffff,
+        0xfffffffffffffffe,
+        0x00000004fffffffd,
+    ]),
+};
+
+/// The order n of the base point.
+const SCALAR: Modulus = Modulus {
+    m: U256([
+        0xf3b9cac2fc632551,
+        0xbce6faada7179e84,
+        0xffffffffffffffff,
+        0xffffffff00000000,
+    ]),
+    m0inv: 0xccd1c8aaee00bc4f,
+    r2: U256([
+        0x83244c95be79eea2,
+        0x4699799c49bd6fa6,
+        0x2845b2392b6bec59,
+        0x66e12d94f3d95620,
+    ]),
+};
+
+/// The constant b in the curve equation y^2 = x^3 - 3x + b.
+const B: U256 = U256([
+    0x3bce3c3e27d2604b,
+    0x651d06b0cc53b0f6,
+    0xb3ebbd55769886bc,
+    0x5ac635d8aa3a93e7,
+]);
+
+/// Coordinates of the base point G.
+const GX: U256 = U256([
+    0xf4a13945d898c296,
+    0x77037d812deb33a0,
+    0xf8bce6e563a440f2,
+    0x6b17d1f2e12c4247,
+]);
+const GY: U256 = U256([
+    0xcbb6406837bf51f5,
+    0x2bce33576b315ece,
+    0x8ee7eb4a7c0f9e16,
+    0x4fe342e2fe1a7f9b,
+]);
+
+/// A point in Jacobian coordinates (X / Z^2, Y / Z^3), with the coordinates
+/// in Montgomery form. The point at infinity has Z = 0.
+#[derive(Clone, Copy)]
+struct Point {
+    x: U256,
+    y: U256,
+    z: U256,
+}
+
+impl Point {
+    const INFINITY: Point = Point {
+        x: U256::ZERO,
+        y: U256::ZERO,
+        z: U256::ZERO,
+    };
+
+    /// Create a point from affine coordinates in Montgomery form.
+    fn from_affine(x: U256, y: U256) -> Point {
+        Point {
+            x: x,
+            y: y,
+            z: FIELD.one(),
+        }
+    }
+
+    fn is_infinity(&self) -> bool {
+        self.z.is_zero()
+    }
+
+    /// Returns 2 * self, using the "dbl-2001-b" formulas for a = -3.
+    fn double(&self) -> Point {
+        if self.is_infinity() || self.y.is_zero() {
+            return Point::INFINITY;
+        }
+        let f = &FIELD;
+        let delta = f.square(&self.z);
+        let gamma = f.square(&self.y);
+        let beta = f.mul(&self.x, &gamma);
+        let t = f.mul(&f.sub(&self.x, &delta), &f.add(&self.x, &delta));
+        let alpha = f.add(&f.add(&t, &t), &t);
+        let beta4 = f.add(&beta, &beta);
+        let beta4 = f.add(&beta4, &beta4);
+        let x = f.sub(&f.square(&alpha), &f.add(&beta4, &beta4));
+        let z = f.sub(&f.sub(&f.square(&f.add(&self.y, &self.z)), &gamma), &delta);
+        let gamma2 = f.square(&gamma);
+        let gamma2_2 = f.add(&gamma2, &gamma2);
+        let gamma2_4 = f.add(&gamma2_2, &gamma2_2);
+        let y = f.sub(
+            &f.mul(&alpha, &f.sub(&beta4, &x)),
+            &f.add(&gamma2_4, &gamma2_4),
+        );
+        Point { x: x, y: y, z: z }
+    }
+
+    /// Returns self + other, using the "add-2007-bl" formulas.
+    fn add(&self, other: &Point) -> Point {
+        if self.is_infinity() {
+            return *other;
+        }
+        if other.is_infinity() {
+            return *self;
+        }
+        let f = &FIELD;
+        let z1z1 = f.square(&self.z);
+        let z2z2 = f.square(&other.z);
+        let u1 = f.mul(&self.x, &z2z2);
+        let u2 = f.mul(&other.x, &z1z1);
+        let s1 = f.mul(&f.mul(&self.y, &other.z), &z2z2);
+        let s2 = f.mul(&f.mul(&other.y, &self.z), &z1z1);
+        let h = f.sub(&u2, &u1);
+        let r = f.sub(&s2, &s1);
+        if h.is_zero() {
+            return if r.is_zero() {
+                self.double()
+            } else {
+                Point::INFINITY
+            };
+        }
+        let h2 = f.add(&h, &h);
+        let i = f.square(&h2);
+        let j = f.mul(&h, &i);
+        let r = f.add(&r, &r);
+        let v = f.mul(&u1, &i);
+        let x = f.sub(&f.sub(&f.square(&r), &j), &f.add(&v, &v));
+        let s1j = f.mul(&s1, &j);
+        let y = f.sub(&f.mul(&r, &f.sub(&v, &x)), &f.add(&s1j, &s1j));
+        let z = f.mul(
+            &f.sub(&f.sub(&f.square(&f.add(&self.z, &other.z)), &z1z1), &z2z2),
+            &h,
+        );
+        Point { x: x, y: y, z: z }
+    }
+
+    /// Returns scalar * self.
+    fn multiply(&self, scalar: &U256) -> Point {
+        let mut result = Point::INFINITY;
+        for i in (0..256).rev() {
+            result = result.double();
+            if scalar.bit(i) {
+                result = result.add(self);
+            }
+        }
+        result
+    }
+
+    /// Returns the affine x coordinate in normal form, or `None` for the point
+    /// at infinity.
+    fn affine_x(&self) -> Option<U256> {
+        if self.is_infinity() {
+            return None;
+        }
+        let z_inverse = FIELD.invert(&self.z);
+        let x = FIELD.mul(&self.x, &FIELD.square(&z_inverse));
+        Some(FIELD.from_montgomery(&x))
+    }
+}
+
+/// Decode an uncompressed public key (the big endian x and y coordinates) and
+/// check that it is a point on the curve.
+fn decode_public_key(key: &[u8; 64]) -> Option<Point> {
+    let mut x_bytes = [0; 32];
+    let mut y_bytes = [0; 32];
+    x_bytes.copy_from_slice(&key[0..32]);
+    y_bytes.copy_from_slice(&key[32..64]);
+    let x = U256::from_be_bytes(&x_bytes);
+    let y = U256::from_be_bytes(&y_bytes);
+    if !x.lt(&FIELD.m) || !y.lt(&FIELD.m) {
+        return None;
+    }
+
+    let f = &FIELD;
+    let x = f.to_montgomery(&x);
+    let y = f.to_montgomery(&y);
+    let x3 = f.mul(&f.square(&x), &x);
+    let three_x = f.add(&f.add(&x, &x), &x);
+    let rhs = f.add(&f.sub(&x3, &three_x), &f.to_montgomery(&B));
+    if f.square(&y) != rhs {
+        return None;
+    }
+    Some(Point::from_affine(x, y))
+}
+
+/// Verify an ECDSA signature.
+///
+/// `public_key` holds the big endian x and y coordinates of the public key,
+/// `digest` is the SHA-256 hash of the signed message, and `signature` holds
+/// the big endian r and s values.
+pub(crate) fn verify(public_key: &[u8; 64], digest: &[u8; 32], signature: &[u8; 64]) -> bool {
+    let q = match decode_public_key(public_key) {
+        Some(q) => q,
+        None => return false,
+    };
+
+    let mut r_bytes = [0; 32];
+    let mut s_bytes = [0; 32];
+    r_bytes.copy_from_slice(&signature[0..32]);
+    s_bytes.copy_from_slice(&signature[32..64]);
+    let r = U256::from_be_bytes(&r_bytes);
+    let s = U256::from_be_bytes(&s_bytes);
+    if r.is_zero() || s.is_zero() || !r.lt(&SCALAR.m) || !s.lt(&SCALAR.m) {
+        return false;
+    }
+
+    // The digest is as long as the order, so it is used whole and only needs
+    // to be reduced.
+    let n = &SCALAR;
+    let e = n.to_montgomery(&U256::from_be_bytes(digest));
+    let w = n.invert(&n.to_montgomery(&s));
+    let u1 = n.from_montgomery(&n.mul(&e, &w));
+    let u2 = n.from_montgomery(&n.mul(&n.to_montgomery(&r), &w));
+
+    let g = Point::from_affine(FIELD.to_montgomery(&GX), FIELD.to_montgomery(&GY));
+    let point = g.multiply(&u1).add(&q.multiply(&u2));
+    match point.affine_x() {
+        // x < p < 2n, so reducing it modulo n takes at most one subtraction.
+        Some(x) => {
+            let x = if x.lt(&n.m) { x } else { x.sub(&n.m).0 };
+            x == r
+        }
+        None => false,
+    }
+}
@@ -59,6 +59,8 @@ use crate::common::cells::{MapCell, TakeCell};
 use crate::common::queue::Queue;
 use crate::common::ring_buffer::RingBuffer;
 use crate::hil;
+use crate::kernel_stack;
+use crate::panic_record;
 use crate::process::ProcessType;
 use crate::Chip;
 use crate::ReturnCode;
@@ -120,10 +122,13 @@ pub unsafe fn panic_begin(nop: &dyn Fn()) {
     }
 }
 
-/// Lightweight prints about the current panic and kernel version.
+/// Lightweight prints about the current panic and kernel version. Also saves
+/// the record of the panic that survives a reset (see `panic_record`).
 ///
 /// **NOTE:** The supplied `writer` must be synchronous.
 pub unsafe fn panic_banner<W: Write>(writer: &mut W, panic_info: &PanicInfo) {
+    panic_record::store(panic_info);
+
     if let Some(location) = panic_info.location() {
         let _ = writer.write_fmt(format_args!(
             "\r\n\nKernel panic at {}:{}:\r\n\t\"",
@@ -143,6 +148,14 @@ pub unsafe fn panic_banner<W: Write>(writer: &mut W, panic_info: &PanicInfo) {
         "\tKernel version {}\r\n",
         option_env!("TOCK_KERNEL_VERSION").unwrap_or("unknown")
     ));
+
+    // A kernel stack that was used up entirely may have overflowed.
+    if let Some((used, size)) = kernel_stack::high_water_mark() {
+        let _ = writer.write_fmt(format_args!(
+            "\tKernel stack: {} of {} bytes used\r\n",
+            used, size
+        ));
+    }
 }
 
 /// Print current machine (CPU) state.
@@ -1,9 +1,10 @@
 //! Data structure to store a list of userspace applications.
 
+use core::cell::Cell;
 use core::marker::PhantomData;
-use core::mem::{align_of, size_of};
+use core::mem::{align_of, align_of_val, size_of, size_of_val};
 use core::ops::{Deref, DerefMut};
-use core::ptr::{write, NonNull};
+use core::ptr::{drop_in_place, write, NonNull};
 
 use crate::callback::AppId;
 use crate::process::{Error, ProcessType};
@@ -18,6 +19,7 @@ pub struct Grant<T: Default> {
 
 pub struct AppliedGrant<T> {
     appid: AppId,
+    grant_num: usize,
     grant: NonNull<T>,
     _phantom: PhantomData<T>,
 }
@@ -28,26 +30,45 @@ impl<T> AppliedGrant<T> {
         F: FnOnce(&mut Owned<T>, &mut Allocator) -> R,
         R: Copy,
     {
-        let mut allocator = Allocator { appid: self.appid };
-        let mut root = Owned::new(self.grant, self.appid);
+        let mut allocator = Allocator {
+            appid: self.appid,
+            grant_num: self.grant_num,
+        };
+        let mut root = Owned::root(self.grant, self.appid);
         fun(&mut root, &mut allocator)
     }
 }
 
 pub struct Allocator {
     appid: AppId,
+    /// The grant allocations are accounted to.
+    grant_num: usize,
 }
 
+/// Memory in a process's grant region. Memory from `Allocator::alloc()` is
+/// dropped and freed along with the `Owned`.
 pub struct Owned<T: ?Sized> {
     data: NonNull<T>,
     appid: AppId,
+    /// The grant the memory was allocated for, or `None` for the grant region
+    /// itself, which lives as long as the process.
+    grant_num: Option<usize>,
 }
 
 impl<T: ?Sized> Owned<T> {
-    fn new(data: NonNull<T>, appid: AppId) -> Owned<T> {
+    fn new(data: NonNull<T>, appid: AppId, grant_num: usize) -> Owned<T> {
         Owned {
             data: data,
             appid: appid,
+            grant_num: Some(grant_num),
+        }
+    }
+
+    fn root(data: NonNull<T>, appid: AppId) -> Owned<T> {
+        Owned {
+            data: data,
+            appid: appid,
+            grant_num: None,
         }
     }
 
@@ -58,11 +79,18 @@ impl<T: ?Sized> Owned<T> {
 
 impl<T: ?Sized> Drop for Owned<T> {
     fn drop(&mut self) {
-        unsafe {
-            let data = self.data.as_ptr() as *mut u8;
-            self.appid.kernel.process_map_or((), self.appid, |process| {
-                process.free(data);
-            });
+        if let Some(grant_num) = self.grant_num {
+            let data = self.data;
+            // If the process was restarted in the meantime, its grant memory
+            // was reset and there is nothing to drop.
+            self.appid
+                .kernel
+                .process_map_or((), self.appid, |process| unsafe {
+                    let size = size_of_val(data.as_ref());
+                    let align = align_of_val(data.as_ref());
+                    drop_in_place(data.as_ptr());
+                    process.free(grant_num, data.as_ptr() as *mut u8, size, align);
+                });
         }
     }
 }
@@ -84,7 +112,7 @@ impl Allocator {
     pub fn alloc<T>(&mut self, data: T) -> Result<Owned<T>, Error> {
         unsafe {
             let ptr = self.alloc_unowned(data)?;
-            Ok(Owned::new(ptr, self.appid))
+            Ok(Owned::new(ptr, self.appid, self.grant_num))
         }
     }
 
@@ -94,9 +122,9 @@ impl Allocator {
         self.appid
             .kernel
             .process_map_or(Err(Error::NoSuchApp), self.appid, |process| {
-                process.alloc(size_of::<T>(), align_of::<T>()).map_or(
-                    Err(Error::OutOfMemory),
-                    |buf| {
+                process
+                    .alloc(self.grant_num, size_of::<T>(), align_of::<T>())
+                    .map(|buf| {
                         // Convert untyped `*mut u8` allocation to allocated type
                         let ptr = NonNull::cast::<T>(buf);
 
@@ -104,9 +132,8 @@ impl Allocator {
                         // case `T` implements the `Drop` trait.
                         write(ptr.as_ptr(), data);
 
-                        Ok(ptr)
-                    },
-                )
+                        ptr
+                    })
             })
     }
 }
@@ -156,6 +183,7 @@ impl<T: Default> Grant<T> {
             if let Some(grant_ptr) = process.get_grant_ptr(self.grant_num) {
                 NonNull::new(grant_ptr).map(|grant| AppliedGrant {
                     appid: appid,
+                    grant_num: self.grant_num,
                     grant: grant.cast::<T>(),
                     _phantom: PhantomData,
                 })
@@ -209,7 +237,10 @@ impl<T: Default> Grant<T> {
                 // u8` here. We will eventually convert this to a `*mut T`.
                 if let Some(untyped_grant_ptr) = process.get_grant_ptr(self.grant_num) {
                     // This is the allocator for this process when needed
-                    let mut allocator = Allocator { appid: appid };
+                    let mut allocator = Allocator {
+                        appid: appid,
+                        grant_num: self.grant_num,
+                    };
 
                     // If the grant pointer is NULL then the memory for the
                     // GrantRegion needs to be allocated. Otherwise, we can
@@ -264,7 +295,7 @@ impl<T: Default> Grant<T> {
         self.kernel.process_each(|process| {
             if let Some(grant_ptr) = process.get_grant_ptr(self.grant_num) {
                 NonNull::new(grant_ptr).map(|grant| {
-                    let mut root = Owned::new(grant.cast::<T>(), process.appid());
+                    let mut root = Owned::root(grant.cast::<T>(), process.appid());
                     fun(&mut root);
                 });
             }
@@ -284,8 +315,8 @@ impl<T: Default> Grant<T> {
 pub struct Iter<'a, T: 'a + Default> {
     grant: &'a Grant<T>,
     subiter: core::iter::FilterMap<
-        core::slice::Iter<'a, Option<&'static dyn ProcessType>>,
-        fn(&Option<&'static dyn ProcessType>) -> Option<&'static dyn ProcessType>,
+        core::slice::Iter<'a, Cell<Option<&'static dyn ProcessType>>>,
+        fn(&Cell<Option<&'static dyn ProcessType>>) -> Option<&'static dyn ProcessType>,
     >,
 }
 
@@ -9,6 +9,33 @@ pub trait DigestType: Eq + Copy + Clone + Sized + AsRef<[u8]> + AsMut<[u8]> {}
 
 impl DigestType for [u8; 32] {}
 
+/// A 64 byte digest, for example from SHA-512. Arrays longer than 32 bytes do
+/// not implement the traits `DigestType` requires, so the bytes are wrapped.
+#[derive(Clone, Copy)]
+pub struct Digest64(pub [u8; 64]);
+
+impl PartialEq for Digest64 {
+    fn eq(&self, other: &Digest64) -> bool {
+        self.0[..] == other.0[..]
+    }
+}
+
+impl Eq for Digest64 {}
+
+impl AsRef<[u8]> for Digest64 {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsMut<[u8]> for Digest64 {
+    fn as_mut(&mut self) -> &mut [u8] {
+        &mut self.0
+    }
+}
+
+impl DigestType for Digest64 {}
+
 /// Implement this trait and use `set_client()` in order to receive callbacks.
 pub trait Client<'a, T: DigestType> {
     /// This callback is called when the data has been added to the digest
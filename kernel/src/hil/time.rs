@@ -1,5 +1,7 @@
 //! Hardware agnostic interfaces for counter-like resources.
 
+use core::cmp;
+
 use crate::ReturnCode;
 
 pub trait Time<W = u32> {
@@ -121,6 +123,31 @@ pub trait AlarmClient {
     fn fired(&self);
 }
 
+/// Tells the kernel when the next alarm fires, so that the chip can sleep as
+/// deeply as possible while there is nothing to do and still wake up in time.
+///
+/// Boards pass the hardware alarm underneath their alarm mux to
+/// `Kernel::set_alarm_deadline()`, as the mux always sets the hardware alarm to
+/// the earliest of the virtual alarms.
+pub trait AlarmDeadline {
+    /// Returns how many microseconds are left until the next alarm fires, or
+    /// `None` if no alarm is set.
+    fn us_until_deadline(&self) -> Option<u32>;
+}
+
+impl<'a, A: Alarm<'a>> AlarmDeadline for A {
+    fn us_until_deadline(&self) -> Option<u32> {
+        if !self.is_enabled() {
+            return None;
+        }
+        // The clock may be narrower than 32 bits, and `max_tics()` masks the
+        // bits it has.
+        let tics = self.get_alarm().wrapping_sub(self.now()) & self.max_tics();
+        let us = tics as u64 * 1_000_000 / A::Frequency::frequency() as u64;
+        Some(cmp::min(us, u32::MAX as u64) as u32)
+    }
+}
+
 /// The `Timer` trait models a timer that can notify when a particular interval
 /// has elapsed.
 pub trait Timer<'a, W = u32>: Time<W> {
@@ -15,6 +15,7 @@ use core::cell::Cell;
 use crate::callback::AppId;
 use crate::capabilities::ProcessManagementCapability;
 use crate::common::cells::NumericCellExt;
+use crate::kernel_stack;
 use crate::process;
 use crate::sched::Kernel;
 
@@ -123,6 +124,16 @@ impl KernelInfo {
             .process_map_or(0, app, |process| process.debug_timeslice_expiration_count())
     }
 
+    /// Returns the number of times this app has missed a real-time deadline.
+    pub fn number_app_deadline_misses(
+        &self,
+        app: AppId,
+        _capability: &dyn ProcessManagementCapability,
+    ) -> usize {
+        self.kernel
+            .process_map_or(0, app, |process| process.debug_deadline_miss_count())
+    }
+
     /// Returns a tuple of the (the number of grants in the grant region this
     /// app has allocated, total number of grants that exist in the system).
     pub fn number_app_grant_uses(
@@ -150,6 +161,45 @@ impl KernelInfo {
         (used, number_of_grants)
     }
 
+    /// Returns how many bytes of the app's grant region grant `grant_num` is
+    /// using, including memory its capsule allocated with
+    /// `grant::Allocator`. Grant numbers go up to the total returned by
+    /// `number_app_grant_uses()`.
+    pub fn number_app_grant_bytes(
+        &self,
+        app: AppId,
+        grant_num: usize,
+        _capability: &dyn ProcessManagementCapability,
+    ) -> usize {
+        self.kernel
+            .process_map_or(0, app, |process| process.grant_memory_size(grant_num))
+    }
+
+    /// Returns how many buffers allowed by the app capsules currently hold.
+    pub fn number_app_outstanding_allows(
+        &self,
+        app: AppId,
+        _capability: &dyn ProcessManagementCapability,
+    ) -> usize {
+        self.kernel
+            .process_map_or(0, app, |process| process.outstanding_allows())
+    }
+
+    /// Returns how often the app ran into each of the board's
+    /// `ProcessQuotas` since it was last (re)started. A grant that keeps
+    /// breaching its quota across apps points at the capsule owning that
+    /// grant, while allow and task queue breaches point at the app.
+    pub fn app_quota_breaches(
+        &self,
+        app: AppId,
+        _capability: &dyn ProcessManagementCapability,
+    ) -> process::QuotaBreaches {
+        self.kernel
+            .process_map_or(process::QuotaBreaches::default(), app, |process| {
+                process.quota_breaches()
+            })
+    }
+
     /// Returns the total number of times all processes have exceeded
     /// their timeslices.
     pub fn timeslice_expirations(&self, _capability: &dyn ProcessManagementCapability) -> usize {
@@ -159,4 +209,35 @@ impl KernelInfo {
         });
         count.get()
     }
+
+    /// Returns the total number of times all processes have missed their
+    /// real-time deadlines.
+    pub fn deadline_misses(&self, _capability: &dyn ProcessManagementCapability) -> usize {
+        let count: Cell<usize> = Cell::new(0);
+        self.kernel.process_each(|proc| {
+            count.add(proc.debug_deadline_miss_count());
+        });
+        count.get()
+    }
+
+    /// Returns the total number of times all processes have run into their
+    /// quotas.
+    pub fn quota_breaches(&self, _capability: &dyn ProcessManagementCapability) -> usize {
+        let count: Cell<usize> = Cell::new(0);
+        self.kernel.process_each(|proc| {
+            let breaches = proc.quota_breaches();
+            count.add(breaches.grant_bytes + breaches.task_queue + breaches.allows);
+        });
+        count.get()
+    }
+
+    /// Returns how many bytes of the kernel stack the kernel used at most so
+    /// far, and the size of the stack. Returns `None` if the board did not
+    /// paint the stack with `kernel_stack::paint()` at boot.
+    pub fn kernel_stack_high_water_mark(
+        &self,
+        _capability: &dyn ProcessManagementCapability,
+    ) -> Option<(usize, usize)> {
+        kernel_stack::high_water_mark()
+    }
 }
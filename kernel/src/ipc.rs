@@ -2,6 +2,18 @@
 //!
 //! This is a special syscall driver that allows userspace applications to
 //! share memory.
+//!
+//! Services can list the clients allowed to use them in their TBF header.
+//! Other processes cannot discover a service that lists its clients, notify it
+//! or send messages to it.
+//!
+//! IPC also provides a `Mailbox` driver for message passing. Instead of
+//! sharing a buffer, the kernel copies each message from the sender's buffer
+//! into a bounded queue in the receiver's grant region, so that neither
+//! process can modify a message the other is still using.
+
+use core::cmp;
+use core::mem;
 
 use crate::callback::{AppId, Callback};
 use crate::capabilities::MemoryAllocationCapability;
@@ -15,6 +27,15 @@ use crate::sched::Kernel;
 /// Syscall number
 pub const DRIVER_NUM: usize = 0x10000;
 
+/// Syscall number of the message-passing mailbox.
+pub const MAILBOX_DRIVER_NUM: usize = 0x10001;
+
+/// Maximum length of a message sent to a mailbox.
+pub const MAX_MESSAGE_LEN: usize = 32;
+
+/// Number of messages a mailbox can hold.
+pub const MAILBOX_DEPTH: usize = 4;
+
 /// Enum to mark which type of callback is scheduled for the IPC mechanism.
 #[derive(Copy, Clone, Debug)]
 pub enum IPCCallbackType {
@@ -39,19 +60,81 @@ struct IPCData {
     callback: Option<Callback>,
 }
 
+/// Returns whether the process `client` may use the IPC service of the process
+/// `service`.
+fn permits_client(kernel: &Kernel, service: AppId, client: AppId) -> bool {
+    kernel.process_map_or(false, service, |service| {
+        kernel.process_map_or(false, client, |client| service.permits_ipc_client(client))
+    })
+}
+
+/// A message queued in a mailbox.
+#[derive(Default)]
+struct Message {
+    /// The identifier of the process that sent the message.
+    sender: usize,
+    len: usize,
+    data: [u8; MAX_MESSAGE_LEN],
+}
+
+/// State that is stored in each process's grant region to support message
+/// passing.
+#[derive(Default)]
+struct MailboxData {
+    /// The buffer messages are sent from.
+    send_buffer: Option<AppSlice<Shared, u8>>,
+    /// The buffer messages are received into.
+    receive_buffer: Option<AppSlice<Shared, u8>>,
+    /// Called when a message is queued in this mailbox.
+    message_callback: Option<Callback>,
+    /// Called when a mailbox that refused a message from this process because
+    /// it was full has space again.
+    space_callback: Option<Callback>,
+    /// The queued messages, starting at `head`.
+    messages: [Message; MAILBOX_DEPTH],
+    head: usize,
+    queued: usize,
+    /// Bitmask of the processes that were refused because this mailbox was
+    /// full, see `waiting_bit()`.
+    waiting: usize,
+}
+
+/// The bit that stands for the process with index `index` in
+/// `MailboxData::waiting`. Processes with indices that do not fit in a `usize`
+/// share the highest bit, so they are all told about space if any of them was
+/// refused.
+fn waiting_bit(index: usize) -> usize {
+    let bits = mem::size_of::<usize>() * 8;
+    1 << cmp::min(index, bits - 1)
+}
+
 /// The IPC mechanism struct.
 pub struct IPC {
     /// The grant regions for each process that holds the per-process IPC data.
     data: Grant<IPCData>,
+    mailbox: Mailbox,
+}
+
+/// The message-passing driver of IPC.
+pub struct Mailbox {
+    data: Grant<MailboxData>,
 }
 
 impl IPC {
     pub fn new(kernel: &'static Kernel, capability: &dyn MemoryAllocationCapability) -> IPC {
         IPC {
             data: kernel.create_grant(capability),
+            mailbox: Mailbox {
+                data: kernel.create_grant(capability),
+            },
         }
     }
 
+    /// The driver for `MAILBOX_DRIVER_NUM`.
+    pub fn mailbox(&self) -> &Mailbox {
+        &self.mailbox
+    }
+
     /// Schedule an IPC callback for a process. This is called by the main
     /// scheduler loop if an IPC task was queued for the process.
     pub(crate) unsafe fn schedule_callback(
@@ -172,7 +255,8 @@ impl Driver for IPC {
     /// In either case, the target_id is the same number as provided in a notify
     /// callback or as returned by allow.
     ///
-    /// Returns EINVAL if the other process doesn't exist.
+    /// Returns EINVAL if the other process doesn't exist, or if it is a service
+    /// that does not permit this process as a client.
     fn command(
         &self,
         target_id: usize,
@@ -192,6 +276,11 @@ impl Driver for IPC {
             .kernel
             .lookup_app_by_identifier(app_identifier)
             .map_or(ReturnCode::EINVAL, |otherapp| {
+                if let IPCCallbackType::Service = cb_type {
+                    if !permits_client(self.data.kernel, otherapp, appid) {
+                        return ReturnCode::EINVAL;
+                    }
+                }
                 self.data
                     .kernel
                     .process_map_or(ReturnCode::EINVAL, otherapp, |target| {
@@ -211,7 +300,8 @@ impl Driver for IPC {
     /// call. The contents of the slice should be the string name of the IPC
     /// service. If this mechanism can find that service, allow will return
     /// an ID that can be used to notify that service. Otherwise an error will
-    /// be returned.
+    /// be returned. Services that do not permit this process as a client are
+    /// not found.
     ///
     /// If allow is called with target_id >= 1, it is a share command where the
     /// application is explicitly sharing a slice with an IPC service (as
@@ -231,6 +321,7 @@ impl Driver for IPC {
                         // are slices equal?
                         if s.len() == slice_data.len()
                             && s.iter().zip(slice_data.iter()).all(|(c1, c2)| c1 == c2)
+                            && permits_client(self.data.kernel, p.appid(), appid)
                         {
                             ReturnCode::SuccessWithValue {
                                 value: (p.appid().id() as usize) + 1,
@@ -272,3 +363,237 @@ impl Driver for IPC {
             .unwrap_or(ReturnCode::EBUSY)
     }
 }
+
+impl Mailbox {
+    /// Look up a process by the identifier IPC syscalls use, which is the
+    /// process identifier plus one.
+    fn lookup_app(&self, target_id: usize) -> Option<AppId> {
+        target_id
+            .checked_sub(1)
+            .and_then(|identifier| self.data.kernel.lookup_app_by_identifier(identifier))
+    }
+
+    /// Copy `len` bytes of the send buffer of `appid` into the mailbox of
+    /// `target_id`.
+    fn send(&self, appid: AppId, target_id: usize, len: usize) -> ReturnCode {
+        let target = match self.lookup_app(target_id) {
+            Some(target) if permits_client(self.data.kernel, target, appid) => target,
+            _ => return ReturnCode::EINVAL,
+        };
+        if len > MAX_MESSAGE_LEN {
+            return ReturnCode::ESIZE;
+        }
+
+        // Copy the message out of the sender's buffer before entering the
+        // receiver's grant, so that processes can send messages to themselves.
+        let mut message = Message {
+            sender: appid.id(),
+            len: len,
+            data: [0; MAX_MESSAGE_LEN],
+        };
+        let result = self
+            .data
+            .enter(appid, |data, _| match data.send_buffer {
+                Some(ref buffer) if buffer.len() >= len => {
+                    message.data[..len].copy_from_slice(&buffer.as_ref()[..len]);
+                    ReturnCode::SUCCESS
+                }
+                Some(_) => ReturnCode::ESIZE,
+                None => ReturnCode::EINVAL,
+            })
+            .unwrap_or_else(|err| err.into());
+        if result != ReturnCode::SUCCESS {
+            return result;
+        }
+
+        self.data
+            .enter(target, |data, _| {
+                if data.queued == MAILBOX_DEPTH {
+                    // Remember the sender, so that it can be told when there
+                    // is space again.
+                    if let Some(index) = appid.index() {
+                        data.waiting |= waiting_bit(index);
+                    }
+                    return ReturnCode::ENOMEM;
+                }
+                let tail = (data.head + data.queued) % MAILBOX_DEPTH;
+                data.messages[tail] = message;
+                data.queued += 1;
+                let queued = data.queued;
+                data.message_callback
+                    .map(|mut callback| callback.schedule(appid.id() + 1, len, queued));
+                ReturnCode::SuccessWithValue {
+                    value: MAILBOX_DEPTH - queued,
+                }
+            })
+            .unwrap_or_else(|err| err.into())
+    }
+
+    /// Copy the oldest message in the mailbox of `appid` into its receive
+    /// buffer and remove it from the mailbox.
+    fn receive(&self, appid: AppId) -> ReturnCode {
+        let (result, free, waiting) = self
+            .data
+            .enter(appid, |data, _| {
+                let data: &mut MailboxData = &mut **data;
+                if data.queued == 0 {
+                    return (ReturnCode::FAIL, 0, 0);
+                }
+                let message = &data.messages[data.head];
+                match data.receive_buffer {
+                    Some(ref mut buffer) if buffer.len() >= message.len => {
+                        buffer.as_mut()[..message.len]
+                            .copy_from_slice(&message.data[..message.len]);
+                    }
+                    Some(_) => return (ReturnCode::ESIZE, 0, 0),
+                    None => return (ReturnCode::EINVAL, 0, 0),
+                }
+                let len = message.len;
+                data.head = (data.head + 1) % MAILBOX_DEPTH;
+                data.queued -= 1;
+                (
+                    ReturnCode::SuccessWithValue { value: len },
+                    MAILBOX_DEPTH - data.queued,
+                    mem::replace(&mut data.waiting, 0),
+                )
+            })
+            .unwrap_or_else(|err| (err.into(), 0, 0));
+
+        // Tell the processes that found the mailbox full that it has space.
+        if waiting != 0 {
+            self.data.kernel.process_each(|process| {
+                let sender = process.appid();
+                let was_waiting = sender
+                    .index()
+                    .map_or(false, |index| waiting & waiting_bit(index) != 0);
+                if was_waiting {
+                    self.data
+                        .enter(sender, |data, _| {
+                            data.space_callback
+                                .map(|mut callback| callback.schedule(appid.id() + 1, free, 0));
+                        })
+                        .unwrap_or(());
+                }
+            });
+        }
+        result
+    }
+}
+
+impl Driver for Mailbox {
+    /// Setup the buffers messages are sent from and received into.
+    ///
+    /// ### `allow_num`
+    ///
+    /// - `0`: The buffer messages are sent from.
+    /// - `1`: The buffer messages are received into.
+    fn allow(
+        &self,
+        appid: AppId,
+        allow_num: usize,
+        slice: Option<AppSlice<Shared, u8>>,
+    ) -> ReturnCode {
+        self.data
+            .enter(appid, |data, _| match allow_num {
+                0 => {
+                    data.send_buffer = slice;
+                    ReturnCode::SUCCESS
+                }
+                1 => {
+                    data.receive_buffer = slice;
+                    ReturnCode::SUCCESS
+                }
+                _ => ReturnCode::ENOSUPPORT,
+            })
+            .unwrap_or_else(|err| err.into())
+    }
+
+    /// Setup callbacks for mailbox events.
+    ///
+    /// ### `subscribe_num`
+    ///
+    /// - `0`: A message was queued in this process's mailbox. The callback is
+    ///   passed the identifier of the sender, the length of the message and
+    ///   the number of queued messages.
+    /// - `1`: A mailbox that was full when this process sent a message has
+    ///   space again. The callback is passed the identifier of the mailbox
+    ///   owner and the number of free slots.
+    fn subscribe(
+        &self,
+        subscribe_num: usize,
+        callback: Option<Callback>,
+        appid: AppId,
+    ) -> ReturnCode {
+        self.data
+            .enter(appid, |data, _| match subscribe_num {
+                0 => {
+                    data.message_callback = callback;
+                    ReturnCode::SUCCESS
+                }
+                1 => {
+                    data.space_callback = callback;
+                    ReturnCode::SUCCESS
+                }
+                _ => ReturnCode::ENOSUPPORT,
+            })
+            .unwrap_or_else(|err| err.into())
+    }
+
+    /// Send and receive messages. Processes are identified by the same
+    /// identifiers as the IPC driver uses.
+    ///
+    /// ### `command_num`
+    ///
+    /// - `0`: Driver check.
+    /// - `1`: Send the first `data2` bytes of the send buffer to the mailbox
+    ///   of process `data`. Returns the number of free slots left in that
+    ///   mailbox, or ENOMEM if the mailbox is full.
+    /// - `2`: Receive the oldest message into the receive buffer. Returns the
+    ///   length of the message, or FAIL if the mailbox is empty.
+    /// - `3`: Returns the identifier of the sender of the oldest message, or
+    ///   FAIL if the mailbox is empty.
+    /// - `4`: Returns the number of free slots in the mailbox of process
+    ///   `data`.
+    fn command(&self, command_num: usize, data: usize, data2: usize, appid: AppId) -> ReturnCode {
+        match command_num {
+            0 => ReturnCode::SUCCESS,
+            1 => self.send(appid, data, data2),
+            2 => self.receive(appid),
+            3 => self
+                .data
+                .enter(appid, |data, _| {
+                    if data.queued == 0 {
+                        ReturnCode::FAIL
+                    } else {
+                        ReturnCode::SuccessWithValue {
+                            value: data.messages[data.head].sender + 1,
+                        }
+                    }
+                })
+                .unwrap_or_else(|err| err.into()),
+            4 => self.lookup_app(data).map_or(ReturnCode::EINVAL, |target| {
+                self.data
+                    .enter(target, |data, _| ReturnCode::SuccessWithValue {
+                        value: MAILBOX_DEPTH - data.queued,
+                    })
+                    .unwrap_or_else(|err| err.into())
+            }),
+            _ => ReturnCode::ENOSUPPORT,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_waiting_bit() {
+        let bits = mem::size_of::<usize>() * 8;
+        assert_eq!(waiting_bit(0), 1);
+        assert_eq!(waiting_bit(3), 8);
+        assert_eq!(waiting_bit(bits - 1), 1 << (bits - 1));
+        assert_eq!(waiting_bit(bits), 1 << (bits - 1));
+        assert_eq!(waiting_bit(usize::MAX), 1 << (bits - 1));
+    }
+}
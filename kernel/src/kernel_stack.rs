@@ -0,0 +1,96 @@
+//! Measuring how much of the kernel stack the kernel uses.
+//!
+//! Boards reserve the kernel stack with a guess like
+//! `static mut STACK_MEMORY: [u8; 0x1000]`. To find out how close the kernel
+//! comes to overflowing it, the board paints the stack with a known pattern at
+//! the start of `reset_handler()`, right after the chip's `init()` cleared
+//! `.bss`. The kernel never writes that pattern, so the lowest word of the
+//! stack that no longer holds it is the deepest the stack has grown so far,
+//! its high-water mark. `KernelInfo`, the process console and the panic output
+//! report it.
+//!
+//! Usage
+//! -----
+//!
+//! ```ignore
+//! #[no_mangle]
+//! pub unsafe fn reset_handler() {
+//!     sam4l::init();
+//!
+//!     kernel::kernel_stack::paint(STACK_MEMORY.as_mut_ptr(), STACK_MEMORY.len());
+//!     ...
+//! }
+//! ```
+
+use core::cmp;
+use core::ptr;
+
+/// The pattern painted on the unused kernel stack.
+const PAINT: u32 = 0xA5A5_A5A5;
+
+/// How many bytes right below the stack frame of `paint()` are left alone,
+/// in case the frame extends below the variables it holds.
+const MARGIN: usize = 64;
+
+/// The bottom and size of the kernel stack, once it was painted.
+static mut STACK: Option<(usize, usize)> = None;
+
+/// Paint the kernel stack, which starts at `bottom` and is `size` bytes large,
+/// from its bottom up to just below the stack frame of this function. The
+/// part of the stack above is in use already.
+///
+/// This must be called once, early in `reset_handler()` so that the stack is
+/// still shallow, but after `.bss` was cleared, which would forget the stack.
+#[inline(never)]
+pub unsafe fn paint(bottom: *mut u8, size: usize) {
+    let frame = 0u8;
+    let frame = &frame as *const u8 as usize;
+    let top = cmp::min(bottom as usize + size, frame.saturating_sub(MARGIN));
+    paint_range(bottom as usize, top);
+    STACK = Some((bottom as usize, size));
+}
+
+/// Returns how many bytes of the kernel stack the kernel used at most so far,
+/// and the size of the stack, or `None` if the board did not paint the stack.
+pub fn high_water_mark() -> Option<(usize, usize)> {
+    unsafe { STACK }.map(|(bottom, size)| (size - unused_bytes(bottom, size), size))
+}
+
+/// Write the pattern to every whole word in `[start, end)`.
+unsafe fn paint_range(start: usize, end: usize) {
+    let mut word = (start + 3) & !3;
+    while word + 4 <= end {
+        ptr::write_volatile(word as *mut u32, PAINT);
+        word += 4;
+    }
+}
+
+/// Returns how many bytes at the bottom of the stack still hold the pattern.
+fn unused_bytes(bottom: usize, size: usize) -> usize {
+    let start = (bottom + 3) & !3;
+    let mut word = start;
+    while word + 4 <= bottom + size && unsafe { ptr::read_volatile(word as *const u32) } == PAINT {
+        word += 4;
+    }
+    word - bottom
+}
+
+#[cfg(test)]
+mod test {
+    use super::{paint_range, unused_bytes};
+
+    #[test]
+    fn test_unused_bytes() {
+        let mut stack = [0u32; 64];
+        let bottom = stack.as_mut_ptr() as usize;
+        let size = stack.len() * 4;
+        unsafe { paint_range(bottom, bottom + size) };
+        assert_eq!(unused_bytes(bottom, size), size);
+
+        // The stack grows down, so the kernel overwrites the top first.
+        stack[40] = 0;
+        assert_eq!(unused_bytes(bottom, size), 40 * 4);
+        stack[3] = 0;
+        assert_eq!(unused_bytes(bottom, size), 3 * 4);
+    }
+}
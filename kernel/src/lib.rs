@@ -19,20 +19,28 @@
 pub mod capabilities;
 pub mod common;
 pub mod component;
+pub mod crash_dump;
+pub mod crypto;
 pub mod debug;
 pub mod hil;
 pub mod introspection;
 pub mod ipc;
+pub mod kernel_stack;
+pub mod panic_record;
+pub mod power_manager;
 pub mod syscall;
+pub mod syscall_trace;
 
 mod callback;
 mod config;
+mod credentials;
 mod driver;
 mod grant;
 mod mem;
 mod memop;
 mod platform;
 mod process;
+mod process_loader;
 mod returncode;
 mod sched;
 mod tbfheader;
@@ -42,10 +50,19 @@ pub use crate::driver::Driver;
 pub use crate::grant::Grant;
 pub use crate::mem::{AppPtr, AppSlice, Private, Shared};
 pub use crate::platform::systick::SysTick;
-pub use crate::platform::{mpu, Chip, Platform};
+pub use crate::platform::{check_syscall_permissions, mpu, Chip, Platform};
 pub use crate::platform::{ClockInterface, NoClockControl, NO_CLOCK_CONTROL};
 pub use crate::returncode::ReturnCode;
-pub use crate::sched::Kernel;
+pub use crate::sched::{Kernel, Scheduler, SchedulingDecision, StoppedExecutingReason};
+
+/// Scheduler implementations that boards can choose from.
+pub mod schedulers {
+    pub use crate::sched::cooperative::CooperativeSched;
+    pub use crate::sched::edf::{EDFProcessState, EDFSched};
+    pub use crate::sched::mlfq::{MLFQProcessState, MLFQSched};
+    pub use crate::sched::priority::PrioritySched;
+    pub use crate::sched::round_robin::RoundRobinSched;
+}
 
 // Export only select items from the process module. To remove the name conflict
 // this cannot be called `process`, so we use a shortened version. These
@@ -53,9 +70,13 @@ pub use crate::sched::Kernel;
 // processes.
 /// Publicly available process-related objects.
 pub mod procs {
+    pub use crate::credentials::{AppSigningKey, CredentialsPolicy};
     pub use crate::process::{
-        load_processes, AlwaysRestart, Error, FaultResponse, FunctionCall, Process,
-        ProcessLoadError, ProcessRestartPolicy, ProcessType, ThresholdRestart,
-        ThresholdRestartThenPanic,
+        load_processes, AlwaysRestart, BackoffRestart, BackoffRestartState, CappedSchedulingPolicy,
+        Error, FaultReason, FaultResponse, FunctionCall, FunctionCallSource, Process,
+        ProcessLoadError, ProcessQuotas, ProcessRestartPolicy, ProcessType, QuotaBreaches,
+        RealTimeParameters, RestartFallback, SchedulingParameters, SchedulingPolicy,
+        ThresholdRestart, ThresholdRestartThenPanic,
     };
+    pub use crate::process_loader::{DynamicProcessLoader, LoadedProcessMemory, ProcessLoader};
 }
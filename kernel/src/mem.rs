@@ -52,9 +52,7 @@ impl<L, T> Drop for AppPtr<L, T> {
     fn drop(&mut self) {
         self.process
             .kernel
-            .process_map_or((), self.process, |process| unsafe {
-                process.free(self.ptr.as_ptr() as *mut u8)
-            })
+            .process_map_or((), self.process, |process| process.release_allow())
     }
 }
 
@@ -1,6 +1,6 @@
 //! Implementation of the MEMOP family of syscalls.
 
-use crate::process::ProcessType;
+use crate::process::{ProcessType, RealTimeParameters};
 use crate::returncode::ReturnCode;
 
 /// Handle the `memop` syscall.
@@ -36,6 +36,20 @@ use crate::returncode::ReturnCode;
 ///   where the app has put the start of its heap. This is not strictly
 ///   necessary for correct operation, but allows for better debugging if the
 ///   app crashes.
+/// - `12`: Register the app for real-time scheduling with a period of r1
+///   microseconds. The deadline is set to the end of the period. Passing 0
+///   unregisters the app.
+/// - `13`: Set the real-time deadline of the app to r1 microseconds after each
+///   activation. Returns EINVAL if the app has not registered a period or if
+///   the deadline is 0 or longer than the period.
+/// - `14`: Exit. Terminate the app with completion code r1. The app does not
+///   run again, and its grants are freed. This call does not return.
+/// - `15`: Get the start address of the app's retained RAM region, which
+///   keeps its contents when the kernel restarts the app. Returns FAIL if
+///   the app has no retained region.
+/// - `16`: Get the address pointing to the first address after the end of the
+///   app's retained RAM region. Returns FAIL if the app has no retained
+///   region.
 pub(crate) fn memop(process: &dyn ProcessType, op_type: usize, r1: usize) -> ReturnCode {
     match op_type {
         // Op Type 0: BRK
@@ -108,6 +122,58 @@ pub(crate) fn memop(process: &dyn ProcessType, op_type: usize, r1: usize) -> Ret
             ReturnCode::SUCCESS
         }
 
+        // Op Type 12: Register a real-time period.
+        12 => {
+            let parameters = match r1 {
+                0 => None,
+                period => Some(RealTimeParameters {
+                    period_us: period as u32,
+                    deadline_us: period as u32,
+                }),
+            };
+            process.set_real_time_parameters(parameters);
+            ReturnCode::SUCCESS
+        }
+
+        // Op Type 13: Set the real-time deadline.
+        13 => {
+            match process.get_real_time_parameters() {
+                Some(parameters) if r1 > 0 && r1 <= parameters.period_us as usize => {
+                    process.set_real_time_parameters(Some(RealTimeParameters {
+                        period_us: parameters.period_us,
+                        deadline_us: r1 as u32,
+                    }));
+                    ReturnCode::SUCCESS
+                }
+                _ => ReturnCode::EINVAL,
+            }
+        }
+
+        // Op Type 14: Exit.
+        14 => {
+            process.terminate(Some(r1 as u32));
+            ReturnCode::SUCCESS
+        }
+
+        // Op Type 15: Retained RAM start
+        15 => {
+            if process.retained_memory_size() == 0 {
+                ReturnCode::FAIL
+            } else {
+                ReturnCode::SuccessWithValue { value: process.mem_start() as usize }
+            }
+        }
+
+        // Op Type 16: Retained RAM end
+        16 => {
+            let size = process.retained_memory_size();
+            if size == 0 {
+                ReturnCode::FAIL
+            } else {
+                ReturnCode::SuccessWithValue { value: process.mem_start() as usize + size }
+            }
+        }
+
         _ => ReturnCode::ENOSUPPORT,
     }
 }
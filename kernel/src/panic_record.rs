@@ -0,0 +1,259 @@
+//! A record of the last kernel panic that survives a reset.
+//!
+//! When the kernel panics, `debug::panic_banner()` saves a compact record of
+//! the panic: its message and location, the PC and LR of a kernel fault, the
+//! process that was running and the uptime. The record is kept in the
+//! `.noinit` section of RAM, which the startup code neither loads nor zeroes,
+//! so it survives a watchdog or software reset (but not a loss of power). On
+//! the next boot, `Kernel::new()` moves the record out of `.noinit`, and it is
+//! available from `last_panic()` until the next reset. The panic record
+//! capsule gives processes access to it and the process console prints it
+//! with its `panic` command.
+//!
+//! The uptime comes from a clock the board assigns. Architectures that
+//! handle kernel faults by panicking report the faulting PC and LR with
+//! `set_fault_registers()`; for other panics both are zero and the location in
+//! the message identifies the panic.
+//!
+//! Usage
+//! -----
+//!
+//! ```ignore
+//! kernel::panic_record::set_uptime_clock(&sam4l::ast::AST);
+//! ```
+
+use core::fmt::{write, Arguments, Write};
+use core::mem::MaybeUninit;
+use core::panic::{Location, PanicInfo};
+use core::ptr;
+use core::str;
+
+use crate::hil::time::{self, Frequency};
+
+/// Marks a valid record, "PNCR".
+const MAGIC: u32 = 0x5243_4e50;
+
+/// How many bytes of the panic message are kept.
+pub const MESSAGE_LENGTH: usize = 96;
+
+/// How many bytes of the process name are kept.
+pub const PROCESS_NAME_LENGTH: usize = 32;
+
+/// The last kernel panic.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct PanicRecord {
+    magic: u32,
+    checksum: u32,
+    pc: u32,
+    lr: u32,
+    uptime_ms: u32,
+    message_length: u32,
+    process_name_length: u32,
+    message: [u8; MESSAGE_LENGTH],
+    process_name: [u8; PROCESS_NAME_LENGTH],
+}
+
+impl PanicRecord {
+    /// The PC of the kernel fault that caused the panic, or 0.
+    pub fn pc(&self) -> u32 {
+        self.pc
+    }
+
+    /// The LR of the kernel fault that caused the panic, or 0.
+    pub fn lr(&self) -> u32 {
+        self.lr
+    }
+
+    /// Milliseconds from when the uptime clock started until the panic, or 0
+    /// if the board did not assign one. Wraps around with the clock.
+    pub fn uptime_ms(&self) -> u32 {
+        self.uptime_ms
+    }
+
+    /// The location and message of the panic, truncated to
+    /// `MESSAGE_LENGTH` bytes.
+    pub fn message(&self) -> &str {
+        utf8_prefix(&self.message[..self.message_length as usize])
+    }
+
+    /// The name of the process that was running, if any.
+    pub fn process_name(&self) -> Option<&str> {
+        if self.process_name_length == 0 {
+            None
+        } else {
+            Some(utf8_prefix(
+                &self.process_name[..self.process_name_length as usize],
+            ))
+        }
+    }
+
+    fn compute_checksum(&self) -> u32 {
+        let mut copy = *self;
+        copy.checksum = 0;
+        let bytes = unsafe {
+            core::slice::from_raw_parts(
+                &copy as *const PanicRecord as *const u8,
+                core::mem::size_of::<PanicRecord>(),
+            )
+        };
+        // FNV-1a
+        bytes.iter().fold(0x811c_9dc5, |hash: u32, byte| {
+            (hash ^ *byte as u32).wrapping_mul(0x0100_0193)
+        })
+    }
+
+    fn is_valid(&self) -> bool {
+        self.magic == MAGIC
+            && self.message_length as usize <= MESSAGE_LENGTH
+            && self.process_name_length as usize <= PROCESS_NAME_LENGTH
+            && self.checksum == self.compute_checksum()
+    }
+}
+
+/// The longest prefix of `bytes` that is valid UTF-8, as truncating may have
+/// split a character.
+fn utf8_prefix(bytes: &[u8]) -> &str {
+    match str::from_utf8(bytes) {
+        Ok(s) => s,
+        Err(e) => str::from_utf8(&bytes[..e.valid_up_to()]).unwrap_or(""),
+    }
+}
+
+/// Writes into a fixed buffer, dropping what does not fit.
+struct TruncatingWriter<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl Write for TruncatingWriter<'_> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let count = core::cmp::min(s.len(), self.buf.len() - self.len);
+        self.buf[self.len..self.len + count].copy_from_slice(&s.as_bytes()[..count]);
+        self.len += count;
+        Ok(())
+    }
+}
+
+/// Gives the uptime of the board in milliseconds.
+pub trait Uptime {
+    fn uptime_ms(&self) -> u32;
+}
+
+impl<T: time::Time> Uptime for T {
+    fn uptime_ms(&self) -> u32 {
+        let frequency = T::Frequency::frequency() as u64;
+        (self.now() as u64 * 1000 / frequency) as u32
+    }
+}
+
+#[cfg_attr(target_os = "none", link_section = ".noinit")]
+static mut NOINIT_RECORD: MaybeUninit<PanicRecord> = MaybeUninit::uninit();
+
+static mut LAST_PANIC: Option<PanicRecord> = None;
+
+static mut UPTIME_CLOCK: Option<&'static dyn Uptime> = None;
+
+static mut CURRENT_PROCESS: Option<&'static str> = None;
+
+static mut FAULT_REGISTERS: (u32, u32) = (0, 0);
+
+/// Set the clock the uptime in panic records is measured with.
+pub unsafe fn set_uptime_clock(clock: &'static dyn Uptime) {
+    UPTIME_CLOCK = Some(clock);
+}
+
+/// Record the PC and LR of a kernel fault, before panicking because of it.
+pub unsafe fn set_fault_registers(pc: u32, lr: u32) {
+    FAULT_REGISTERS = (pc, lr);
+}
+
+/// Record which process is running, if any.
+pub(crate) fn set_current_process(name: Option<&'static str>) {
+    unsafe {
+        CURRENT_PROCESS = name;
+    }
+}
+
+/// The panic before the last reset, if it left a record.
+pub fn last_panic() -> Option<&'static PanicRecord> {
+    unsafe { LAST_PANIC.as_ref() }
+}
+
+/// Move the record of the panic before this boot out of `.noinit`, so that a
+/// later reset without a panic does not report it again.
+pub(crate) unsafe fn load() {
+    let noinit = NOINIT_RECORD.as_mut_ptr();
+    let record = ptr::read_volatile(noinit);
+    if record.is_valid() {
+        LAST_PANIC = Some(record);
+    }
+    ptr::write_volatile(&mut (*noinit).magic, 0);
+}
+
+/// Save a record of the panic to `.noinit`.
+pub(crate) unsafe fn store(panic_info: &PanicInfo) {
+    store_record(panic_info.location(), panic_info.message());
+}
+
+unsafe fn store_record(location: Option<&Location>, args: Option<&Arguments>) {
+    let mut record = PanicRecord {
+        magic: MAGIC,
+        checksum: 0,
+        pc: FAULT_REGISTERS.0,
+        lr: FAULT_REGISTERS.1,
+        uptime_ms: UPTIME_CLOCK.map_or(0, |clock| clock.uptime_ms()),
+        message_length: 0,
+        process_name_length: 0,
+        message: [0; MESSAGE_LENGTH],
+        process_name: [0; PROCESS_NAME_LENGTH],
+    };
+
+    let mut message = TruncatingWriter {
+        buf: &mut record.message,
+        len: 0,
+    };
+    if let Some(location) = location {
+        let _ = message.write_fmt(format_args!("{}:{}: ", location.file(), location.line()));
+    }
+    if let Some(args) = args {
+        let _ = write(&mut message, *args);
+    }
+    record.message_length = message.len as u32;
+
+    if let Some(name) = CURRENT_PROCESS {
+        let length = core::cmp::min(name.len(), PROCESS_NAME_LENGTH);
+        record.process_name[..length].copy_from_slice(&name.as_bytes()[..length]);
+        record.process_name_length = length as u32;
+    }
+
+    record.checksum = record.compute_checksum();
+    ptr::write_volatile(NOINIT_RECORD.as_mut_ptr(), record);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_record_survives_reset() {
+        unsafe {
+            set_current_process(Some("blink"));
+            set_fault_registers(0x1234, 0x5678);
+            store_record(None, Some(&format_args!("oops {}", 42)));
+            load();
+        }
+        let record = last_panic().unwrap();
+        assert_eq!(record.pc(), 0x1234);
+        assert_eq!(record.lr(), 0x5678);
+        assert_eq!(record.process_name(), Some("blink"));
+        assert_eq!(record.message(), "oops 42");
+
+        // A second reset without a panic finds no record.
+        unsafe {
+            LAST_PANIC = None;
+            load();
+        }
+        assert!(last_panic().is_none());
+    }
+}
@@ -1,5 +1,6 @@
 //! Interface for chips and boards.
 
+use crate::debug;
 use crate::driver::Driver;
 use crate::process;
 use crate::returncode;
@@ -50,17 +51,72 @@ pub trait Platform {
     /// calls.  If the system call is allowed for the provided process then
     /// return Ok(()).  Otherwise, return Err with a ReturnCode that will be
     /// returned to the calling application.  The default implementation allows
-    /// all system calls. This API should be considered unstable, and is likely
-    /// to change in the future.
+    /// the system calls the permissions in the app's TBF header allow, see
+    /// `check_syscall_permissions()`. Implementations should call that
+    /// function as well, unless they deliberately ignore the permissions. This
+    /// API should be considered unstable, and is likely to change in the
+    /// future.
     fn filter_syscall(
         &self,
-        _process: &dyn process::ProcessType,
-        _syscall: &syscall::Syscall,
+        process: &dyn process::ProcessType,
+        syscall: &syscall::Syscall,
     ) -> Result<(), returncode::ReturnCode> {
-        Ok(())
+        check_syscall_permissions(process, syscall)
     }
 }
 
+/// Check a system call against the permissions in the TBF header of the app,
+/// and log the system calls that are denied.
+///
+/// Apps that list their permissions may only make subscribe, command and allow
+/// calls to the drivers on the list, and only call the listed commands. Other
+/// drivers appear not to exist and return ENODEVICE, and other commands return
+/// ENOSUPPORT. Apps without permissions may make all system calls, unless the
+/// board calls `Kernel::require_syscall_permissions()`.
+///
+/// Apps choose their own permissions, so the permissions only limit what an
+/// app can do if the board requires apps to be signed with
+/// `CredentialsPolicy::RequireSignature`, and also requires permissions so
+/// that apps cannot leave them out.
+pub fn check_syscall_permissions(
+    process: &dyn process::ProcessType,
+    syscall: &syscall::Syscall,
+) -> Result<(), returncode::ReturnCode> {
+    let (driver_num, command_num) = match *syscall {
+        syscall::Syscall::SUBSCRIBE { driver_number, .. }
+        | syscall::Syscall::ALLOW { driver_number, .. } => (driver_number, None),
+        syscall::Syscall::COMMAND {
+            driver_number,
+            subdriver_number,
+            ..
+        } => (driver_number, Some(subdriver_number)),
+        syscall::Syscall::YIELD | syscall::Syscall::MEMOP { .. } => return Ok(()),
+    };
+
+    if !process.permits_syscall(driver_num, None) {
+        debug!(
+            "[{:?}] {} has no permission to use driver {:#x}",
+            process.appid(),
+            process.get_process_name(),
+            driver_num
+        );
+        return Err(returncode::ReturnCode::ENODEVICE);
+    }
+    if let Some(command_num) = command_num {
+        if !process.permits_syscall(driver_num, Some(command_num)) {
+            debug!(
+                "[{:?}] {} has no permission to call command {} of driver {:#x}",
+                process.appid(),
+                process.get_process_name(),
+                command_num,
+                driver_num
+            );
+            return Err(returncode::ReturnCode::ENOSUPPORT);
+        }
+    }
+    Ok(())
+}
+
 /// Interface for individual MCUs.
 ///
 /// The trait defines chip-specific properties of Tock's operation. These
@@ -111,6 +167,17 @@ pub trait Chip {
     /// chip and resumes the scheduler.
     fn sleep(&self);
 
+    /// Called instead of `sleep()` when the board told the kernel where to
+    /// find the next alarm deadline. `us_until_deadline` is how many
+    /// microseconds are left until the next alarm fires, or `None` if no alarm
+    /// is set. The chip can enter a deeper sleep state when the deadline is far
+    /// away, as long as the alarm still wakes it up in time.
+    ///
+    /// The default implementation ignores the deadline and calls `sleep()`.
+    fn sleep_until(&self, _us_until_deadline: Option<u32>) {
+        self.sleep()
+    }
+
     /// Run a function in an atomic state, which means that interrupts are
     /// disabled so that an interrupt will not fire during the passed in
     /// function's execution.
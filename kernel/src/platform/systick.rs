@@ -24,6 +24,11 @@ pub trait SysTick {
     /// Returns true if the timer has expired
     fn overflowed(&self) -> bool;
 
+    /// Returns the number of microseconds left before the timer expires.
+    ///
+    /// The value is only meaningful while the timer has not yet expired.
+    fn get_remaining_us(&self) -> u32;
+
     /// Resets the timer
     ///
     /// Resets the timer to 0 and disables it
@@ -56,4 +61,8 @@ impl SysTick for () {
     fn greater_than(&self, _: u32) -> bool {
         true
     }
+
+    fn get_remaining_us(&self) -> u32 {
+        u32::MAX
+    }
 }
@@ -0,0 +1,258 @@
+//! Reference-counted clocks and power domains.
+//!
+//! `ClockInterface` switches a clock on or off, but does not know who uses the
+//! clock, so peripherals usually switch their clocks on at boot and leave them
+//! on. A `ManagedClock` wraps a clock and counts its users instead: drivers
+//! `acquire()` the clock while they need the hardware, for example for the
+//! duration of a transfer, and `release()` it afterwards. The clock is on while
+//! it has at least one user. A clock can depend on a power domain, itself a
+//! `ManagedClock`, which is on while any clock in it is on.
+//!
+//! A `PowerManager` lists the managed clocks of a board and measures how long
+//! each of them was on, for energy profiling. The process console's `power`
+//! command prints the list.
+//!
+//! Usage
+//! -----
+//!
+//! ```ignore
+//! let power_manager = static_init!(
+//!     PowerManager<'static>,
+//!     PowerManager::new(&stm32f446re::tim2::TIM2)
+//! );
+//! power_manager.register(stm32f446re::usart::USART2.managed_clock());
+//! board_kernel.set_power_manager(power_manager, &process_management_capability);
+//! ```
+
+use core::cell::Cell;
+
+use crate::common::cells::{NumericCellExt, OptionalCell};
+use crate::common::{List, ListLink, ListNode};
+use crate::hil::time::{self, Frequency};
+use crate::platform::ClockInterface;
+
+/// The clock a `PowerManager` measures active time with.
+trait Stopwatch {
+    fn ticks(&self) -> u32;
+    fn max_ticks(&self) -> u32;
+    fn frequency(&self) -> u32;
+}
+
+impl<T: time::Time> Stopwatch for T {
+    fn ticks(&self) -> u32 {
+        self.now()
+    }
+
+    fn max_ticks(&self) -> u32 {
+        self.max_tics()
+    }
+
+    fn frequency(&self) -> u32 {
+        T::Frequency::frequency()
+    }
+}
+
+/// A clock or power domain that is on while it has users.
+pub struct ManagedClock<'a> {
+    name: &'static str,
+    clock: &'a dyn ClockInterface,
+    /// The power domain the clock needs.
+    domain: Option<&'a ManagedClock<'a>>,
+    users: Cell<usize>,
+    /// The clock of the power manager the clock is registered with.
+    stopwatch: OptionalCell<&'a dyn Stopwatch>,
+    /// When the active time was last updated, in ticks of the stopwatch.
+    updated_at: Cell<u32>,
+    /// How long the clock was on until `updated_at`, in ticks.
+    active_ticks: Cell<u64>,
+    next: ListLink<'a, ManagedClock<'a>>,
+}
+
+impl<'a> ListNode<'a, ManagedClock<'a>> for ManagedClock<'a> {
+    fn next(&'a self) -> &'a ListLink<'a, ManagedClock<'a>> {
+        &self.next
+    }
+}
+
+impl<'a> ManagedClock<'a> {
+    /// Manage `clock`, which needs the power domain `domain` to be on.
+    pub const fn new(
+        name: &'static str,
+        clock: &'a dyn ClockInterface,
+        domain: Option<&'a ManagedClock<'a>>,
+    ) -> ManagedClock<'a> {
+        ManagedClock {
+            name: name,
+            clock: clock,
+            domain: domain,
+            users: Cell::new(0),
+            stopwatch: OptionalCell::empty(),
+            updated_at: Cell::new(0),
+            active_ticks: Cell::new(0),
+            next: ListLink::empty(),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// How many users hold the clock.
+    pub fn users(&self) -> usize {
+        self.users.get()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.clock.is_enabled()
+    }
+
+    /// Start using the clock. The clock and its power domain stay on until
+    /// every `acquire()` is matched by a `release()`.
+    pub fn acquire(&self) {
+        if self.users.get() == 0 {
+            self.domain.map(|domain| domain.acquire());
+            self.clock.enable();
+            self.stopwatch
+                .map(|stopwatch| self.updated_at.set(stopwatch.ticks()));
+        }
+        self.users.increment();
+    }
+
+    /// Stop using the clock. The last user to release the clock switches it
+    /// off.
+    pub fn release(&self) {
+        // A release without an acquire is a bug in a driver, but the clock is
+        // off already.
+        if self.users.get() == 0 {
+            return;
+        }
+        self.users.decrement();
+        if self.users.get() == 0 {
+            self.update_active_time();
+            self.clock.disable();
+            self.domain.map(|domain| domain.release());
+        }
+    }
+
+    /// How many microseconds the clock was on since it was registered with a
+    /// power manager, or `None` if it was not registered.
+    ///
+    /// The stopwatch of the power manager wraps around, so the time of a clock
+    /// that stays on for longer than the stopwatch's wrap-around period is
+    /// only correct if it is read at least once per period.
+    pub fn active_us(&self) -> Option<u64> {
+        if self.users.get() > 0 {
+            self.update_active_time();
+        }
+        self.stopwatch
+            .map(|stopwatch| self.active_ticks.get() * 1_000_000 / stopwatch.frequency() as u64)
+    }
+
+    /// Add the time since the last update to the active time. The clock must
+    /// have been on since then.
+    fn update_active_time(&self) {
+        self.stopwatch.map(|stopwatch| {
+            let now = stopwatch.ticks();
+            let elapsed = now.wrapping_sub(self.updated_at.get()) & stopwatch.max_ticks();
+            self.active_ticks
+                .set(self.active_ticks.get() + elapsed as u64);
+            self.updated_at.set(now);
+        });
+    }
+}
+
+/// The managed clocks of a board, and the time source that measures how long
+/// they are on.
+pub struct PowerManager<'a> {
+    stopwatch: &'a dyn Stopwatch,
+    clocks: List<'a, ManagedClock<'a>>,
+}
+
+impl<'a> PowerManager<'a> {
+    /// Measure active time with `time`.
+    pub fn new<T: time::Time>(time: &'a T) -> PowerManager<'a> {
+        PowerManager {
+            stopwatch: time,
+            clocks: List::new(),
+        }
+    }
+
+    /// Measure the active time of `clock` from now on, and list it.
+    pub fn register(&self, clock: &'a ManagedClock<'a>) {
+        clock.stopwatch.set(self.stopwatch);
+        clock.updated_at.set(self.stopwatch.ticks());
+        self.clocks.push_head(clock);
+    }
+
+    /// The registered clocks.
+    pub fn clocks(&self) -> impl Iterator<Item = &'a ManagedClock<'a>> {
+        self.clocks.iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use core::cell::Cell;
+
+    use super::{ManagedClock, PowerManager};
+    use crate::hil::time::{Freq1KHz, Time};
+    use crate::platform::ClockInterface;
+
+    struct TestClock(Cell<bool>);
+
+    impl ClockInterface for TestClock {
+        fn is_enabled(&self) -> bool {
+            self.0.get()
+        }
+
+        fn enable(&self) {
+            self.0.set(true);
+        }
+
+        fn disable(&self) {
+            self.0.set(false);
+        }
+    }
+
+    struct TestTime(Cell<u32>);
+
+    impl Time for TestTime {
+        type Frequency = Freq1KHz;
+
+        fn now(&self) -> u32 {
+            self.0.get()
+        }
+
+        fn max_tics(&self) -> u32 {
+            0xFFFF
+        }
+    }
+
+    #[test]
+    fn test_clocks_are_counted_and_timed() {
+        let time = TestTime(Cell::new(0xFFF0));
+        let manager = PowerManager::new(&time);
+        let domain_clock = TestClock(Cell::new(false));
+        let domain = ManagedClock::new("domain", &domain_clock, None);
+        let uart_clock = TestClock(Cell::new(false));
+        let uart = ManagedClock::new("uart", &uart_clock, Some(&domain));
+        manager.register(&domain);
+        manager.register(&uart);
+
+        uart.acquire();
+        uart.acquire();
+        assert!(uart.is_enabled() && domain.is_enabled());
+        uart.release();
+        assert!(uart.is_enabled());
+
+        // The stopwatch wraps around while the clock is on.
+        time.0.set(0x10);
+        uart.release();
+        assert!(!uart.is_enabled() && !domain.is_enabled());
+        uart.release();
+        assert_eq!(uart.users(), 0);
+        assert_eq!(uart.active_us(), Some(32_000));
+        assert_eq!(domain.active_us(), Some(32_000));
+        assert_eq!(manager.clocks().count(), 2);
+    }
+}
@@ -12,7 +12,10 @@ use crate::capabilities::ProcessManagementCapability;
 use crate::common::cells::{MapCell, NumericCellExt};
 use crate::common::{Queue, RingBuffer};
 use crate::config;
+use crate::crash_dump;
+use crate::credentials;
 use crate::debug;
+use crate::hil::time::{self, Frequency};
 use crate::ipc;
 use crate::mem::{AppSlice, Shared};
 use crate::platform::mpu::{self, MPU};
@@ -56,6 +59,15 @@ pub enum ProcessLoadError {
         expected_address: u32,
     },
 
+    /// All slots in the processes array are in use, so there is nowhere to
+    /// put a new process.
+    NoFreeProcessSlot,
+
+    /// The board set a credentials policy, and the app's TBF footers do not
+    /// contain credentials that satisfy it, or contain credentials that do not
+    /// match the app.
+    CredentialsCheckFailed,
+
     /// Process loading error due (likely) to a bug in the kernel. If you get
     /// this error please open a bug report.
     InternalError,
@@ -109,6 +121,11 @@ impl fmt::Debug for ProcessLoadError {
                 actual_address, expected_address
             ),
 
+            ProcessLoadError::NoFreeProcessSlot => write!(f, "No free slot for a new process"),
+            ProcessLoadError::CredentialsCheckFailed => {
+                write!(f, "App credentials do not satisfy the credentials policy")
+            }
+
             ProcessLoadError::InternalError => write!(f, "Error in kernel. Likely a bug."),
         }
     }
@@ -122,20 +139,20 @@ impl fmt::Debug for ProcessLoadError {
 /// through Tock Binary Format headers. Processes are given memory out of the
 /// `app_memory` buffer until either the memory is exhausted or the allocated
 /// number of processes are created, with process structures placed in the
-/// provided array. How process faults are handled by the kernel is also
-/// selected.
+/// processes array of the kernel. How process faults are handled by the kernel
+/// is also selected.
 pub fn load_processes<C: Chip>(
     kernel: &'static Kernel,
     chip: &'static C,
     app_flash: &'static [u8],
     app_memory: &mut [u8],
-    procs: &'static mut [Option<&'static dyn ProcessType>],
     fault_response: FaultResponse,
     _capability: &dyn ProcessManagementCapability,
 ) -> Result<(), ProcessLoadError> {
     let mut remaining_flash = app_flash;
     let mut app_memory_ptr = app_memory.as_mut_ptr();
     let mut app_memory_size = app_memory.len();
+    let mut result = Ok(());
 
     if config::CONFIG.debug_load_processes {
         debug!(
@@ -146,7 +163,7 @@ pub fn load_processes<C: Chip>(
         );
     }
 
-    for i in 0..procs.len() {
+    for i in 0..kernel.num_process_slots() {
         unsafe {
             // Get the first eight bytes of flash to check if there is another
             // app.
@@ -156,7 +173,7 @@ pub fn load_processes<C: Chip>(
                     // Not enough flash to test for another app. This just means
                     // we are at the end of flash, and there are no more apps to
                     // load.
-                    return Ok(());
+                    return result;
                 }
             };
 
@@ -174,7 +191,7 @@ pub fn load_processes<C: Chip>(
                     // the header we started to parse is intentionally invalid
                     // to signal the end of apps. This is ok and just means we
                     // have finished loading apps.
-                    return Ok(());
+                    return result;
                 }
             };
 
@@ -186,8 +203,10 @@ pub fn load_processes<C: Chip>(
                 .get(0..app_length as usize)
                 .ok_or(ProcessLoadError::NotEnoughFlash)?;
 
-            // Try to create a process object from that app slice.
-            let (process, memory_offset) = Process::create(
+            // Try to create a process object from that app slice. An app
+            // that fails its credentials check is skipped so that the apps
+            // after it still load, and the error is reported at the end.
+            let (process, memory_offset) = match Process::create(
                 kernel,
                 chip,
                 app_flash,
@@ -197,7 +216,14 @@ pub fn load_processes<C: Chip>(
                 app_memory_size,
                 fault_response,
                 i,
-            )?;
+            ) {
+                Ok(created) => created,
+                Err(ProcessLoadError::CredentialsCheckFailed) => {
+                    result = Err(ProcessLoadError::CredentialsCheckFailed);
+                    (None, 0)
+                }
+                Err(error) => return Err(error),
+            };
 
             // Check to see if actually got a valid process to execute. If we
             // didn't and we didn't get a loading error (aka we got to this
@@ -214,7 +240,7 @@ pub fn load_processes<C: Chip>(
                         process.map(|p| p.get_process_name())
                     );
                 }
-                procs[i] = process;
+                kernel.set_process(i, process);
             }
 
             // Advance in our buffers before seeing if there is an additional
@@ -227,7 +253,7 @@ pub fn load_processes<C: Chip>(
         }
     }
 
-    Ok(())
+    result
 }
 
 /// This trait is implemented by process structs.
@@ -262,6 +288,11 @@ pub trait ProcessType {
     /// or "yielded".
     fn get_state(&self) -> State;
 
+    /// Returns whether this process is ready to execute. A process is ready if
+    /// it is running, or if it is yielded or unstarted and has a `Task` queued
+    /// for it. Stopped and faulted processes are never ready.
+    fn ready(&self) -> bool;
+
     /// Move this process from the running state to the yielded state.
     ///
     /// This will fail (i.e. not do anything) if the process was not previously
@@ -284,12 +315,78 @@ pub trait ProcessType {
     /// `FaultResponse` for this process to occur.
     fn set_fault_state(&self);
 
+    /// Terminate this process and move it to the `Terminated` state.
+    ///
+    /// All queued tasks and grants of the process are dropped, and the process
+    /// will not run again. Its memory remains allocated until the process is
+    /// unloaded. `completion_code` is the value the process passed when it
+    /// exited, or `None` if the kernel terminated the process.
+    fn terminate(&self, completion_code: Option<u32>);
+
+    /// Returns the completion code the process exited with, or `None` if the
+    /// process has not exited.
+    fn get_completion_code(&self) -> Option<u32>;
+
+    /// Restart a faulted process whose `ProcessRestartPolicy` delayed the
+    /// restart.
+    ///
+    /// This does nothing if the process is not waiting for a delayed restart,
+    /// for example because it was terminated in the meantime.
+    fn restart_delayed(&self);
+
     /// Returns how many times this process has been restarted.
     fn get_restart_count(&self) -> usize;
 
+    /// Returns why the process faulted the last time, or `None` if it never
+    /// faulted.
+    fn get_fault_reason(&self) -> Option<FaultReason>;
+
     /// Get the name of the process. Used for IPC.
     fn get_process_name(&self) -> &'static str;
 
+    /// Returns whether `client` may discover and notify the IPC service of
+    /// this process, as listed in its TBF header. Clients listed by package
+    /// name are only permitted if the board requires all apps to be signed.
+    fn permits_ipc_client(&self, client: &dyn ProcessType) -> bool;
+
+    /// Get the identifier of the app this process runs that stays the same
+    /// across reboots and updates of the app. For apps accepted because they
+    /// are signed, this is derived from the signing key and the identifier the
+    /// TBF header sets, or the package name if the header sets none. For other
+    /// apps it is the identifier the TBF header sets. Returns `None` if an
+    /// unsigned app sets no identifier.
+    ///
+    /// Identifiers of unsigned apps are not authenticated: any app can set any
+    /// identifier in its TBF header, including that of another app.
+    fn persistent_id(&self) -> Option<u32>;
+
+    /// Returns whether the TBF header of the app lets this process make system
+    /// calls to driver `driver_num`, and if `command_num` is given, call that
+    /// command of the driver. Apps whose header does not list permissions may
+    /// use every driver, unless the board requires permissions.
+    fn permits_syscall(&self, driver_num: usize, command_num: Option<usize>) -> bool;
+
+    /// Enable or disable recording the system calls of this process in the
+    /// kernel's syscall trace.
+    fn set_syscall_tracing(&self, enabled: bool);
+
+    /// Returns whether the system calls of this process are recorded in the
+    /// kernel's syscall trace.
+    fn syscall_tracing(&self) -> bool;
+
+    /// Get the scheduling parameters for this process. These are the values
+    /// the process requested in its TBF header after the board's
+    /// `SchedulingPolicy` (if any) has been applied.
+    fn get_scheduling_parameters(&self) -> SchedulingParameters;
+
+    /// Get the period and deadline this process registered for real-time
+    /// scheduling, or `None` if it has not registered as a real-time process.
+    fn get_real_time_parameters(&self) -> Option<RealTimeParameters>;
+
+    /// Register (or with `None`, unregister) this process for real-time
+    /// scheduling. Registrations are cleared when the process restarts.
+    fn set_real_time_parameters(&self, parameters: Option<RealTimeParameters>);
+
     // memop operations
 
     /// Change the location of the program break and reallocate the MPU region
@@ -324,6 +421,11 @@ pub trait ProcessType {
     /// The lowest address of the grant region for the process.
     fn kernel_memory_break(&self) -> *const u8;
 
+    /// Size of the region at the start of process RAM (at `mem_start()`) that
+    /// keeps its contents when the process is restarted. This is 0 if the
+    /// process did not ask for retained RAM in its TBF header.
+    fn retained_memory_size(&self) -> usize;
+
     /// How many writeable flash regions defined in the TBF header for this
     /// process.
     fn number_writeable_flash_regions(&self) -> usize;
@@ -394,13 +496,33 @@ pub trait ProcessType {
 
     // grants
 
-    /// Create new memory in the grant region, and check that the MPU region
-    /// covering program memory does not extend past the kernel memory break.
+    /// Create new memory in the grant region for grant `grant_num`, and check
+    /// that the MPU region covering program memory does not extend past the
+    /// kernel memory break.
     ///
-    /// This will return `None` and fail if the process is inactive.
-    fn alloc(&self, size: usize, align: usize) -> Option<NonNull<u8>>;
+    /// This will fail with `Error::QuotaExceeded` if the allocation would take
+    /// the grant past the board's `ProcessQuotas::grant_bytes_per_driver`, and
+    /// with `Error::InactiveApp` if the process is inactive.
+    fn alloc(&self, grant_num: usize, size: usize, align: usize) -> Result<NonNull<u8>, Error>;
 
-    unsafe fn free(&self, _: *mut u8);
+    /// Return memory allocated with `alloc()` for grant `grant_num`, which
+    /// was `size` bytes with alignment `align`. It is reused by later
+    /// allocations of any grant in this process.
+    unsafe fn free(&self, grant_num: usize, ptr: *mut u8, size: usize, align: usize);
+
+    /// Note that a capsule dropped a buffer this process allowed.
+    fn release_allow(&self);
+
+    /// How many bytes of grant memory have been allocated in this process for
+    /// grant `grant_num`, including the grant region itself.
+    fn grant_memory_size(&self, grant_num: usize) -> usize;
+
+    /// How many buffers allowed by this process capsules currently hold.
+    fn outstanding_allows(&self) -> usize;
+
+    /// How often this process ran into the board's `ProcessQuotas` since it
+    /// was last (re)started.
+    fn quota_breaches(&self) -> QuotaBreaches;
 
     /// Get the grant pointer for this grant number.
     ///
@@ -447,6 +569,11 @@ pub trait ProcessType {
     /// context, and the state of the memory protection unit (MPU).
     unsafe fn print_full_process(&self, writer: &mut dyn Write);
 
+    /// Write a crash dump of the process, in the format described in the
+    /// `crash_dump` module, into `buf` and return its length. The end of the
+    /// stack is left out if `buf` is too small to hold all of it.
+    fn write_crash_dump(&self, buf: &mut [u8]) -> usize;
+
     // debug
 
     /// Returns how many syscalls this app has called.
@@ -461,6 +588,13 @@ pub trait ProcessType {
     /// Increment the number of times the process has exceeded its timeslice.
     fn debug_timeslice_expired(&self);
 
+    /// Returns how many times this process has missed a real-time deadline.
+    fn debug_deadline_miss_count(&self) -> usize;
+
+    /// Increment the number of times the process has missed a real-time
+    /// deadline.
+    fn debug_deadline_missed(&self);
+
     /// Increment the number of times the process called a syscall and record
     /// the last syscall that was called.
     fn debug_syscall_called(&self, last_syscall: Syscall);
@@ -471,10 +605,21 @@ pub trait ProcessType {
 /// This policy allows a board to specify how the kernel should decide whether
 /// to restart an app after it crashes.
 pub trait ProcessRestartPolicy {
-    /// Decide whether to restart the `process` or not.
+    /// Decide whether to restart the `process` or not. This is called once
+    /// each time the process faults.
     ///
     /// Returns `true` if the process should be restarted, `false` otherwise.
     fn should_restart(&self, process: &dyn ProcessType) -> bool;
+
+    /// Decide whether to delay the restart of a `process` that
+    /// `should_restart()` decided to restart.
+    ///
+    /// Returns `true` to leave the process stopped for now. The policy must
+    /// then restart it later with `ProcessType::restart_delayed()`. By default
+    /// processes are restarted right away.
+    fn delay_restart(&self, _process: &dyn ProcessType) -> bool {
+        false
+    }
 }
 
 /// Implementation of `ProcessRestartPolicy` that uses a threshold to decide
@@ -535,10 +680,375 @@ impl ProcessRestartPolicy for AlwaysRestart {
     }
 }
 
+/// What a restart policy does with a process that faults more often than the
+/// policy allows.
+#[derive(Copy, Clone)]
+pub enum RestartFallback {
+    /// Leave the process stopped in the faulted state.
+    Stop,
+
+    /// Panic, which stops the whole system and prints debugging information.
+    Panic,
+
+    /// Reboot the board by calling the given function, for example a wrapper
+    /// around `cortexm::scb::reset()`.
+    Reboot(fn()),
+}
+
+/// State `BackoffRestart` keeps for one slot in the processes array.
+#[derive(Default)]
+pub struct BackoffRestartState {
+    /// Number of faults since the process last ran for the stable period.
+    faults: Cell<usize>,
+
+    /// Time (in ms) when the process was last restarted.
+    restarted_ms: Cell<u64>,
+
+    /// The process waiting for a delayed restart, and the time (in ms) when it
+    /// is due.
+    pending: Cell<Option<(AppId, u64)>>,
+}
+
+/// Implementation of `ProcessRestartPolicy` that restarts apps with an
+/// exponentially increasing delay, and forgets about faults once an app has
+/// run without faulting for a while.
+///
+/// The first restart after a fault is delayed by `initial_delay_ms`, and each
+/// further consecutive restart doubles the delay up to `max_delay_ms`. Once an
+/// app has run for `stable_period_ms` after a restart, its fault count is
+/// reset. An app that faults more than `max_restarts` times without running
+/// for the stable period in between is handled by the `fallback`.
+///
+/// With an `initial_delay_ms` of 0 apps are restarted right away, which makes
+/// this a threshold restart policy where the threshold applies to a time
+/// window instead of the lifetime of the app.
+///
+/// A `Panic` or `Reboot` fallback is not carried out while the kernel handles
+/// the fault, but from the alarm right afterwards, once the faulted process has
+/// been cleaned up and stopped.
+///
+/// The policy keeps time with `alarm`, and needs one `BackoffRestartState` for
+/// each slot in the processes array. Processes in slots without an entry are
+/// not restarted.
+pub struct BackoffRestart<'a, A: 'static + time::Alarm<'static>> {
+    kernel: &'static Kernel,
+    alarm: &'static A,
+    states: &'a [BackoffRestartState],
+    initial_delay_ms: u32,
+    max_delay_ms: u32,
+    stable_period_ms: u32,
+    max_restarts: usize,
+    fallback: RestartFallback,
+
+    /// Tics counted by the alarm since the policy was created. This keeps
+    /// counting past the alarm wrapping around, as long as the alarm is armed
+    /// often enough.
+    elapsed_tics: Cell<u64>,
+
+    /// Value of the alarm when `elapsed_tics` was last updated.
+    last_now: Cell<u32>,
+
+    /// Whether a process faulted too often and the `fallback` is due the next
+    /// time the alarm fires.
+    fallback_due: Cell<bool>,
+}
+
+impl<'a, A: 'static + time::Alarm<'static>> BackoffRestart<'a, A> {
+    pub fn new(
+        kernel: &'static Kernel,
+        alarm: &'static A,
+        states: &'a [BackoffRestartState],
+        initial_delay_ms: u32,
+        max_delay_ms: u32,
+        stable_period_ms: u32,
+        max_restarts: usize,
+        fallback: RestartFallback,
+    ) -> BackoffRestart<'a, A> {
+        BackoffRestart {
+            kernel: kernel,
+            alarm: alarm,
+            states: states,
+            initial_delay_ms: initial_delay_ms,
+            max_delay_ms: max_delay_ms,
+            stable_period_ms: stable_period_ms,
+            max_restarts: max_restarts,
+            fallback: fallback,
+            elapsed_tics: Cell::new(0),
+            last_now: Cell::new(0),
+            fallback_due: Cell::new(false),
+        }
+    }
+
+    fn state(&self, process: &dyn ProcessType) -> Option<&BackoffRestartState> {
+        process
+            .appid()
+            .index()
+            .and_then(|index| self.states.get(index))
+    }
+
+    /// Milliseconds since the policy was created.
+    fn now_ms(&self) -> u64 {
+        let now = self.alarm.now();
+        let tics = now.wrapping_sub(self.last_now.get()) & self.alarm.max_tics();
+        self.last_now.set(now);
+        self.elapsed_tics.set(self.elapsed_tics.get() + tics as u64);
+        self.elapsed_tics.get() * 1000 / A::Frequency::frequency() as u64
+    }
+
+    /// Delay before restarting a process for its `faults`th consecutive time.
+    fn delay_ms(&self, faults: usize) -> u64 {
+        let doublings = core::cmp::min(faults.saturating_sub(1), 32) as u32;
+        let delay = (self.initial_delay_ms as u64) << doublings;
+        core::cmp::min(delay, self.max_delay_ms as u64)
+    }
+
+    /// Arm the alarm for the fallback, the next delayed restart or the end of
+    /// the next stable period, or disable it if there is nothing to wait for.
+    fn arm(&self) {
+        let now_ms = self.now_ms();
+        let mut next_ms: Option<u64> = if self.fallback_due.get() {
+            Some(now_ms)
+        } else {
+            None
+        };
+        for state in self.states.iter() {
+            let due_ms = match state.pending.get() {
+                Some((_, due_ms)) => due_ms,
+                None if state.faults.get() > 0 => {
+                    let stable_ms = state.restarted_ms.get() + self.stable_period_ms as u64;
+                    if stable_ms <= now_ms {
+                        state.faults.set(0);
+                        continue;
+                    }
+                    stable_ms
+                }
+                None => continue,
+            };
+            next_ms = Some(next_ms.map_or(due_ms, |next_ms| core::cmp::min(next_ms, due_ms)));
+        }
+
+        match next_ms {
+            Some(next_ms) => {
+                // Wake up at least twice per wrap of the alarm, so that
+                // `elapsed_tics` stays correct.
+                let frequency = A::Frequency::frequency() as u64;
+                let max_wait_tics = self.alarm.max_tics() as u64 / 2;
+                let wait_tics = next_ms.saturating_sub(now_ms) * frequency / 1000;
+                let wait_tics = core::cmp::min(core::cmp::max(wait_tics, 1), max_wait_tics);
+                self.alarm.set_alarm(
+                    self.last_now.get().wrapping_add(wait_tics as u32) & self.alarm.max_tics(),
+                );
+            }
+            None => self.alarm.disable(),
+        }
+    }
+}
+
+impl<'a, A: 'static + time::Alarm<'static>> ProcessRestartPolicy for BackoffRestart<'a, A> {
+    fn should_restart(&self, process: &dyn ProcessType) -> bool {
+        let state = match self.state(process) {
+            Some(state) => state,
+            None => return false,
+        };
+        let now_ms = self.now_ms();
+        if state.restarted_ms.get() + self.stable_period_ms as u64 <= now_ms {
+            state.faults.set(0);
+        }
+        state.faults.increment();
+        if state.faults.get() <= self.max_restarts {
+            return true;
+        }
+
+        // The process stays stopped. Panicking or rebooting has to wait until
+        // the kernel is done handling the fault.
+        match self.fallback {
+            RestartFallback::Stop => {}
+            RestartFallback::Panic | RestartFallback::Reboot(_) => self.fallback_due.set(true),
+        }
+        self.arm();
+        false
+    }
+
+    fn delay_restart(&self, process: &dyn ProcessType) -> bool {
+        let state = match self.state(process) {
+            Some(state) => state,
+            None => return false,
+        };
+        let now_ms = self.now_ms();
+        let delay_ms = self.delay_ms(state.faults.get());
+        let delayed = delay_ms > 0;
+        if delayed {
+            state
+                .pending
+                .set(Some((process.appid(), now_ms + delay_ms)));
+        } else {
+            state.restarted_ms.set(now_ms);
+        }
+        self.arm();
+        delayed
+    }
+}
+
+impl<'a, A: 'static + time::Alarm<'static>> time::AlarmClient for BackoffRestart<'a, A> {
+    fn fired(&self) {
+        if self.fallback_due.get() {
+            match self.fallback {
+                RestartFallback::Stop => {}
+                RestartFallback::Panic => panic!("Restart threshold surpassed!"),
+                RestartFallback::Reboot(reboot) => {
+                    reboot();
+                    panic!("Reboot after restart threshold failed!");
+                }
+            }
+        }
+
+        let now_ms = self.now_ms();
+        for state in self.states.iter() {
+            if let Some((appid, due_ms)) = state.pending.get() {
+                if due_ms <= now_ms {
+                    state.pending.set(None);
+                    state.restarted_ms.set(now_ms);
+                    self.kernel
+                        .process_map_or((), appid, |process| process.restart_delayed());
+                }
+            }
+        }
+        self.arm();
+    }
+}
+
+/// Scheduling parameters for a process.
+///
+/// A `None` field means no value was requested, and the scheduler should use
+/// its own default.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct SchedulingParameters {
+    /// Priority of the process. Lower values mean higher priority, with 0 being
+    /// the highest priority.
+    pub priority: Option<u32>,
+
+    /// Length of the timeslice for the process, in microseconds.
+    pub timeslice_us: Option<u32>,
+
+    /// CPU time the process may use in each scheduling period, in
+    /// microseconds.
+    pub cpu_budget_us: Option<u32>,
+}
+
+/// Period and deadline a process registers for real-time scheduling.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct RealTimeParameters {
+    /// How often the process needs to be activated, in microseconds.
+    pub period_us: u32,
+
+    /// How long after an activation the process must have finished its work,
+    /// in microseconds. This is never longer than the period.
+    pub deadline_us: u32,
+}
+
+/// Generic trait for implementing scheduling parameter policies.
+///
+/// Processes request scheduling parameters in their TBF header. This policy
+/// allows a board to decide which of those requests are honored, for example by
+/// capping them or replacing them with values of its own.
+pub trait SchedulingPolicy {
+    /// Decide the scheduling parameters of `process`, given the parameters it
+    /// `requested`.
+    fn scheduling_parameters(
+        &self,
+        process: &dyn ProcessType,
+        requested: SchedulingParameters,
+    ) -> SchedulingParameters;
+}
+
+/// Implementation of `SchedulingPolicy` that honors requests up to a limit.
+///
+/// Processes cannot request a priority higher than `highest_priority`, nor a
+/// timeslice or CPU budget longer than the configured maximums.
+pub struct CappedSchedulingPolicy {
+    highest_priority: u32,
+    max_timeslice_us: u32,
+    max_cpu_budget_us: u32,
+}
+
+impl CappedSchedulingPolicy {
+    pub const fn new(
+        highest_priority: u32,
+        max_timeslice_us: u32,
+        max_cpu_budget_us: u32,
+    ) -> CappedSchedulingPolicy {
+        CappedSchedulingPolicy {
+            highest_priority,
+            max_timeslice_us,
+            max_cpu_budget_us,
+        }
+    }
+}
+
+impl SchedulingPolicy for CappedSchedulingPolicy {
+    fn scheduling_parameters(
+        &self,
+        _process: &dyn ProcessType,
+        requested: SchedulingParameters,
+    ) -> SchedulingParameters {
+        SchedulingParameters {
+            priority: requested.priority.map(|p| max(p, self.highest_priority)),
+            timeslice_us: requested
+                .timeslice_us
+                .map(|t| core::cmp::min(t, self.max_timeslice_us)),
+            cpu_budget_us: requested
+                .cpu_budget_us
+                .map(|b| core::cmp::min(b, self.max_cpu_budget_us)),
+        }
+    }
+}
+
+/// Limits on the kernel resources each process can use, set by the board
+/// with `Kernel::set_process_quotas()`. `None` means no limit beyond the
+/// memory available to the process.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct ProcessQuotas {
+    /// The most bytes of grant memory any single grant, i.e. the capsule
+    /// owning it, can allocate in a process. The same limit applies to every
+    /// grant, so it must leave room for the capsule that needs the most grant
+    /// memory.
+    pub grant_bytes_per_driver: Option<usize>,
+
+    /// The most tasks (callbacks and IPC notifications) that can be queued
+    /// for a process. Further tasks are dropped.
+    pub task_queue_depth: Option<usize>,
+
+    /// The most buffers a process can have allowed to capsules at the same
+    /// time. Once it is reached, a process must unallow a buffer (allow a
+    /// null buffer) before allowing another one.
+    pub outstanding_allows: Option<usize>,
+}
+
+/// How often a process ran into each of the `ProcessQuotas`.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct QuotaBreaches {
+    /// Grant allocations that failed with `Error::QuotaExceeded`.
+    pub grant_bytes: usize,
+
+    /// The grant number of the most recent grant allocation that failed. This
+    /// points at the capsule which asked for the memory.
+    pub last_grant: Option<usize>,
+
+    /// Tasks dropped because the task queue was at its quota.
+    pub task_queue: usize,
+
+    /// Allow system calls that failed with `ENOMEM` because the process held
+    /// its quota of allowed buffers.
+    pub allows: usize,
+}
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum Error {
     NoSuchApp,
     OutOfMemory,
+    /// The allocation would exceed the memory the board allows one grant to
+    /// use in a process (`ProcessQuotas::grant_bytes_per_driver`).
+    QuotaExceeded,
     AddressOutOfBounds,
     /// The process is inactive (likely in a fault or exit state) and the
     /// attempted operation is therefore invalid.
@@ -552,6 +1062,7 @@ impl From<Error> for ReturnCode {
     fn from(err: Error) -> ReturnCode {
         match err {
             Error::OutOfMemory => ReturnCode::ENOMEM,
+            Error::QuotaExceeded => ReturnCode::ENOMEM,
             Error::AddressOutOfBounds => ReturnCode::EINVAL,
             Error::NoSuchApp => ReturnCode::EINVAL,
             Error::InactiveApp => ReturnCode::FAIL,
@@ -560,6 +1071,22 @@ impl From<Error> for ReturnCode {
     }
 }
 
+/// The header of a freed block of grant memory, stored in the block itself.
+struct FreeGrantBlock {
+    next: *mut FreeGrantBlock,
+    size: usize,
+}
+
+/// The size and alignment an allocation of `size` bytes aligned to `align`
+/// takes up in the grant region. Rounding every allocation to a multiple of
+/// the size of a `FreeGrantBlock` makes sure any freed block, and any part of
+/// one left after reusing it, can hold the header.
+fn grant_block_layout(size: usize, align: usize) -> (usize, usize) {
+    let granule = mem::size_of::<FreeGrantBlock>();
+    let size = max(size, 1);
+    ((size + granule - 1) & !(granule - 1), max(align, granule))
+}
+
 /// Various states a process can be in.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum State {
@@ -594,6 +1121,11 @@ pub enum State {
     /// The process has caused a fault.
     Fault,
 
+    /// The process exited or was terminated by the kernel. Its grants have
+    /// been freed and it will not run again, but it still holds its memory and
+    /// its slot in the processes array until it is unloaded.
+    Terminated,
+
     /// The process has never actually been executed. This of course happens
     /// when the board first boots and the kernel has not switched to any
     /// processes yet. It can also happen if an process is terminated and all
@@ -626,6 +1158,41 @@ pub enum FaultResponse {
     Stop,
 }
 
+/// Why a process faulted.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FaultReason {
+    /// The stack of the process grew past its bottom, into the retained
+    /// memory or out of the memory of the process.
+    StackOverflow,
+
+    /// Any other fault, for example an MPU violation or an illegal
+    /// instruction, or the kernel putting the process in the fault state.
+    Other,
+}
+
+impl fmt::Display for FaultReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FaultReason::StackOverflow => write!(f, "stack overflow"),
+            FaultReason::Other => write!(f, "other"),
+        }
+    }
+}
+
+/// The size of a retained region of `size` bytes, rounded up to a multiple of
+/// 8 bytes so that the stack the app places after it stays aligned. Returns
+/// `None` if the rounded size cannot be addressed.
+fn retained_region_size(size: usize) -> Option<usize> {
+    size.checked_add(7).map(|size| size & !7)
+}
+
+/// Value of the canary words at the bottom of the stack of a process.
+const STACK_CANARY: u32 = 0xDEAD_C0DE;
+
+/// Number of bytes at the bottom of the stack of a process that hold the
+/// canary. A process whose stack reaches them has overflowed its stack.
+const STACK_GUARD_SIZE: usize = 16;
+
 #[derive(Copy, Clone)]
 pub enum Task {
     FunctionCall(FunctionCall),
@@ -690,6 +1257,13 @@ struct ProcessDebug {
     /// How many times this process has been paused because it exceeded its
     /// timeslice.
     timeslice_expiration_count: usize,
+
+    /// How many times this process did not finish its work before its
+    /// real-time deadline.
+    deadline_miss_count: usize,
+
+    /// How often the process ran into its quotas.
+    quota_breaches: QuotaBreaches,
 }
 
 /// A type for userspace processes in Tock.
@@ -733,12 +1307,19 @@ pub struct Process<'a, C: 'static + Chip> {
     ///  E  │                                    S B
     ///  D  │ ──────  ← current_stack_pointer      L
     ///     │                                    ║ E
+    ///  ║  │ ──────  ← retained_memory_size     ║
+    ///  ║  │ Retained                           ║
     ///  ╚═ ╘════════ ← memory[0]               ═╝
     /// ```
     ///
     /// The process's memory.
     memory: &'static mut [u8],
 
+    /// Size of the region at the start of `memory` that is not cleared or
+    /// handed to the process's `_start` function, so that it keeps its
+    /// contents when the process is restarted.
+    retained_memory_size: usize,
+
     /// Pointer to the end of the allocated (and MPU protected) grant region.
     kernel_memory_break: Cell<*const u8>,
 
@@ -764,7 +1345,7 @@ pub struct Process<'a, C: 'static + Chip> {
     flash: &'static [u8],
 
     /// Collection of pointers to the TBF header in flash.
-    header: tbfheader::TbfHeader,
+    header: tbfheader::TbfHeader<'static>,
 
     /// State saved on behalf of the process each time the app switches to the
     /// kernel.
@@ -803,6 +1384,40 @@ pub struct Process<'a, C: 'static + Chip> {
     /// Name of the app.
     process_name: &'static str,
 
+    /// Identifier of the app that does not change across reboots.
+    persistent_id: Option<u32>,
+
+    /// Scheduling parameters decided when the process was loaded, from its
+    /// TBF header and the board's scheduling policy.
+    scheduling_parameters: SchedulingParameters,
+
+    /// Period and deadline if the process registered for real-time scheduling.
+    real_time_parameters: Cell<Option<RealTimeParameters>>,
+
+    /// The value the process passed when it exited, if it has exited.
+    completion_code: Cell<Option<u32>>,
+
+    /// Whether the restart policy delayed restarting this faulted process.
+    restart_pending: Cell<bool>,
+
+    /// Whether the system calls of the process are recorded in the kernel's
+    /// syscall trace.
+    syscall_tracing: Cell<bool>,
+
+    /// How many buffers allowed by the process capsules currently hold.
+    outstanding_allows: Cell<usize>,
+
+    /// Grant memory that was freed, other than at the kernel memory break.
+    grant_free_list: Cell<*mut FreeGrantBlock>,
+
+    /// Whether the kernel placed a canary at the bottom of the stack of the
+    /// process. This happens once the process tells the kernel where its stack
+    /// starts, as the stack then covers the memory above the bottom.
+    stack_canary: Cell<bool>,
+
+    /// Why the process faulted the last time.
+    fault_reason: Cell<Option<FaultReason>>,
+
     /// Values kept so that we can print useful debug messages when apps fault.
     debug: MapCell<ProcessDebug>,
 }
@@ -819,6 +1434,22 @@ impl<C: Chip> ProcessType for Process<'_, C> {
             return false;
         }
 
+        if let Some(depth) = self.kernel.get_process_quotas().task_queue_depth {
+            if self.tasks.map_or(0, |tasks| tasks.len()) >= depth {
+                self.debug.map(|debug| {
+                    debug.dropped_callback_count += 1;
+                    debug.quota_breaches.task_queue += 1;
+                    if debug.quota_breaches.task_queue == 1 {
+                        debug!(
+                            "[{:?}] Task queue quota of {} reached, dropping tasks",
+                            self.process_name, depth
+                        );
+                    }
+                });
+                return false;
+            }
+        }
+
         self.kernel.increment_work();
 
         let ret = self.tasks.map_or(false, |tasks| tasks.enqueue(task));
@@ -863,6 +1494,16 @@ impl<C: Chip> ProcessType for Process<'_, C> {
         self.state.get()
     }
 
+    fn ready(&self) -> bool {
+        match self.state.get() {
+            State::Running => true,
+            State::Yielded | State::Unstarted => {
+                self.tasks.map_or(false, |tasks| tasks.has_elements())
+            }
+            _ => false,
+        }
+    }
+
     fn set_yielded_state(&self) {
         if self.state.get() == State::Running {
             self.state.set(State::Yielded);
@@ -887,24 +1528,35 @@ impl<C: Chip> ProcessType for Process<'_, C> {
     }
 
     fn set_fault_state(&self) {
-        self.state.set(State::Fault);
+        // The hardware does not tell a stack overflow apart from other faults,
+        // so look at the stack of the process.
+        if self.stack_overflowed() {
+            self.fault(FaultReason::StackOverflow);
+        } else {
+            self.fault(FaultReason::Other);
+        }
+    }
 
-        match self.fault_response {
-            FaultResponse::Panic => {
-                // process faulted. Panic and print status
-                panic!("Process {} had a fault", self.process_name);
-            }
-            FaultResponse::Restart(_) => {
-                self.restart(State::StoppedFaulted);
-            }
-            FaultResponse::Stop => {
-                // This looks a lot like restart, except we just leave the app
-                // how it faulted and mark it as `StoppedFaulted`. By clearing
-                // all of the app's todo work it will not be scheduled, and
-                // clearing all of the grant regions will cause capsules to drop
-                // this app as well.
-                self.terminate();
-            }
+    fn terminate(&self, completion_code: Option<u32>) {
+        // A running process counts as work for the kernel, which is done now.
+        if self.state.get() == State::Running {
+            self.kernel.decrement_work();
+        }
+        self.cleanup();
+        self.real_time_parameters.set(None);
+        self.restart_pending.set(false);
+        self.completion_code.set(completion_code);
+        self.state.set(State::Terminated);
+    }
+
+    fn get_completion_code(&self) -> Option<u32> {
+        self.completion_code.get()
+    }
+
+    fn restart_delayed(&self) {
+        if self.restart_pending.get() {
+            self.restart_pending.set(false);
+            self.reinitialize();
         }
     }
 
@@ -912,6 +1564,10 @@ impl<C: Chip> ProcessType for Process<'_, C> {
         self.restart_count.get()
     }
 
+    fn get_fault_reason(&self) -> Option<FaultReason> {
+        self.fault_reason.get()
+    }
+
     fn dequeue_task(&self) -> Option<Task> {
         self.tasks.map_or(None, |tasks| {
             tasks.dequeue().map(|cb| {
@@ -945,6 +1601,10 @@ impl<C: Chip> ProcessType for Process<'_, C> {
         self.kernel_memory_break.get()
     }
 
+    fn retained_memory_size(&self) -> usize {
+        self.retained_memory_size
+    }
+
     fn number_writeable_flash_regions(&self) -> usize {
         self.header.number_writeable_flash_regions()
     }
@@ -962,6 +1622,16 @@ impl<C: Chip> ProcessType for Process<'_, C> {
                 // we had could be entirely wrong by now.
                 debug.min_stack_pointer = stack_pointer;
             });
+
+            // The stack grows down from here to the bottom of the stack, so the
+            // process must not use the memory at the bottom for anything else.
+            // Guard it with a canary, if the stack is large enough.
+            if !self.stack_canary.get()
+                && stack_pointer >= self.stack_bottom().wrapping_add(STACK_GUARD_SIZE)
+                && !self.stack_overflowed()
+            {
+                self.place_stack_canary();
+            }
         }
     }
 
@@ -1064,6 +1734,21 @@ impl<C: Chip> ProcessType for Process<'_, C> {
                 Ok(None)
             }
             Some(buf_start) => {
+                if let Some(max_allows) = self.kernel.get_process_quotas().outstanding_allows {
+                    if self.outstanding_allows.get() >= max_allows {
+                        self.debug.map(|debug| {
+                            debug.quota_breaches.allows += 1;
+                            if debug.quota_breaches.allows == 1 {
+                                debug!(
+                                    "[{:?}] Allow quota of {} buffers reached",
+                                    self.process_name, max_allows
+                                );
+                            }
+                        });
+                        return Err(ReturnCode::ENOMEM);
+                    }
+                }
+
                 if self.in_app_owned_memory(buf_start_addr, size) {
                     // Valid slice, we need to adjust the app's watermark
                     // note: in_app_owned_memory ensures this offset does not wrap
@@ -1076,6 +1761,10 @@ impl<C: Chip> ProcessType for Process<'_, C> {
                     // aliases (i.e. the same buffer has not been `allow`ed twice).
                     //
                     // TODO: We do not currently satisfy the second promise.
+                    //
+                    // Dropping the slice calls `release_allow()`, which undoes
+                    // this.
+                    self.outstanding_allows.increment();
                     let slice = unsafe { AppSlice::new(buf_start, size, self.appid()) };
                     Ok(Some(slice))
                 } else {
@@ -1085,75 +1774,151 @@ impl<C: Chip> ProcessType for Process<'_, C> {
         }
     }
 
-    fn alloc(&self, size: usize, align: usize) -> Option<NonNull<u8>> {
+    fn alloc(&self, grant_num: usize, size: usize, align: usize) -> Result<NonNull<u8>, Error> {
         // Do not modify an inactive process.
         if !self.is_active() {
-            return None;
+            return Err(Error::InactiveApp);
         }
 
-        self.mpu_config.and_then(|mut config| {
-            // First, compute the candidate new pointer. Note that at this
-            // point we have not yet checked whether there is space for
-            // this allocation or that it meets alignment requirements.
-            let new_break_unaligned = self
-                .kernel_memory_break
-                .get()
-                .wrapping_offset(-(size as isize));
-
-            // The alignment must be a power of two, 2^a. The expression
-            // `!(align - 1)` then returns a mask with leading ones,
-            // followed by `a` trailing zeros.
-            let alignment_mask = !(align - 1);
-            let new_break = (new_break_unaligned as usize & alignment_mask) as *const u8;
-
-            // Verify there is space for this allocation
-            if new_break < self.app_break.get() {
-                None
-            // Verify it didn't wrap around
-            } else if new_break > self.kernel_memory_break.get() {
-                None
-            } else if let Err(_) = self.chip.mpu().update_app_memory_region(
-                self.app_break.get(),
-                new_break,
-                mpu::Permissions::ReadWriteOnly,
-                &mut config,
-            ) {
-                None
-            } else {
-                self.kernel_memory_break.set(new_break);
-                unsafe {
-                    // Two unsafe steps here, both okay as we just made this pointer
-                    Some(NonNull::new_unchecked(new_break as *mut u8))
+        if grant_num >= self.kernel.get_grant_count_and_finalize() {
+            return Err(Error::KernelError);
+        }
+
+        let (size, align) = grant_block_layout(size, align);
+        let grant_size = self.grant_memory_size(grant_num) + size;
+        if self
+            .kernel
+            .get_process_quotas()
+            .grant_bytes_per_driver
+            .map_or(false, |quota| grant_size > quota)
+        {
+            self.debug.map(|debug| {
+                debug.quota_breaches.grant_bytes += 1;
+                debug.quota_breaches.last_grant = Some(grant_num);
+                if debug.quota_breaches.grant_bytes == 1 {
+                    debug!(
+                        "[{:?}] Grant {} would use {} bytes, more than its quota",
+                        self.process_name, grant_num, grant_size
+                    );
                 }
+            });
+            return Err(Error::QuotaExceeded);
+        }
+
+        // Reuse freed grant memory if possible, and only otherwise move the
+        // kernel memory break down.
+        let block = match unsafe { self.take_free_grant_block(size, align) } {
+            Some(block) => block,
+            None => self
+                .mpu_config
+                .map_or(Err(Error::KernelError), |mut config| {
+                    // First, compute the candidate new pointer. Note that at this
+                    // point we have not yet checked whether there is space for
+                    // this allocation or that it meets alignment requirements.
+                    let old_break = self.kernel_memory_break.get();
+                    let new_break_unaligned = old_break.wrapping_offset(-(size as isize));
+
+                    // The alignment must be a power of two, 2^a. The expression
+                    // `!(align - 1)` then returns a mask with leading ones,
+                    // followed by `a` trailing zeros.
+                    let alignment_mask = !(align - 1);
+                    let new_break = (new_break_unaligned as usize & alignment_mask) as *const u8;
+
+                    // Verify there is space for this allocation
+                    if new_break < self.app_break.get() {
+                        Err(Error::OutOfMemory)
+                    // Verify it didn't wrap around
+                    } else if new_break > old_break {
+                        Err(Error::OutOfMemory)
+                    } else if let Err(_) = self.chip.mpu().update_app_memory_region(
+                        self.app_break.get(),
+                        new_break,
+                        mpu::Permissions::ReadWriteOnly,
+                        &mut config,
+                    ) {
+                        Err(Error::OutOfMemory)
+                    } else {
+                        self.kernel_memory_break.set(new_break);
+                        unsafe {
+                            // Aligning the break may leave padding above the
+                            // allocation. Free it, so that the allocation
+                            // spans exactly `size` bytes and `free` can merge
+                            // it with the memory above. Only the first
+                            // break may not be a multiple of the block size,
+                            // and the few bytes that are left over stay unused.
+                            let padding_start = new_break.add(size) as *mut u8;
+                            let padding = (old_break as usize - padding_start as usize)
+                                & !(mem::size_of::<FreeGrantBlock>() - 1);
+                            if padding > 0 {
+                                self.push_free_grant_block(padding_start, padding);
+                            }
+                            // Two unsafe steps here, both okay as we just made this pointer
+                            Ok(NonNull::new_unchecked(new_break as *mut u8))
+                        }
+                    }
+                })?,
+        };
+
+        unsafe {
+            *self.grant_sizes().offset(-(grant_num as isize + 1)) = grant_size;
+        }
+        Ok(block)
+    }
+
+    unsafe fn free(&self, grant_num: usize, ptr: *mut u8, size: usize, align: usize) {
+        if grant_num >= self.kernel.get_grant_count_and_finalize()
+            || (ptr as *const u8) < self.kernel_memory_break.get()
+            || (ptr as *const u8) >= self.mem_end()
+        {
+            return;
+        }
+
+        let (size, _) = grant_block_layout(size, align);
+        let grant_size = self.grant_sizes().offset(-(grant_num as isize + 1));
+        *grant_size = (*grant_size).saturating_sub(size);
+
+        if ptr as *const u8 == self.kernel_memory_break.get() {
+            // The most recent allocation, and any free blocks directly above
+            // it, can go back to the unallocated memory between the app and
+            // the grants.
+            let mut new_break = ptr.add(size);
+            while let Some(block_size) = self.remove_free_grant_block(new_break) {
+                new_break = new_break.add(block_size);
             }
-        })
+            self.kernel_memory_break.set(new_break);
+        } else {
+            self.push_free_grant_block(ptr, size);
+        }
     }
 
-    unsafe fn free(&self, _: *mut u8) {}
+    fn release_allow(&self) {
+        self.outstanding_allows
+            .set(self.outstanding_allows.get().saturating_sub(1));
+    }
+
+    fn grant_memory_size(&self, grant_num: usize) -> usize {
+        if grant_num >= self.kernel.get_grant_count_and_finalize() {
+            return 0;
+        }
+        unsafe { *self.grant_sizes().offset(-(grant_num as isize + 1)) }
+    }
+
+    fn outstanding_allows(&self) -> usize {
+        self.outstanding_allows.get()
+    }
+
+    fn quota_breaches(&self) -> QuotaBreaches {
+        self.debug
+            .map_or(QuotaBreaches::default(), |debug| debug.quota_breaches)
+    }
 
-    // This is safe today, as MPU constraints ensure that `mem_end` will always
-    // be aligned on at least a word boundary. While this is unlikely to
-    // change, it should be more proactively enforced.
-    //
-    // TODO: https://github.com/tock/tock/issues/1739
-    #[allow(clippy::cast_ptr_alignment)]
     fn get_grant_ptr(&self, grant_num: usize) -> Option<*mut u8> {
         // Do not try to access the grant region of inactive process.
         if !self.is_active() {
             return None;
         }
 
-        // Sanity check the argument
-        if grant_num >= self.kernel.get_grant_count_and_finalize() {
-            return None;
-        }
-
-        let grant_num = grant_num as isize;
-        let grant_pointer = unsafe {
-            let grant_pointer_array = self.mem_end() as *const *mut u8;
-            *grant_pointer_array.offset(-(grant_num + 1))
-        };
-        Some(grant_pointer)
+        self.read_grant_ptr(grant_num)
     }
 
     // This is safe today, as MPU constraints ensure that `mem_end` will always
@@ -1173,6 +1938,49 @@ impl<C: Chip> ProcessType for Process<'_, C> {
         self.process_name
     }
 
+    fn permits_ipc_client(&self, client: &dyn ProcessType) -> bool {
+        // Any app can take any package name unless all apps must be signed.
+        let package_name = match self.kernel.get_credentials_policy() {
+            Some(credentials::CredentialsPolicy::RequireSignature(_)) => {
+                Some(client.get_process_name())
+            }
+            _ => None,
+        };
+        self.header
+            .permits_ipc_client(package_name, client.persistent_id())
+    }
+
+    fn persistent_id(&self) -> Option<u32> {
+        self.persistent_id
+    }
+
+    fn permits_syscall(&self, driver_num: usize, command_num: Option<usize>) -> bool {
+        if !self.header.has_permissions() {
+            return !self.kernel.syscall_permissions_required();
+        }
+        self.header.permits_syscall(driver_num, command_num)
+    }
+
+    fn set_syscall_tracing(&self, enabled: bool) {
+        self.syscall_tracing.set(enabled);
+    }
+
+    fn syscall_tracing(&self) -> bool {
+        self.syscall_tracing.get()
+    }
+
+    fn get_scheduling_parameters(&self) -> SchedulingParameters {
+        self.scheduling_parameters
+    }
+
+    fn get_real_time_parameters(&self) -> Option<RealTimeParameters> {
+        self.real_time_parameters.get()
+    }
+
+    fn set_real_time_parameters(&self, parameters: Option<RealTimeParameters>) {
+        self.real_time_parameters.set(parameters);
+    }
+
     unsafe fn set_syscall_return_value(&self, return_value: isize) {
         self.stored_state.map(|stored_state| {
             self.chip
@@ -1184,8 +1992,13 @@ impl<C: Chip> ProcessType for Process<'_, C> {
     unsafe fn set_process_function(&self, callback: FunctionCall) {
         // First we need to get how much memory is available for this app's
         // stack. Since the stack is at the bottom of the process's memory
-        // region, this is straightforward.
-        let remaining_stack_bytes = self.sp() as usize - self.memory.as_ptr() as usize;
+        // region, right above the retained memory and below the guard, this
+        // is straightforward.
+        let mut stack_limit = self.stack_bottom() as usize;
+        if self.stack_canary.get() {
+            stack_limit += STACK_GUARD_SIZE;
+        }
+        let remaining_stack_bytes = (self.sp() as usize).saturating_sub(stack_limit);
 
         // Next we should see if we can actually add the frame to the process's
         // stack. Architecture-specific code handles actually doing the push
@@ -1232,7 +2045,7 @@ impl<C: Chip> ProcessType for Process<'_, C> {
                         debug.min_stack_pointer = bad_stack_bottom;
                     }
                 });
-                self.set_fault_state();
+                self.fault(FaultReason::StackOverflow);
             }
 
             None => {
@@ -1254,7 +2067,14 @@ impl<C: Chip> ProcessType for Process<'_, C> {
                 .userspace_kernel_boundary()
                 .switch_to_process(self.sp(), stored_state);
             self.current_stack_pointer.set(stack_pointer as *const u8);
-            switch_reason
+
+            // A process that overwrote the canary at the bottom of its stack
+            // has overflowed its stack, whatever it asks the kernel to do.
+            if self.stack_canary_intact() {
+                switch_reason
+            } else {
+                syscall::ContextSwitchReason::Fault
+            }
         });
 
         // Update debug state as needed after running this process.
@@ -1293,6 +2113,14 @@ impl<C: Chip> ProcessType for Process<'_, C> {
             .map(|debug| debug.timeslice_expiration_count += 1);
     }
 
+    fn debug_deadline_miss_count(&self) -> usize {
+        self.debug.map_or(0, |debug| debug.deadline_miss_count)
+    }
+
+    fn debug_deadline_missed(&self) {
+        self.debug.map(|debug| debug.deadline_miss_count += 1);
+    }
+
     fn debug_syscall_called(&self, last_syscall: Syscall) {
         self.debug.map(|debug| {
             debug.syscall_count += 1;
@@ -1352,6 +2180,10 @@ impl<C: Chip> ProcessType for Process<'_, C> {
             None => writer.write_str(" Last Syscall: None"),
         };
 
+        if let Some(reason) = self.fault_reason.get() {
+            let _ = writer.write_fmt(format_args!("\r\n Last Fault: {}", reason));
+        }
+
         let _ = writer.write_fmt(format_args!(
             "\
              \r\n\
@@ -1440,7 +2272,23 @@ impl<C: Chip> ProcessType for Process<'_, C> {
         let _ = writer.write_fmt(format_args!(
             "\
              \r\n  {:#010X} ┼───────────────────────────────────────────\
-             \r\n             │ Unused\
+             \r\n             │ Unused",
+            sram_stack_bottom,
+        ));
+
+        if self.retained_memory_size > 0 {
+            let _ = writer.write_fmt(format_args!(
+                "\
+                 \r\n  {:#010X} ┼───────────────────────────────────────────\
+                 \r\n             │ Retained     {:6} | {:6}",
+                sram_start + self.retained_memory_size,
+                self.retained_memory_size,
+                self.retained_memory_size,
+            ));
+        }
+
+        let _ = writer.write_fmt(format_args!(
+            "\
              \r\n  {:#010X} ┴───────────────────────────────────────────\
              \r\n             .....\
              \r\n  {:#010X} ┬─────────────────────────────────────────── F\
@@ -1449,7 +2297,6 @@ impl<C: Chip> ProcessType for Process<'_, C> {
              \r\n             │ Protected    {:6}                        S\
              \r\n  {:#010X} ┴─────────────────────────────────────────── H\
              \r\n",
-            sram_stack_bottom,
             sram_start,
             flash_end,
             flash_app_size,
@@ -1484,6 +2331,70 @@ impl<C: Chip> ProcessType for Process<'_, C> {
             sram_start, flash_init_fn
         ));
     }
+
+    fn write_crash_dump(&self, buf: &mut [u8]) -> usize {
+        let mut dump = crash_dump::CrashDumpWriter::new(buf);
+
+        dump.start_record(crash_dump::RECORD_PROCESS);
+        dump.push_word(self.flash_start() as usize);
+        dump.push_word(self.mem_start() as usize);
+        dump.push_word(self.mem_end() as usize);
+        dump.push_word(self.app_break.get() as usize);
+        dump.push_word(self.kernel_memory_break.get() as usize);
+        dump.push_word(self.restart_count.get());
+        dump.push_bytes(self.process_name.as_bytes());
+
+        let mut registers = [0; crash_dump::MAX_REGISTERS];
+        let count = self.stored_state.map_or(0, |stored_state| unsafe {
+            self.chip.userspace_kernel_boundary().store_registers(
+                self.sp(),
+                stored_state,
+                &mut registers,
+            )
+        });
+        dump.start_record(crash_dump::RECORD_REGISTERS);
+        for register in registers[..count].iter() {
+            dump.push_word(*register);
+        }
+
+        // `get_grant_ptr()` refuses to read the grant pointers of a faulted
+        // process.
+        dump.start_record(crash_dump::RECORD_GRANT_POINTERS);
+        for grant_num in 0..self.kernel.get_grant_count_and_finalize() {
+            let grant_ptr = self.read_grant_ptr(grant_num).unwrap_or(ptr::null_mut());
+            dump.push_word(grant_ptr as usize);
+        }
+
+        let header_length = self
+            .flash
+            .get(0..8)
+            .and_then(|lengths| lengths.try_into().ok())
+            .and_then(|lengths| tbfheader::parse_tbf_header_lengths(lengths).ok())
+            .map_or(0, |(_, header_length, _)| header_length as usize);
+        dump.start_record(crash_dump::RECORD_TBF_HEADER);
+        dump.push_word(self.flash_start() as usize);
+        dump.push_bytes(&self.flash[..core::cmp::min(header_length, self.flash.len())]);
+
+        // The stack grows down from where the app placed it, or if the app did
+        // not tell the kernel, at most to the app break.
+        let stack_pointer = self.sp() as *const u8;
+        let stack_top = self
+            .debug
+            .map_or(None, |debug| debug.app_stack_start_pointer)
+            .map_or(self.app_break.get(), |top| {
+                core::cmp::min(top, self.app_break.get())
+            });
+        dump.start_record(crash_dump::RECORD_MEMORY);
+        dump.push_word(stack_pointer as usize);
+        if stack_pointer >= self.mem_start() && stack_pointer < stack_top {
+            let stack = unsafe {
+                slice::from_raw_parts(stack_pointer, stack_top as usize - stack_pointer as usize)
+            };
+            dump.push_bytes(stack);
+        }
+
+        dump.finish()
+    }
 }
 
 fn exceeded_check(size: usize, allocated: usize) -> &'static str {
@@ -1557,6 +2468,24 @@ impl<C: 'static + Chip> Process<'_, C> {
             return Ok((None, 0));
         }
 
+        // If the board only runs apps with valid credentials, check them
+        // before giving the app any resources.
+        let mut accepted = None;
+        if let Some(policy) = kernel.get_credentials_policy() {
+            accepted = credentials::check_credentials(policy, app_flash, &tbf_header);
+            if accepted.is_none() {
+                if config::CONFIG.debug_load_processes {
+                    debug!(
+                        "[!] flash=[{:#010X}:{:#010X}] process={:?} - credentials check failed",
+                        app_flash.as_ptr() as usize,
+                        app_flash.as_ptr() as usize + app_flash.len(),
+                        process_name
+                    );
+                }
+                return Err(ProcessLoadError::CredentialsCheckFailed);
+            }
+        }
+
         // Otherwise, actually load the app.
         let mut min_app_ram_size = tbf_header.get_minimum_app_ram_size() as usize;
         let init_fn = app_flash
@@ -1593,10 +2522,11 @@ impl<C: 'static + Chip> Process<'_, C> {
         // memory space just for kernel and grant state. We need to make
         // sure we allocate enough memory just for that.
 
-        // Make room for grant pointers.
+        // Make room for grant pointers, and below them the number of bytes
+        // allocated for each grant.
         let grant_ptr_size = mem::size_of::<*const usize>();
         let grant_ptrs_num = kernel.get_grant_count_and_finalize();
-        let grant_ptrs_offset = grant_ptrs_num * grant_ptr_size;
+        let grant_ptrs_offset = grant_ptrs_num * (grant_ptr_size + mem::size_of::<usize>());
 
         // Allocate memory for callback ring buffer.
         let callback_size = mem::size_of::<Task>();
@@ -1616,8 +2546,22 @@ impl<C: 'static + Chip> Process<'_, C> {
             min_app_ram_size = initial_app_memory_size;
         }
 
+        // The retained region sits below the memory the app asked for. A
+        // retained region too large to address cannot fit in memory either.
+        let retained_memory_size =
+            retained_region_size(tbf_header.get_retained_ram_size() as usize)
+                .ok_or(ProcessLoadError::NotEnoughMemory)?;
+        let initial_app_memory_size = initial_app_memory_size
+            .checked_add(retained_memory_size)
+            .ok_or(ProcessLoadError::NotEnoughMemory)?;
+        min_app_ram_size = min_app_ram_size
+            .checked_add(retained_memory_size)
+            .ok_or(ProcessLoadError::NotEnoughMemory)?;
+
         // Minimum memory size for the process.
-        let min_total_memory_size = min_app_ram_size + initial_kernel_memory_size;
+        let min_total_memory_size = min_app_ram_size
+            .checked_add(initial_kernel_memory_size)
+            .ok_or(ProcessLoadError::NotEnoughMemory)?;
 
         // Determine where process memory will go and allocate MPU region for app-owned memory.
         let (memory_start, memory_size) = match chip.mpu().allocate_app_memory_region(
@@ -1667,7 +2611,12 @@ impl<C: 'static + Chip> Process<'_, C> {
             }
         }
 
-        // Set the initial process stack and memory to 3072 bytes.
+        // A new process starts with an empty retained region. Restarts leave
+        // it alone.
+        ptr::write_bytes(memory_start as *mut u8, 0, retained_memory_size);
+
+        // Set the initial process stack and memory to 3072 bytes above the
+        // retained region.
         let initial_stack_pointer = memory_start.add(initial_app_memory_size);
         let initial_sbrk_pointer = memory_start.add(initial_app_memory_size);
 
@@ -1686,9 +2635,16 @@ impl<C: 'static + Chip> Process<'_, C> {
         //
         // TODO: https://github.com/tock/tock/issues/1739
         #[allow(clippy::cast_ptr_alignment)]
-        // Set all pointers to null.
-        let opts =
-            slice::from_raw_parts_mut(kernel_memory_break as *mut *const usize, grant_ptrs_num);
+        // Set all sizes to zero and all pointers to null.
+        let sizes = slice::from_raw_parts_mut(kernel_memory_break as *mut usize, grant_ptrs_num);
+        for size in sizes.iter_mut() {
+            *size = 0;
+        }
+        #[allow(clippy::cast_ptr_alignment)]
+        let opts = slice::from_raw_parts_mut(
+            kernel_memory_break.add(grant_ptrs_num * mem::size_of::<usize>()) as *mut *const usize,
+            grant_ptrs_num,
+        );
         for opt in opts.iter_mut() {
             *opt = ptr::null()
         }
@@ -1734,6 +2690,7 @@ impl<C: 'static + Chip> Process<'_, C> {
         process.kernel = kernel;
         process.chip = chip;
         process.memory = app_memory;
+        process.retained_memory_size = retained_memory_size;
         process.header = tbf_header;
         process.kernel_memory_break = Cell::new(kernel_memory_break);
         process.original_kernel_memory_break = kernel_memory_break;
@@ -1762,6 +2719,22 @@ impl<C: 'static + Chip> Process<'_, C> {
         ];
         process.tasks = MapCell::new(tasks);
         process.process_name = process_name.unwrap_or("");
+        process.persistent_id = match accepted {
+            Some(credentials::Accepted::Signature(key)) => Some(credentials::key_derived_app_id(
+                key,
+                process.process_name,
+                process.header.get_persistent_app_id(),
+            )),
+            _ => process.header.get_persistent_app_id(),
+        };
+        process.real_time_parameters = Cell::new(None);
+        process.completion_code = Cell::new(None);
+        process.restart_pending = Cell::new(false);
+        process.syscall_tracing = Cell::new(false);
+        process.outstanding_allows = Cell::new(0);
+        process.grant_free_list = Cell::new(ptr::null_mut());
+        process.stack_canary = Cell::new(false);
+        process.fault_reason = Cell::new(None);
 
         process.debug = MapCell::new(ProcessDebug {
             app_heap_start_pointer: app_heap_start_pointer,
@@ -1771,18 +2744,33 @@ impl<C: 'static + Chip> Process<'_, C> {
             last_syscall: None,
             dropped_callback_count: 0,
             timeslice_expiration_count: 0,
+            deadline_miss_count: 0,
+            quota_breaches: QuotaBreaches::default(),
         });
 
+        // Schedulers ask for the parameters on every scheduling decision, so
+        // decide them once.
+        let requested = SchedulingParameters {
+            priority: process.header.get_scheduling_priority(),
+            timeslice_us: process.header.get_scheduling_timeslice_us(),
+            cpu_budget_us: process.header.get_scheduling_cpu_budget_us(),
+        };
+        process.scheduling_parameters =
+            kernel.get_scheduling_policy().map_or(requested, |policy| {
+                policy.scheduling_parameters(process, requested)
+            });
+
         let flash_protected_size = process.header.get_protected_size() as usize;
         let flash_app_start_addr = app_flash.as_ptr() as usize + flash_protected_size;
 
+        // The app lays out its memory after the retained region.
         process.tasks.map(|tasks| {
             tasks.enqueue(Task::FunctionCall(FunctionCall {
                 source: FunctionCallSource::Kernel,
                 pc: init_fn,
                 argument0: flash_app_start_addr,
-                argument1: process.memory.as_ptr() as usize,
-                argument2: process.memory.len() as usize,
+                argument1: process.memory.as_ptr() as usize + retained_memory_size,
+                argument2: process.memory.len() - retained_memory_size,
                 argument3: process.app_break.get() as usize,
             }));
         });
@@ -1838,12 +2826,13 @@ impl<C: 'static + Chip> Process<'_, C> {
     ///    the syscall state for the process fails to initialize.
     ///
     /// After `restart()` runs the process will either be queued to run its
-    /// `_start` function, or it will be left in `failure_state`.
+    /// `_start` function, or it will be left in `failure_state`. In the latter
+    /// case the restart policy may still restart the process later.
     fn restart(&self, failure_state: State) {
-        // Start with the generic terminate operations. This frees state for
+        // Start with the generic cleanup operations. This frees state for
         // this process and removes any pending tasks from the scheduler's
         // queue.
-        self.terminate();
+        self.cleanup();
 
         // Set the state the process will be in if it cannot be restarted.
         self.state.set(failure_state);
@@ -1862,6 +2851,14 @@ impl<C: 'static + Chip> Process<'_, C> {
                 if !restart_policy.should_restart(self) {
                     return;
                 }
+
+                // The policy may also want to wait before restarting the
+                // process. It then restarts the process later with
+                // `restart_delayed()`.
+                if restart_policy.delay_restart(self) {
+                    self.restart_pending.set(true);
+                    return;
+                }
             }
 
             _ => {
@@ -1872,6 +2869,14 @@ impl<C: 'static + Chip> Process<'_, C> {
             }
         }
 
+        self.reinitialize();
+    }
+
+    /// Reset the memory and architecture state of a process that was cleaned
+    /// up, and queue its `_start` function so it runs again.
+    ///
+    /// If the process cannot be reset it is left in its current state.
+    fn reinitialize(&self) {
         // We need a new process identifier for this process since the restarted
         // version is in effect a new process. This is also necessary to
         // invalidate any stored `AppId`s that point to the old version of the
@@ -1888,8 +2893,22 @@ impl<C: 'static + Chip> Process<'_, C> {
             debug.last_syscall = None;
             debug.dropped_callback_count = 0;
             debug.timeslice_expiration_count = 0;
+            debug.deadline_miss_count = 0;
+            debug.quota_breaches = QuotaBreaches::default();
         });
 
+        // Buffers allowed before the restart belong to the old `AppId` and no
+        // longer count against the process.
+        self.outstanding_allows.set(0);
+
+        // The restarted process tells the kernel again where its stack is.
+        self.stack_canary.set(false);
+
+        // The restarted process must register again if it wants to be
+        // scheduled as a real-time process.
+        self.real_time_parameters.set(None);
+        self.completion_code.set(None);
+
         // We are going to start this process over again, so need the init_fn
         // location.
         let app_flash_address = self.flash_start();
@@ -1903,11 +2922,28 @@ impl<C: 'static + Chip> Process<'_, C> {
         // to use saved values.
         self.kernel_memory_break
             .set(self.original_kernel_memory_break);
+        self.grant_free_list.set(ptr::null_mut());
         self.app_break.set(self.original_app_break);
         self.current_stack_pointer.set(self.original_stack_pointer);
         self.allow_high_water_mark
             .set(self.original_allow_high_water_mark);
 
+        // The MPU region for app-owned memory must shrink back as well, so
+        // that the restarted process cannot access memory above its break.
+        let mpu_res = self.mpu_config.map_or(Err(()), |config| {
+            self.chip.mpu().update_app_memory_region(
+                self.app_break.get(),
+                self.kernel_memory_break.get(),
+                mpu::Permissions::ReadWriteOnly,
+                config,
+            )
+        });
+        if mpu_res.is_err() {
+            // This cannot fail, as the process was set up with these breaks
+            // before. If it does, leave the app faulted.
+            return;
+        }
+
         // Handle any architecture-specific requirements for a process when it
         // first starts (as it would when it is new).
         let new_stack_pointer_res = self.stored_state.map_or(Err(()), |stored_state| unsafe {
@@ -1949,8 +2985,8 @@ impl<C: 'static + Chip> Process<'_, C> {
                 source: FunctionCallSource::Kernel,
                 pc: init_fn,
                 argument0: flash_app_start,
-                argument1: self.memory.as_ptr() as usize,
-                argument2: self.memory.len() as usize,
+                argument1: self.memory.as_ptr() as usize + self.retained_memory_size,
+                argument2: self.memory.len() - self.retained_memory_size,
                 argument3: self.app_break.get() as usize,
             }));
         });
@@ -1959,13 +2995,14 @@ impl<C: 'static + Chip> Process<'_, C> {
         self.kernel.increment_work();
     }
 
-    /// Stop and clear a process's state.
+    /// Clear a process's state.
     ///
     /// This will end the process, but does not reset it such that it could be
     /// restarted and run again. This function instead frees grants and any
     /// queued tasks for this process, but leaves the debug information about
-    /// the process and other state intact.
-    fn terminate(&self) {
+    /// the process and other state intact. Callers set the state the process
+    /// is left in.
+    fn cleanup(&self) {
         // Remove the tasks that were scheduled for the app from the
         // amount of work queue.
         let tasks_len = self.tasks.map_or(0, |tasks| tasks.len());
@@ -1982,9 +3019,6 @@ impl<C: 'static + Chip> Process<'_, C> {
         unsafe {
             self.grant_ptrs_reset();
         }
-
-        // Mark the app as stopped so the scheduler won't try to run it.
-        self.state.set(State::StoppedFaulted);
     }
 
     /// Get the current stack pointer as a pointer.
@@ -2013,6 +3047,28 @@ impl<C: 'static + Chip> Process<'_, C> {
             && buf_end_addr <= self.app_break.get()
     }
 
+    /// Read the pointer of grant `grant_num`, whether or not the process is
+    /// active.
+    // This is safe today, as MPU constraints ensure that `mem_end` will always
+    // be aligned on at least a word boundary. While this is unlikely to
+    // change, it should be more proactively enforced.
+    //
+    // TODO: https://github.com/tock/tock/issues/1739
+    #[allow(clippy::cast_ptr_alignment)]
+    fn read_grant_ptr(&self, grant_num: usize) -> Option<*mut u8> {
+        // Sanity check the argument
+        if grant_num >= self.kernel.get_grant_count_and_finalize() {
+            return None;
+        }
+
+        let grant_num = grant_num as isize;
+        let grant_pointer = unsafe {
+            let grant_pointer_array = self.mem_end() as *const *mut u8;
+            *grant_pointer_array.offset(-(grant_num + 1))
+        };
+        Some(grant_pointer)
+    }
+
     /// Reset all `grant_ptr`s to NULL.
     // This is safe today, as MPU constraints ensure that `mem_end` will always
     // be aligned on at least a word boundary. While this is unlikely to
@@ -2026,9 +3082,161 @@ impl<C: 'static + Chip> Process<'_, C> {
             let grant_num = grant_num as isize;
             let ctr_ptr = (self.mem_end() as *mut *mut usize).offset(-(grant_num + 1));
             write_volatile(ctr_ptr, ptr::null_mut());
+            write_volatile(self.grant_sizes().offset(-(grant_num + 1)), 0);
         }
     }
 
+    /// Add the `size` bytes of grant memory at `start` to the free list.
+    // This is safe today, as freed blocks are aligned by `grant_block_layout`
+    // to hold a `FreeGrantBlock`.
+    #[allow(clippy::cast_ptr_alignment)]
+    unsafe fn push_free_grant_block(&self, start: *mut u8, size: usize) {
+        ptr::write(
+            start as *mut FreeGrantBlock,
+            FreeGrantBlock {
+                next: self.grant_free_list.get(),
+                size: size,
+            },
+        );
+        self.grant_free_list.set(start as *mut FreeGrantBlock);
+    }
+
+    /// Remove the first free grant block that can hold `size` bytes aligned
+    /// to `align` from the free list, keeping what is left of the block in
+    /// the list.
+    // This is safe today, as freed blocks are aligned by `grant_block_layout`
+    // to hold a `FreeGrantBlock`.
+    #[allow(clippy::cast_ptr_alignment)]
+    unsafe fn take_free_grant_block(&self, size: usize, align: usize) -> Option<NonNull<u8>> {
+        let mut link = self.grant_free_list.as_ptr();
+        while !(*link).is_null() {
+            let block = *link;
+            if block as usize % align == 0 && (*block).size >= size {
+                if (*block).size == size {
+                    *link = (*block).next;
+                } else {
+                    let rest = (block as *mut u8).add(size) as *mut FreeGrantBlock;
+                    ptr::write(
+                        rest,
+                        FreeGrantBlock {
+                            next: (*block).next,
+                            size: (*block).size - size,
+                        },
+                    );
+                    *link = rest;
+                }
+                return NonNull::new(block as *mut u8);
+            }
+            link = &mut (*block).next;
+        }
+        None
+    }
+
+    /// Remove the free grant block starting at `start` from the free list,
+    /// if there is one, and return its size.
+    unsafe fn remove_free_grant_block(&self, start: *mut u8) -> Option<usize> {
+        let mut link = self.grant_free_list.as_ptr();
+        while !(*link).is_null() {
+            let block = *link;
+            if block as *mut u8 == start {
+                *link = (*block).next;
+                return Some((*block).size);
+            }
+            link = &mut (*block).next;
+        }
+        None
+    }
+
+    /// Pointer to the end of the array of grant sizes, which sits below the
+    /// grant pointers. The size of grant `n` is at offset `-(n + 1)`.
+    // This is safe today for the same reason as `grant_ptrs_reset`.
+    #[allow(clippy::cast_ptr_alignment)]
+    fn grant_sizes(&self) -> *mut usize {
+        let grant_ptrs_num = self.kernel.get_grant_count_and_finalize();
+        self.mem_end()
+            .wrapping_sub(grant_ptrs_num * mem::size_of::<*const usize>()) as *mut usize
+    }
+
+    /// Put this process in the fault state for `reason`, and respond to the
+    /// fault as the `FaultResponse` of the process asks.
+    fn fault(&self, reason: FaultReason) {
+        self.fault_reason.set(Some(reason));
+        self.state.set(State::Fault);
+
+        // Let the board dump the process before its memory is reset.
+        if let Some(client) = self.kernel.get_crash_dump_client() {
+            client.process_faulted(self);
+        }
+
+        match self.fault_response {
+            FaultResponse::Panic => {
+                // process faulted. Panic and print status
+                match reason {
+                    FaultReason::StackOverflow => {
+                        panic!("Process {} overflowed its stack", self.process_name)
+                    }
+                    FaultReason::Other => panic!("Process {} had a fault", self.process_name),
+                }
+            }
+            FaultResponse::Restart(_) => {
+                self.restart(State::StoppedFaulted);
+            }
+            FaultResponse::Stop => {
+                // This looks a lot like restart, except we just leave the app
+                // how it faulted and mark it as `StoppedFaulted`. By clearing
+                // all of the app's todo work it will not be scheduled, and
+                // clearing all of the grant regions will cause capsules to drop
+                // this app as well.
+                self.cleanup();
+                self.state.set(State::StoppedFaulted);
+            }
+        }
+    }
+
+    /// The bottom of the stack of the process, right above its retained
+    /// memory.
+    fn stack_bottom(&self) -> *const u8 {
+        self.mem_start().wrapping_add(self.retained_memory_size)
+    }
+
+    /// Place the canary words at the bottom of the stack.
+    // The canary is written with unaligned writes, so the bottom of the stack
+    // does not need to be word aligned.
+    #[allow(clippy::cast_ptr_alignment)]
+    fn place_stack_canary(&self) {
+        let canary = self.stack_bottom() as *mut u32;
+        for i in 0..STACK_GUARD_SIZE / 4 {
+            unsafe {
+                ptr::write_unaligned(canary.wrapping_add(i), STACK_CANARY);
+            }
+        }
+        self.stack_canary.set(true);
+    }
+
+    /// Whether the canary at the bottom of the stack is intact. This is always
+    /// the case if the kernel did not place one.
+    // The canary is read with unaligned reads, so the bottom of the stack
+    // does not need to be word aligned.
+    #[allow(clippy::cast_ptr_alignment)]
+    fn stack_canary_intact(&self) -> bool {
+        let canary = self.stack_bottom() as *const u32;
+        !self.stack_canary.get()
+            || (0..STACK_GUARD_SIZE / 4)
+                .all(|i| unsafe { ptr::read_unaligned(canary.wrapping_add(i)) } == STACK_CANARY)
+    }
+
+    /// Whether the stack of the process overflowed: its stack pointer is below
+    /// the bottom of the stack or in the guard there, or it overwrote the
+    /// canary.
+    fn stack_overflowed(&self) -> bool {
+        let limit = if self.stack_canary.get() {
+            self.stack_bottom().wrapping_add(STACK_GUARD_SIZE)
+        } else {
+            self.stack_bottom()
+        };
+        self.current_stack_pointer.get() < limit || !self.stack_canary_intact()
+    }
+
     fn debug_set_max_stack_depth(&self) {
         self.debug.map(|debug| {
             if self.current_stack_pointer.get() < debug.min_stack_pointer {
@@ -2049,6 +3257,24 @@ impl<C: 'static + Chip> Process<'_, C> {
     /// explicitly exits.
     fn is_active(&self) -> bool {
         let current_state = self.state.get();
-        current_state != State::StoppedFaulted && current_state != State::Fault
+        current_state != State::StoppedFaulted
+            && current_state != State::Fault
+            && current_state != State::Terminated
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::retained_region_size;
+
+    #[test]
+    fn test_retained_region_size() {
+        assert_eq!(retained_region_size(0), Some(0));
+        assert_eq!(retained_region_size(1), Some(8));
+        assert_eq!(retained_region_size(16), Some(16));
+        assert_eq!(retained_region_size(usize::MAX - 7), Some(usize::MAX - 7));
+        // Rounding up the largest sizes overflows.
+        assert_eq!(retained_region_size(usize::MAX - 6), None);
+        assert_eq!(retained_region_size(usize::MAX), None);
     }
 }
@@ -0,0 +1,316 @@
+//! Support for loading processes while the kernel is running.
+//!
+//! `load_processes()` creates processes for the apps that are in flash when the
+//! board boots. `ProcessLoader` lets a capsule add apps afterwards, without a
+//! reboot. It reports where in app flash the next app can be written, validates
+//! the TBF header of an app before it is written, and, once the app is in
+//! flash, creates a process for it in an empty slot of the processes array.
+//!
+//! Processes loaded at runtime get their memory from a region the board sets
+//! aside for this purpose, separate from the memory used by
+//! `load_processes()`.
+//!
+//! Processes that have exited or been stopped after a fault can be unloaded.
+//! This frees their slot in the processes array, and their memory is reused
+//! for processes loaded later. Processes that have exited are unloaded
+//! automatically before a new app is loaded. The app itself stays in flash, so
+//! it is loaded again when the board restarts.
+//!
+//! The loader remembers which block of memory it gave the process in each
+//! slot, including any padding the MPU needed before the start of the process
+//! memory, so it needs a buffer with one `LoadedProcessMemory` for each slot in
+//! the processes array.
+
+use core::cell::Cell;
+use core::convert::TryInto;
+
+use crate::callback::AppId;
+use crate::capabilities::ProcessManagementCapability;
+use crate::config;
+use crate::debug;
+use crate::platform::Chip;
+use crate::process::{FaultResponse, Process, ProcessLoadError, State};
+use crate::returncode::ReturnCode;
+use crate::sched::Kernel;
+use crate::tbfheader;
+
+/// Interface a capsule uses to load new apps at runtime.
+pub trait DynamicProcessLoader {
+    /// Check the TBF header at the start of `app`, which must contain at least
+    /// the entire header, and check that the app can be loaded. Returns the
+    /// total length of the app in bytes.
+    fn validate_tbf_header(&self, app: &[u8]) -> Result<usize, ProcessLoadError>;
+
+    /// Returns the unused region of app flash after the last app. A new app
+    /// must be written at the start of this region.
+    fn free_app_flash(&self) -> &'static [u8];
+
+    /// Create a process for the app that has been written to app flash
+    /// starting at `app_address`. Returns the identifier of the new process, or
+    /// `None` if the app is not enabled and therefore was not started.
+    fn load_process(&self, app_address: usize) -> Result<Option<AppId>, ProcessLoadError>;
+
+    /// Remove the process `app` from the processes array so that its slot and
+    /// memory can be used for a new process. Only processes that have exited
+    /// or were stopped after a fault can be unloaded. Returns `EBUSY` if the
+    /// process is still active, or `EINVAL` if it does not exist.
+    fn unload_process(&self, app: AppId) -> ReturnCode;
+}
+
+/// How many separate regions of free memory the loader keeps track of.
+const MAX_FREE_MEMORY_BLOCKS: usize = 4;
+
+/// The memory the `ProcessLoader` gave the process in one slot of the
+/// processes array.
+#[derive(Default)]
+pub struct LoadedProcessMemory {
+    /// Start address and length of the memory block the process was created
+    /// in, or `None` if the loader did not create the process in the slot.
+    block: Cell<Option<(usize, usize)>>,
+}
+
+/// Loads apps written to app flash into empty process slots.
+pub struct ProcessLoader<C: 'static + Chip> {
+    kernel: &'static Kernel,
+    chip: &'static C,
+
+    /// All of the flash reserved for apps.
+    app_flash: &'static [u8],
+
+    /// Memory not used by any process, as (start address, length) pairs.
+    /// Adjacent blocks are always merged.
+    free_memory: [Cell<Option<(usize, usize)>>; MAX_FREE_MEMORY_BLOCKS],
+
+    /// The memory of the processes the loader created, indexed by the slot of
+    /// the process in the processes array.
+    process_memory: &'static [LoadedProcessMemory],
+
+    fault_response: FaultResponse,
+}
+
+impl<C: 'static + Chip> ProcessLoader<C> {
+    /// Create a process loader. `process_memory` must contain one entry for
+    /// every slot in the processes array of the kernel. Processes are not
+    /// loaded into slots without an entry.
+    pub fn new(
+        kernel: &'static Kernel,
+        chip: &'static C,
+        app_flash: &'static [u8],
+        app_memory: &'static mut [u8],
+        process_memory: &'static [LoadedProcessMemory],
+        fault_response: FaultResponse,
+        _capability: &dyn ProcessManagementCapability,
+    ) -> ProcessLoader<C> {
+        let free_memory: [Cell<Option<(usize, usize)>>; MAX_FREE_MEMORY_BLOCKS] =
+            Default::default();
+        free_memory[0].set(Some((app_memory.as_mut_ptr() as usize, app_memory.len())));
+        ProcessLoader {
+            kernel: kernel,
+            chip: chip,
+            app_flash: app_flash,
+            free_memory: free_memory,
+            process_memory: process_memory,
+            fault_response: fault_response,
+        }
+    }
+
+    /// Returns the index of the first empty slot in the processes array.
+    fn find_free_slot(&self) -> Option<usize> {
+        let num_procs = core::cmp::min(self.kernel.num_process_slots(), self.process_memory.len());
+        (0..num_procs).find(|&index| self.kernel.get_process(index).is_none())
+    }
+
+    /// Unload all processes that have exited to make room for a new one.
+    fn unload_terminated_processes(&self) {
+        for index in 0..self.kernel.num_process_slots() {
+            let terminated = self
+                .kernel
+                .get_process(index)
+                .filter(|p| p.get_state() == State::Terminated)
+                .map(|p| p.appid());
+            if let Some(app) = terminated {
+                self.unload_process(app);
+            }
+        }
+    }
+
+    /// Add a region of memory that is no longer used to the free memory,
+    /// merging it with the blocks next to it.
+    fn release_memory(&self, start: usize, length: usize) {
+        let mut start = start;
+        let mut end = start + length;
+        for block in self.free_memory.iter() {
+            if let Some((block_start, block_length)) = block.get() {
+                if block_start + block_length == start {
+                    start = block_start;
+                    block.set(None);
+                } else if block_start == end {
+                    end = block_start + block_length;
+                    block.set(None);
+                }
+            }
+        }
+
+        match self.free_memory.iter().find(|block| block.get().is_none()) {
+            Some(block) => block.set(Some((start, end - start))),
+            None => {
+                if config::CONFIG.debug_load_processes {
+                    debug!(
+                        "ProcessLoader: too many free memory blocks, dropping [{:#010X}:{:#010X}]",
+                        start, end
+                    );
+                }
+            }
+        }
+    }
+}
+
+impl<C: 'static + Chip> DynamicProcessLoader for ProcessLoader<C> {
+    fn validate_tbf_header(&self, app: &[u8]) -> Result<usize, ProcessLoadError> {
+        self.unload_terminated_processes();
+        let (tbf_header, app_length) = tbfheader::parse_and_validate_tbf_header(app)?;
+
+        let free_flash = self.free_app_flash();
+        if app_length as usize > free_flash.len() {
+            return Err(ProcessLoadError::NotEnoughFlash);
+        }
+
+        // An app compiled for a fixed flash address can only be loaded if it
+        // would end up at that address.
+        if let Some(fixed_flash_start) = tbf_header.get_fixed_address_flash() {
+            let actual_address = free_flash.as_ptr() as u32 + tbf_header.get_protected_size();
+            if actual_address != fixed_flash_start {
+                return Err(ProcessLoadError::IncorrectFlashAddress {
+                    actual_address,
+                    expected_address: fixed_flash_start,
+                });
+            }
+        }
+
+        if self.find_free_slot().is_none() {
+            return Err(ProcessLoadError::NoFreeProcessSlot);
+        }
+
+        Ok(app_length as usize)
+    }
+
+    fn free_app_flash(&self) -> &'static [u8] {
+        // Walk the linked list of apps until we find something that is not a
+        // valid TBF header.
+        let mut remaining = self.app_flash;
+        while let Some(header) = remaining.get(0..8) {
+            let app_length = match header
+                .try_into()
+                .map(|header| tbfheader::parse_tbf_header_lengths(header))
+            {
+                Ok(Ok((_, _, app_length))) if app_length > 0 => app_length as usize,
+                _ => break,
+            };
+            remaining = remaining.get(app_length..).unwrap_or(&[]);
+        }
+        remaining
+    }
+
+    fn load_process(&self, app_address: usize) -> Result<Option<AppId>, ProcessLoadError> {
+        self.unload_terminated_processes();
+        let app_offset = app_address.wrapping_sub(self.app_flash.as_ptr() as usize);
+        let remaining_flash = self
+            .app_flash
+            .get(app_offset..)
+            .ok_or(ProcessLoadError::NotEnoughFlash)?;
+        let (version, header_length, app_length) = tbfheader::parse_tbf_header_lengths(
+            remaining_flash
+                .get(0..8)
+                .ok_or(ProcessLoadError::NotEnoughFlash)?
+                .try_into()
+                .or(Err(ProcessLoadError::InternalError))?,
+        )?;
+        let app_flash = remaining_flash
+            .get(0..app_length as usize)
+            .ok_or(ProcessLoadError::NotEnoughFlash)?;
+        let index = self
+            .find_free_slot()
+            .ok_or(ProcessLoadError::NoFreeProcessSlot)?;
+
+        // Try the free memory blocks in turn until the process fits in one.
+        for block in self.free_memory.iter() {
+            let (memory_start, memory_length) = match block.get() {
+                Some(free) => free,
+                None => continue,
+            };
+            let result = unsafe {
+                Process::create(
+                    self.kernel,
+                    self.chip,
+                    app_flash,
+                    header_length as usize,
+                    version,
+                    memory_start as *mut u8,
+                    memory_length,
+                    self.fault_response,
+                    index,
+                )
+            };
+
+            match result {
+                Ok((process, memory_offset)) => {
+                    // Keep the memory the new process did not use for the
+                    // next one.
+                    block.set(if memory_offset < memory_length {
+                        Some((memory_start + memory_offset, memory_length - memory_offset))
+                    } else {
+                        None
+                    });
+                    self.process_memory[index]
+                        .block
+                        .set(process.map(|_| (memory_start, memory_offset)));
+
+                    if config::CONFIG.debug_load_processes {
+                        debug!(
+                            "Loaded process[{}] from flash=[{:#010X}:{:#010X}] = {:?}",
+                            index,
+                            app_flash.as_ptr() as usize,
+                            app_flash.as_ptr() as usize + app_flash.len(),
+                            process.map(|p| p.get_process_name())
+                        );
+                    }
+
+                    self.kernel.set_process(index, process);
+                    return Ok(process.map(|p| p.appid()));
+                }
+                Err(ProcessLoadError::NotEnoughMemory)
+                | Err(ProcessLoadError::MemoryAddressMismatch { .. }) => continue,
+                Err(error) => return Err(error),
+            }
+        }
+
+        Err(ProcessLoadError::NotEnoughMemory)
+    }
+
+    fn unload_process(&self, app: AppId) -> ReturnCode {
+        let process = match self.kernel.get_process(app.index) {
+            Some(process) if process.appid() == app => process,
+            _ => return ReturnCode::EINVAL,
+        };
+        match process.get_state() {
+            State::Terminated => {}
+            State::StoppedFaulted => process.terminate(None),
+            _ => return ReturnCode::EBUSY,
+        }
+
+        // Nothing refers to the process once its slot is empty, so all of its
+        // memory, including the process struct, can be reused. The memory of
+        // processes loaded at boot is reused as well.
+        self.kernel.set_process(app.index, None);
+        let (start, length) = self
+            .process_memory
+            .get(app.index)
+            .and_then(|memory| memory.block.take())
+            .unwrap_or((
+                process.mem_start() as usize,
+                process.mem_end() as usize - process.mem_start() as usize,
+            ));
+        self.release_memory(start, length);
+        ReturnCode::SUCCESS
+    }
+}
@@ -8,30 +8,143 @@ use crate::capabilities;
 use crate::common::cells::NumericCellExt;
 use crate::common::dynamic_deferred_call::DynamicDeferredCall;
 use crate::config;
+use crate::crash_dump::CrashDumpClient;
+use crate::credentials::CredentialsPolicy;
 use crate::debug;
 use crate::grant::Grant;
+use crate::hil::time::AlarmDeadline;
 use crate::ipc;
 use crate::memop;
+use crate::panic_record;
 use crate::platform::mpu::MPU;
 use crate::platform::systick::SysTick;
 use crate::platform::{Chip, Platform};
+use crate::power_manager::PowerManager;
 use crate::process::{self, Task};
 use crate::returncode::ReturnCode;
 use crate::syscall::{ContextSwitchReason, Syscall};
+use crate::syscall_trace::SyscallTracer;
+
+pub(crate) mod cooperative;
+pub(crate) mod edf;
+pub(crate) mod mlfq;
+pub(crate) mod priority;
+pub(crate) mod round_robin;
 
-/// The time a process is permitted to run before being pre-empted
-const KERNEL_TICK_DURATION_US: u32 = 10000;
 /// Skip re-scheduling a process if its quanta is nearly exhausted
 const MIN_QUANTA_THRESHOLD_US: u32 = 500;
 
+/// Trait which any scheduler must implement.
+///
+/// The kernel main loop asks the scheduler which process to run next and for
+/// how long, and then informs the scheduler why that process stopped
+/// executing. Boards choose the scheduler they want in `main.rs` and pass it to
+/// `Kernel::kernel_loop()`.
+pub trait Scheduler<C: Chip> {
+    /// Decide which process to run next.
+    ///
+    /// The scheduler must decide whether to run a process, and if so, which
+    /// one. If the scheduler chooses not to run a process, it can request that
+    /// the chip enter sleep mode.
+    ///
+    /// If the scheduler selects a process to run it must provide its `AppId`
+    /// and an optional timeslice length in microseconds to provide to that
+    /// process. If the timeslice is `None`, the process will be run
+    /// cooperatively (i.e. without preemption). Otherwise the process will run
+    /// with a timeslice set to the specified length.
+    fn next(&self, kernel: &Kernel) -> SchedulingDecision;
+
+    /// Inform the scheduler of why the last process stopped executing, and how
+    /// long it executed for. Notably, `execution_time_us` will be `None` if
+    /// the scheduler requested this process be run cooperatively.
+    fn result(&self, result: StoppedExecutingReason, execution_time_us: Option<u32>);
+
+    /// Tell the scheduler to execute kernel work such as interrupt bottom
+    /// halves and dynamic deferred calls. Most schedulers will use this default
+    /// implementation, but schedulers which at times wish to defer interrupt
+    /// handling will reimplement it.
+    unsafe fn execute_kernel_work(&self, chip: &C) {
+        chip.service_pending_interrupts();
+        DynamicDeferredCall::call_global_instance_while(|| !chip.has_pending_interrupts());
+    }
+
+    /// Ask the scheduler whether to take a break from executing userspace
+    /// processes to handle kernel tasks. Most schedulers will use this default
+    /// implementation, which always prioritizes kernel work.
+    unsafe fn do_kernel_work_now(&self, chip: &C) -> bool {
+        chip.has_pending_interrupts()
+            || DynamicDeferredCall::global_instance_calls_pending().unwrap_or(false)
+    }
+
+    /// Ask the scheduler whether to continue trying to execute a process.
+    ///
+    /// Once a process is scheduled the kernel will try to execute it until it
+    /// has no more work to do or exhausts its timeslice. The kernel will call
+    /// this function before every loop to check with the scheduler if it wants
+    /// to continue trying to execute this process.
+    ///
+    /// Most schedulers will use this default implementation, which causes
+    /// `do_process()` to return if there are interrupts or deferred calls that
+    /// need to be serviced. If this returns `false`, then `do_process()` will
+    /// exit with `StoppedExecutingReason::KernelPreemption`.
+    unsafe fn continue_process(&self, _appid: AppId, chip: &C) -> bool {
+        !(chip.has_pending_interrupts()
+            || DynamicDeferredCall::global_instance_calls_pending().unwrap_or(false))
+    }
+}
+
+/// Enum representing the actions the scheduler can request in each call to
+/// `scheduler.next()`.
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub enum SchedulingDecision {
+    /// Tell the kernel to run the specified process with the passed timeslice.
+    /// If `None` is passed as a timeslice, the process will be run
+    /// cooperatively.
+    RunProcess((AppId, Option<u32>)),
+
+    /// Tell the kernel to go to sleep. If the scheduler asks the kernel to
+    /// sleep while kernel work is pending, the kernel will not sleep and will
+    /// instead restart the main loop and call `next()` again.
+    TrySleep,
+}
+
+/// Why a process stopped executing and control returned to the scheduler.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum StoppedExecutingReason {
+    /// The process returned because it is no longer ready to run.
+    NoWorkLeft,
+
+    /// The process faulted, and the board restart policy was configured such
+    /// that it was not restarted and there was not a kernel panic.
+    StoppedFaulted,
+
+    /// The kernel stopped the process.
+    Stopped,
+
+    /// The process exited or was terminated by the kernel.
+    Terminated,
+
+    /// The process was preempted because its timeslice expired.
+    TimesliceExpired,
+
+    /// The process returned because it was preempted by the kernel. This can
+    /// mean that kernel work became ready (most likely because an interrupt
+    /// fired and the kernel needs to execute the bottom half of the
+    /// interrupt), or because the scheduler no longer wants to execute that
+    /// process.
+    KernelPreemption,
+}
+
 /// Main object for the kernel. Each board will need to create one.
 pub struct Kernel {
     /// How many "to-do" items exist at any given time. These include
     /// outstanding callbacks and processes in the Running state.
     work: Cell<usize>,
 
-    /// This holds a pointer to the static array of Process pointers.
-    processes: &'static [Option<&'static dyn process::ProcessType>],
+    /// This holds a pointer to the static array of Process pointers. Slots
+    /// change while the kernel runs when processes are loaded or unloaded, so
+    /// they are only accessed through cells.
+    processes: &'static [Cell<Option<&'static dyn process::ProcessType>>],
 
     /// A counter which keeps track of how many process identifiers have been
     /// created. This is used to create new unique identifiers for processes.
@@ -47,16 +160,204 @@ pub struct Kernel {
     /// created and the data structures for grants have already been
     /// established.
     grants_finalized: Cell<bool>,
+
+    /// Board policy applied to the scheduling parameters processes request in
+    /// their TBF headers.
+    scheduling_policy: Cell<Option<&'static dyn process::SchedulingPolicy>>,
+
+    /// Board policy deciding which apps are allowed to run based on the
+    /// credentials in their TBF footers.
+    credentials_policy: Cell<Option<CredentialsPolicy>>,
+
+    /// Whether apps without a permissions element in their TBF header are
+    /// denied every driver instead of allowed every driver.
+    require_syscall_permissions: Cell<bool>,
+
+    /// Where the system calls of processes with tracing enabled are recorded.
+    syscall_tracer: Cell<Option<&'static dyn SyscallTracer>>,
+
+    /// Limits on the kernel resources each process can use.
+    process_quotas: Cell<process::ProcessQuotas>,
+
+    /// Where processes that fault are dumped.
+    crash_dump_client: Cell<Option<&'static dyn CrashDumpClient>>,
+
+    /// When the next alarm fires, which tells the chip how long it can sleep.
+    alarm_deadline: Cell<Option<&'static dyn AlarmDeadline>>,
+
+    /// The managed clocks of the board.
+    power_manager: Cell<Option<&'static PowerManager<'static>>>,
 }
 
 impl Kernel {
-    pub fn new(processes: &'static [Option<&'static dyn process::ProcessType>]) -> Kernel {
+    /// Create the kernel. The kernel takes over `processes`, which must not be
+    /// accessed directly afterwards: processes are added to it with
+    /// `load_processes()` or a `ProcessLoader`.
+    pub fn new(processes: &'static mut [Option<&'static dyn process::ProcessType>]) -> Kernel {
+        // Pick up the record of a panic before this boot.
+        unsafe {
+            panic_record::load();
+        }
+
         Kernel {
             work: Cell::new(0),
-            processes: processes,
+            processes: Cell::from_mut(processes).as_slice_of_cells(),
             process_identifier_max: Cell::new(0),
             grant_counter: Cell::new(0),
             grants_finalized: Cell::new(false),
+            scheduling_policy: Cell::new(None),
+            credentials_policy: Cell::new(None),
+            require_syscall_permissions: Cell::new(false),
+            syscall_tracer: Cell::new(None),
+            process_quotas: Cell::new(process::ProcessQuotas::default()),
+            crash_dump_client: Cell::new(None),
+            alarm_deadline: Cell::new(None),
+            power_manager: Cell::new(None),
+        }
+    }
+
+    /// Set the policy used to decide the scheduling parameters of processes.
+    /// This must be set before processes are loaded. Without a policy,
+    /// processes get the parameters they request in their TBF headers.
+    pub fn set_scheduling_policy(
+        &self,
+        policy: &'static dyn process::SchedulingPolicy,
+        _capability: &dyn capabilities::ProcessManagementCapability,
+    ) {
+        self.scheduling_policy.set(Some(policy));
+    }
+
+    /// Get the scheduling policy set by the board, if any.
+    pub(crate) fn get_scheduling_policy(&self) -> Option<&'static dyn process::SchedulingPolicy> {
+        self.scheduling_policy.get()
+    }
+
+    /// Set the policy used to decide which apps may run based on their
+    /// credentials. This must be set before processes are loaded. Without a
+    /// policy, all apps run.
+    pub fn set_credentials_policy(
+        &self,
+        policy: CredentialsPolicy,
+        _capability: &dyn capabilities::ProcessManagementCapability,
+    ) {
+        self.credentials_policy.set(Some(policy));
+    }
+
+    /// Get the credentials policy set by the board, if any.
+    pub(crate) fn get_credentials_policy(&self) -> Option<CredentialsPolicy> {
+        self.credentials_policy.get()
+    }
+
+    /// Deny apps whose TBF header does not list their permissions all
+    /// `subscribe`, `command` and `allow` system calls. Otherwise such apps
+    /// may use every driver.
+    ///
+    /// Apps list their own permissions, so this only keeps apps from using
+    /// drivers they were not meant to if the board also sets a
+    /// `CredentialsPolicy::RequireSignature`, whose signatures cover the TBF
+    /// header.
+    pub fn require_syscall_permissions(
+        &self,
+        _capability: &dyn capabilities::ProcessManagementCapability,
+    ) {
+        self.require_syscall_permissions.set(true);
+    }
+
+    /// Whether apps without permissions in their TBF header are denied all
+    /// drivers.
+    pub(crate) fn syscall_permissions_required(&self) -> bool {
+        self.require_syscall_permissions.get()
+    }
+
+    /// Set the limits on the grant memory, queued tasks and allowed buffers
+    /// of each process. Without quotas, processes are only limited by their
+    /// memory.
+    pub fn set_process_quotas(
+        &self,
+        quotas: process::ProcessQuotas,
+        _capability: &dyn capabilities::ProcessManagementCapability,
+    ) {
+        self.process_quotas.set(quotas);
+    }
+
+    /// Get the quotas set by the board.
+    pub(crate) fn get_process_quotas(&self) -> process::ProcessQuotas {
+        self.process_quotas.get()
+    }
+
+    /// Set the client that dumps processes when they fault.
+    pub fn set_crash_dump_client(
+        &self,
+        client: &'static dyn CrashDumpClient,
+        _capability: &dyn capabilities::ProcessManagementCapability,
+    ) {
+        self.crash_dump_client.set(Some(client));
+    }
+
+    /// Get the crash dump client set by the board, if any.
+    pub(crate) fn get_crash_dump_client(&self) -> Option<&'static dyn CrashDumpClient> {
+        self.crash_dump_client.get()
+    }
+
+    /// Set the alarm that tells the kernel when it must wake up next. When
+    /// there is nothing to do, the kernel passes the time until its deadline
+    /// to `Chip::sleep_until()`, so that the chip can sleep more deeply when
+    /// the deadline is far away. Without it, the kernel calls `Chip::sleep()`.
+    pub fn set_alarm_deadline(
+        &self,
+        deadline: &'static dyn AlarmDeadline,
+        _capability: &dyn capabilities::MainLoopCapability,
+    ) {
+        self.alarm_deadline.set(Some(deadline));
+    }
+
+    /// Set where the system calls of processes with tracing enabled are
+    /// recorded. Without a tracer, enabling tracing has no effect.
+    pub fn set_syscall_tracer(
+        &self,
+        tracer: &'static dyn SyscallTracer,
+        _capability: &dyn capabilities::ProcessManagementCapability,
+    ) {
+        self.syscall_tracer.set(Some(tracer));
+    }
+
+    /// Get the tracer set by the board, if any.
+    pub fn get_syscall_tracer(
+        &self,
+        _capability: &dyn capabilities::ProcessManagementCapability,
+    ) -> Option<&'static dyn SyscallTracer> {
+        self.syscall_tracer.get()
+    }
+
+    /// Set the power manager that lists the managed clocks of the board.
+    pub fn set_power_manager(
+        &self,
+        power_manager: &'static PowerManager<'static>,
+        _capability: &dyn capabilities::ProcessManagementCapability,
+    ) {
+        self.power_manager.set(Some(power_manager));
+    }
+
+    /// Get the power manager set by the board, if any.
+    pub fn get_power_manager(
+        &self,
+        _capability: &dyn capabilities::ProcessManagementCapability,
+    ) -> Option<&'static PowerManager<'static>> {
+        self.power_manager.get()
+    }
+
+    /// Record a system call of `process` in the syscall trace, if tracing is
+    /// enabled for the process.
+    fn trace_syscall(
+        &self,
+        process: &dyn process::ProcessType,
+        syscall: &Syscall,
+        result: ReturnCode,
+    ) {
+        if process.syscall_tracing() {
+            if let Some(tracer) = self.syscall_tracer.get() {
+                tracer.record(process.appid(), syscall, result);
+            }
         }
     }
 
@@ -73,7 +374,7 @@ impl Kernel {
 
     /// Helper function for determining if we should service processes or go to
     /// sleep.
-    fn processes_blocked(&self) -> bool {
+    pub(crate) fn processes_blocked(&self) -> bool {
         self.work.get() == 0
     }
 
@@ -104,7 +405,7 @@ impl Kernel {
             .map_or(None, |process_entry| {
                 // Check if there is any process state here, or if the entry is
                 // `None`.
-                process_entry.map_or(None, |process| {
+                process_entry.get().map_or(None, |process| {
                     // Check that the process stored here matches the identifier
                     // in the `appid`.
                     if process.appid() == appid {
@@ -124,9 +425,9 @@ impl Kernel {
         F: Fn(&dyn process::ProcessType),
     {
         for process in self.processes.iter() {
-            match process {
+            match process.get() {
                 Some(p) => {
-                    closure(*p);
+                    closure(p);
                 }
                 None => {}
             }
@@ -137,13 +438,15 @@ impl Kernel {
     pub(crate) fn get_process_iter(
         &self,
     ) -> core::iter::FilterMap<
-        core::slice::Iter<Option<&dyn process::ProcessType>>,
-        fn(&Option<&'static dyn process::ProcessType>) -> Option<&'static dyn process::ProcessType>,
+        core::slice::Iter<Cell<Option<&'static dyn process::ProcessType>>>,
+        fn(
+            &Cell<Option<&'static dyn process::ProcessType>>,
+        ) -> Option<&'static dyn process::ProcessType>,
     > {
         fn keep_some(
-            &x: &Option<&'static dyn process::ProcessType>,
+            x: &Cell<Option<&'static dyn process::ProcessType>>,
         ) -> Option<&'static dyn process::ProcessType> {
-            x
+            x.get()
         }
         self.processes.iter().filter_map(keep_some)
     }
@@ -162,9 +465,9 @@ impl Kernel {
         F: Fn(&dyn process::ProcessType),
     {
         for process in self.processes.iter() {
-            match process {
+            match process.get() {
                 Some(p) => {
-                    closure(*p);
+                    closure(p);
                 }
                 None => {}
             }
@@ -180,9 +483,9 @@ impl Kernel {
         F: Fn(&dyn process::ProcessType) -> ReturnCode,
     {
         for process in self.processes.iter() {
-            match process {
+            match process.get() {
                 Some(p) => {
-                    let ret = closure(*p);
+                    let ret = closure(p);
                     if ret != ReturnCode::FAIL {
                         return ret;
                     }
@@ -198,8 +501,8 @@ impl Kernel {
     /// as from userspace) and needs to be expanded to a full `AppId` for use
     /// with other APIs.
     pub(crate) fn lookup_app_by_identifier(&self, identifier: usize) -> Option<AppId> {
-        self.processes.iter().find_map(|&p| {
-            p.map_or(None, |p2| {
+        self.processes.iter().find_map(|p| {
+            p.get().map_or(None, |p2| {
                 if p2.appid().id() == identifier {
                     Some(p2.appid())
                 } else {
@@ -217,7 +520,8 @@ impl Kernel {
     /// verify that the referenced app is still at the correct index.
     pub(crate) fn appid_is_valid(&self, appid: &AppId) -> bool {
         self.processes.get(appid.index).map_or(false, |p| {
-            p.map_or(false, |process| process.appid().id() == appid.id())
+            p.get()
+                .map_or(false, |process| process.appid().id() == appid.id())
         })
     }
 
@@ -258,6 +562,40 @@ impl Kernel {
         self.grant_counter.get()
     }
 
+    /// The number of slots in the processes array.
+    pub(crate) fn num_process_slots(&self) -> usize {
+        self.processes.len()
+    }
+
+    /// The process in slot `index` of the processes array, if there is one.
+    pub(crate) fn get_process(&self, index: usize) -> Option<&'static dyn process::ProcessType> {
+        self.processes.get(index).and_then(|slot| slot.get())
+    }
+
+    /// The process in slot `index` of the processes array, if there is one.
+    ///
+    /// This is functionally the same as `get_process()`, but this method is
+    /// available outside the kernel crate and requires a
+    /// `ProcessManagementCapability` to use.
+    pub fn get_process_capability(
+        &self,
+        index: usize,
+        _capability: &dyn capabilities::ProcessManagementCapability,
+    ) -> Option<&'static dyn process::ProcessType> {
+        self.get_process(index)
+    }
+
+    /// Put `process` in slot `index` of the processes array, or empty the
+    /// slot with `None`. Only the process loaders change slots, and they
+    /// require the `ProcessManagementCapability`.
+    pub(crate) fn set_process(
+        &self,
+        index: usize,
+        process: Option<&'static dyn process::ProcessType>,
+    ) {
+        self.processes.get(index).map(|slot| slot.set(process));
+    }
+
     /// Create a new unique identifier for a process and return the identifier.
     ///
     /// Typically we just choose a larger number than we have used for any process
@@ -278,69 +616,131 @@ impl Kernel {
     /// apps.
     pub fn hardfault_all_apps<C: capabilities::ProcessManagementCapability>(&self, _c: &C) {
         for p in self.processes.iter() {
-            p.map(|process| {
+            p.get().map(|process| {
                 process.set_fault_state();
             });
         }
     }
 
-    /// Main loop.
-    pub fn kernel_loop<P: Platform, C: Chip>(
+    /// Run one iteration of the core kernel loop: do kernel work if the
+    /// scheduler asks for it, otherwise run the next process, or sleep if
+    /// there is nothing to run.
+    ///
+    /// `kernel_loop()` calls this forever. It is public so that tests can run
+    /// the kernel for a bounded number of iterations.
+    pub fn kernel_loop_operation<P: Platform, C: Chip, SC: ?Sized + Scheduler<C>>(
         &'static self,
         platform: &P,
         chip: &C,
         ipc: Option<&ipc::IPC>,
+        scheduler: &SC,
         _capability: &dyn capabilities::MainLoopCapability,
     ) {
-        loop {
-            unsafe {
-                chip.service_pending_interrupts();
-                DynamicDeferredCall::call_global_instance_while(|| !chip.has_pending_interrupts());
-
-                for p in self.processes.iter() {
-                    p.map(|process| {
-                        self.do_process(platform, chip, process, ipc);
-                    });
-                    if chip.has_pending_interrupts()
-                        || DynamicDeferredCall::global_instance_calls_pending().unwrap_or(false)
-                    {
-                        break;
+        unsafe {
+            // Ask the scheduler if we should do tasks inside of the kernel,
+            // such as handle interrupts. A scheduler may want to
+            // prioritize processes instead, or there may be no kernel work
+            // to do.
+            if scheduler.do_kernel_work_now(chip) {
+                scheduler.execute_kernel_work(chip);
+            } else {
+                match scheduler.next(self) {
+                    SchedulingDecision::RunProcess((appid, timeslice_us)) => {
+                        self.process_map_or((), appid, |process| {
+                            let (reason, time_executed) = self.do_process(
+                                platform,
+                                chip,
+                                scheduler,
+                                process,
+                                ipc,
+                                timeslice_us,
+                            );
+                            scheduler.result(reason, time_executed);
+                        });
+                    }
+                    SchedulingDecision::TrySleep => {
+                        chip.atomic(|| {
+                            // Cannot sleep if interrupts are pending, as on
+                            // most platforms unhandled interrupts will wake
+                            // the device. Also, if the only pending
+                            // interrupt occurred after the scheduler
+                            // decided to put the chip to sleep, but before
+                            // this atomic section starts, the interrupt
+                            // will not be serviced and the chip will never
+                            // wake from sleep.
+                            if !chip.has_pending_interrupts()
+                                && !DynamicDeferredCall::global_instance_calls_pending()
+                                    .unwrap_or(false)
+                            {
+                                match self.alarm_deadline.get() {
+                                    Some(deadline) => {
+                                        chip.sleep_until(deadline.us_until_deadline())
+                                    }
+                                    None => chip.sleep(),
+                                }
+                            }
+                        });
                     }
                 }
+            }
+        };
+    }
 
-                chip.atomic(|| {
-                    if !chip.has_pending_interrupts()
-                        && !DynamicDeferredCall::global_instance_calls_pending().unwrap_or(false)
-                        && self.processes_blocked()
-                    {
-                        chip.sleep();
-                    }
-                });
-            };
+    /// Main loop.
+    ///
+    /// The provided `scheduler` decides which process runs next and for how
+    /// long.
+    pub fn kernel_loop<P: Platform, C: Chip, SC: ?Sized + Scheduler<C>>(
+        &'static self,
+        platform: &P,
+        chip: &C,
+        ipc: Option<&ipc::IPC>,
+        scheduler: &SC,
+        capability: &dyn capabilities::MainLoopCapability,
+    ) {
+        loop {
+            self.kernel_loop_operation(platform, chip, ipc, scheduler, capability);
         }
     }
 
-    unsafe fn do_process<P: Platform, C: Chip>(
+    /// Transfer control to a process until it runs out of work to do, its
+    /// timeslice expires, or the scheduler asks for it to be preempted.
+    ///
+    /// Returns why the process stopped executing and, if the process ran with
+    /// a timeslice, how many microseconds of that timeslice it used.
+    unsafe fn do_process<P: Platform, C: Chip, SC: ?Sized + Scheduler<C>>(
         &self,
         platform: &P,
         chip: &C,
+        scheduler: &SC,
         process: &dyn process::ProcessType,
         ipc: Option<&crate::ipc::IPC>,
-    ) {
-        let systick = chip.systick();
+        timeslice_us: Option<u32>,
+    ) -> (StoppedExecutingReason, Option<u32>) {
+        // A process run cooperatively uses the dummy `SysTick`, which never
+        // expires and therefore never preempts the process.
+        let systick: &dyn SysTick = match timeslice_us {
+            Some(_) => chip.systick(),
+            None => &(),
+        };
         systick.reset();
-        systick.set_timer(KERNEL_TICK_DURATION_US);
+        timeslice_us.map(|timeslice| systick.set_timer(timeslice));
         systick.enable(false);
+        panic_record::set_current_process(Some(process.get_process_name()));
+
+        // Track why the process is no longer executing so that we can inform
+        // the scheduler.
+        let mut return_reason = StoppedExecutingReason::NoWorkLeft;
 
         loop {
-            if chip.has_pending_interrupts()
-                || DynamicDeferredCall::global_instance_calls_pending().unwrap_or(false)
-            {
+            if !scheduler.continue_process(process.appid(), chip) {
+                return_reason = StoppedExecutingReason::KernelPreemption;
                 break;
             }
 
             if systick.overflowed() || !systick.greater_than(MIN_QUANTA_THRESHOLD_US) {
                 process.debug_timeslice_expired();
+                return_reason = StoppedExecutingReason::TimesliceExpired;
                 break;
             }
 
@@ -382,6 +782,7 @@ impl Kernel {
                             // decide how to handle the error.
                             if syscall != Syscall::YIELD {
                                 if let Err(response) = platform.filter_syscall(process, &syscall) {
+                                    self.trace_syscall(process, &syscall, response);
                                     process.set_syscall_return_value(response.into());
                                     continue;
                                 }
@@ -401,12 +802,14 @@ impl Kernel {
                                             res
                                         );
                                     }
+                                    self.trace_syscall(process, &syscall, res);
                                     process.set_syscall_return_value(res.into());
                                 }
                                 Syscall::YIELD => {
                                     if config::CONFIG.trace_syscalls {
                                         debug!("[{:?}] yield", process.appid());
                                     }
+                                    self.trace_syscall(process, &syscall, ReturnCode::SUCCESS);
                                     process.set_yielded_state();
 
                                     // There might be already enqueued callbacks
@@ -457,6 +860,7 @@ impl Kernel {
                                             res
                                         );
                                     }
+                                    self.trace_syscall(process, &syscall, res);
                                     process.set_syscall_return_value(res.into());
                                 }
                                 Syscall::COMMAND {
@@ -490,6 +894,7 @@ impl Kernel {
                                             res
                                         );
                                     }
+                                    self.trace_syscall(process, &syscall, res);
                                     process.set_syscall_return_value(res.into());
                                 }
                                 Syscall::ALLOW {
@@ -525,16 +930,19 @@ impl Kernel {
                                             res
                                         );
                                     }
+                                    self.trace_syscall(process, &syscall, res);
                                     process.set_syscall_return_value(res.into());
                                 }
                             }
                         }
                         Some(ContextSwitchReason::TimesliceExpired) => {
                             // break to handle other processes.
+                            return_reason = StoppedExecutingReason::TimesliceExpired;
                             break;
                         }
                         Some(ContextSwitchReason::Interrupted) => {
                             // break to handle other processes.
+                            return_reason = StoppedExecutingReason::KernelPreemption;
                             break;
                         }
                         None => {
@@ -586,19 +994,39 @@ impl Kernel {
                     panic!("Attempted to schedule a faulty process");
                 }
                 process::State::StoppedRunning => {
+                    return_reason = StoppedExecutingReason::Stopped;
                     break;
-                    // Do nothing
                 }
                 process::State::StoppedYielded => {
+                    return_reason = StoppedExecutingReason::Stopped;
                     break;
-                    // Do nothing
                 }
                 process::State::StoppedFaulted => {
+                    return_reason = StoppedExecutingReason::StoppedFaulted;
+                    break;
+                }
+                process::State::Terminated => {
+                    return_reason = StoppedExecutingReason::Terminated;
                     break;
-                    // Do nothing
                 }
             }
         }
+
+        // Check how much of its timeslice the process used so we can provide
+        // it to the scheduler. A process that exhausted its timeslice used all
+        // of it, and we cannot trust the remaining time reported by the timer
+        // once it has expired.
+        let time_executed_us = timeslice_us.map(|timeslice| {
+            if return_reason == StoppedExecutingReason::TimesliceExpired {
+                timeslice
+            } else {
+                timeslice.saturating_sub(systick.get_remaining_us())
+            }
+        });
+
         systick.reset();
+        panic_record::set_current_process(None);
+
+        (return_reason, time_executed_us)
     }
 }
@@ -0,0 +1,69 @@
+//! Cooperative Scheduler for Tock
+//!
+//! This scheduler runs all processes in a round-robin fashion, but does not use
+//! a scheduler timer to enforce process timeslices. That is, all processes are
+//! run cooperatively. Processes are run until they yield or stop executing
+//! (i.e. they crash or exit).
+//!
+//! When hardware interrupts occur while a userspace process is executing, this
+//! scheduler executes the top half of the interrupt, and then stops executing
+//! the userspace process immediately and handles the bottom half of the
+//! interrupt. This design decision was made to mimic the behavior of the
+//! original Tock scheduler. In order to ensure fair use of timeslices, when
+//! userspace processes are interrupted the scheduler continues with the same
+//! process once the kernel work has been handled.
+
+use core::cell::Cell;
+
+use crate::platform::Chip;
+use crate::sched::{Kernel, Scheduler, SchedulingDecision, StoppedExecutingReason};
+
+/// Cooperative round robin scheduler.
+pub struct CooperativeSched {
+    /// Index in the processes array of the first process to consider on the
+    /// next call to `next()`.
+    next_index: Cell<usize>,
+
+    /// Index in the processes array of the process that was last scheduled.
+    last_index: Cell<usize>,
+}
+
+impl CooperativeSched {
+    pub const fn new() -> CooperativeSched {
+        CooperativeSched {
+            next_index: Cell::new(0),
+            last_index: Cell::new(0),
+        }
+    }
+}
+
+impl<C: Chip> Scheduler<C> for CooperativeSched {
+    fn next(&self, kernel: &Kernel) -> SchedulingDecision {
+        if kernel.processes_blocked() {
+            return SchedulingDecision::TrySleep;
+        }
+
+        let num_procs = kernel.num_process_slots();
+        let start = self.next_index.get();
+        for offset in 0..num_procs {
+            let index = (start + offset) % num_procs;
+            if let Some(process) = kernel.get_process(index) {
+                if process.ready() {
+                    self.last_index.set(index);
+                    return SchedulingDecision::RunProcess((process.appid(), None));
+                }
+            }
+        }
+
+        SchedulingDecision::TrySleep
+    }
+
+    fn result(&self, result: StoppedExecutingReason, _execution_time_us: Option<u32>) {
+        let last_index = self.last_index.get();
+        match result {
+            // Resume the same process after the kernel work has been done.
+            StoppedExecutingReason::KernelPreemption => self.next_index.set(last_index),
+            _ => self.next_index.set(last_index + 1),
+        }
+    }
+}
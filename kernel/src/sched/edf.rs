@@ -0,0 +1,291 @@
+//! Earliest Deadline First Scheduler for Tock
+//!
+//! This scheduler runs real-time processes according to their deadlines.
+//! Processes register as real-time processes with a period and a relative
+//! deadline (see memop operations 12 and 13). Each time a real-time process
+//! becomes ready, and at least one period has passed since its previous
+//! activation, a new job is released with an absolute deadline of the release
+//! time plus the relative deadline. The job finishes when the process yields
+//! with no more work to do.
+//!
+//! The ready job with the earliest deadline always runs. When an interrupt
+//! makes a process with an earlier deadline ready, the running process is
+//! preempted once the interrupt is handled. Real-time processes that requested
+//! a CPU budget in their TBF header are preempted with the scheduler timer when
+//! they exhaust it, and the rest of their job is run in the background.
+//!
+//! Processes that are not real-time, or real-time processes without a released
+//! job, are run in a round-robin fashion whenever no job is ready.
+//!
+//! A job that has not finished by its deadline counts as a deadline miss for
+//! its process. Deadline misses are reported through `KernelInfo`.
+//!
+//! The scheduler needs a time source to release jobs and track deadlines, and a
+//! buffer with one `EDFProcessState` for each slot in the processes array.
+
+use core::cell::Cell;
+
+use crate::hil::time::{self, Frequency};
+use crate::platform::Chip;
+use crate::process::ProcessType;
+use crate::sched::{
+    Kernel, Scheduler, SchedulingDecision, StoppedExecutingReason, MIN_QUANTA_THRESHOLD_US,
+};
+
+/// Timeslice for processes that are run in the background.
+const BACKGROUND_TIMESLICE_US: u32 = 10000;
+
+/// Shortest timeslice a job is given. The kernel does not run a process with
+/// less than `MIN_QUANTA_THRESHOLD_US` of its timeslice left, so a job with
+/// less budget or time to its deadline than that runs for this long instead,
+/// and its deadline miss is recorded when it finishes.
+const MIN_JOB_TIMESLICE_US: u32 = 2 * MIN_QUANTA_THRESHOLD_US;
+
+/// Scheduling state the EDF scheduler keeps for one slot in the processes
+/// array.
+#[derive(Default)]
+pub struct EDFProcessState {
+    /// Whether the process has a job that has not finished yet.
+    job_active: Cell<bool>,
+
+    /// Time (in tics) when the current or most recent job was released.
+    release: Cell<Option<u32>>,
+
+    /// Absolute deadline (in tics) of the current job.
+    deadline: Cell<u32>,
+
+    /// CPU time the current job has used, in microseconds.
+    used_us: Cell<u32>,
+
+    /// Whether the current job has already been counted as a deadline miss.
+    missed: Cell<bool>,
+}
+
+/// Earliest deadline first scheduler.
+pub struct EDFSched<'a, T: 'static + time::Time> {
+    kernel: &'static Kernel,
+
+    /// Time source used for releases and deadlines.
+    time: &'static T,
+
+    /// Per-process scheduling state, indexed by the slot of the process in the
+    /// processes array.
+    states: &'a [EDFProcessState],
+
+    /// Index in the processes array of the first process to consider when
+    /// running processes in the background.
+    next_index: Cell<usize>,
+
+    /// Index in the processes array of the process that was last scheduled.
+    last_index: Cell<usize>,
+
+    /// Whether the last scheduled process was running a real-time job.
+    last_was_job: Cell<bool>,
+}
+
+impl<'a, T: 'static + time::Time> EDFSched<'a, T> {
+    /// Create a new EDF scheduler.
+    ///
+    /// `states` must contain one entry for every slot in the processes array.
+    /// Processes in slots without an entry are never scheduled.
+    pub fn new(
+        kernel: &'static Kernel,
+        time: &'static T,
+        states: &'a [EDFProcessState],
+    ) -> EDFSched<'a, T> {
+        EDFSched {
+            kernel: kernel,
+            time: time,
+            states: states,
+            next_index: Cell::new(0),
+            last_index: Cell::new(0),
+            last_was_job: Cell::new(false),
+        }
+    }
+
+    fn us_to_tics(&self, us: u32) -> u32 {
+        (us as u64 * T::Frequency::frequency() as u64 / 1_000_000) as u32
+    }
+
+    fn tics_to_us(&self, tics: u32) -> u32 {
+        (tics as u64 * 1_000_000 / T::Frequency::frequency() as u64) as u32
+    }
+
+    /// Number of tics from `now` until `deadline`. This is negative if the
+    /// deadline has passed.
+    fn tics_until(&self, now: u32, deadline: u32) -> i64 {
+        let max = self.time.max_tics();
+        let diff = deadline.wrapping_sub(now) & max;
+        if diff > max / 2 {
+            diff as i64 - max as i64 - 1
+        } else {
+            diff as i64
+        }
+    }
+
+    /// Count a deadline miss for the job of `process` if its deadline has
+    /// passed and the miss has not been counted yet.
+    fn check_deadline(&self, process: &dyn ProcessType, state: &EDFProcessState, now: u32) {
+        if state.job_active.get()
+            && !state.missed.get()
+            && self.tics_until(now, state.deadline.get()) < 0
+        {
+            state.missed.set(true);
+            process.debug_deadline_missed();
+        }
+    }
+
+    /// Release a new job for `process` if it is ready, does not have an
+    /// unfinished job, and its period has elapsed since its last release.
+    fn try_release(&self, process: &dyn ProcessType, state: &EDFProcessState, now: u32) {
+        let parameters = match process.get_real_time_parameters() {
+            Some(parameters) => parameters,
+            None => {
+                state.job_active.set(false);
+                return;
+            }
+        };
+        if state.job_active.get() || !process.ready() {
+            return;
+        }
+        let period_elapsed = state.release.get().map_or(true, |release| {
+            now.wrapping_sub(release) & self.time.max_tics()
+                >= self.us_to_tics(parameters.period_us)
+        });
+        if period_elapsed {
+            state.job_active.set(true);
+            state.release.set(Some(now));
+            state.deadline.set(
+                now.wrapping_add(self.us_to_tics(parameters.deadline_us)) & self.time.max_tics(),
+            );
+            state.used_us.set(0);
+            state.missed.set(false);
+        }
+    }
+
+    /// CPU time the job of `process` may still use, or `None` if the process
+    /// did not request a budget.
+    fn budget_remaining(&self, process: &dyn ProcessType, state: &EDFProcessState) -> Option<u32> {
+        process
+            .get_scheduling_parameters()
+            .cpu_budget_us
+            .map(|budget| budget.saturating_sub(state.used_us.get()))
+    }
+}
+
+impl<'a, T: 'static + time::Time, C: Chip> Scheduler<C> for EDFSched<'a, T> {
+    fn next(&self, kernel: &Kernel) -> SchedulingDecision {
+        let now = self.time.now();
+        let num_procs = core::cmp::min(kernel.num_process_slots(), self.states.len());
+
+        // Update the jobs of all real-time processes and find the ready job
+        // with the earliest deadline.
+        let mut earliest: Option<(i64, usize)> = None;
+        for index in 0..num_procs {
+            let state = &self.states[index];
+            if let Some(process) = kernel.get_process(index) {
+                self.try_release(process, state, now);
+                self.check_deadline(process, state, now);
+
+                if state.job_active.get()
+                    && process.ready()
+                    && self.budget_remaining(process, state) != Some(0)
+                {
+                    let until = self.tics_until(now, state.deadline.get());
+                    if earliest.map_or(true, |(earliest_until, _)| until < earliest_until) {
+                        earliest = Some((until, index));
+                    }
+                }
+            }
+        }
+
+        if kernel.processes_blocked() {
+            return SchedulingDecision::TrySleep;
+        }
+
+        if let Some((until, index)) = earliest {
+            if let Some(process) = kernel.get_process(index) {
+                let state = &self.states[index];
+
+                // Stop the job when its budget runs out or, if it has not
+                // missed its deadline yet, when the deadline passes so that
+                // the miss is recorded.
+                let until_deadline = if until > 0 {
+                    Some(self.tics_to_us(until as u32))
+                } else {
+                    None
+                };
+                let timeslice = match (self.budget_remaining(process, state), until_deadline) {
+                    (Some(budget), Some(deadline)) => core::cmp::min(budget, deadline),
+                    (Some(budget), None) => budget,
+                    (None, Some(deadline)) => deadline,
+                    (None, None) => BACKGROUND_TIMESLICE_US,
+                };
+                let timeslice = core::cmp::max(timeslice, MIN_JOB_TIMESLICE_US);
+
+                self.last_index.set(index);
+                self.last_was_job.set(true);
+                return SchedulingDecision::RunProcess((process.appid(), Some(timeslice)));
+            }
+        }
+
+        // No job is ready, so run the other ready processes in round-robin
+        // order.
+        let start = self.next_index.get();
+        for offset in 0..num_procs {
+            let index = (start + offset) % num_procs;
+            if let Some(process) = kernel.get_process(index) {
+                if process.ready() {
+                    self.last_index.set(index);
+                    self.last_was_job.set(false);
+                    return SchedulingDecision::RunProcess((
+                        process.appid(),
+                        Some(BACKGROUND_TIMESLICE_US),
+                    ));
+                }
+            }
+        }
+
+        SchedulingDecision::TrySleep
+    }
+
+    fn result(&self, result: StoppedExecutingReason, execution_time_us: Option<u32>) {
+        let index = self.last_index.get();
+        let state = match self.states.get(index) {
+            Some(state) => state,
+            None => return,
+        };
+
+        if self.last_was_job.get() {
+            state
+                .used_us
+                .set(state.used_us.get() + execution_time_us.unwrap_or(0));
+        }
+
+        match result {
+            StoppedExecutingReason::NoWorkLeft
+            | StoppedExecutingReason::Stopped
+            | StoppedExecutingReason::StoppedFaulted
+            | StoppedExecutingReason::Terminated => {
+                // The process has no more work to do, so its job (if any) is
+                // finished. A late finish counts as a miss.
+                if state.job_active.get() {
+                    if !state.missed.get()
+                        && self.tics_until(self.time.now(), state.deadline.get()) < 0
+                    {
+                        state.missed.set(true);
+                        if let Some(process) = self.kernel.get_process(index) {
+                            process.debug_deadline_missed();
+                        }
+                    }
+                    state.job_active.set(false);
+                }
+            }
+            _ => {}
+        }
+
+        if !self.last_was_job.get() && result != StoppedExecutingReason::KernelPreemption {
+            self.next_index.set(index + 1);
+        }
+    }
+}
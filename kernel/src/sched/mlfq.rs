@@ -0,0 +1,192 @@
+//! Multilevel feedback queue scheduler for Tock
+//!
+//! Based on the MLFQ rules described in "Operating Systems: Three Easy Pieces"
+//! by Remzi H. Arpaci-Dusseau and Andrea C. Arpaci-Dusseau.
+//!
+//! This scheduler can be summarized by the following rules:
+//!
+//! - Rule 1: If Priority(A) > Priority(B), and both are ready, A runs (B
+//!   doesn't).
+//! - Rule 2: If Priority(A) = Priority(B), A & B run in round-robin fashion
+//!   using the time slice (quantum length) of the given queue.
+//! - Rule 3: When a job enters the system, it is placed at the highest
+//!   priority (the topmost queue).
+//! - Rule 4: Once a job uses up its time allotment at a given level (regardless
+//!   of how many times it has given up the CPU), its priority is reduced (i.e.,
+//!   it moves down one queue).
+//! - Rule 5: A job that yields after using less than half of the time slice of
+//!   its queue moves up one queue, so interactive processes quickly regain a
+//!   high priority.
+//! - Rule 6: After some time period S, move all the jobs in the system to the
+//!   topmost queue.
+//!
+//! The scheduler needs a time source to know when to boost all processes back
+//! to the topmost queue (rule 6), and a buffer with one `MLFQProcessState` for
+//! each slot in the processes array to track the queue of each process.
+
+use core::cell::Cell;
+
+use crate::hil::time::{self, Frequency};
+use crate::platform::Chip;
+use crate::sched::{Kernel, Scheduler, SchedulingDecision, StoppedExecutingReason};
+
+/// Number of priority levels used by the scheduler.
+const NUM_QUEUES: usize = 3;
+
+/// Timeslice for processes in each queue, from highest to lowest priority.
+const TIMESLICES_US: [u32; NUM_QUEUES] = [10000, 20000, 50000];
+
+/// How often all processes are moved back to the highest priority queue.
+const PRIORITY_BOOST_PERIOD_MS: u32 = 500;
+
+/// Scheduling state the MLFQ scheduler keeps for one slot in the processes
+/// array.
+#[derive(Default)]
+pub struct MLFQProcessState {
+    /// Index of the queue the process is currently in. Queue 0 has the highest
+    /// priority.
+    queue: Cell<usize>,
+
+    /// How much of the time allotment for its current queue the process has
+    /// used.
+    us_used_this_queue: Cell<u32>,
+
+    /// Identifier of the process the state belongs to.
+    process_id: Cell<Option<usize>>,
+}
+
+impl MLFQProcessState {
+    fn reset(&self, queue: usize) {
+        self.queue.set(queue);
+        self.us_used_this_queue.set(0);
+    }
+
+    /// Place the process with identifier `process_id` in the highest priority
+    /// queue if the state belonged to another process before (rule 3). A
+    /// process gets a new identifier when it restarts, and a slot gets a new
+    /// process when an app is loaded into it.
+    fn track(&self, process_id: usize) {
+        if self.process_id.get() != Some(process_id) {
+            self.process_id.set(Some(process_id));
+            self.reset(0);
+        }
+    }
+}
+
+/// Multilevel feedback queue scheduler.
+pub struct MLFQSched<'a, T: 'static + time::Time> {
+    /// Time source used to decide when to boost all processes.
+    time: &'static T,
+
+    /// Per-process scheduling state, indexed by the slot of the process in the
+    /// processes array.
+    states: &'a [MLFQProcessState],
+
+    /// Time (in `time` tics) of the last priority boost.
+    last_boost: Cell<u32>,
+
+    /// For each queue, index in the processes array of the first process to
+    /// consider on the next call to `next()`.
+    next_index: [Cell<usize>; NUM_QUEUES],
+
+    /// Index in the processes array of the process that was last scheduled.
+    last_index: Cell<usize>,
+}
+
+impl<'a, T: 'static + time::Time> MLFQSched<'a, T> {
+    /// Create a new MLFQ scheduler.
+    ///
+    /// `states` must contain one entry for every slot in the processes array.
+    /// Processes in slots without an entry are never scheduled.
+    pub fn new(time: &'static T, states: &'a [MLFQProcessState]) -> MLFQSched<'a, T> {
+        MLFQSched {
+            time: time,
+            states: states,
+            last_boost: Cell::new(time.now()),
+            next_index: Default::default(),
+            last_index: Cell::new(0),
+        }
+    }
+
+    /// Move every process back to the highest priority queue if the boost
+    /// period has elapsed.
+    fn check_priority_boost(&self) {
+        let now = self.time.now();
+        let elapsed = now.wrapping_sub(self.last_boost.get()) & self.time.max_tics();
+        let period =
+            (PRIORITY_BOOST_PERIOD_MS as u64 * T::Frequency::frequency() as u64 / 1000) as u32;
+        if elapsed >= period {
+            for state in self.states.iter() {
+                state.reset(0);
+            }
+            self.last_boost.set(now);
+        }
+    }
+}
+
+impl<'a, T: 'static + time::Time, C: Chip> Scheduler<C> for MLFQSched<'a, T> {
+    fn next(&self, kernel: &Kernel) -> SchedulingDecision {
+        self.check_priority_boost();
+
+        if kernel.processes_blocked() {
+            return SchedulingDecision::TrySleep;
+        }
+
+        let num_procs = core::cmp::min(kernel.num_process_slots(), self.states.len());
+        for (index, state) in self.states.iter().enumerate().take(num_procs) {
+            kernel
+                .get_process(index)
+                .map(|process| state.track(process.appid().id()));
+        }
+        for queue in 0..NUM_QUEUES {
+            let start = self.next_index[queue].get();
+            for offset in 0..num_procs {
+                let index = (start + offset) % num_procs;
+                let state = &self.states[index];
+                if state.queue.get() != queue {
+                    continue;
+                }
+                if let Some(process) = kernel.get_process(index) {
+                    if process.ready() {
+                        self.last_index.set(index);
+                        let timeslice =
+                            TIMESLICES_US[queue].saturating_sub(state.us_used_this_queue.get());
+                        return SchedulingDecision::RunProcess((process.appid(), Some(timeslice)));
+                    }
+                }
+            }
+        }
+
+        SchedulingDecision::TrySleep
+    }
+
+    fn result(&self, result: StoppedExecutingReason, execution_time_us: Option<u32>) {
+        let index = self.last_index.get();
+        let state = match self.states.get(index) {
+            Some(state) => state,
+            None => return,
+        };
+
+        let queue = state.queue.get();
+        let used = state.us_used_this_queue.get() + execution_time_us.unwrap_or(0);
+        state.us_used_this_queue.set(used);
+
+        if result == StoppedExecutingReason::TimesliceExpired || used >= TIMESLICES_US[queue] {
+            // The process used its entire allotment for this queue, so it
+            // moves down one queue (rule 4).
+            state.reset(core::cmp::min(queue + 1, NUM_QUEUES - 1));
+        } else if result == StoppedExecutingReason::NoWorkLeft
+            && used < TIMESLICES_US[queue] / 2
+            && queue > 0
+        {
+            // The process yielded quickly, so it moves up one queue (rule 5).
+            state.reset(queue - 1);
+        }
+
+        // Unless the process was only interrupted by kernel work, the next
+        // process in its queue gets a turn.
+        if result != StoppedExecutingReason::KernelPreemption {
+            self.next_index[queue].set(index + 1);
+        }
+    }
+}
@@ -0,0 +1,98 @@
+//! Fixed Priority Scheduler for Tock
+//!
+//! This scheduler assigns priority to processes based on the priority they
+//! request in their TBF header, where lower values mean higher priority.
+//! Processes that do not request a priority have the lowest priority, and ties
+//! are broken by the order in the processes array: the process in the earlier
+//! slot has the higher priority. The highest priority process that is ready
+//! always runs, and it runs until it yields or a higher priority process
+//! becomes ready. Processes are run cooperatively, so a lower priority process
+//! is only scheduled once every higher priority process is waiting for an
+//! event.
+
+use crate::callback::AppId;
+use crate::common::cells::OptionalCell;
+use crate::common::dynamic_deferred_call::DynamicDeferredCall;
+use crate::platform::Chip;
+use crate::sched::{Kernel, Scheduler, SchedulingDecision, StoppedExecutingReason};
+
+/// Priority scheduler based on requested priorities and the order of processes
+/// in the processes array.
+pub struct PrioritySched {
+    kernel: &'static Kernel,
+
+    /// Index in the processes array of the process currently executing.
+    running: OptionalCell<usize>,
+}
+
+impl PrioritySched {
+    pub const fn new(kernel: &'static Kernel) -> PrioritySched {
+        PrioritySched {
+            kernel: kernel,
+            running: OptionalCell::empty(),
+        }
+    }
+
+    /// Returns the sort key for the process at `index`. Lower keys mean higher
+    /// priority.
+    fn priority_key(&self, index: usize) -> (u32, usize) {
+        let priority = self.kernel.get_process(index).map_or(u32::MAX, |process| {
+            process
+                .get_scheduling_parameters()
+                .priority
+                .unwrap_or(u32::MAX)
+        });
+        (priority, index)
+    }
+
+    /// Returns the index of the highest priority process that is ready.
+    fn highest_ready(&self) -> Option<usize> {
+        self.kernel
+            .processes
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| p.get().map_or(false, |process| process.ready()))
+            .map(|(index, _)| self.priority_key(index))
+            .min()
+            .map(|(_, index)| index)
+    }
+}
+
+impl<C: Chip> Scheduler<C> for PrioritySched {
+    fn next(&self, kernel: &Kernel) -> SchedulingDecision {
+        if kernel.processes_blocked() {
+            return SchedulingDecision::TrySleep;
+        }
+
+        match self.highest_ready() {
+            Some(index) => match kernel.get_process(index) {
+                Some(process) => {
+                    self.running.set(index);
+                    SchedulingDecision::RunProcess((process.appid(), None))
+                }
+                None => SchedulingDecision::TrySleep,
+            },
+            None => SchedulingDecision::TrySleep,
+        }
+    }
+
+    unsafe fn continue_process(&self, _appid: AppId, chip: &C) -> bool {
+        // In addition to checking for interrupts, also check if any higher
+        // priority process has become ready. This can happen if a syscall from
+        // this process makes another process ready, for example when
+        // communicating with a higher priority process over IPC.
+        let preempted = self.running.map_or(false, |running| {
+            self.highest_ready().map_or(false, |highest| {
+                self.priority_key(highest) < self.priority_key(*running)
+            })
+        });
+
+        !(chip.has_pending_interrupts()
+            || DynamicDeferredCall::global_instance_calls_pending().unwrap_or(false)
+            || preempted)
+    }
+
+    fn result(&self, _result: StoppedExecutingReason, _execution_time_us: Option<u32>) {
+        self.running.clear();
+    }
+}
@@ -0,0 +1,110 @@
+//! Round Robin Scheduler for Tock
+//!
+//! This scheduler is specifically a Round Robin Scheduler with Interrupts.
+//!
+//! See: <https://www.cs.ucr.edu/~vahid/rtos/> for more details.
+//!
+//! Notice that for this scheduler, processes are visited in the order of their
+//! slot in the processes array. If a process is preempted by an interrupt it
+//! keeps its position and resumes with whatever remains of its timeslice the
+//! next time it is scheduled. Otherwise, the next ready process in the array
+//! (wrapping around at the end) is scheduled with a full timeslice.
+//!
+//! Processes that request a timeslice in their TBF header get that timeslice
+//! instead of the scheduler's default.
+
+use core::cell::Cell;
+
+use crate::platform::Chip;
+use crate::sched::{Kernel, Scheduler, SchedulingDecision, StoppedExecutingReason};
+
+/// The default time a process is permitted to run before being pre-empted.
+const DEFAULT_TIMESLICE_US: u32 = 10000;
+
+/// Round robin scheduler.
+pub struct RoundRobinSched {
+    /// Length of the timeslice processes receive unless they request their own.
+    timeslice_us: u32,
+
+    /// Index in the processes array of the first process to consider on the
+    /// next call to `next()`.
+    next_index: Cell<usize>,
+
+    /// Index in the processes array of the process that was last scheduled.
+    last_index: Cell<usize>,
+
+    /// How much of its timeslice the process at `next_index` has left, or
+    /// `None` if it should start a new timeslice.
+    time_remaining: Cell<Option<u32>>,
+}
+
+impl RoundRobinSched {
+    /// Create a round robin scheduler that uses the default timeslice.
+    pub const fn new() -> RoundRobinSched {
+        RoundRobinSched::new_with_timeslice(DEFAULT_TIMESLICE_US)
+    }
+
+    /// Create a round robin scheduler that gives each process `timeslice_us`
+    /// microseconds before it is preempted.
+    pub const fn new_with_timeslice(timeslice_us: u32) -> RoundRobinSched {
+        RoundRobinSched {
+            timeslice_us: timeslice_us,
+            next_index: Cell::new(0),
+            last_index: Cell::new(0),
+            time_remaining: Cell::new(None),
+        }
+    }
+}
+
+impl<C: Chip> Scheduler<C> for RoundRobinSched {
+    fn next(&self, kernel: &Kernel) -> SchedulingDecision {
+        if kernel.processes_blocked() {
+            return SchedulingDecision::TrySleep;
+        }
+
+        let num_procs = kernel.num_process_slots();
+        let start = self.next_index.get();
+        for offset in 0..num_procs {
+            let index = (start + offset) % num_procs;
+            if let Some(process) = kernel.get_process(index) {
+                if process.ready() {
+                    // If we skipped over the process that was preempted it no
+                    // longer gets to use the rest of its timeslice.
+                    if offset != 0 {
+                        self.time_remaining.set(None);
+                    }
+                    let timeslice = self.time_remaining.get().unwrap_or_else(|| {
+                        process
+                            .get_scheduling_parameters()
+                            .timeslice_us
+                            .unwrap_or(self.timeslice_us)
+                    });
+                    self.time_remaining.set(Some(timeslice));
+                    self.last_index.set(index);
+                    return SchedulingDecision::RunProcess((process.appid(), Some(timeslice)));
+                }
+            }
+        }
+
+        SchedulingDecision::TrySleep
+    }
+
+    fn result(&self, result: StoppedExecutingReason, execution_time_us: Option<u32>) {
+        let last_index = self.last_index.get();
+        match result {
+            StoppedExecutingReason::KernelPreemption => {
+                // Keep the process at the front so it can finish its
+                // timeslice once the kernel work is done.
+                let used = execution_time_us.unwrap_or(0);
+                self.time_remaining
+                    .set(self.time_remaining.get().map(|t| t.saturating_sub(used)));
+                self.next_index.set(last_index);
+            }
+            _ => {
+                // Move on to the next process, which gets a full timeslice.
+                self.time_remaining.set(None);
+                self.next_index.set(last_index + 1);
+            }
+        }
+    }
+}
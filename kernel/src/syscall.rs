@@ -160,6 +160,17 @@ pub trait UserspaceKernelBoundary {
         state: &Self::StoredState,
         writer: &mut dyn Write,
     );
+
+    /// Store the registers of a process identified by its stack pointer in
+    /// `registers`, in the order GDB numbers them for the architecture.
+    /// Returns how many registers were stored, which is at most
+    /// `crash_dump::MAX_REGISTERS`.
+    unsafe fn store_registers(
+        &self,
+        stack_pointer: *const usize,
+        state: &Self::StoredState,
+        registers: &mut [usize],
+    ) -> usize;
 }
 
 /// Helper function for converting raw values passed back from an application
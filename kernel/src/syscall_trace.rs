@@ -0,0 +1,176 @@
+//! Recording the system calls of processes in a ring buffer.
+//!
+//! Unlike `config::CONFIG.trace_syscalls`, which prints every system call
+//! with `debug!()`, a `SyscallTrace` stores compact binary entries with a
+//! timestamp, and can be switched on for individual processes at runtime
+//! (`ProcessType::set_syscall_tracing()`). The process console can dump the
+//! trace, and `tools/syscall_trace.py` decodes the dump.
+//!
+//! Each entry has a sequence number. Once the buffer is full new entries
+//! overwrite the oldest ones, so a reader can tell from the sequence numbers
+//! which entries it missed.
+//!
+//! Usage
+//! -----
+//!
+//! ```ignore
+//! let syscall_trace = static_init!(
+//!     SyscallTrace<'static, sam4l::ast::Ast>,
+//!     SyscallTrace::new(&sam4l::ast::AST, &mut SYSCALL_TRACE_ENTRIES)
+//! );
+//! board_kernel.set_syscall_tracer(syscall_trace, &process_management_capability);
+//! ```
+
+use core::cell::Cell;
+
+use crate::callback::AppId;
+use crate::common::cells::TakeCell;
+use crate::hil::time::{self, Frequency};
+use crate::returncode::ReturnCode;
+use crate::syscall::Syscall;
+
+/// A system call in the trace.
+///
+/// Arguments are recorded in the fields of the matching `Syscall` variant, in
+/// order: subscribe records the callback pointer and appdata in `arg0` and
+/// `arg1`, allow records the address and size, and memop records the operand
+/// in `subdriver_number` and its argument in `arg0`.
+#[derive(Clone, Copy, Default)]
+pub struct SyscallTraceEntry {
+    /// The time of the system call, in ticks of the trace's clock.
+    pub timestamp: u32,
+    /// The identifier of the process, as returned by `AppId::id()`.
+    pub app: u32,
+    /// The SVC number of the system call.
+    pub syscall: u32,
+    pub driver_number: u32,
+    pub subdriver_number: u32,
+    pub arg0: u32,
+    pub arg1: u32,
+    /// The value returned to the process.
+    pub result: u32,
+}
+
+impl SyscallTraceEntry {
+    fn new(timestamp: u32, appid: AppId, syscall: &Syscall, result: ReturnCode) -> Self {
+        let (number, driver_number, subdriver_number, arg0, arg1) = match *syscall {
+            Syscall::YIELD => (0, 0, 0, 0, 0),
+            Syscall::SUBSCRIBE {
+                driver_number,
+                subdriver_number,
+                callback_ptr,
+                appdata,
+            } => (
+                1,
+                driver_number,
+                subdriver_number,
+                callback_ptr as usize,
+                appdata,
+            ),
+            Syscall::COMMAND {
+                driver_number,
+                subdriver_number,
+                arg0,
+                arg1,
+            } => (2, driver_number, subdriver_number, arg0, arg1),
+            Syscall::ALLOW {
+                driver_number,
+                subdriver_number,
+                allow_address,
+                allow_size,
+            } => (
+                3,
+                driver_number,
+                subdriver_number,
+                allow_address as usize,
+                allow_size,
+            ),
+            Syscall::MEMOP { operand, arg0 } => (4, 0, operand, arg0, 0),
+        };
+        SyscallTraceEntry {
+            timestamp: timestamp,
+            app: appid.id() as u32,
+            syscall: number,
+            driver_number: driver_number as u32,
+            subdriver_number: subdriver_number as u32,
+            arg0: arg0 as u32,
+            arg1: arg1 as u32,
+            result: usize::from(result) as u32,
+        }
+    }
+}
+
+/// A record of system calls the kernel adds to for processes that have
+/// tracing enabled.
+pub trait SyscallTracer {
+    /// Record that the process `appid` made `syscall`, which returned
+    /// `result`.
+    fn record(&self, appid: AppId, syscall: &Syscall, result: ReturnCode);
+
+    /// The sequence number of the oldest entry still in the trace.
+    fn first_sequence(&self) -> usize;
+
+    /// The sequence number the next entry will get.
+    fn next_sequence(&self) -> usize;
+
+    /// Get the entry with sequence number `sequence`, if it is in the trace.
+    fn entry(&self, sequence: usize) -> Option<SyscallTraceEntry>;
+
+    /// The frequency of the timestamps in the trace in Hz.
+    fn frequency(&self) -> u32;
+}
+
+/// A `SyscallTracer` that keeps the most recent entries in a buffer, with
+/// timestamps from `time`.
+pub struct SyscallTrace<'a, T: time::Time> {
+    time: &'a T,
+    entries: TakeCell<'static, [SyscallTraceEntry]>,
+    next: Cell<usize>,
+}
+
+impl<'a, T: time::Time> SyscallTrace<'a, T> {
+    pub fn new(time: &'a T, entries: &'static mut [SyscallTraceEntry]) -> SyscallTrace<'a, T> {
+        SyscallTrace {
+            time: time,
+            entries: TakeCell::new(entries),
+            next: Cell::new(0),
+        }
+    }
+
+    fn capacity(&self) -> usize {
+        self.entries.map_or(0, |entries| entries.len())
+    }
+}
+
+impl<'a, T: time::Time> SyscallTracer for SyscallTrace<'a, T> {
+    fn record(&self, appid: AppId, syscall: &Syscall, result: ReturnCode) {
+        let sequence = self.next.get();
+        self.entries.map(|entries| {
+            if !entries.is_empty() {
+                let index = sequence % entries.len();
+                entries[index] = SyscallTraceEntry::new(self.time.now(), appid, syscall, result);
+                self.next.set(sequence + 1);
+            }
+        });
+    }
+
+    fn first_sequence(&self) -> usize {
+        self.next.get().saturating_sub(self.capacity())
+    }
+
+    fn next_sequence(&self) -> usize {
+        self.next.get()
+    }
+
+    fn entry(&self, sequence: usize) -> Option<SyscallTraceEntry> {
+        if sequence < self.first_sequence() || sequence >= self.next.get() {
+            return None;
+        }
+        self.entries
+            .map(|entries| entries[sequence % entries.len()])
+    }
+
+    fn frequency(&self) -> u32 {
+        T::Frequency::frequency()
+    }
+}
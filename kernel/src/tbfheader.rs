@@ -97,6 +97,12 @@ pub(crate) enum TbfHeaderTypes {
     TbfHeaderWriteableFlashRegions = 2,
     TbfHeaderPackageName = 3,
     TbfHeaderFixedAddresses = 5,
+    TbfHeaderSchedulingParameters = 6,
+    TbfHeaderBinaryEnd = 7,
+    TbfHeaderRetainedRam = 8,
+    TbfHeaderPermittedIpcClients = 9,
+    TbfHeaderPersistentAppId = 10,
+    TbfHeaderPermissions = 11,
 
     /// Some field in the header that we do not understand. Since the TLV format
     /// specifies the length of each section, if we get a field we do not
@@ -156,6 +162,109 @@ pub(crate) struct TbfHeaderV2FixedAddresses {
     start_process_flash: u32,
 }
 
+/// Optional scheduling parameters requested by this process.
+///
+/// This lets an app ask for a priority, a timeslice length, and a CPU budget
+/// without requiring changes to the kernel. These are only requests: the
+/// scheduler decides how to use them, and a board can cap or override them.
+///
+/// Any field the process does not want to request can be set to 0xFFFFFFFF.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct TbfHeaderV2SchedulingParameters {
+    /// Requested priority of the process. Lower values mean higher priority,
+    /// with 0 being the highest priority.
+    priority: u32,
+    /// Length of the timeslice the process would like to run for before being
+    /// preempted, in microseconds.
+    timeslice_us: u32,
+    /// Amount of CPU time the process expects to use in each scheduling
+    /// period, in microseconds.
+    cpu_budget_us: u32,
+}
+
+/// Optional end of the app binary, for apps that have footers.
+///
+/// Everything from the start of the TBF header up to `binary_end_offset` is
+/// the app binary, and is covered by any credentials in the footers. The
+/// footers fill the rest of the app's flash, up to `total_size`.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct TbfHeaderV2BinaryEnd {
+    /// Offset from the start of the TBF header to the end of the app binary.
+    binary_end_offset: u32,
+}
+
+/// Optional RAM the process keeps when the kernel restarts it.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct TbfHeaderV2RetainedRam {
+    /// Size of the retained region at the start of process RAM, in bytes.
+    size: u32,
+}
+
+/// A driver the app may use, in the optional permissions TLV.
+///
+/// There can be multiple permissions for a driver, for different sets of
+/// commands.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct TbfHeaderV2Permission {
+    driver_number: u32,
+    /// Which 64 commands `allowed_commands` is for, starting at command
+    /// `64 * offset`.
+    offset: u32,
+    /// Bitmask of the commands of the driver the app may call.
+    allowed_commands: u64,
+}
+
+/// Size of a `TbfHeaderV2Permission` entry in flash, in bytes.
+const PERMISSION_ENTRY_SIZE: usize = 16;
+
+/// Optional identifier of the app that stays the same across reboots and
+/// updates of the app.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct TbfHeaderV2PersistentAppId {
+    id: u32,
+}
+
+/// Types in TLV structures for each footer of an app.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum TbfFooterTypes {
+    TbfFooterCredentials = 128,
+
+    /// A footer the kernel does not understand, which it skips.
+    Unknown,
+}
+
+/// Formats of the credentials in a credentials footer.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum TbfFooterV2CredentialsType {
+    /// Space reserved for padding, with any length.
+    Padding = 0,
+    /// The 32 byte SHA-256 hash of the app binary.
+    SHA256 = 1,
+    /// A 64 byte ECDSA NIST P-256 signature (r and s, big endian) of the
+    /// SHA-256 hash of the app binary.
+    EcdsaNistP256 = 2,
+    /// A 64 byte Ed25519 signature of the SHA-256 hash of the app binary.
+    Ed25519 = 3,
+}
+
+/// Credentials stored in a footer, which the kernel can use to check the
+/// integrity and origin of the app.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct TbfFooterV2Credentials<'a> {
+    format: TbfFooterV2CredentialsType,
+    data: &'a [u8],
+}
+
+impl<'a> TbfFooterV2Credentials<'a> {
+    pub(crate) fn format(&self) -> TbfFooterV2CredentialsType {
+        self.format
+    }
+
+    pub(crate) fn data(&self) -> &'a [u8] {
+        self.data
+    }
+}
+
 // Conversion functions from slices to the various TBF fields.
 
 impl core::convert::TryFrom<&[u8]> for TbfHeaderV2Base {
@@ -201,11 +310,83 @@ impl core::convert::TryFrom<u16> for TbfHeaderTypes {
             2 => Ok(TbfHeaderTypes::TbfHeaderWriteableFlashRegions),
             3 => Ok(TbfHeaderTypes::TbfHeaderPackageName),
             5 => Ok(TbfHeaderTypes::TbfHeaderFixedAddresses),
+            6 => Ok(TbfHeaderTypes::TbfHeaderSchedulingParameters),
+            7 => Ok(TbfHeaderTypes::TbfHeaderBinaryEnd),
+            8 => Ok(TbfHeaderTypes::TbfHeaderRetainedRam),
+            9 => Ok(TbfHeaderTypes::TbfHeaderPermittedIpcClients),
+            10 => Ok(TbfHeaderTypes::TbfHeaderPersistentAppId),
+            11 => Ok(TbfHeaderTypes::TbfHeaderPermissions),
             _ => Ok(TbfHeaderTypes::Unknown),
         }
     }
 }
 
+impl core::convert::TryFrom<&[u8]> for TbfHeaderV2BinaryEnd {
+    type Error = TbfParseError;
+
+    fn try_from(b: &[u8]) -> Result<TbfHeaderV2BinaryEnd, Self::Error> {
+        Ok(TbfHeaderV2BinaryEnd {
+            binary_end_offset: u32::from_le_bytes(
+                b.get(0..4)
+                    .ok_or(TbfParseError::InternalError)?
+                    .try_into()?,
+            ),
+        })
+    }
+}
+
+impl core::convert::TryFrom<&[u8]> for TbfHeaderV2RetainedRam {
+    type Error = TbfParseError;
+
+    fn try_from(b: &[u8]) -> Result<TbfHeaderV2RetainedRam, Self::Error> {
+        Ok(TbfHeaderV2RetainedRam {
+            size: u32::from_le_bytes(
+                b.get(0..4)
+                    .ok_or(TbfParseError::InternalError)?
+                    .try_into()?,
+            ),
+        })
+    }
+}
+
+impl core::convert::TryFrom<&[u8]> for TbfHeaderV2Permission {
+    type Error = TbfParseError;
+
+    fn try_from(b: &[u8]) -> Result<TbfHeaderV2Permission, Self::Error> {
+        Ok(TbfHeaderV2Permission {
+            driver_number: u32::from_le_bytes(
+                b.get(0..4)
+                    .ok_or(TbfParseError::InternalError)?
+                    .try_into()?,
+            ),
+            offset: u32::from_le_bytes(
+                b.get(4..8)
+                    .ok_or(TbfParseError::InternalError)?
+                    .try_into()?,
+            ),
+            allowed_commands: u64::from_le_bytes(
+                b.get(8..16)
+                    .ok_or(TbfParseError::InternalError)?
+                    .try_into()?,
+            ),
+        })
+    }
+}
+
+impl core::convert::TryFrom<&[u8]> for TbfHeaderV2PersistentAppId {
+    type Error = TbfParseError;
+
+    fn try_from(b: &[u8]) -> Result<TbfHeaderV2PersistentAppId, Self::Error> {
+        Ok(TbfHeaderV2PersistentAppId {
+            id: u32::from_le_bytes(
+                b.get(0..4)
+                    .ok_or(TbfParseError::InternalError)?
+                    .try_into()?,
+            ),
+        })
+    }
+}
+
 impl core::convert::TryFrom<&[u8]> for TbfHeaderTlv {
     type Error = TbfParseError;
 
@@ -288,18 +469,48 @@ impl core::convert::TryFrom<&[u8]> for TbfHeaderV2FixedAddresses {
     }
 }
 
+impl core::convert::TryFrom<&[u8]> for TbfHeaderV2SchedulingParameters {
+    type Error = TbfParseError;
+
+    fn try_from(b: &[u8]) -> Result<TbfHeaderV2SchedulingParameters, Self::Error> {
+        Ok(TbfHeaderV2SchedulingParameters {
+            priority: u32::from_le_bytes(
+                b.get(0..4)
+                    .ok_or(TbfParseError::InternalError)?
+                    .try_into()?,
+            ),
+            timeslice_us: u32::from_le_bytes(
+                b.get(4..8)
+                    .ok_or(TbfParseError::InternalError)?
+                    .try_into()?,
+            ),
+            cpu_budget_us: u32::from_le_bytes(
+                b.get(8..12)
+                    .ok_or(TbfParseError::InternalError)?
+                    .try_into()?,
+            ),
+        })
+    }
+}
+
 /// Single header that can contain all parts of a v2 header.
 ///
 /// Note, this struct limits the number of writeable regions an app can have to
 /// four since we need to statically know the length of the array to store in
 /// this type.
 #[derive(Clone, Copy, Debug)]
-pub(crate) struct TbfHeaderV2 {
+pub(crate) struct TbfHeaderV2<'a> {
     base: TbfHeaderV2Base,
     main: Option<TbfHeaderV2Main>,
-    package_name: Option<&'static str>,
+    package_name: Option<&'a str>,
     writeable_regions: Option<[Option<TbfHeaderV2WriteableFlashRegion>; 4]>,
     fixed_addresses: Option<TbfHeaderV2FixedAddresses>,
+    scheduling_parameters: Option<TbfHeaderV2SchedulingParameters>,
+    binary_end: Option<TbfHeaderV2BinaryEnd>,
+    retained_ram: Option<TbfHeaderV2RetainedRam>,
+    permitted_ipc_clients: Option<&'a [u8]>,
+    persistent_app_id: Option<TbfHeaderV2PersistentAppId>,
+    permissions: Option<&'a [u8]>,
 }
 
 /// Type that represents the fields of the Tock Binary Format header.
@@ -309,12 +520,12 @@ pub(crate) struct TbfHeaderV2 {
 /// The kernel can also use this header to keep persistent state about
 /// the application.
 #[derive(Debug)]
-pub(crate) enum TbfHeader {
-    TbfHeaderV2(TbfHeaderV2),
+pub(crate) enum TbfHeader<'a> {
+    TbfHeaderV2(TbfHeaderV2<'a>),
     Padding(TbfHeaderV2Base),
 }
 
-impl TbfHeader {
+impl<'a> TbfHeader<'a> {
     /// Return whether this is an app or just padding between apps.
     pub(crate) fn is_app(&self) -> bool {
         match *self {
@@ -368,7 +579,7 @@ impl TbfHeader {
     }
 
     /// Get the name of the app.
-    pub(crate) fn get_package_name(&self) -> Option<&'static str> {
+    pub(crate) fn get_package_name(&self) -> Option<&'a str> {
         match *self {
             TbfHeader::TbfHeaderV2(hd) => hd.package_name,
             _ => None,
@@ -426,6 +637,147 @@ impl TbfHeader {
             start => Some(start),
         }
     }
+
+    /// Get the scheduling priority this process requested. Lower values mean
+    /// higher priority. Returns `None` if the process did not request one.
+    pub(crate) fn get_scheduling_priority(&self) -> Option<u32> {
+        let hd = match self {
+            TbfHeader::TbfHeaderV2(hd) => hd,
+            _ => return None,
+        };
+        match hd.scheduling_parameters.as_ref()?.priority {
+            0xFFFFFFFF => None,
+            priority => Some(priority),
+        }
+    }
+
+    /// Get the timeslice length in microseconds this process requested.
+    /// Returns `None` if the process did not request one.
+    pub(crate) fn get_scheduling_timeslice_us(&self) -> Option<u32> {
+        let hd = match self {
+            TbfHeader::TbfHeaderV2(hd) => hd,
+            _ => return None,
+        };
+        match hd.scheduling_parameters.as_ref()?.timeslice_us {
+            0xFFFFFFFF => None,
+            timeslice => Some(timeslice),
+        }
+    }
+
+    /// Get the CPU budget in microseconds this process requested. Returns
+    /// `None` if the process did not request one.
+    pub(crate) fn get_scheduling_cpu_budget_us(&self) -> Option<u32> {
+        let hd = match self {
+            TbfHeader::TbfHeaderV2(hd) => hd,
+            _ => return None,
+        };
+        match hd.scheduling_parameters.as_ref()?.cpu_budget_us {
+            0xFFFFFFFF => None,
+            budget => Some(budget),
+        }
+    }
+
+    /// Get the offset from the start of the TBF header to the end of the app
+    /// binary, where the footers start. Returns `None` if the app has no
+    /// footers.
+    pub(crate) fn get_binary_end_offset(&self) -> Option<u32> {
+        match self {
+            TbfHeader::TbfHeaderV2(hd) => hd.binary_end.map(|be| be.binary_end_offset),
+            _ => None,
+        }
+    }
+
+    /// Get the size of the RAM region at the start of process memory that is
+    /// kept when the process is restarted. This is 0 if the app did not ask
+    /// for one.
+    pub(crate) fn get_retained_ram_size(&self) -> u32 {
+        match self {
+            TbfHeader::TbfHeaderV2(hd) => hd.retained_ram.map_or(0, |rr| rr.size),
+            _ => 0,
+        }
+    }
+
+    /// Return whether the header lists the drivers the app may use.
+    pub(crate) fn has_permissions(&self) -> bool {
+        match self {
+            TbfHeader::TbfHeaderV2(hd) => hd.permissions.is_some(),
+            _ => false,
+        }
+    }
+
+    /// Return whether the app may make system calls to driver `driver_num`,
+    /// and if `command_num` is given, call that command of the driver. Apps
+    /// that do not list their permissions may use all drivers.
+    pub(crate) fn permits_syscall(&self, driver_num: usize, command_num: Option<usize>) -> bool {
+        let permissions = match self {
+            TbfHeader::TbfHeaderV2(hd) => match hd.permissions {
+                Some(permissions) => permissions,
+                None => return true,
+            },
+            _ => return true,
+        };
+        permissions
+            .chunks_exact(PERMISSION_ENTRY_SIZE)
+            .filter_map(|entry| entry.try_into().ok())
+            .any(|permission: TbfHeaderV2Permission| {
+                permission.driver_number as usize == driver_num
+                    && command_num.map_or(true, |command_num| {
+                        command_num / 64 == permission.offset as usize
+                            && permission.allowed_commands & (1 << (command_num % 64)) != 0
+                    })
+            })
+    }
+
+    /// Get the persistent identifier this app set for itself, if any.
+    pub(crate) fn get_persistent_app_id(&self) -> Option<u32> {
+        match self {
+            TbfHeader::TbfHeaderV2(hd) => hd.persistent_app_id.map(|pai| pai.id),
+            _ => None,
+        }
+    }
+
+    /// Return whether the IPC service of this app lets the app named
+    /// `package_name` with the persistent identifier `app_id` discover and
+    /// notify it. Apps that do not list their permitted clients allow every
+    /// client. Clients listed by package name are ignored if `package_name` is
+    /// `None`, as the name of an app is only verified when all apps must be
+    /// signed.
+    pub(crate) fn permits_ipc_client(
+        &self,
+        package_name: Option<&str>,
+        app_id: Option<u32>,
+    ) -> bool {
+        let clients = match self {
+            TbfHeader::TbfHeaderV2(hd) => match hd.permitted_ipc_clients {
+                Some(clients) => clients,
+                None => return true,
+            },
+            _ => return true,
+        };
+        ipc_client_entries(clients).any(|(kind, value)| match kind {
+            IPC_CLIENT_PACKAGE_NAME => package_name.map_or(false, |name| value == name.as_bytes()),
+            IPC_CLIENT_APP_ID => app_id.map_or(false, |id| value == id.to_le_bytes()),
+            _ => false,
+        })
+    }
+}
+
+/// The kinds of permitted IPC client entries, which hold a package name or a
+/// little endian persistent app identifier.
+const IPC_CLIENT_PACKAGE_NAME: u8 = 0;
+const IPC_CLIENT_APP_ID: u8 = 1;
+
+/// Iterate over the (kind, value) entries of a permitted IPC clients TLV. Each
+/// entry is a one byte kind, a one byte length and that many bytes of value.
+/// Iteration stops at the first entry that does not fit.
+fn ipc_client_entries(mut clients: &[u8]) -> impl Iterator<Item = (u8, &[u8])> {
+    core::iter::from_fn(move || {
+        let kind = *clients.get(0)?;
+        let len = *clients.get(1)? as usize;
+        let value = clients.get(2..2 + len)?;
+        clients = &clients[2 + len..];
+        Some((kind, value))
+    })
 }
 
 /// Parse the TBF header length and the entire length of the TBF binary.
@@ -433,9 +785,7 @@ impl TbfHeader {
 /// ## Return
 ///
 /// Ok((Version, TBF header length, entire TBF length))
-pub(crate) fn parse_tbf_header_lengths(
-    app: &'static [u8; 8],
-) -> Result<(u16, u16, u32), TbfParseError> {
+pub(crate) fn parse_tbf_header_lengths(app: &[u8; 8]) -> Result<(u16, u16, u32), TbfParseError> {
     // Version is the first 16 bits of the app TBF contents. We need this to
     // correctly parse the other lengths.
     //
@@ -486,10 +836,10 @@ pub(crate) fn parse_tbf_header_lengths(
 /// The `header` must be a slice that only contains the TBF header. The caller
 /// should use the `parse_tbf_header_lengths()` function to determine this
 /// length to create the correct sized slice.
-pub(crate) fn parse_tbf_header(
-    header: &'static [u8],
+pub(crate) fn parse_tbf_header<'a>(
+    header: &'a [u8],
     version: u16,
-) -> Result<TbfHeader, TbfParseError> {
+) -> Result<TbfHeader<'a>, TbfParseError> {
     match version {
         2 => {
             // Get the required base. This will succeed because we parsed the
@@ -541,6 +891,13 @@ pub(crate) fn parse_tbf_header(
                     Default::default();
                 let mut app_name_str = "";
                 let mut fixed_address_pointer: Option<TbfHeaderV2FixedAddresses> = None;
+                let mut scheduling_parameters_pointer: Option<TbfHeaderV2SchedulingParameters> =
+                    None;
+                let mut binary_end_pointer: Option<TbfHeaderV2BinaryEnd> = None;
+                let mut retained_ram_pointer: Option<TbfHeaderV2RetainedRam> = None;
+                let mut permitted_ipc_clients_pointer: Option<&[u8]> = None;
+                let mut persistent_app_id_pointer: Option<TbfHeaderV2PersistentAppId> = None;
+                let mut permissions_pointer: Option<&[u8]> = None;
 
                 // Iterate the remainder of the header looking for TLV entries.
                 while remaining.len() > 0 {
@@ -621,6 +978,72 @@ pub(crate) fn parse_tbf_header(
                             }
                         }
 
+                        TbfHeaderTypes::TbfHeaderSchedulingParameters => {
+                            let entry_len = mem::size_of::<TbfHeaderV2SchedulingParameters>();
+                            if tlv_header.length as usize == entry_len {
+                                scheduling_parameters_pointer = Some(remaining.try_into()?);
+                            } else {
+                                return Err(TbfParseError::BadTlvEntry(tlv_header.tipe as usize));
+                            }
+                        }
+
+                        TbfHeaderTypes::TbfHeaderBinaryEnd => {
+                            let entry_len = mem::size_of::<TbfHeaderV2BinaryEnd>();
+                            if tlv_header.length as usize == entry_len {
+                                binary_end_pointer = Some(remaining.try_into()?);
+                            } else {
+                                return Err(TbfParseError::BadTlvEntry(tlv_header.tipe as usize));
+                            }
+                        }
+
+                        TbfHeaderTypes::TbfHeaderRetainedRam => {
+                            let entry_len = mem::size_of::<TbfHeaderV2RetainedRam>();
+                            if tlv_header.length as usize == entry_len {
+                                retained_ram_pointer = Some(remaining.try_into()?);
+                            } else {
+                                return Err(TbfParseError::BadTlvEntry(tlv_header.tipe as usize));
+                            }
+                        }
+
+                        TbfHeaderTypes::TbfHeaderPermittedIpcClients => {
+                            let clients = remaining
+                                .get(0..tlv_header.length as usize)
+                                .ok_or(TbfParseError::NotEnoughFlash)?;
+
+                            // Every byte must be part of an entry.
+                            let entries_len: usize = ipc_client_entries(clients)
+                                .map(|(_, value)| 2 + value.len())
+                                .sum();
+                            if entries_len == clients.len() {
+                                permitted_ipc_clients_pointer = Some(clients);
+                            } else {
+                                return Err(TbfParseError::BadTlvEntry(tlv_header.tipe as usize));
+                            }
+                        }
+
+                        TbfHeaderTypes::TbfHeaderPersistentAppId => {
+                            let entry_len = mem::size_of::<TbfHeaderV2PersistentAppId>();
+                            if tlv_header.length as usize == entry_len {
+                                persistent_app_id_pointer = Some(remaining.try_into()?);
+                            } else {
+                                return Err(TbfParseError::BadTlvEntry(tlv_header.tipe as usize));
+                            }
+                        }
+
+                        TbfHeaderTypes::TbfHeaderPermissions => {
+                            // Length must be a multiple of the size of a
+                            // permission.
+                            if tlv_header.length as usize % PERMISSION_ENTRY_SIZE == 0 {
+                                permissions_pointer = Some(
+                                    remaining
+                                        .get(0..tlv_header.length as usize)
+                                        .ok_or(TbfParseError::NotEnoughFlash)?,
+                                );
+                            } else {
+                                return Err(TbfParseError::BadTlvEntry(tlv_header.tipe as usize));
+                            }
+                        }
+
                         _ => {}
                     }
 
@@ -638,6 +1061,12 @@ pub(crate) fn parse_tbf_header(
                     package_name: Some(app_name_str),
                     writeable_regions: Some(wfr_pointer),
                     fixed_addresses: fixed_address_pointer,
+                    scheduling_parameters: scheduling_parameters_pointer,
+                    binary_end: binary_end_pointer,
+                    retained_ram: retained_ram_pointer,
+                    permitted_ipc_clients: permitted_ipc_clients_pointer,
+                    persistent_app_id: persistent_app_id_pointer,
+                    permissions: permissions_pointer,
                 };
 
                 Ok(TbfHeader::TbfHeaderV2(tbf_header))
@@ -646,3 +1075,178 @@ pub(crate) fn parse_tbf_header(
         _ => Err(TbfParseError::UnsupportedVersion(version)),
     }
 }
+
+/// Parse and validate the TBF header at the start of `app`.
+///
+/// Unlike `parse_tbf_header()`, `app` does not need to be exactly the length
+/// of the header: it must start with the header and contain at least the entire
+/// header, for example a buffer holding the beginning of an app binary that is
+/// being received.
+///
+/// ## Return
+///
+/// Ok((Parsed TBF header, entire TBF length))
+pub(crate) fn parse_and_validate_tbf_header<'a>(
+    app: &'a [u8],
+) -> Result<(TbfHeader<'a>, u32), TbfParseError> {
+    let (version, header_length, app_length) = parse_tbf_header_lengths(
+        app.get(0..8)
+            .ok_or(TbfParseError::NotEnoughFlash)?
+            .try_into()?,
+    )?;
+    let header = app
+        .get(0..header_length as usize)
+        .ok_or(TbfParseError::NotEnoughFlash)?;
+    let tbf_header = parse_tbf_header(header, version)?;
+    Ok((tbf_header, app_length))
+}
+
+/// Parse the footer at the start of `footers`, which holds the footers of an
+/// app (everything after the end of its binary).
+///
+/// ## Return
+///
+/// Ok((Credentials in the footer, remaining footers)). The credentials are
+/// `None` if the footer is not a credentials footer, or uses a format the
+/// kernel does not know.
+pub(crate) fn parse_tbf_footer<'a>(
+    footers: &'a [u8],
+) -> Result<(Option<TbfFooterV2Credentials<'a>>, &'a [u8]), TbfParseError> {
+    let tipe = u16::from_le_bytes(
+        footers
+            .get(0..2)
+            .ok_or(TbfParseError::NotEnoughFlash)?
+            .try_into()?,
+    );
+    let length = u16::from_le_bytes(
+        footers
+            .get(2..4)
+            .ok_or(TbfParseError::NotEnoughFlash)?
+            .try_into()?,
+    ) as usize;
+    let value = footers
+        .get(4..4 + length)
+        .ok_or(TbfParseError::NotEnoughFlash)?;
+    let remaining = footers
+        .get(4 + align4!(length)..)
+        .ok_or(TbfParseError::NotEnoughFlash)?;
+
+    let footer_type = match tipe {
+        128 => TbfFooterTypes::TbfFooterCredentials,
+        _ => TbfFooterTypes::Unknown,
+    };
+    let credentials = match footer_type {
+        TbfFooterTypes::TbfFooterCredentials => {
+            let format = u32::from_le_bytes(
+                value
+                    .get(0..4)
+                    .ok_or(TbfParseError::BadTlvEntry(tipe as usize))?
+                    .try_into()?,
+            );
+            let data = value.get(4..).ok_or(TbfParseError::InternalError)?;
+            let (format, data_len) = match format {
+                0 => (TbfFooterV2CredentialsType::Padding, data.len()),
+                1 => (TbfFooterV2CredentialsType::SHA256, 32),
+                2 => (TbfFooterV2CredentialsType::EcdsaNistP256, 64),
+                3 => (TbfFooterV2CredentialsType::Ed25519, 64),
+                _ => return Ok((None, remaining)),
+            };
+            if data.len() != data_len {
+                return Err(TbfParseError::BadTlvEntry(tipe as usize));
+            }
+            Some(TbfFooterV2Credentials {
+                format: format,
+                data: data,
+            })
+        }
+        TbfFooterTypes::Unknown => None,
+    };
+    Ok((credentials, remaining))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_and_validate_tbf_header, TbfHeader};
+
+    /// Build an app header with a main TLV and the given TLV.
+    fn header_with_tlv(tipe: u16, value: &[u8]) -> [u8; 128] {
+        let mut header = [0; 128];
+        let header_size = 16 + 16 + 4 + (value.len() + 3) / 4 * 4;
+        header[0..2].copy_from_slice(&2u16.to_le_bytes());
+        header[2..4].copy_from_slice(&(header_size as u16).to_le_bytes());
+        header[4..8].copy_from_slice(&128u32.to_le_bytes());
+        header[8..12].copy_from_slice(&1u32.to_le_bytes());
+        header[16..18].copy_from_slice(&1u16.to_le_bytes());
+        header[18..20].copy_from_slice(&12u16.to_le_bytes());
+        header[32..34].copy_from_slice(&tipe.to_le_bytes());
+        header[34..36].copy_from_slice(&(value.len() as u16).to_le_bytes());
+        header[36..36 + value.len()].copy_from_slice(value);
+
+        let checksum = header[..header_size]
+            .chunks(4)
+            .enumerate()
+            .filter(|(i, _)| *i != 3)
+            .fold(0, |checksum, (_, word)| {
+                checksum ^ u32::from_le_bytes([word[0], word[1], word[2], word[3]])
+            });
+        header[12..16].copy_from_slice(&checksum.to_le_bytes());
+        header
+    }
+
+    fn parse(app: &[u8]) -> TbfHeader {
+        match parse_and_validate_tbf_header(app) {
+            Ok((header, _)) => header,
+            Err(err) => panic!("{:?}", err),
+        }
+    }
+
+    #[test]
+    fn test_permitted_ipc_clients() {
+        // The client named "client", and the client with identifier 7.
+        let clients = [0, 6, b'c', b'l', b'i', b'e', b'n', b't', 1, 4, 7, 0, 0, 0];
+        let app = header_with_tlv(9, &clients);
+        let header = parse(&app);
+
+        assert!(header.permits_ipc_client(Some("client"), None));
+        assert!(!header.permits_ipc_client(Some("other"), None));
+        assert!(header.permits_ipc_client(None, Some(7)));
+        assert!(!header.permits_ipc_client(None, Some(8)));
+        // Without verified names, only the identifier lets a client in.
+        assert!(!header.permits_ipc_client(None, None));
+
+        // Apps without the element allow every client.
+        let app = header_with_tlv(3, b"app\0");
+        assert!(parse(&app).permits_ipc_client(None, None));
+    }
+
+    #[test]
+    fn test_permissions() {
+        // Driver 1 with commands 0 and 2, and driver 4 with command 65.
+        let mut permissions = [0; 32];
+        permissions[0..4].copy_from_slice(&1u32.to_le_bytes());
+        permissions[8..16].copy_from_slice(&0b101u64.to_le_bytes());
+        permissions[16..20].copy_from_slice(&4u32.to_le_bytes());
+        permissions[20..24].copy_from_slice(&1u32.to_le_bytes());
+        permissions[24..32].copy_from_slice(&0b10u64.to_le_bytes());
+        let app = header_with_tlv(11, &permissions);
+        let header = parse(&app);
+
+        assert!(header.has_permissions());
+        assert!(header.permits_syscall(1, None));
+        assert!(header.permits_syscall(1, Some(0)));
+        assert!(!header.permits_syscall(1, Some(1)));
+        assert!(header.permits_syscall(1, Some(2)));
+        assert!(header.permits_syscall(4, Some(65)));
+        assert!(!header.permits_syscall(4, Some(1)));
+        assert!(!header.permits_syscall(2, None));
+
+        // Apps without permissions may use every driver.
+        let app = header_with_tlv(3, b"app\0");
+        let header = parse(&app);
+        assert!(!header.has_permissions());
+        assert!(header.permits_syscall(2, Some(1)));
+
+        // Permissions must be complete.
+        assert!(parse_and_validate_tbf_header(&header_with_tlv(11, &permissions[..20])).is_err());
+    }
+}
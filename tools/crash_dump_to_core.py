@@ -0,0 +1,214 @@
+#!/usr/bin/env python3
+
+# Converts a Tock process crash dump into an ELF core file for GDB.
+#
+# Usage: crash_dump_to_core.py [-o CORE] DUMP
+
+'''
+Convert a crash dump written by the `crash_dump` capsule into an ELF core file.
+
+DUMP is either console output containing the `crashdump` lines the capsule
+sends over a UART (the last complete dump in it is used), or the raw contents
+of the flash region the capsule writes dumps to.
+
+The core file holds the registers of the process, its stack, its grant
+pointers and its TBF header. Load it together with the app's ELF file:
+
+    gdb-multiarch app.elf CORE
+
+GDB reads the registers of ELF core files through its Linux support, so with
+a bare-metal GDB run `set osabi GNU/Linux` before `core-file CORE`. Apps are
+relocated when they are loaded, so the symbols of position independent apps
+must be loaded at the addresses printed by this tool.
+'''
+
+import argparse
+import re
+import struct
+import sys
+
+MAGIC = b'TKCD'
+
+RECORD_PROCESS = 1
+RECORD_REGISTERS = 2
+RECORD_GRANT_POINTERS = 3
+RECORD_TBF_HEADER = 4
+RECORD_MEMORY = 5
+
+# Architectures by the number of registers in the dump: the ELF machine and
+# the order of the registers in the `pr_reg` field of the core file.
+EM_ARM = 40
+EM_RISCV = 243
+ARCHITECTURES = {
+    # r0-r15 and xPSR, stored by Linux as r0-r15, cpsr and orig_r0.
+    17: (EM_ARM, lambda regs: regs + [regs[0]]),
+    # x0-x31 and pc, stored by Linux as pc and x1-x31.
+    33: (EM_RISCV, lambda regs: [regs[32]] + regs[1:32]),
+}
+
+SIGSEGV = 11
+NT_PRSTATUS = 1
+PT_LOAD = 1
+PT_NOTE = 4
+PF_R = 4
+PF_W = 2
+
+
+def parse_text(text):
+    '''Returns the last complete dump in console output, or None.'''
+    dump = None
+    current = None
+    for line in text.splitlines():
+        match = re.search(r'crashdump (begin [0-9a-f]+|end|[0-9a-f]+ [0-9a-f]*)\s*$', line)
+        if not match:
+            continue
+        fields = match.group(1).split()
+        if fields[0] == 'begin':
+            current = bytearray(int(fields[1], 16))
+        elif fields[0] == 'end':
+            if current is not None:
+                dump = bytes(current)
+            current = None
+        elif current is not None:
+            offset = int(fields[0], 16)
+            data = bytes.fromhex(fields[1]) if len(fields) > 1 else b''
+            current[offset:offset + len(data)] = data
+    return dump
+
+
+def parse_binary(data):
+    '''Returns the dump in the contents of the flash region, or None.'''
+    start = data.find(MAGIC)
+    if start < 0:
+        return None
+    length = struct.unpack_from('<I', data, start + 4)[0]
+    if length < 8 or start + length > len(data):
+        return None
+    return data[start:start + length]
+
+
+def parse_records(dump):
+    records = []
+    offset = 8
+    while offset + 8 <= len(dump):
+        kind, length = struct.unpack_from('<II', dump, offset)
+        data = dump[offset + 8:offset + 8 + length]
+        records.append((kind, data))
+        offset += 8 + ((length + 3) & ~3)
+    return records
+
+
+def words(data):
+    return list(struct.unpack('<{}I'.format(len(data) // 4), data[:len(data) // 4 * 4]))
+
+
+def prstatus(registers):
+    '''The NT_PRSTATUS note of a 32 bit Linux process.'''
+    # si_signo, si_code, si_errno, pr_cursig and padding, pr_sigpend,
+    # pr_sighold, pr_pid, pr_ppid, pr_pgrp, pr_sid and four struct timevals.
+    status = struct.pack('<IIIHHIIIIII', SIGSEGV, 0, 0, SIGSEGV, 0, 0, 0, 1, 0, 0, 0)
+    status += bytes(32)
+    status += struct.pack('<{}I'.format(len(registers)), *registers)
+    # pr_fpvalid
+    status += struct.pack('<I', 0)
+    return status
+
+
+def note(name, kind, desc):
+    name = name + b'\0'
+    pad = lambda b: b + bytes(-len(b) % 4)
+    return struct.pack('<III', len(name), len(desc), kind) + pad(name) + pad(desc)
+
+
+def write_core(path, machine, registers, segments):
+    notes = note(b'CORE', NT_PRSTATUS, prstatus(registers))
+
+    phnum = 1 + len(segments)
+    offset = 52 + 32 * phnum
+    headers = [struct.pack('<IIIIIIII', PT_NOTE, offset, 0, 0, len(notes), 0, 0, 4)]
+    contents = [notes]
+    offset += len(notes)
+    for address, data, flags in segments:
+        headers.append(struct.pack('<IIIIIIII', PT_LOAD, offset, address, address,
+                                   len(data), len(data), flags, 4))
+        contents.append(data)
+        offset += len(data)
+
+    ident = b'\x7fELF' + bytes([1, 1, 1, 0]) + bytes(8)
+    header = ident + struct.pack('<HHIIIIIHHHHHH', 4, machine, 1, 0, 52, 0, 0,
+                                 52, 32, phnum, 0, 0, 0)
+    with open(path, 'wb') as f:
+        f.write(header)
+        f.write(b''.join(headers))
+        f.write(b''.join(contents))
+
+
+def main():
+    parser = argparse.ArgumentParser(
+        description=__doc__, formatter_class=argparse.RawDescriptionHelpFormatter)
+    parser.add_argument('dump', help='Console output or flash contents with the dump')
+    parser.add_argument('-o', '--output', default='core', help='Core file to write')
+    args = parser.parse_args()
+
+    with open(args.dump, 'rb') as f:
+        data = f.read()
+    dump = parse_binary(data) if data.find(MAGIC) >= 0 else None
+    if dump is None:
+        dump = parse_text(data.decode('utf-8', errors='replace'))
+    if dump is None:
+        print('No complete crash dump found in {}.'.format(args.dump), file=sys.stderr)
+        sys.exit(1)
+
+    info = None
+    name = None
+    init_offset = None
+    registers = []
+    segments = []
+    for kind, record in parse_records(dump):
+        if kind == RECORD_PROCESS:
+            info = words(record[:24])
+            name = record[24:].decode('utf-8', errors='replace')
+        elif kind == RECORD_REGISTERS:
+            registers = words(record)
+        elif kind == RECORD_GRANT_POINTERS and info is not None:
+            # Grant pointers are stored at the end of process memory, the
+            # pointer of grant 0 last.
+            pointers = words(record)
+            table = struct.pack('<{}I'.format(len(pointers)), *reversed(pointers))
+            segments.append((info[2] - len(table), table, PF_R | PF_W))
+        elif kind == RECORD_TBF_HEADER:
+            address = words(record[:4])[0]
+            segments.append((address, record[4:], PF_R))
+            # The Main element of the header has the offset of the entry point.
+            offset = 16
+            while offset + 4 <= len(record[4:]):
+                tipe, length = struct.unpack_from('<HH', record, 4 + offset)
+                if tipe == 1 and length >= 4:
+                    init_offset = struct.unpack_from('<I', record, 4 + offset + 4)[0]
+                offset += 4 + ((length + 3) & ~3)
+        elif kind == RECORD_MEMORY:
+            address = words(record[:4])[0]
+            segments.append((address, record[4:], PF_R | PF_W))
+
+    if len(registers) not in ARCHITECTURES:
+        print('Unknown architecture with {} registers.'.format(len(registers)),
+              file=sys.stderr)
+        sys.exit(1)
+    machine, order = ARCHITECTURES[len(registers)]
+    write_core(args.output, machine, order(registers), segments)
+
+    if info is not None:
+        flash_start, memory_start, memory_end, app_break, kernel_break, restarts = info
+        print('Process {} (restarted {} times)'.format(name, restarts))
+        print('  flash   {:#010x}'.format(flash_start))
+        print('  memory  {:#010x}-{:#010x}, app break {:#010x}, grants from {:#010x}'.format(
+            memory_start, memory_end, app_break, kernel_break))
+        if init_offset is not None:
+            print('To load the symbols of the app, run `make debug RAM_START={:#x} '
+                  'FLASH_INIT={:#x}` in its folder.'.format(memory_start,
+                                                            flash_start + init_offset))
+    print('Wrote {}'.format(args.output))
+
+
+if __name__ == '__main__':
+    main()
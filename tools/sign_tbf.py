@@ -0,0 +1,189 @@
+#!/usr/bin/env python3
+
+# Adds credentials footers to a Tock Binary Format (TBF) app.
+#
+# Usage: sign_tbf.py [--sha256] [--key KEY.pem]... -o OUTPUT.tbf INPUT.tbf
+#        sign_tbf.py --show-public-key KEY.pem
+
+'''
+Add credentials footers (a SHA-256 hash and/or signatures) to a TBF app, so
+that boards which set a credentials policy will run it.
+
+The footers cover the TBF header and the app binary. To make room for the
+"Binary End" header element, the app must have been built with at least 8
+bytes of protected region after the header (for example with the
+`--protected-region-size` option of elf2tab). TBFs can be extracted from a
+.tab file with `tar xf`.
+
+Keys are PEM encoded private keys, either ECDSA on the NIST P-256 curve or
+Ed25519. They can be created with:
+
+    openssl ecparam -name prime256v1 -genkey -noout -out key.pem
+    openssl genpkey -algorithm ed25519 -out key.pem
+
+`--show-public-key` prints the `AppSigningKey` a board passes to
+`Kernel::set_credentials_policy()` to accept apps signed with a key.
+
+Options:
+  --sha256                Add a SHA-256 footer.
+  --key KEY.pem           Add a signature footer made with KEY.pem. Can be
+                          given several times.
+  -o, --output FILE       Where to write the signed TBF.
+  --show-public-key KEY   Print the public key of KEY as Rust code.
+'''
+
+import argparse
+import hashlib
+import struct
+import sys
+
+from cryptography.hazmat.primitives import hashes, serialization
+from cryptography.hazmat.primitives.asymmetric import ec, ed25519, utils
+
+TLV_MAIN = 1
+TLV_BINARY_END = 7
+FOOTER_CREDENTIALS = 128
+
+FORMAT_PADDING = 0
+FORMAT_SHA256 = 1
+FORMAT_ECDSA_NIST_P256 = 2
+FORMAT_ED25519 = 3
+
+
+def load_key(path):
+    with open(path, 'rb') as f:
+        key = serialization.load_pem_private_key(f.read(), password=None)
+    if isinstance(key, ec.EllipticCurvePrivateKey):
+        if not isinstance(key.curve, ec.SECP256R1):
+            sys.exit('{}: only the NIST P-256 curve is supported'.format(path))
+    elif not isinstance(key, ed25519.Ed25519PrivateKey):
+        sys.exit('{}: not an ECDSA P-256 or Ed25519 key'.format(path))
+    return key
+
+
+def sign(key, digest):
+    '''Returns the credentials format and data for a signature of digest.'''
+    if isinstance(key, ed25519.Ed25519PrivateKey):
+        return FORMAT_ED25519, key.sign(digest)
+    der = key.sign(digest, ec.ECDSA(utils.Prehashed(hashes.SHA256())))
+    r, s = utils.decode_dss_signature(der)
+    return FORMAT_ECDSA_NIST_P256, r.to_bytes(32, 'big') + s.to_bytes(32, 'big')
+
+
+def footer(credentials_format, data):
+    length = 4 + len(data)
+    padding = b'\0' * ((4 - length % 4) % 4)
+    return struct.pack('<HHI', FOOTER_CREDENTIALS, length, credentials_format) + data + padding
+
+
+def checksum(header):
+    result = 0
+    for i in range(0, len(header), 4):
+        if i != 12:
+            result ^= struct.unpack_from('<I', header, i)[0]
+    return result
+
+
+def is_power_of_two(value):
+    return value & (value - 1) == 0
+
+
+def show_public_key(path):
+    key = load_key(path).public_key()
+    if isinstance(key, ed25519.Ed25519PublicKey):
+        raw = key.public_bytes(serialization.Encoding.Raw, serialization.PublicFormat.Raw)
+        variant = 'Ed25519'
+    else:
+        numbers = key.public_numbers()
+        raw = numbers.x.to_bytes(32, 'big') + numbers.y.to_bytes(32, 'big')
+        variant = 'EcdsaNistP256'
+    lines = []
+    for i in range(0, len(raw), 12):
+        lines.append('    ' + ', '.join('0x{:02x}'.format(b) for b in raw[i:i + 12]) + ',')
+    print('kernel::procs::AppSigningKey::{}(['.format(variant))
+    print('\n'.join(lines))
+    print('])')
+
+
+def main():
+    parser = argparse.ArgumentParser(description='Add credentials footers to a TBF app.')
+    parser.add_argument('input', nargs='?', help='TBF to sign')
+    parser.add_argument('-o', '--output', help='where to write the signed TBF')
+    parser.add_argument('--sha256', action='store_true', help='add a SHA-256 footer')
+    parser.add_argument('--key', action='append', default=[], help='private key to sign with')
+    parser.add_argument('--show-public-key', metavar='KEY', help='print the public key of KEY')
+    args = parser.parse_args()
+
+    if args.show_public_key:
+        show_public_key(args.show_public_key)
+        return
+    if not args.input or not args.output:
+        parser.error('an input TBF and an output file are required')
+    if not args.sha256 and not args.key:
+        parser.error('nothing to add, use --sha256 and/or --key')
+    keys = [load_key(path) for path in args.key]
+
+    with open(args.input, 'rb') as f:
+        tbf = bytearray(f.read())
+    version, header_size, total_size = struct.unpack_from('<HHI', tbf, 0)
+    if version != 2:
+        sys.exit('unsupported TBF version {}'.format(version))
+    if total_size != len(tbf):
+        sys.exit('TBF size {} does not match the file size {}'.format(total_size, len(tbf)))
+
+    # Find the Main element, and make sure the app has no footers yet.
+    main_offset = None
+    offset = 16
+    while offset < header_size:
+        tipe, length = struct.unpack_from('<HH', tbf, offset)
+        if tipe == TLV_MAIN:
+            main_offset = offset + 4
+        elif tipe == TLV_BINARY_END:
+            sys.exit('the TBF already has footers')
+        offset += 4 + length + (4 - length % 4) % 4
+    if main_offset is None:
+        sys.exit('the TBF is not an app')
+
+    # Grow the header into the protected region so that the app binary does
+    # not move. The init function offset and protected size are relative to
+    # the end of the header.
+    init_fn_offset, protected_size, _ = struct.unpack_from('<III', tbf, main_offset)
+    if protected_size < 8 or init_fn_offset < 8:
+        sys.exit('the TBF needs at least 8 bytes of protected region after the header')
+    if any(tbf[header_size:header_size + 8]):
+        sys.exit('the protected region after the header is not empty')
+    struct.pack_into('<II', tbf, main_offset, init_fn_offset - 8, protected_size - 8)
+    binary_end = total_size
+    struct.pack_into('<HHI', tbf, header_size, TLV_BINARY_END, 4, binary_end)
+    header_size += 8
+
+    # The footers must be sized before signing, since the total size is part
+    # of the signed header. Apps that were padded to a power of two for the MPU
+    # stay a power of two.
+    footers_size = (40 if args.sha256 else 0) + 72 * len(keys)
+    new_total_size = binary_end + footers_size
+    if is_power_of_two(total_size):
+        padded = total_size
+        while padded < new_total_size or padded - new_total_size == 4:
+            padded *= 2
+        new_total_size = padded
+
+    struct.pack_into('<HHI', tbf, 0, version, header_size, new_total_size)
+    struct.pack_into('<I', tbf, 12, checksum(tbf[:header_size]))
+
+    digest = hashlib.sha256(tbf[:binary_end]).digest()
+    footers = b''
+    if args.sha256:
+        footers += footer(FORMAT_SHA256, digest)
+    for key in keys:
+        footers += footer(*sign(key, digest))
+    padding = new_total_size - binary_end - len(footers)
+    if padding:
+        footers += footer(FORMAT_PADDING, b'\0' * (padding - 8))
+
+    with open(args.output, 'wb') as f:
+        f.write(tbf + footers)
+
+
+if __name__ == '__main__':
+    main()
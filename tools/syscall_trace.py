@@ -0,0 +1,113 @@
+#!/usr/bin/env python3
+
+# Decodes a syscall trace printed by the process console.
+#
+# Usage: syscall_trace.py [LOG]
+
+'''
+Decode the output of the process console's `tracedump` command.
+
+Capture the console output of one or more `tracedump` runs in a file (or pipe
+it in) and this prints one line per traced system call, in order. Lines that
+are not part of the trace are ignored. Entries that were overwritten in the
+kernel's trace buffer before they were dumped are reported as gaps.
+'''
+
+import argparse
+import re
+import sys
+
+SYSCALLS = ['yield', 'subscribe', 'command', 'allow', 'memop']
+
+RETURN_CODES = {
+    0: 'SUCCESS',
+    -1: 'FAIL',
+    -2: 'EBUSY',
+    -3: 'EALREADY',
+    -4: 'EOFF',
+    -5: 'ERESERVE',
+    -6: 'EINVAL',
+    -7: 'ESIZE',
+    -8: 'ECANCEL',
+    -9: 'ENOMEM',
+    -10: 'ENOSUPPORT',
+    -11: 'ENODEVICE',
+    -12: 'EUNINSTALLED',
+    -13: 'ENOACK',
+}
+
+FREQUENCY = re.compile(r'trace frequency (\d+)')
+ENTRY = re.compile(r'trace ((?:[0-9a-f]+ ){8}[0-9a-f]+)\s*$')
+
+
+def signed(value):
+    return value - (1 << 32) if value & (1 << 31) else value
+
+
+def describe(entry):
+    _, _, syscall, driver, subdriver, arg0, arg1, result = entry
+    name = SYSCALLS[syscall] if syscall < len(SYSCALLS) else 'svc {}'.format(syscall)
+    if name == 'yield':
+        call = 'yield'
+    elif name == 'subscribe':
+        call = 'subscribe(0x{:x}, {}, callback=0x{:x}, appdata=0x{:x})'.format(
+            driver, subdriver, arg0, arg1)
+    elif name == 'command':
+        call = 'command(0x{:x}, {}, {}, {})'.format(driver, subdriver, arg0, arg1)
+    elif name == 'allow':
+        call = 'allow(0x{:x}, {}, address=0x{:x}, size={})'.format(
+            driver, subdriver, arg0, arg1)
+    elif name == 'memop':
+        call = 'memop({}, 0x{:x})'.format(subdriver, arg0)
+    else:
+        call = name
+    code = signed(result)
+    if code < 0:
+        return '{} -> {}'.format(call, RETURN_CODES.get(code, code))
+    return '{} -> {}'.format(call, code)
+
+
+def main():
+    parser = argparse.ArgumentParser(
+        description=__doc__, formatter_class=argparse.RawDescriptionHelpFormatter)
+    parser.add_argument('log', nargs='?', type=argparse.FileType('r'), default=sys.stdin,
+                        help='Captured console output (default: stdin)')
+    args = parser.parse_args()
+
+    frequency = None
+    entries = {}
+    for line in args.log:
+        match = FREQUENCY.search(line)
+        if match:
+            frequency = int(match.group(1))
+            continue
+        match = ENTRY.search(line)
+        if match:
+            fields = [int(field, 16) for field in match.group(1).split()]
+            entries[fields[0]] = fields[1:]
+
+    if not entries:
+        print('No trace entries found.', file=sys.stderr)
+        sys.exit(1)
+
+    first_timestamp = None
+    previous = None
+    for sequence in sorted(entries):
+        if previous is not None and sequence != previous + 1:
+            print('--- {} entries missing ---'.format(sequence - previous - 1))
+        previous = sequence
+
+        entry = entries[sequence]
+        if first_timestamp is None:
+            first_timestamp = entry[0]
+        # Timestamps are ticks of a 32 bit clock that may have wrapped.
+        ticks = (entry[0] - first_timestamp) % (1 << 32)
+        if frequency:
+            time = '{:12.1f}us'.format(ticks * 1e6 / frequency)
+        else:
+            time = '{:12d}'.format(ticks)
+        print('{:6d} {} app {:3d}  {}'.format(sequence, time, entry[1], describe(entry)))
+
+
+if __name__ == '__main__':
+    main()